@@ -413,6 +413,77 @@ impl<F: MatchFunc> Scoring<F> {
         self.yclip_suffix = penalty;
         self
     }
+
+    /// Computes the score of an existing alignment of x against y under this scoring, e.g.
+    /// to re-score an alignment read from a file. Each run of `k` insertions or deletions
+    /// is scored as `gap_open + gap_extend * k`, and clip operations are scored with the
+    /// prefix or suffix penalty, depending on their position.
+    ///
+    /// ```rust
+    /// use bio::alignment::pairwise::*;
+    ///
+    /// let x = b"ACCGTGGAT";
+    /// let y = b"AAAAACCGTTGAT";
+    /// let mut aligner = Aligner::new(-5, -1, |a: u8, b: u8| if a == b { 1i32 } else { -1i32 });
+    /// let alignment = aligner.semiglobal(x, y);
+    /// let scoring = Scoring::from_scores(-2, -1, 2, -3);
+    /// assert_eq!(scoring.score_alignment(&alignment, x, y), 13);
+    /// ```
+    pub fn score_alignment(
+        &self,
+        alignment: &Alignment,
+        x: TextSlice<'_>,
+        y: TextSlice<'_>,
+    ) -> i32 {
+        let mut score = 0;
+        let (mut i, mut j) = (alignment.xstart, alignment.ystart);
+        let mut aligned = false;
+        let mut last = None;
+        for &op in &alignment.operations {
+            match op {
+                AlignmentOperation::Match | AlignmentOperation::Subst => {
+                    score += self.match_fn.score(x[i], y[j]);
+                    i += 1;
+                    j += 1;
+                }
+                AlignmentOperation::Ins => {
+                    if last != Some(op) {
+                        score += self.gap_open;
+                    }
+                    score += self.gap_extend;
+                    i += 1;
+                }
+                AlignmentOperation::Del => {
+                    if last != Some(op) {
+                        score += self.gap_open;
+                    }
+                    score += self.gap_extend;
+                    j += 1;
+                }
+                AlignmentOperation::Xclip(len) if len > 0 => {
+                    score += if aligned {
+                        self.xclip_suffix
+                    } else {
+                        self.xclip_prefix
+                    };
+                }
+                AlignmentOperation::Yclip(len) if len > 0 => {
+                    score += if aligned {
+                        self.yclip_suffix
+                    } else {
+                        self.yclip_prefix
+                    };
+                }
+                AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_) => (),
+            }
+            aligned |= !matches!(
+                op,
+                AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_)
+            );
+            last = Some(op);
+        }
+        score
+    }
 }
 
 /// A generalized Smith-Waterman aligner.
@@ -1280,6 +1351,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_score_alignment() {
+        let x = b"GGGGGGACGTACGTTCGTACGT";
+        let y = b"AAAAACGTACAAGTTCGTACGTAAAA";
+        let scoring = Scoring::from_scores(-5, -1, 1, -3)
+            .xclip_prefix(-10)
+            .yclip(0);
+        let mut aligner = Aligner::with_scoring(scoring.clone());
+        let alignment = aligner.custom(x, y);
+        assert!(alignment.operations.contains(&Xclip(12)));
+        assert_eq!(scoring.score_alignment(&alignment, x, y), alignment.score);
+
+        let mut aligner = Aligner::with_scoring(Scoring::from_scores(-5, -1, 1, -3));
+        for alignment in &[
+            aligner.global(x, y),
+            aligner.semiglobal(x, y),
+            aligner.local(x, y),
+        ] {
+            assert_eq!(
+                aligner.scoring.score_alignment(alignment, x, y),
+                alignment.score
+            );
+        }
+    }
+
     #[test]
     fn test_global() {
         let x = b"ACCGTGGAT";
@@ -90,6 +90,38 @@ where
         .collect()
 }
 
+/// Translate the given coding sequence into a protein sequence using the standard
+/// genetic code.
+///
+/// Stop codons are translated to `*`, codons containing characters other than `ACGTU`
+/// (in any casing) to `X`. A trailing incomplete codon is ignored.
+///
+/// ```
+/// use bio::alphabets::dna;
+///
+/// assert_eq!(dna::translate(b"ATGGCcTAAg"), b"MA*");
+/// assert_eq!(dna::translate(b"ATGNNN"), b"MX");
+/// ```
+pub fn translate(text: &[u8]) -> Vec<u8> {
+    // amino acids for codons in TCAG order
+    const CODE: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+    let base = |a: u8| match a.to_ascii_uppercase() {
+        b'T' | b'U' => Some(0),
+        b'C' => Some(1),
+        b'A' => Some(2),
+        b'G' => Some(3),
+        _ => None,
+    };
+    text.chunks_exact(3)
+        .map(
+            |codon| match (base(codon[0]), base(codon[1]), base(codon[2])) {
+                (Some(a), Some(b), Some(c)) => CODE[a * 16 + b * 4 + c],
+                _ => b'X',
+            },
+        )
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,4 +145,22 @@ mod tests {
     fn number_is_no_word() {
         assert!(!alphabet().is_word(b"42"));
     }
+
+    #[test]
+    fn translate_all_codons() {
+        let bases = b"TCAG";
+        let mut codons = Vec::new();
+        for &a in bases {
+            for &b in bases {
+                for &c in bases {
+                    codons.extend_from_slice(&[a, b, c]);
+                }
+            }
+        }
+        assert_eq!(
+            translate(&codons),
+            &b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"[..]
+        );
+        assert_eq!(translate(b"augUGA"), b"M*");
+    }
 }
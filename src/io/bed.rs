@@ -5,6 +5,10 @@
 
 //! BED format reading and writing.
 //!
+//! Besides the first six fields, the typed BED12 fields (thick region, color and blocks)
+//! and the ENCODE [`NarrowPeak`](NarrowPeak) and [`BroadPeak`](BroadPeak) formats are
+//! supported.
+//!
 //! # Example
 //!
 //! ```
@@ -19,18 +23,158 @@
 //! }
 //! ```
 
-use std::convert::AsRef;
+use std::convert::{AsRef, TryFrom};
+use std::fmt;
 use std::fmt::Write;
 use std::fs;
 use std::io;
 use std::marker::Copy;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::Path;
+use std::str::FromStr;
 
 use anyhow::Context;
 use bio_types::annot;
 use bio_types::annot::loc::Loc;
 use bio_types::strand;
+use thiserror::Error;
+
+use crate::io::compression::Decoder;
+use crate::io::gff;
+use crate::io::transcript::Transcript;
+
+/// An error that occurs while reading BED records or in their typed fields.
+/// Lines are counted from one and byte offsets from zero, both referring to the start of the
+/// offending line.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line} (byte {offset}): {message}")]
+    Format {
+        line: usize,
+        offset: u64,
+        message: String,
+    },
+
+    #[error(
+        "line {line} (byte {offset}): truncated record with {fields} fields, expected at least 3"
+    )]
+    TruncatedRecord {
+        line: usize,
+        offset: u64,
+        fields: usize,
+    },
+
+    #[error("line {line} (byte {offset}): start {start} is greater than end {end}")]
+    InvalidCoordinates {
+        line: usize,
+        offset: u64,
+        start: u64,
+        end: u64,
+    },
+
+    #[error("line {line} (byte {offset}): invalid strand {strand:?}")]
+    InvalidStrand {
+        line: usize,
+        offset: u64,
+        strand: String,
+    },
+
+    #[error("invalid {field} field: {value:?}")]
+    InvalidField { field: &'static str, value: String },
+
+    #[error("missing {0} field")]
+    MissingField(&'static str),
+
+    #[error("invalid blocks: {0}")]
+    InvalidBlocks(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        let (line, offset) = err
+            .position()
+            .map_or((0, 0), |pos| (pos.line() as usize, pos.byte()));
+        let message = err.to_string();
+        match err.into_kind() {
+            csv::ErrorKind::Io(err) => Error::ReadError(err),
+            csv::ErrorKind::UnequalLengths { len, .. } if len < 3 => Error::TruncatedRecord {
+                line,
+                offset,
+                fields: len as usize,
+            },
+            csv::ErrorKind::Deserialize { err, .. } => Error::Format {
+                line,
+                offset,
+                message: err.to_string(),
+            },
+            _ => Error::Format {
+                line,
+                offset,
+                message,
+            },
+        }
+    }
+}
+
+/// Parse the given field, reporting the field name on failure.
+fn parse_field<T: FromStr>(field: &'static str, value: Option<&str>) -> Result<T> {
+    let value = value.ok_or(Error::MissingField(field))?;
+    value.parse().map_err(|_| Error::InvalidField {
+        field,
+        value: value.to_owned(),
+    })
+}
+
+/// Parse a comma separated list of integers, allowing a trailing comma.
+fn parse_list(field: &'static str, value: Option<&str>) -> Result<Vec<u64>> {
+    let value = value.ok_or(Error::MissingField(field))?;
+    value
+        .trim_end_matches(',')
+        .split(',')
+        .map(|v| parse_field(field, Some(v)))
+        .collect()
+}
+
+/// The display color of a BED feature (`itemRgb` field).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = Error;
+
+    /// Parse a color given as `r,g,b` or `0`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidField {
+            field: "itemRgb",
+            value: s.to_owned(),
+        };
+        if s == "0" {
+            return Ok(Rgb(0, 0, 0));
+        }
+        let channels = s
+            .split(',')
+            .map(|c| c.parse().map_err(|_| invalid()))
+            .collect::<Result<Vec<u8>>>()?;
+        match channels[..] {
+            [r, g, b] => Ok(Rgb(r, g, b)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Rgb(0, 0, 0) {
+            write!(f, "0")
+        } else {
+            write!(f, "{},{},{}", self.0, self.1, self.2)
+        }
+    }
+}
 
 /// A BED reader.
 #[derive(Debug)]
@@ -38,10 +182,12 @@ pub struct Reader<R: io::Read> {
     inner: csv::Reader<R>,
 }
 
-impl Reader<fs::File> {
+impl Reader<Decoder<fs::File>> {
     /// Read from a given file path.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
         fs::File::open(&path)
+            .and_then(Decoder::new)
             .map(Reader::new)
             .with_context(|| format!("Failed to read bed from {:#?}", path))
     }
@@ -59,23 +205,63 @@ impl<R: io::Read> Reader<R> {
     }
 
     /// Iterate over all records.
+    ///
+    /// Records with fewer than three fields, a start greater than the end or a strand other
+    /// than `+`, `-` or `.` are reported as errors. Use [`Record::validate`] to check the
+    /// remaining fields.
     pub fn records(&mut self) -> Records<'_, R> {
         Records {
-            inner: self.inner.deserialize(),
+            inner: self.inner.records(),
         }
     }
 }
 
 /// An iterator over the records of a BED file.
 pub struct Records<'a, R: io::Read> {
-    inner: csv::DeserializeRecordsIter<'a, R, Record>,
+    inner: csv::StringRecordsIter<'a, R>,
+}
+
+impl<'a, R: io::Read> Records<'a, R> {
+    fn parse(fields: csv::StringRecord) -> Result<Record> {
+        let (line, offset) = fields
+            .position()
+            .map_or((0, 0), |pos| (pos.line() as usize, pos.byte()));
+        if fields.len() < 3 {
+            return Err(Error::TruncatedRecord {
+                line,
+                offset,
+                fields: fields.len(),
+            });
+        }
+        let record: Record = fields.deserialize(None)?;
+        if record.start > record.end {
+            return Err(Error::InvalidCoordinates {
+                line,
+                offset,
+                start: record.start,
+                end: record.end,
+            });
+        }
+        if let Some(strand) = record.aux(5) {
+            if !matches!(strand, "+" | "-" | ".") {
+                return Err(Error::InvalidStrand {
+                    line,
+                    offset,
+                    strand: strand.to_owned(),
+                });
+            }
+        }
+        Ok(record)
+    }
 }
 
 impl<'a, R: io::Read> Iterator for Records<'a, R> {
-    type Item = csv::Result<Record>;
+    type Item = Result<Record>;
 
-    fn next(&mut self) -> Option<csv::Result<Record>> {
-        self.inner.next()
+    fn next(&mut self) -> Option<Result<Record>> {
+        self.inner
+            .next()
+            .map(|fields| fields.map_err(Error::from).and_then(Self::parse))
     }
 }
 
@@ -183,6 +369,126 @@ impl Record {
         }
     }
 
+    /// Start of the thickly drawn (usually coding) region (BED12 `thickStart`).
+    pub fn thick_start(&self) -> Option<u64> {
+        self.aux(6).and_then(|v| v.parse().ok())
+    }
+
+    /// End of the thickly drawn (usually coding) region (BED12 `thickEnd`).
+    pub fn thick_end(&self) -> Option<u64> {
+        self.aux(7).and_then(|v| v.parse().ok())
+    }
+
+    /// Display color of the feature (BED12 `itemRgb`).
+    pub fn item_rgb(&self) -> Option<Rgb> {
+        self.aux(8).and_then(|v| v.parse().ok())
+    }
+
+    /// Number of blocks (BED12 `blockCount`).
+    pub fn block_count(&self) -> Option<usize> {
+        self.aux(9).and_then(|v| v.parse().ok())
+    }
+
+    /// The blocks (usually exons) as 0-based, half-open intervals on the chromosome,
+    /// decoded from the BED12 `blockSizes` and `blockStarts` fields. Records with less than
+    /// 12 fields consist of a single block spanning the whole feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block fields can't be parsed or describe invalid blocks,
+    /// see [`validate`](Record::validate).
+    pub fn blocks(&self) -> Result<Vec<Range<u64>>> {
+        if self.aux.len() < 9 {
+            let whole = self.start..self.end;
+            return Ok(vec![whole]);
+        }
+        let count: usize = parse_field("blockCount", self.aux(9))?;
+        let sizes = parse_list("blockSizes", self.aux(10))?;
+        let starts = parse_list("blockStarts", self.aux(11))?;
+        if sizes.len() != count || starts.len() != count {
+            return Err(Error::InvalidBlocks(format!(
+                "blockCount is {}, but there are {} sizes and {} starts",
+                count,
+                sizes.len(),
+                starts.len()
+            )));
+        }
+        let blocks: Vec<Range<u64>> = starts
+            .iter()
+            .zip(&sizes)
+            .map(|(&start, &size)| self.start + start..self.start + start + size)
+            .collect();
+        if blocks.first().map(|b| b.start) != Some(self.start) {
+            return Err(Error::InvalidBlocks(
+                "first block does not start at chromStart".to_owned(),
+            ));
+        }
+        if blocks.last().map(|b| b.end) != Some(self.end) {
+            return Err(Error::InvalidBlocks(
+                "last block does not end at chromEnd".to_owned(),
+            ));
+        }
+        if blocks.windows(2).any(|w| w[1].start < w[0].end) {
+            return Err(Error::InvalidBlocks(
+                "blocks are unsorted or overlapping".to_owned(),
+            ));
+        }
+        Ok(blocks)
+    }
+
+    /// Check the fields of a BED3 to BED12 record that are present, i.e. that start ≤ end,
+    /// the score is an integer in `0..=1000`, the strand is `+`, `-` or `.`, the thick region
+    /// lies within the feature, and the blocks are sorted, non-overlapping and span the
+    /// feature.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_bed6()?;
+        if self.aux.len() > 3 {
+            let thick_start: u64 = parse_field("thickStart", self.aux(6))?;
+            let thick_end: u64 = parse_field("thickEnd", self.aux(7))?;
+            if thick_start > thick_end || thick_start < self.start || thick_end > self.end {
+                return Err(Error::InvalidField {
+                    field: "thickStart",
+                    value: thick_start.to_string(),
+                });
+            }
+        }
+        if self.aux.len() > 5 {
+            parse_field::<Rgb>("itemRgb", self.aux(8))?;
+        }
+        if self.aux.len() > 6 {
+            self.blocks()?;
+        }
+        Ok(())
+    }
+
+    /// Check the first six fields, which are shared by BED12 and its extensions.
+    fn validate_bed6(&self) -> Result<()> {
+        if self.start > self.end {
+            return Err(Error::InvalidField {
+                field: "chromEnd",
+                value: self.end.to_string(),
+            });
+        }
+        if let Some(score) = self.score() {
+            let score: u16 = parse_field("score", Some(score))?;
+            if score > 1000 {
+                return Err(Error::InvalidField {
+                    field: "score",
+                    value: score.to_string(),
+                });
+            }
+        }
+        if let Some(strand) = self.aux(5) {
+            if !matches!(strand, "+" | "-" | ".") {
+                return Err(Error::InvalidField {
+                    field: "strand",
+                    value: strand.to_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Set chromosome.
     pub fn set_chrom(&mut self, chrom: &str) {
         self.chrom = chrom.to_owned();
@@ -219,10 +525,156 @@ impl Record {
         }
     }
 
+    /// Set strand. Missing name and score fields are filled with `.` and `0`.
+    pub fn set_strand(&mut self, strand: strand::Strand) {
+        for (i, default) in [".", "0"].iter().enumerate() {
+            if self.aux.len() <= i {
+                self.aux.push((*default).to_owned());
+            }
+        }
+        let strand = strand.strand_symbol().to_owned();
+        if self.aux.len() < 3 {
+            self.aux.push(strand);
+        } else {
+            self.aux[2] = strand;
+        }
+    }
+
     /// Add auxilliary field. This has to happen after name and score have been set.
     pub fn push_aux(&mut self, field: &str) {
         self.aux.push(field.to_owned());
     }
+
+    /// Set the BED12 fields from the given thick region, color and blocks. Start and end of
+    /// the feature are set to the span of the blocks, which have to be sorted. Missing
+    /// name, score and strand fields are filled with `.`, `0` and `.`.
+    pub fn set_bed12(&mut self, thick: Range<u64>, rgb: Rgb, blocks: &[Range<u64>]) {
+        if let (Some(first), Some(last)) = (blocks.first(), blocks.last()) {
+            self.start = first.start;
+            self.end = last.end;
+        }
+        for (i, default) in [".", "0", "."].iter().enumerate() {
+            if self.aux.len() <= i {
+                self.aux.push((*default).to_owned());
+            }
+        }
+        self.aux.truncate(3);
+        self.aux.push(thick.start.to_string());
+        self.aux.push(thick.end.to_string());
+        self.aux.push(rgb.to_string());
+        self.aux.push(blocks.len().to_string());
+        let mut block_sizes = String::new();
+        let mut block_starts = String::new();
+        for block in blocks {
+            write!(block_sizes, "{},", block.end - block.start).unwrap();
+            write!(block_starts, "{},", block.start - self.start).unwrap();
+        }
+        self.aux.push(block_sizes);
+        self.aux.push(block_starts);
+    }
+
+    /// Convert a BED12 record into GFF3 records of a transcript model: a `mRNA` (or
+    /// `transcript` if the thick region is empty) with `exon` and `CDS` children. The name
+    /// of the record is used as ID of the transcript.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blocks or the thick region are invalid.
+    pub fn to_gff(&self) -> Result<Vec<gff::Record>> {
+        self.validate()?;
+        let blocks = self.blocks()?;
+        let thick = match (self.thick_start(), self.thick_end()) {
+            (Some(start), Some(end)) => start..end,
+            _ => self.start..self.start,
+        };
+        let id = self.name().unwrap_or(".");
+        let strand = match self.strand() {
+            Some(strand::Strand::Forward) => "+",
+            Some(strand::Strand::Reverse) => "-",
+            _ => ".",
+        };
+        let gff_record = |feature_type: &str, range: &Range<u64>, frame: &str| {
+            let mut record = gff::Record::new();
+            *record.seqname_mut() = self.chrom.clone();
+            *record.source_mut() = ".".to_owned();
+            *record.feature_type_mut() = feature_type.to_owned();
+            *record.start_mut() = range.start + 1;
+            *record.end_mut() = range.end;
+            *record.strand_mut() = strand.to_owned();
+            *record.frame_mut() = frame.to_owned();
+            record
+        };
+
+        let feature_type = if thick.start < thick.end {
+            "mRNA"
+        } else {
+            "transcript"
+        };
+        let mut transcript = gff_record(feature_type, &(self.start..self.end), ".");
+        transcript
+            .attributes_mut()
+            .insert("ID".to_owned(), id.to_owned());
+        let mut records = vec![transcript];
+        for block in &blocks {
+            records.push(gff_record("exon", block, "."));
+        }
+        let cds: Vec<Range<u64>> = blocks
+            .iter()
+            .map(|b| b.start.max(thick.start)..b.end.min(thick.end))
+            .filter(|b| b.start < b.end)
+            .collect();
+        for (range, phase) in cds.iter().zip(phases(&cds, strand == "-")) {
+            records.push(gff_record("CDS", range, &phase.to_string()));
+        }
+        for record in &mut records[1..] {
+            record
+                .attributes_mut()
+                .insert("Parent".to_owned(), id.to_owned());
+        }
+        Ok(records)
+    }
+}
+
+/// Compute the phases of the given sorted CDS segments, assuming the first one in
+/// transcription order starts with a complete codon.
+fn phases(cds: &[Range<u64>], reverse: bool) -> Vec<u64> {
+    let mut phases = vec![0; cds.len()];
+    let mut len = 0;
+    let order: Vec<usize> = if reverse {
+        (0..cds.len()).rev().collect()
+    } else {
+        (0..cds.len()).collect()
+    };
+    for i in order {
+        phases[i] = (3 - len % 3) % 3;
+        len += cds[i].end - cds[i].start;
+    }
+    phases
+}
+
+impl From<&Transcript> for Record {
+    /// Returns a BED12 record for a transcript model, e.g. from a GFF file. For non-coding
+    /// transcripts, the thick region is empty and placed at the end of the feature.
+    fn from(transcript: &Transcript) -> Self {
+        let mut bed = Record::new();
+        bed.set_chrom(transcript.seqname());
+        bed.set_name(transcript.id());
+        bed.set_score("0");
+        bed.push_aux(
+            transcript
+                .strand()
+                .unwrap_or(strand::Strand::Unknown)
+                .strand_symbol(),
+        );
+        let exons = transcript.exons();
+        let end = exons.last().map_or(0, |e| e.end);
+        let thick = match (transcript.cds().first(), transcript.cds().last()) {
+            (Some(first), Some(last)) => first.start..last.end,
+            _ => end..end,
+        };
+        bed.set_bed12(thick, Rgb::default(), exons);
+        bed
+    }
 }
 
 impl<'a> From<&'a Record> for annot::contig::Contig<String, strand::Strand> {
@@ -238,7 +690,7 @@ impl<'a> From<&'a Record> for annot::contig::Contig<String, strand::Strand> {
     ///     .records()
     ///     .next()
     ///     .expect("Found no bed record.")
-    ///     .expect("Got a csv::Error");
+    ///     .expect("Got a bed::Error");
     /// let loc = Contig::from(&rec);
     /// assert_eq!(loc.to_string(), "chr1:5-5000");
     /// ```
@@ -382,6 +834,137 @@ where
     }
 }
 
+/// A peak in the ENCODE narrowPeak format (BED6+4).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NarrowPeak {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+    /// Score in `0..=1000`.
+    pub score: u16,
+    pub strand: Option<strand::Strand>,
+    /// Overall enrichment of the region.
+    pub signal_value: f64,
+    /// -log10 p-value, -1 if not available.
+    pub p_value: f64,
+    /// -log10 q-value, -1 if not available.
+    pub q_value: f64,
+    /// Offset of the peak summit from the start, -1 if not available.
+    pub peak: i64,
+}
+
+/// A region in the ENCODE broadPeak format (BED6+3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadPeak {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+    /// Score in `0..=1000`.
+    pub score: u16,
+    pub strand: Option<strand::Strand>,
+    /// Overall enrichment of the region.
+    pub signal_value: f64,
+    /// -log10 p-value, -1 if not available.
+    pub p_value: f64,
+    /// -log10 q-value, -1 if not available.
+    pub q_value: f64,
+}
+
+/// Build the BED6 part of a peak record.
+fn peak_record(
+    chrom: &str,
+    start: u64,
+    end: u64,
+    name: &str,
+    score: u16,
+    strand: Option<strand::Strand>,
+) -> Record {
+    let mut bed = Record::new();
+    bed.set_chrom(chrom);
+    bed.set_start(start);
+    bed.set_end(end);
+    bed.set_name(name);
+    bed.set_score(&score.to_string());
+    bed.push_aux(strand.unwrap_or(strand::Strand::Unknown).strand_symbol());
+    bed
+}
+
+impl TryFrom<&Record> for NarrowPeak {
+    type Error = Error;
+
+    fn try_from(record: &Record) -> Result<Self> {
+        record.validate_bed6()?;
+        Ok(NarrowPeak {
+            chrom: record.chrom.clone(),
+            start: record.start,
+            end: record.end,
+            name: record.name().ok_or(Error::MissingField("name"))?.to_owned(),
+            score: parse_field("score", record.score())?,
+            strand: record.strand(),
+            signal_value: parse_field("signalValue", record.aux(6))?,
+            p_value: parse_field("pValue", record.aux(7))?,
+            q_value: parse_field("qValue", record.aux(8))?,
+            peak: parse_field("peak", record.aux(9))?,
+        })
+    }
+}
+
+impl From<&NarrowPeak> for Record {
+    fn from(peak: &NarrowPeak) -> Self {
+        let mut bed = peak_record(
+            &peak.chrom,
+            peak.start,
+            peak.end,
+            &peak.name,
+            peak.score,
+            peak.strand,
+        );
+        bed.push_aux(&peak.signal_value.to_string());
+        bed.push_aux(&peak.p_value.to_string());
+        bed.push_aux(&peak.q_value.to_string());
+        bed.push_aux(&peak.peak.to_string());
+        bed
+    }
+}
+
+impl TryFrom<&Record> for BroadPeak {
+    type Error = Error;
+
+    fn try_from(record: &Record) -> Result<Self> {
+        record.validate_bed6()?;
+        Ok(BroadPeak {
+            chrom: record.chrom.clone(),
+            start: record.start,
+            end: record.end,
+            name: record.name().ok_or(Error::MissingField("name"))?.to_owned(),
+            score: parse_field("score", record.score())?,
+            strand: record.strand(),
+            signal_value: parse_field("signalValue", record.aux(6))?,
+            p_value: parse_field("pValue", record.aux(7))?,
+            q_value: parse_field("qValue", record.aux(8))?,
+        })
+    }
+}
+
+impl From<&BroadPeak> for Record {
+    fn from(peak: &BroadPeak) -> Self {
+        let mut bed = peak_record(
+            &peak.chrom,
+            peak.start,
+            peak.end,
+            &peak.name,
+            peak.score,
+            peak.strand,
+        );
+        bed.push_aux(&peak.signal_value.to_string());
+        bed.push_aux(&peak.p_value.to_string());
+        bed.push_aux(&peak.q_value.to_string());
+        bed
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -428,6 +1011,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reader_errors() {
+        let read = |bed: &[u8]| Reader::new(bed).records().collect::<Result<Vec<_>>>();
+        assert!(matches!(
+            read(b"chr1\t0\t10\nchr1\t20\t10\n"),
+            Err(Error::InvalidCoordinates {
+                line: 2,
+                offset: 10,
+                start: 20,
+                end: 10
+            })
+        ));
+        assert!(matches!(
+            read(b"chr1\t0\t10\tx\t0\t+\nchr1\t0\t10\tx\t0\tup\n"),
+            Err(Error::InvalidStrand {
+                line: 2,
+                offset: 16,
+                strand
+            }) if strand == "up"
+        ));
+        assert!(matches!(
+            read(b"chr1\t0\n"),
+            Err(Error::TruncatedRecord {
+                line: 1,
+                offset: 0,
+                fields: 2
+            })
+        ));
+        assert!(matches!(
+            read(b"chr1\t0\t10\nchr1\t5\n"),
+            Err(Error::TruncatedRecord {
+                line: 2,
+                offset: 10,
+                fields: 2
+            })
+        ));
+        let err = read(b"chr1\t0\t10\nchr1\tx\t10\n").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Format {
+                line: 2,
+                offset: 10,
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("line 2 (byte 10): "));
+    }
+
+    #[test]
+    fn test_reader_format_error() {
+        let mut reader = Reader::new(&b"chr1\t0\t10\nchr1\tx\t10\n"[..]);
+        let mut records = reader.records();
+        assert!(records.next().unwrap().is_ok());
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::Format { line: 2, .. })
+        ));
+    }
+
     #[test]
     fn test_reader_from_file_path_doesnt_exist_returns_err() {
         let path = Path::new("/I/dont/exist.bed");
@@ -439,6 +1081,26 @@ mod tests {
         assert_eq!(&error, "Failed to read bed from \"/I/dont/exist.bed\"")
     }
 
+    #[test]
+    fn test_reader_from_bgzf_file() {
+        use crate::io::compression::{Encoder, Format};
+        use std::io::Write;
+
+        let file = tempfile::NamedTempFile::new().expect("Could not create temp file");
+        {
+            let mut encoder = Encoder::new(fs::File::create(file.path()).unwrap(), Format::Bgzf);
+            encoder.write_all(BED_FILE).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        let mut reader = Reader::from_file(file.path()).unwrap();
+        let chroms: Vec<String> = reader
+            .records()
+            .map(|r| r.unwrap().chrom().to_owned())
+            .collect();
+        assert_eq!(chroms, vec!["1", "2"]);
+    }
+
     #[test]
     fn test_writer() {
         let mut reader = Reader::new(BED_FILE);
@@ -547,4 +1209,160 @@ mod tests {
         assert_eq!(record.score(), Some("0"));
         assert_eq!(record.strand(), Some(Strand::Reverse));
     }
+
+    const BED12_FILE: &[u8] =
+        b"chr1\t100\t1000\ttx1\t0\t-\t150\t900\t255,0,0\t3\t100,200,300,\t0,300,600,
+chr1\t100\t200\ttx2\t0\t+\t200\t200\t0\t1\t100,\t0,
+";
+
+    #[test]
+    fn test_bed12() {
+        let mut reader = Reader::new(BED12_FILE);
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        let record = &records[0];
+        assert_eq!(record.thick_start(), Some(150));
+        assert_eq!(record.thick_end(), Some(900));
+        assert_eq!(record.item_rgb(), Some(Rgb(255, 0, 0)));
+        assert_eq!(record.block_count(), Some(3));
+        assert_eq!(
+            record.blocks().unwrap(),
+            vec![100..200, 400..600, 700..1000]
+        );
+        assert!(record.validate().is_ok());
+        assert_eq!(records[1].item_rgb(), Some(Rgb(0, 0, 0)));
+        assert_eq!(records[1].blocks().unwrap(), vec![100..200]);
+
+        let mut rebuilt = Record::new();
+        rebuilt.set_chrom("chr1");
+        rebuilt.set_name("tx1");
+        rebuilt.set_score("0");
+        rebuilt.push_aux("-");
+        rebuilt.set_bed12(150..900, Rgb(255, 0, 0), &[100..200, 400..600, 700..1000]);
+        let mut writer = Writer::new(vec![]);
+        writer.write(&rebuilt).unwrap();
+        assert_eq!(
+            writer.inner.into_inner().unwrap(),
+            BED12_FILE
+                .split(|&b| b == b'\n')
+                .next()
+                .unwrap()
+                .iter()
+                .chain(b"\n")
+                .copied()
+                .collect::<Vec<_>>()
+        );
+
+        // records with less than 12 fields consist of a single block
+        let mut reader = Reader::new(BED_FILE);
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(record.blocks().unwrap(), vec![5..5000]);
+    }
+
+    #[test]
+    fn test_bed12_validation() {
+        let invalid = |line: &[u8]| {
+            let mut reader = Reader::new(line);
+            reader.records().next().unwrap().and_then(|r| r.validate())
+        };
+        assert!(matches!(
+            invalid(b"chr1\t100\t1000\ttx\t0\t-\t150\t900\t0\t2\t100,200\t0,300,600"),
+            Err(Error::InvalidBlocks(_))
+        ));
+        assert!(matches!(
+            invalid(b"chr1\t100\t1000\ttx\t0\t-\t150\t900\t0\t2\t100,200\t0,300"),
+            Err(Error::InvalidBlocks(_))
+        ));
+        assert!(matches!(
+            invalid(b"chr1\t100\t1000\ttx\t0\t-\t150\t900\t0\t2\t500,600\t0,300"),
+            Err(Error::InvalidBlocks(_))
+        ));
+        assert!(matches!(
+            invalid(b"chr1\t100\t1000\ttx\t0\t-\t50\t900\t0"),
+            Err(Error::InvalidField {
+                field: "thickStart",
+                value
+            }) if value == "50"
+        ));
+        assert!(invalid(b"chr1\t100\t1000\ttx\t0\t-\t150\t900\t1,2").is_err());
+        assert!(invalid(b"chr1\t100\t1000\ttx\t1001\t-").is_err());
+        assert!(invalid(b"chr1\t100\t1000\ttx\t0\tx").is_err());
+        assert!(invalid(b"chr1\t100\t1000\ttx\t0\t+\t100\t100").is_ok());
+    }
+
+    #[test]
+    fn test_bed12_gff_conversion() {
+        let mut reader = Reader::new(BED12_FILE);
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        let gff_records = records[0].to_gff().unwrap();
+        let summary: Vec<_> = gff_records
+            .iter()
+            .map(|r| (r.feature_type(), *r.start(), *r.end(), r.frame()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("mRNA", 101, 1000, "."),
+                ("exon", 101, 200, "."),
+                ("exon", 401, 600, "."),
+                ("exon", 701, 1000, "."),
+                ("CDS", 151, 200, "2"),
+                ("CDS", 401, 600, "1"),
+                ("CDS", 701, 900, "0"),
+            ]
+        );
+        assert_eq!(gff_records[0].attributes().get("ID").unwrap(), "tx1");
+        assert_eq!(gff_records[4].attributes().get("Parent").unwrap(), "tx1");
+        assert_eq!(gff_records[4].strand(), Some(strand::Strand::Reverse));
+        assert_eq!(records[1].to_gff().unwrap()[0].feature_type(), "transcript");
+
+        // and back
+        let mut gff = gff_records;
+        gff.extend(records[1].to_gff().unwrap());
+        let tree =
+            gff::FeatureTree::from_records(gff.into_iter().map(Ok), gff::GffType::GFF3).unwrap();
+        let transcripts = Transcript::all(&tree);
+        assert_eq!(transcripts[0].phase(), 0);
+        let mut bed = Record::from(&transcripts[0]);
+        assert_eq!(bed.item_rgb(), Some(Rgb(0, 0, 0)));
+        bed.aux[5] = "255,0,0".to_owned();
+        assert_eq!(bed.aux, records[0].aux);
+        assert_eq!((bed.start(), bed.end()), (100, 1000));
+        assert_eq!(Record::from(&transcripts[1]).aux, records[1].aux);
+    }
+
+    #[test]
+    fn test_peaks() {
+        let narrow = b"chr1\t9356548\t9356648\tpeak1\t0\t.\t182\t5.0945\t-1\t50\n";
+        let mut reader = Reader::new(&narrow[..]);
+        let record = reader.records().next().unwrap().unwrap();
+        let peak = NarrowPeak::try_from(&record).unwrap();
+        assert_eq!(peak.name, "peak1");
+        assert_eq!(peak.strand, None);
+        assert_eq!(peak.signal_value, 182.0);
+        assert_eq!(peak.p_value, 5.0945);
+        assert_eq!(peak.q_value, -1.0);
+        assert_eq!(peak.peak, 50);
+        let mut writer = Writer::new(vec![]);
+        writer.write(&Record::from(&peak)).unwrap();
+        assert_eq!(writer.inner.into_inner().unwrap(), &narrow[..]);
+
+        let broad = b"chr1\t100\t2000\tregion1\t500\t+\t3.5\t10\t8.25\n";
+        let mut reader = Reader::new(&broad[..]);
+        let record = reader.records().next().unwrap().unwrap();
+        let peak = BroadPeak::try_from(&record).unwrap();
+        assert_eq!(peak.score, 500);
+        assert_eq!(peak.strand, Some(strand::Strand::Forward));
+        assert_eq!(peak.q_value, 8.25);
+        let mut writer = Writer::new(vec![]);
+        writer.write(&Record::from(&peak)).unwrap();
+        assert_eq!(writer.inner.into_inner().unwrap(), &broad[..]);
+
+        assert!(matches!(
+            NarrowPeak::try_from(&record),
+            Err(Error::MissingField("peak"))
+        ));
+        let mut reader = Reader::new(BED_FILE);
+        let record = reader.records().next().unwrap().unwrap();
+        assert!(BroadPeak::try_from(&record).is_err());
+    }
 }
@@ -0,0 +1,533 @@
+//! Reading and writing of quantitative signal tracks in the [bedGraph] format.
+//!
+//! Records are intervals with a value, given in 0-based, half-open coordinates. Track and
+//! browser lines are collected while reading.
+//!
+//! [bedGraph]: https://genome.ucsc.edu/goldenPath/help/bedgraph.html
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::bedgraph;
+//!
+//! let mut writer = bedgraph::Writer::new(Vec::new());
+//! writer
+//!     .write_coverage("chr1", &[0u32, 0, 3, 3, 3, 1, 0])
+//!     .unwrap();
+//! writer.flush().unwrap();
+//!
+//! let output = writer.into_inner().unwrap();
+//! assert_eq!(output, b"chr1\t2\t5\t3\nchr1\t5\t6\t1\n");
+//! let reader = bedgraph::Reader::new(&output[..]);
+//! let values: Vec<f64> = reader.records().map(|r| r.unwrap().value()).collect();
+//! assert_eq!(values, vec![3.0, 1.0]);
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::io::compression::Decoder;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A track definition line, e.g. `track type=bedGraph name="coverage" visibility=full`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Track {
+    /// The attributes in the order given, with quotes removed.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Track {
+    /// Create a track line with the given type, e.g. `bedGraph` or `wiggle_0`.
+    pub fn new(kind: &str) -> Self {
+        let mut track = Track::default();
+        track.set("type", kind);
+        track
+    }
+
+    /// Return the value of the given attribute.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the given attribute, replacing an existing value.
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(k, _)| k == key) {
+            Some(attribute) => attribute.1 = value.to_owned(),
+            None => self.attributes.push((key.to_owned(), value.to_owned())),
+        }
+    }
+}
+
+impl FromStr for Track {
+    type Err = String;
+
+    /// Parse a track line including the leading `track`.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut rest = line
+            .strip_prefix("track")
+            .ok_or_else(|| format!("'{}' is not a track line", line))?
+            .trim_start();
+        let mut track = Track::default();
+        while !rest.is_empty() {
+            let eq = rest
+                .find('=')
+                .ok_or_else(|| format!("missing '=' in track attribute '{}'", rest))?;
+            let key = rest[..eq].trim();
+            rest = &rest[eq + 1..];
+            let value = if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted
+                    .find('"')
+                    .ok_or_else(|| format!("unterminated quote in track line '{}'", line))?;
+                rest = &quoted[end + 1..];
+                &quoted[..end]
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            };
+            track.attributes.push((key.to_owned(), value.to_owned()));
+            rest = rest.trim_start();
+        }
+        Ok(track)
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "track")?;
+        for (key, value) in &self.attributes {
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                write!(f, " {}=\"{}\"", key, value)?;
+            } else {
+                write!(f, " {}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An interval of a signal track with its value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    chrom: String,
+    start: u64,
+    end: u64,
+    value: f64,
+}
+
+impl Record {
+    /// Create a new record.
+    pub fn new(chrom: &str, start: u64, end: u64, value: f64) -> Self {
+        Record {
+            chrom: chrom.to_owned(),
+            start,
+            end,
+            value,
+        }
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chrom.is_empty()
+    }
+
+    /// Chromosome of the interval.
+    pub fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    /// Start position of the interval (0-based).
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// End position of the interval (0-based, not included).
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Value of the interval.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Get mutable reference on the chromosome.
+    pub fn chrom_mut(&mut self) -> &mut String {
+        &mut self.chrom
+    }
+
+    /// Get mutable reference on the start position.
+    pub fn start_mut(&mut self) -> &mut u64 {
+        &mut self.start
+    }
+
+    /// Get mutable reference on the end position.
+    pub fn end_mut(&mut self) -> &mut u64 {
+        &mut self.end
+    }
+
+    /// Get mutable reference on the value.
+    pub fn value_mut(&mut self) -> &mut f64 {
+        &mut self.value
+    }
+}
+
+impl fmt::Display for Record {
+    /// Format the record as bedGraph line without line terminator.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.chrom, self.start, self.end, self.value
+        )
+    }
+}
+
+/// Iterate over the runs of equal, non-zero values of the given per-base coverage as
+/// 0-based, half-open intervals and their value. Values are compared by their bits, such
+/// that consecutive NaNs form a run as well.
+pub(crate) fn runs<T>(coverage: &[T]) -> impl Iterator<Item = (u64, u64, f64)> + '_
+where
+    T: Copy + Into<f64>,
+{
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos < coverage.len() {
+            let value: f64 = coverage[pos].into();
+            let start = pos;
+            while pos < coverage.len() && coverage[pos].into().to_bits() == value.to_bits() {
+                pos += 1;
+            }
+            if value != 0.0 {
+                return Some((start as u64, pos as u64, value));
+            }
+        }
+        None
+    })
+}
+
+/// A bedGraph reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    track: Option<Track>,
+    browser_lines: Vec<String>,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read bedGraph from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            track: None,
+            browser_lines: Vec::new(),
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// The last track line read so far.
+    pub fn track(&self) -> Option<&Track> {
+        self.track.as_ref()
+    }
+
+    /// The browser lines read so far, without the leading `browser`.
+    pub fn browser_lines(&self) -> &[String] {
+        &self.browser_lines
+    }
+
+    /// Read the next record into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line does not consist of four tab or space
+    /// separated fields, or a track line is malformed.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                *record = Record::default();
+                return Ok(());
+            }
+            self.line_no += 1;
+            let line = self.line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let format_error = |message: String| Error::Format {
+                line: self.line_no,
+                message,
+            };
+            if let Some(browser) = line.strip_prefix("browser") {
+                self.browser_lines.push(browser.trim().to_owned());
+                continue;
+            }
+            if line.starts_with("track") {
+                self.track = Some(line.parse().map_err(format_error)?);
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(format_error(format!(
+                    "expected 4 fields, found {}",
+                    fields.len()
+                )));
+            }
+            let parse_pos = |field: &str| {
+                field
+                    .parse()
+                    .map_err(|_| format_error(format!("invalid position '{}'", field)))
+            };
+            *record = Record {
+                chrom: fields[0].to_owned(),
+                start: parse_pos(fields[1])?,
+                end: parse_pos(fields[2])?,
+                value: fields[3]
+                    .parse()
+                    .map_err(|_| format_error(format!("invalid value '{}'", fields[3])))?,
+            };
+            return Ok(());
+        }
+    }
+
+    /// Return an iterator over the records of this bedGraph file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the records of a bedGraph file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::default();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A bedGraph writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write a track line.
+    pub fn write_track(&mut self, track: &Track) -> io::Result<()> {
+        writeln!(self.writer, "{}", track)
+    }
+
+    /// Write a browser line, given without the leading `browser`.
+    pub fn write_browser_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "browser {}", line)
+    }
+
+    /// Write a record.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Write the given per-base coverage of a chromosome, starting at position 0.
+    /// Runs of equal values are merged into one record, runs of zeros are omitted.
+    pub fn write_coverage<T>(&mut self, chrom: &str, coverage: &[T]) -> io::Result<()>
+    where
+        T: Copy + Into<f64>,
+    {
+        for (start, end, value) in runs(coverage) {
+            writeln!(self.writer, "{}\t{}\t{}\t{}", chrom, start, end, value)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEDGRAPH_FILE: &[u8] = b"browser position chr19:49302001-49304701
+browser hide all
+track type=bedGraph name=\"BedGraph Format\" description=\"BedGraph format\" visibility=full
+# a comment
+chr19\t49302000\t49302300\t-1.0
+chr19 49302300 49302600 -0.75
+
+chr19\t49302600\t49302900\t0.5
+";
+
+    #[test]
+    fn test_read() {
+        let mut reader = Reader::new(BEDGRAPH_FILE);
+        let mut record = Record::default();
+        reader.read(&mut record).unwrap();
+        assert_eq!(record, Record::new("chr19", 49302000, 49302300, -1.0));
+        assert_eq!(
+            reader.browser_lines(),
+            &["position chr19:49302001-49304701", "hide all"]
+        );
+        let track = reader.track().unwrap();
+        assert_eq!(track.get("type"), Some("bedGraph"));
+        assert_eq!(track.get("name"), Some("BedGraph Format"));
+        assert_eq!(track.get("visibility"), Some("full"));
+
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value(), -0.75);
+        assert_eq!(records[1].start(), 49302600);
+        assert_eq!(records[1].end(), 49302900);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let reader = Reader::new(&b"chr1\t0\t10\n"[..]);
+        assert!(matches!(
+            reader.records().next().unwrap(),
+            Err(Error::Format { line: 1, .. })
+        ));
+        let reader = Reader::new(&b"track name=x\nchr1\t0\tx\t1\n"[..]);
+        assert!(matches!(
+            reader.records().next().unwrap(),
+            Err(Error::Format { line: 2, .. })
+        ));
+        let reader = Reader::new(&b"track name=\"x\n"[..]);
+        assert!(matches!(
+            reader.records().next().unwrap(),
+            Err(Error::Format { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_write() {
+        let mut track = Track::new("bedGraph");
+        track.set("name", "my coverage");
+        let mut writer = Writer::new(Vec::new());
+        writer.write_browser_line("hide all").unwrap();
+        writer.write_track(&track).unwrap();
+        writer.write(&Record::new("chr1", 0, 10, 2.5)).unwrap();
+        writer
+            .write_coverage("chr2", &[1u8, 1, 0, 2, 2, 2])
+            .unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "browser hide all
+track type=bedGraph name=\"my coverage\"
+chr1\t0\t10\t2.5
+chr2\t0\t2\t1
+chr2\t3\t6\t2
+"
+        );
+
+        let reader = Reader::new(&output[..]);
+        assert_eq!(reader.records().count(), 3);
+    }
+
+    #[test]
+    fn test_track_roundtrip() {
+        let line = "track type=wiggle_0 name=\"a b\" color=0,0,255 description=\"\"";
+        let track: Track = line.parse().unwrap();
+        assert_eq!(track.attributes.len(), 4);
+        assert_eq!(track.get("description"), Some(""));
+        assert_eq!(track.to_string(), line);
+    }
+
+    #[test]
+    fn test_runs() {
+        let coverage = [0.0, 0.5, 0.5, 0.0, 0.0, 1.0];
+        assert_eq!(
+            runs(&coverage).collect::<Vec<_>>(),
+            vec![(1, 3, 0.5), (5, 6, 1.0)]
+        );
+        assert_eq!(runs::<u32>(&[]).count(), 0);
+
+        let coverage = [1.0, f32::NAN, f32::NAN, 2.0];
+        let runs: Vec<_> = runs(&coverage).collect();
+        assert_eq!(runs.len(), 3);
+        assert_eq!((runs[1].0, runs[1].1), (1, 3));
+        assert!(runs[1].2.is_nan());
+        assert_eq!(runs[2], (3, 4, 2.0));
+    }
+}
@@ -0,0 +1,606 @@
+//! Reading and writing of the blocked gzip format (BGZF) as defined in the
+//! [SAM specification](https://samtools.github.io/hts-specs/SAMv1.pdf).
+//!
+//! A BGZF file is a series of concatenated gzip members, each holding at most 64 KiB of
+//! uncompressed data. Hence, it can be decompressed by any gzip implementation, but also allows
+//! random access when combined with a block index ([`GziIndex`], as created by `bgzip -i`).
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::bgzf;
+//! use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+//!
+//! let mut writer = bgzf::Writer::new(Vec::new());
+//! writer.write_all(b">chr1\nACGT\n").unwrap();
+//! let gzi = writer.index().clone();
+//! let compressed = writer.finish().unwrap();
+//! assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+//!
+//! let mut reader = bgzf::Reader::with_index(Cursor::new(compressed), gzi);
+//! reader.seek(SeekFrom::Start(6)).unwrap();
+//! let mut seq = String::new();
+//! reader.read_to_string(&mut seq).unwrap();
+//! assert_eq!(seq, "ACGT\n");
+//! ```
+
+use std::cmp::min;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use anyhow::Context;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+
+/// Maximum size of a BGZF block, including header and footer.
+pub const MAX_BLOCK_SIZE: usize = 0x10000;
+
+/// Maximum amount of uncompressed data stored in a single block. This leaves enough room for
+/// incompressible input to still fit into `MAX_BLOCK_SIZE` after deflating.
+const BLOCK_DATA_SIZE: usize = 0xff00;
+
+/// Size of the BGZF block header.
+const HEADER_SIZE: usize = 18;
+
+/// Size of the fixed part of a gzip member header, before the extra field.
+const GZIP_HEADER_SIZE: usize = 12;
+
+/// Size of the BGZF block footer (CRC32 and ISIZE).
+const FOOTER_SIZE: usize = 8;
+
+/// The empty block that marks the end of a BGZF file.
+pub const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Check whether the given bytes start with a BGZF block header.
+pub fn is_bgzf(bytes: &[u8]) -> bool {
+    bytes.len() >= 16
+        && bytes[0] == 0x1f
+        && bytes[1] == 0x8b
+        && bytes[2] == 0x08
+        && bytes[3] & 0x04 != 0
+        && bytes[12] == b'B'
+        && bytes[13] == b'C'
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "BGZF file is truncated.")
+}
+
+/// A BGZF block index (.gzi) as created by `bgzip -i` or `samtools faidx`.
+///
+/// It maps the uncompressed offset of each block to its compressed offset in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GziIndex {
+    /// Pairs of (compressed offset, uncompressed offset), sorted and starting with `(0, 0)`.
+    entries: Vec<(u64, u64)>,
+}
+
+impl Default for GziIndex {
+    fn default() -> Self {
+        GziIndex {
+            entries: vec![(0, 0)],
+        }
+    }
+}
+
+impl GziIndex {
+    /// Read a BGZF block index from a given `io::Read` instance.
+    pub fn new<R: io::Read>(mut gzi: R) -> io::Result<Self> {
+        let mut buf = [0; 8];
+        gzi.read_exact(&mut buf)?;
+        let n = u64::from_le_bytes(buf);
+
+        let mut entries = vec![(0, 0)];
+        for _ in 0..n {
+            gzi.read_exact(&mut buf)?;
+            let compressed = u64::from_le_bytes(buf);
+            gzi.read_exact(&mut buf)?;
+            let uncompressed = u64::from_le_bytes(buf);
+            entries.push((compressed, uncompressed));
+        }
+        if entries
+            .windows(2)
+            .any(|w| w[0].0 > w[1].0 || w[0].1 > w[1].1)
+        {
+            return Err(invalid_data("BGZF index entries are not sorted."));
+        }
+        Ok(GziIndex { entries })
+    }
+
+    /// Read a BGZF block index from a given file path.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        fs::File::open(&path)
+            .and_then(Self::new)
+            .with_context(|| format!("Failed to read BGZF index from {:#?}", path))
+    }
+
+    /// Read the BGZF block index of the given compressed file.
+    /// That is, for ref.fa.gz we expect ref.fa.gz.gzi.
+    pub fn with_bgzf_file<P: AsRef<Path>>(bgzf_path: &P) -> anyhow::Result<Self> {
+        let mut gzi_path = bgzf_path.as_ref().as_os_str().to_owned();
+        gzi_path.push(".gzi");
+
+        Self::from_file(&gzi_path)
+    }
+
+    /// Write the index in .gzi format.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&((self.entries.len() - 1) as u64).to_le_bytes())?;
+        for (compressed, uncompressed) in &self.entries[1..] {
+            writer.write_all(&compressed.to_le_bytes())?;
+            writer.write_all(&uncompressed.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Return the compressed and uncompressed offset of the block containing the given
+    /// uncompressed offset.
+    pub fn block(&self, uncompressed: u64) -> (u64, u64) {
+        match self
+            .entries
+            .binary_search_by_key(&uncompressed, |&(_, u)| u)
+        {
+            Ok(i) => self.entries[i],
+            // entries[0] is (0, 0), hence i > 0
+            Err(i) => self.entries[i - 1],
+        }
+    }
+
+    /// Translate an uncompressed offset into a BGZF virtual offset, i.e. the compressed offset
+    /// of the containing block shifted by 16 bits, combined with the offset within that block.
+    pub fn virtual_offset(&self, uncompressed: u64) -> u64 {
+        let (compressed, block_start) = self.block(uncompressed);
+        (compressed << 16) | (uncompressed - block_start)
+    }
+
+    fn push(&mut self, compressed: u64, uncompressed: u64) {
+        self.entries.push((compressed, uncompressed));
+    }
+}
+
+/// A BGZF reader. It implements `io::Read` on the uncompressed data and `io::Seek` on
+/// uncompressed offsets. With a [`GziIndex`], seeking jumps directly to the containing block.
+/// Without, blocks are decompressed from the start of the file (or the current block) until the
+/// requested offset is reached.
+#[derive(Debug)]
+pub struct Reader<R: io::Read> {
+    inner: R,
+    index: Option<GziIndex>,
+    block: Vec<u8>,
+    block_pos: usize,
+    /// Uncompressed offset of the start of the current block.
+    block_offset: u64,
+    buf: Vec<u8>,
+}
+
+impl Reader<fs::File> {
+    /// Read from a given file path. If present, the block index `<path>.gzi` is used for
+    /// seeking.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        let index = GziIndex::with_bgzf_file(path).ok();
+        fs::File::open(&path)
+            .map(|f| Reader {
+                index,
+                ..Reader::new(f)
+            })
+            .with_context(|| format!("Failed to read BGZF from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Create a new BGZF reader without block index.
+    pub fn new(reader: R) -> Self {
+        Reader {
+            inner: reader,
+            index: None,
+            block: Vec::with_capacity(MAX_BLOCK_SIZE),
+            block_pos: 0,
+            block_offset: 0,
+            buf: Vec::with_capacity(MAX_BLOCK_SIZE),
+        }
+    }
+
+    /// Create a new BGZF reader that uses the given block index for seeking.
+    pub fn with_index(reader: R, index: GziIndex) -> Self {
+        Reader {
+            index: Some(index),
+            ..Reader::new(reader)
+        }
+    }
+
+    /// The block index of this reader, if any.
+    pub fn index(&self) -> Option<&GziIndex> {
+        self.index.as_ref()
+    }
+
+    /// The current uncompressed offset.
+    pub fn position(&self) -> u64 {
+        self.block_offset + self.block_pos as u64
+    }
+
+    /// Read exactly `len` bytes into the internal buffer, or nothing at the end of the stream.
+    /// Returns whether bytes have been read.
+    fn read_exact_or_eof(&mut self, len: usize) -> io::Result<bool> {
+        self.buf.resize(len, 0);
+        let mut filled = 0;
+        while filled < len {
+            match self.inner.read(&mut self.buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(truncated()),
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Decompress the next block into the block buffer. Returns `false` at the end of the
+    /// stream. Empty blocks (like the EOF marker) are loaded as well.
+    fn read_block(&mut self) -> io::Result<bool> {
+        self.block_offset += self.block.len() as u64;
+        self.block.clear();
+        self.block_pos = 0;
+
+        if !self.read_exact_or_eof(GZIP_HEADER_SIZE)? {
+            return Ok(false);
+        }
+        if self.buf[0] != 0x1f || self.buf[1] != 0x8b || self.buf[2] != 0x08 {
+            return Err(invalid_data("Invalid BGZF block header."));
+        }
+        if self.buf[3] & 0x04 == 0 {
+            return Err(invalid_data("BGZF block header lacks extra field."));
+        }
+        let xlen = u16::from_le_bytes([self.buf[10], self.buf[11]]) as usize;
+
+        if !self.read_exact_or_eof(xlen)? {
+            return Err(truncated());
+        }
+        let mut bsize = None;
+        let mut extra = &self.buf[..];
+        while extra.len() >= 4 {
+            let slen = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+            if extra[0] == b'B' && extra[1] == b'C' && slen == 2 && extra.len() >= 6 {
+                bsize = Some(u16::from_le_bytes([extra[4], extra[5]]) as usize + 1);
+            }
+            extra = &extra[min(4 + slen, extra.len())..];
+        }
+        let bsize = bsize.ok_or_else(|| invalid_data("BGZF block size field missing."))?;
+        if bsize < GZIP_HEADER_SIZE + xlen + FOOTER_SIZE {
+            return Err(invalid_data("Invalid BGZF block size."));
+        }
+
+        if !self.read_exact_or_eof(bsize - GZIP_HEADER_SIZE - xlen)? {
+            return Err(truncated());
+        }
+        let cdata_len = self.buf.len() - FOOTER_SIZE;
+        let footer = &self.buf[cdata_len..];
+        let crc32 = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+        let data_len = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]) as usize;
+
+        DeflateDecoder::new(&self.buf[..cdata_len]).read_to_end(&mut self.block)?;
+        if self.block.len() != data_len {
+            return Err(invalid_data("BGZF block size does not match its content."));
+        }
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if crc.sum() != crc32 {
+            return Err(invalid_data("BGZF block checksum mismatch."));
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: io::Read> io::Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.block_pos >= self.block.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+        let n = min(buf.len(), self.block.len() - self.block_pos);
+        buf[..n].copy_from_slice(&self.block[self.block_pos..self.block_pos + n]);
+        self.block_pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: io::Read + io::Seek> io::Seek for Reader<R> {
+    /// Seek to an uncompressed offset. Seeking relative to the end is not supported.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            io::SeekFrom::Start(offset) => offset,
+            io::SeekFrom::Current(delta) => {
+                let target = self.position() as i64 + delta;
+                if target < 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Invalid seek to a negative position.",
+                    ));
+                }
+                target as u64
+            }
+            io::SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Seeking relative to the end of a BGZF file is not supported.",
+                ))
+            }
+        };
+
+        let block_end = self.block_offset + self.block.len() as u64;
+        if target < self.block_offset || target >= block_end {
+            // Restart from the closest known block, unless the target lies ahead and no index
+            // is available. In that case, just keep decompressing from the current block.
+            let restart = match &self.index {
+                Some(index) => {
+                    let (compressed, uncompressed) = index.block(target);
+                    if uncompressed > self.block_offset || target < self.block_offset {
+                        Some((compressed, uncompressed))
+                    } else {
+                        None
+                    }
+                }
+                None if target < self.block_offset => Some((0, 0)),
+                None => None,
+            };
+            if let Some((compressed, uncompressed)) = restart {
+                self.inner.seek(io::SeekFrom::Start(compressed))?;
+                self.block.clear();
+                self.block_offset = uncompressed;
+            }
+            while target >= self.block_offset + self.block.len() as u64 {
+                if !self.read_block()? {
+                    break;
+                }
+            }
+        }
+        self.block_pos = min(target - self.block_offset, self.block.len() as u64) as usize;
+
+        Ok(target)
+    }
+}
+
+/// A BGZF writer. Data is buffered until a block is full, then compressed and written.
+///
+/// The end-of-file marker is written by [`finish`](Writer::finish), or when the writer is
+/// dropped.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    inner: Option<W>,
+    buffer: Vec<u8>,
+    level: flate2::Compression,
+    index: GziIndex,
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Create a new BGZF writer with the default compression level.
+    pub fn new(writer: W) -> Self {
+        Self::with_level(writer, flate2::Compression::default())
+    }
+
+    /// Create a new BGZF writer with the given compression level.
+    pub fn with_level(writer: W, level: flate2::Compression) -> Self {
+        Writer {
+            inner: Some(writer),
+            buffer: Vec::with_capacity(BLOCK_DATA_SIZE),
+            level,
+            index: GziIndex::default(),
+            compressed_offset: 0,
+            uncompressed_offset: 0,
+        }
+    }
+
+    /// Return a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// The block index of all blocks written so far. Call [`flush`](io::Write::flush) first
+    /// to include the currently buffered data.
+    pub fn index(&self) -> &GziIndex {
+        &self.index
+    }
+
+    /// Compress and write all buffered data, append the end-of-file marker and return the
+    /// underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn try_finish(&mut self) -> io::Result<()> {
+        if self.inner.is_some() {
+            self.write_block()?;
+            let inner = self.inner.as_mut().unwrap();
+            inner.write_all(&EOF_BLOCK)?;
+            inner.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Compress the buffered data into a single block and write it.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::with_capacity(MAX_BLOCK_SIZE), self.level);
+        encoder.write_all(&self.buffer)?;
+        let cdata = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+
+        let block_size = HEADER_SIZE + cdata.len() + FOOTER_SIZE;
+        if block_size > MAX_BLOCK_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "BGZF block exceeds maximum block size.",
+            ));
+        }
+        let bsize = ((block_size - 1) as u16).to_le_bytes();
+        let header = [
+            0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, b'B', b'C',
+            0x02, 0x00, bsize[0], bsize[1],
+        ];
+
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&header)?;
+        inner.write_all(&cdata)?;
+        inner.write_all(&crc.sum().to_le_bytes())?;
+        inner.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+
+        self.compressed_offset += block_size as u64;
+        self.uncompressed_offset += self.buffer.len() as u64;
+        self.index
+            .push(self.compressed_offset, self.uncompressed_offset);
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+impl<W: io::Write> io::Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = min(BLOCK_DATA_SIZE - self.buffer.len(), buf.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == BLOCK_DATA_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    /// Flush the buffered data as a (possibly short) block.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: io::Write> Drop for Writer<W> {
+    fn drop(&mut self) {
+        let _ = self.try_finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+
+    fn data() -> Vec<u8> {
+        (0..200_000u32)
+            .map(|i| b"ACGT"[(i % 7 % 4) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_writer_roundtrip() {
+        let data = data();
+        let mut writer = Writer::new(Vec::new());
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        assert!(is_bgzf(&compressed));
+        assert!(compressed.ends_with(&EOF_BLOCK));
+
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_writer_empty() {
+        let writer = Writer::new(Vec::new());
+        assert_eq!(writer.finish().unwrap(), EOF_BLOCK.to_vec());
+    }
+
+    #[test]
+    fn test_reader() {
+        let data = data();
+        let mut writer = Writer::new(Vec::new());
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut decompressed = Vec::new();
+        Reader::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_all(&data()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut decompressed = Vec::new();
+        assert!(Reader::new(&compressed[..100])
+            .read_to_end(&mut decompressed)
+            .is_err());
+    }
+
+    #[test]
+    fn test_index_roundtrip() {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_all(&data()).unwrap();
+        writer.flush().unwrap();
+        let index = writer.index().clone();
+        // 200_000 bytes make up three full blocks and one partial block
+        assert_eq!(index.entries.len(), 5);
+
+        let mut gzi = Vec::new();
+        index.write(&mut gzi).unwrap();
+        assert_eq!(gzi.len(), 8 + 4 * 16);
+        assert_eq!(GziIndex::new(&gzi[..]).unwrap(), index);
+
+        let (compressed, uncompressed) = index.block(BLOCK_DATA_SIZE as u64 + 10);
+        assert_eq!(uncompressed, BLOCK_DATA_SIZE as u64);
+        assert_eq!(
+            index.virtual_offset(BLOCK_DATA_SIZE as u64 + 10),
+            compressed << 16 | 10
+        );
+    }
+
+    #[test]
+    fn test_reader_seek() {
+        let data = data();
+        let mut writer = Writer::new(Vec::new());
+        writer.write_all(&data).unwrap();
+        writer.flush().unwrap();
+        let index = writer.index().clone();
+        let compressed = writer.finish().unwrap();
+
+        let mut indexed = Reader::with_index(io::Cursor::new(&compressed), index);
+        let mut unindexed = Reader::new(io::Cursor::new(&compressed));
+        let mut buf = [0; 100];
+        for &offset in &[150_000, 10, 65_270, 130_000, 199_950] {
+            for reader in &mut [&mut indexed, &mut unindexed] {
+                assert_eq!(reader.seek(io::SeekFrom::Start(offset)).unwrap(), offset);
+                let n = reader.read(&mut buf).unwrap();
+                assert!(n > 0);
+                assert_eq!(&buf[..n], &data[offset as usize..offset as usize + n]);
+            }
+        }
+
+        indexed.seek(io::SeekFrom::Start(199_990)).unwrap();
+        let mut rest = Vec::new();
+        indexed.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, &data[199_990..]);
+    }
+}
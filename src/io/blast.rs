@@ -0,0 +1,736 @@
+//! Reading and writing of BLAST tabular output (`-outfmt 6` and `-outfmt 7`).
+//!
+//! The columns of a file are described by a list of [`Field`](enum.Field.html)s. By
+//! default, the twelve standard columns of BLAST+ are assumed. For the commented format
+//! (`-outfmt 7`), the `# Fields:` comment lines define the columns of the hits that follow
+//! them, so that custom formats like `-outfmt "7 std qlen slen"` are read without further
+//! configuration. For `-outfmt 6` with custom columns, set them with
+//! [`Reader::fields`](struct.Reader.html#method.fields).
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::blast;
+//!
+//! let input = b"# BLASTN 2.12.0+
+//! ## Query: q1
+//! ## Database: nt
+//! ## Fields: query acc.ver, subject acc.ver, % identity, alignment length, mismatches, gap opens, q. start, q. end, s. start, s. end, evalue, bit score, query length
+//! ## 1 hits found
+//! q1\ts1\t98.50\t200\t3\t0\t1\t200\t1001\t1200\t1.2e-95\t350\t250
+//! ";
+//! let reader = blast::Reader::new(&input[..]);
+//! for record in reader.records() {
+//!     let record = record.unwrap();
+//!     assert_eq!(record.subject(), "s1");
+//!     assert_eq!(record.evalue(), 1.2e-95);
+//!     assert_eq!(record.get(&blast::Field::QueryLength), Some("250"));
+//! }
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::io::compression::Decoder;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A column of BLAST tabular output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Field {
+    QuerySeqId,
+    QueryAccVer,
+    SubjectSeqId,
+    SubjectAccVer,
+    PercentIdentity,
+    Length,
+    Mismatches,
+    GapOpens,
+    QueryStart,
+    QueryEnd,
+    SubjectStart,
+    SubjectEnd,
+    Evalue,
+    BitScore,
+    QueryLength,
+    SubjectLength,
+    Score,
+    Identical,
+    Positives,
+    Gaps,
+    PercentPositives,
+    QuerySeq,
+    SubjectSeq,
+    SubjectTitle,
+    SubjectStrand,
+    QueryFrame,
+    SubjectFrame,
+    QueryCoverage,
+    QueryCoverageHsp,
+    /// Any other column, with its description as given in the `# Fields:` line.
+    Other(String),
+}
+
+/// All known fields with their format specifier and description.
+const FIELDS: [(Field, &str, &str); 29] = [
+    (Field::QuerySeqId, "qseqid", "query id"),
+    (Field::QueryAccVer, "qaccver", "query acc.ver"),
+    (Field::SubjectSeqId, "sseqid", "subject id"),
+    (Field::SubjectAccVer, "saccver", "subject acc.ver"),
+    (Field::PercentIdentity, "pident", "% identity"),
+    (Field::Length, "length", "alignment length"),
+    (Field::Mismatches, "mismatch", "mismatches"),
+    (Field::GapOpens, "gapopen", "gap opens"),
+    (Field::QueryStart, "qstart", "q. start"),
+    (Field::QueryEnd, "qend", "q. end"),
+    (Field::SubjectStart, "sstart", "s. start"),
+    (Field::SubjectEnd, "send", "s. end"),
+    (Field::Evalue, "evalue", "evalue"),
+    (Field::BitScore, "bitscore", "bit score"),
+    (Field::QueryLength, "qlen", "query length"),
+    (Field::SubjectLength, "slen", "subject length"),
+    (Field::Score, "score", "score"),
+    (Field::Identical, "nident", "identical"),
+    (Field::Positives, "positive", "positives"),
+    (Field::Gaps, "gaps", "gaps"),
+    (Field::PercentPositives, "ppos", "% positives"),
+    (Field::QuerySeq, "qseq", "query seq"),
+    (Field::SubjectSeq, "sseq", "subject seq"),
+    (Field::SubjectTitle, "stitle", "subject title"),
+    (Field::SubjectStrand, "sstrand", "subject strand"),
+    (Field::QueryFrame, "qframe", "query frame"),
+    (Field::SubjectFrame, "sframe", "sbjct frame"),
+    (
+        Field::QueryCoverage,
+        "qcovs",
+        "% query coverage per subject",
+    ),
+    (
+        Field::QueryCoverageHsp,
+        "qcovhsp",
+        "% query coverage per hsp",
+    ),
+];
+
+impl Field {
+    /// The twelve standard fields (`std`): query and subject accession, percent identity,
+    /// alignment length, mismatches, gap opens, query start and end, subject start and end,
+    /// e-value and bit score.
+    pub fn standard() -> Vec<Field> {
+        vec![
+            Field::QueryAccVer,
+            Field::SubjectAccVer,
+            Field::PercentIdentity,
+            Field::Length,
+            Field::Mismatches,
+            Field::GapOpens,
+            Field::QueryStart,
+            Field::QueryEnd,
+            Field::SubjectStart,
+            Field::SubjectEnd,
+            Field::Evalue,
+            Field::BitScore,
+        ]
+    }
+
+    /// Get the field for a format specifier of `-outfmt`, e.g. `qlen`.
+    pub fn from_specifier(specifier: &str) -> Option<Field> {
+        FIELDS
+            .iter()
+            .find(|(_, s, _)| *s == specifier)
+            .map(|(field, _, _)| field.clone())
+    }
+
+    /// Get the field for a description in a `# Fields:` line, e.g. `query length`. Unknown
+    /// descriptions yield `Field::Other`.
+    pub fn from_description(description: &str) -> Field {
+        FIELDS
+            .iter()
+            .find(|(_, _, d)| *d == description)
+            .map_or_else(
+                || Field::Other(description.to_owned()),
+                |(field, _, _)| field.clone(),
+            )
+    }
+
+    /// The format specifier of the field, or `None` for `Field::Other`.
+    pub fn specifier(&self) -> Option<&'static str> {
+        FIELDS
+            .iter()
+            .find(|(field, _, _)| field == self)
+            .map(|(_, s, _)| *s)
+    }
+
+    /// The description of the field as used in `# Fields:` lines.
+    pub fn description(&self) -> &str {
+        match self {
+            Field::Other(description) => description,
+            _ => FIELDS
+                .iter()
+                .find(|(field, _, _)| field == self)
+                .map(|(_, _, d)| *d)
+                .unwrap(),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// A hit of BLAST tabular output.
+///
+/// The standard columns are typed; any other columns are kept as strings in order of
+/// appearance. Standard columns missing from the input are left at their defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    query: String,
+    subject: String,
+    percent_identity: f64,
+    length: u64,
+    mismatches: u64,
+    gap_opens: u64,
+    query_start: u64,
+    query_end: u64,
+    subject_start: u64,
+    subject_end: u64,
+    evalue: f64,
+    bit_score: f64,
+    extra: Vec<(Field, String)>,
+}
+
+impl Record {
+    /// Create a new, empty record.
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.query.is_empty()
+    }
+
+    /// Query id (`qseqid` or `qaccver`).
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Subject id (`sseqid` or `saccver`).
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Percentage of identical matches.
+    pub fn percent_identity(&self) -> f64 {
+        self.percent_identity
+    }
+
+    /// Alignment length.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Number of mismatches.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches
+    }
+
+    /// Number of gap openings.
+    pub fn gap_opens(&self) -> u64 {
+        self.gap_opens
+    }
+
+    /// Start of the alignment in the query (1-based).
+    pub fn query_start(&self) -> u64 {
+        self.query_start
+    }
+
+    /// End of the alignment in the query (1-based, inclusive).
+    pub fn query_end(&self) -> u64 {
+        self.query_end
+    }
+
+    /// Start of the alignment in the subject (1-based). Greater than the end for hits on
+    /// the minus strand.
+    pub fn subject_start(&self) -> u64 {
+        self.subject_start
+    }
+
+    /// End of the alignment in the subject (1-based, inclusive).
+    pub fn subject_end(&self) -> u64 {
+        self.subject_end
+    }
+
+    /// Expect value.
+    pub fn evalue(&self) -> f64 {
+        self.evalue
+    }
+
+    /// Bit score.
+    pub fn bit_score(&self) -> f64 {
+        self.bit_score
+    }
+
+    /// The non-standard columns in order of appearance.
+    pub fn extra(&self) -> &[(Field, String)] {
+        &self.extra
+    }
+
+    /// Return the value of a column as it is written, or `None` if the record has no
+    /// such column.
+    pub fn get(&self, field: &Field) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(f, _)| f == field)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a non-standard column, replacing an existing value.
+    pub fn push_extra(&mut self, field: Field, value: &str) {
+        match self.extra.iter_mut().find(|(f, _)| *f == field) {
+            Some((_, existing)) => *existing = value.to_owned(),
+            None => self.extra.push((field, value.to_owned())),
+        }
+    }
+
+    pub fn query_mut(&mut self) -> &mut String {
+        &mut self.query
+    }
+
+    pub fn subject_mut(&mut self) -> &mut String {
+        &mut self.subject
+    }
+
+    pub fn percent_identity_mut(&mut self) -> &mut f64 {
+        &mut self.percent_identity
+    }
+
+    pub fn length_mut(&mut self) -> &mut u64 {
+        &mut self.length
+    }
+
+    pub fn mismatches_mut(&mut self) -> &mut u64 {
+        &mut self.mismatches
+    }
+
+    pub fn gap_opens_mut(&mut self) -> &mut u64 {
+        &mut self.gap_opens
+    }
+
+    pub fn query_start_mut(&mut self) -> &mut u64 {
+        &mut self.query_start
+    }
+
+    pub fn query_end_mut(&mut self) -> &mut u64 {
+        &mut self.query_end
+    }
+
+    pub fn subject_start_mut(&mut self) -> &mut u64 {
+        &mut self.subject_start
+    }
+
+    pub fn subject_end_mut(&mut self) -> &mut u64 {
+        &mut self.subject_end
+    }
+
+    pub fn evalue_mut(&mut self) -> &mut f64 {
+        &mut self.evalue
+    }
+
+    pub fn bit_score_mut(&mut self) -> &mut f64 {
+        &mut self.bit_score
+    }
+
+    /// Parse a line with the given columns.
+    fn parse(line: &str, fields: &[Field]) -> std::result::Result<Self, String> {
+        let values: Vec<&str> = line.split('\t').collect();
+        if values.len() != fields.len() {
+            return Err(format!(
+                "expected {} tab separated fields, found {}",
+                fields.len(),
+                values.len()
+            ));
+        }
+        fn number<T: std::str::FromStr>(
+            value: &str,
+            field: &Field,
+        ) -> std::result::Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid {}: {}", field, value))
+        }
+
+        let mut record = Record::new();
+        for (field, &value) in fields.iter().zip(&values) {
+            match field {
+                Field::QuerySeqId | Field::QueryAccVer if record.query.is_empty() => {
+                    record.query = value.to_owned()
+                }
+                Field::SubjectSeqId | Field::SubjectAccVer if record.subject.is_empty() => {
+                    record.subject = value.to_owned()
+                }
+                Field::PercentIdentity => record.percent_identity = number(value, field)?,
+                Field::Length => record.length = number(value, field)?,
+                Field::Mismatches => record.mismatches = number(value, field)?,
+                Field::GapOpens => record.gap_opens = number(value, field)?,
+                Field::QueryStart => record.query_start = number(value, field)?,
+                Field::QueryEnd => record.query_end = number(value, field)?,
+                Field::SubjectStart => record.subject_start = number(value, field)?,
+                Field::SubjectEnd => record.subject_end = number(value, field)?,
+                Field::Evalue => record.evalue = number(value, field)?,
+                Field::BitScore => record.bit_score = number(value, field)?,
+                _ => record.extra.push((field.clone(), value.to_owned())),
+            }
+        }
+        if record.query.is_empty() {
+            return Err("missing query id".to_owned());
+        }
+        Ok(record)
+    }
+}
+
+/// Format an e-value the way BLAST does: in scientific notation for small values.
+fn format_evalue(evalue: f64) -> String {
+    if evalue != 0.0 && evalue < 1e-3 {
+        format!("{:.2e}", evalue)
+    } else {
+        evalue.to_string()
+    }
+}
+
+/// A reader for BLAST tabular output, with or without comment lines.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    fields: Vec<Field>,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read BLAST output from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            fields: Field::standard(),
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// Set the columns of the input, as given to `-outfmt`. A `# Fields:` comment line
+    /// overrides them for the hits that follow it.
+    pub fn fields(mut self, fields: Vec<Field>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// The columns of the hits read last.
+    pub fn current_fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Read the next hit into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line doesn't match the columns or a column
+    /// can't be parsed.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                *record = Record::new();
+                return Ok(());
+            }
+            self.line_no += 1;
+            let line = self.line.trim_end_matches(&['\n', '\r'][..]);
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(fields) = comment.trim_start().strip_prefix("Fields:") {
+                    self.fields = fields
+                        .split(',')
+                        .map(|d| Field::from_description(d.trim()))
+                        .collect();
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            *record = Record::parse(line, &self.fields).map_err(|message| Error::Format {
+                line: self.line_no,
+                message,
+            })?;
+            return Ok(());
+        }
+    }
+
+    /// Return an iterator over the hits of this file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the hits of BLAST tabular output.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::new();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A writer for BLAST tabular output.
+///
+/// Hits are written as `-outfmt 6`; adding headers with
+/// [`write_query_header`](#method.write_query_header) yields `-outfmt 7`.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+    fields: Vec<Field>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`, with the standard columns.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+            fields: Field::standard(),
+        }
+    }
+
+    /// Set the columns to write.
+    pub fn fields(mut self, fields: Vec<Field>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Write the comment lines preceding the hits of a query in `-outfmt 7`, including
+    /// the `# Fields:` line. The fields line is omitted if there are no hits.
+    pub fn write_query_header(
+        &mut self,
+        program: &str,
+        query: &str,
+        database: &str,
+        hits: usize,
+    ) -> io::Result<()> {
+        writeln!(self.writer, "# {}", program)?;
+        writeln!(self.writer, "# Query: {}", query)?;
+        writeln!(self.writer, "# Database: {}", database)?;
+        if hits > 0 {
+            let descriptions: Vec<&str> = self.fields.iter().map(Field::description).collect();
+            writeln!(self.writer, "# Fields: {}", descriptions.join(", "))?;
+        }
+        writeln!(self.writer, "# {} hits found", hits)
+    }
+
+    /// Write a comment line, e.g. the final `# BLAST processed 2 queries`.
+    pub fn write_comment(&mut self, comment: &str) -> io::Result<()> {
+        writeln!(self.writer, "# {}", comment)
+    }
+
+    /// Write a hit. Columns that the record does not have are written as `N/A`.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let values: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| match field {
+                Field::QuerySeqId | Field::QueryAccVer => record.query.clone(),
+                Field::SubjectSeqId | Field::SubjectAccVer => record.subject.clone(),
+                Field::PercentIdentity => format!("{:.3}", record.percent_identity),
+                Field::Length => record.length.to_string(),
+                Field::Mismatches => record.mismatches.to_string(),
+                Field::GapOpens => record.gap_opens.to_string(),
+                Field::QueryStart => record.query_start.to_string(),
+                Field::QueryEnd => record.query_end.to_string(),
+                Field::SubjectStart => record.subject_start.to_string(),
+                Field::SubjectEnd => record.subject_end.to_string(),
+                Field::Evalue => format_evalue(record.evalue),
+                Field::BitScore => record.bit_score.to_string(),
+                _ => record.get(field).unwrap_or("N/A").to_owned(),
+            })
+            .collect();
+        writeln!(self.writer, "{}", values.join("\t"))
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTFMT7: &[u8] = b"# BLASTP 2.12.0+
+# Query: sp|P69905|HBA_HUMAN
+# Database: swissprot
+# Fields: query acc.ver, subject acc.ver, % identity, alignment length, mismatches, gap opens, q. start, q. end, s. start, s. end, evalue, bit score, subject title
+# 2 hits found
+sp|P69905|HBA_HUMAN\tP69907.2\t100.000\t142\t0\t0\t1\t142\t1\t142\t1.35e-103\t291\tHemoglobin subunit alpha
+sp|P69905|HBA_HUMAN\tP01942.2\t85.211\t142\t21\t0\t1\t142\t1\t142\t4.21e-88\t252\tHemoglobin subunit alpha mouse
+# BLASTP 2.12.0+
+# Query: q2
+# Database: swissprot
+# 0 hits found
+# BLAST processed 2 queries
+";
+
+    #[test]
+    fn test_read_commented() {
+        let records: Vec<Record> = Reader::new(OUTFMT7)
+            .records()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        let record = &records[1];
+        assert_eq!(record.query(), "sp|P69905|HBA_HUMAN");
+        assert_eq!(record.subject(), "P01942.2");
+        assert_eq!(record.percent_identity(), 85.211);
+        assert_eq!(
+            (record.length(), record.mismatches(), record.gap_opens()),
+            (142, 21, 0)
+        );
+        assert_eq!((record.query_start(), record.query_end()), (1, 142));
+        assert_eq!((record.subject_start(), record.subject_end()), (1, 142));
+        assert_eq!(record.evalue(), 4.21e-88);
+        assert_eq!(record.bit_score(), 252.0);
+        assert_eq!(
+            record.get(&Field::SubjectTitle),
+            Some("Hemoglobin subunit alpha mouse")
+        );
+    }
+
+    #[test]
+    fn test_read_tabular() {
+        let input = b"q1\ts1\t250\t99.6\t0.0\n\nq1\ts2\t250\t87.2\t2e-40\n";
+        let fields: Vec<Field> = "qseqid sseqid qlen pident evalue"
+            .split(' ')
+            .map(|s| Field::from_specifier(s).unwrap())
+            .collect();
+        let mut reader = Reader::new(&input[..]).fields(fields);
+        let mut record = Record::new();
+        reader.read(&mut record).unwrap();
+        assert_eq!(record.get(&Field::QueryLength), Some("250"));
+        assert_eq!(record.evalue(), 0.0);
+        reader.read(&mut record).unwrap();
+        assert_eq!(record.subject(), "s2");
+        reader.read(&mut record).unwrap();
+        assert!(record.is_empty());
+
+        let mut reader = Reader::new(&b"q1\ts1\t99.0\n"[..]);
+        assert!(matches!(
+            reader.read(&mut record),
+            Err(Error::Format { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_write() {
+        let mut fields = Field::standard();
+        fields.push(Field::SubjectTitle);
+        let mut writer = Writer::new(Vec::new()).fields(fields);
+        let mut records = Reader::new(OUTFMT7).records();
+        writer
+            .write_query_header("BLASTP 2.12.0+", "sp|P69905|HBA_HUMAN", "swissprot", 2)
+            .unwrap();
+        writer.write(&records.next().unwrap().unwrap()).unwrap();
+        writer.write(&records.next().unwrap().unwrap()).unwrap();
+        writer
+            .write_query_header("BLASTP 2.12.0+", "q2", "swissprot", 0)
+            .unwrap();
+        writer.write_comment("BLAST processed 2 queries").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), OUTFMT7);
+
+        let mut writer = Writer::new(Vec::new()).fields(vec![
+            Field::QuerySeqId,
+            Field::Evalue,
+            Field::QueryLength,
+        ]);
+        let mut record = Record::new();
+        *record.query_mut() = "q".to_owned();
+        *record.evalue_mut() = 0.5;
+        writer.write(&record).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b"q\t0.5\tN/A\n");
+    }
+
+    #[test]
+    fn test_fields() {
+        for field in Field::standard() {
+            let specifier = field.specifier().unwrap();
+            assert_eq!(Field::from_specifier(specifier), Some(field.clone()));
+            assert_eq!(Field::from_description(field.description()), field);
+        }
+        assert_eq!(
+            Field::from_description("query/sbjct frames"),
+            Field::Other("query/sbjct frames".to_owned())
+        );
+        assert_eq!(Field::Other("x".to_owned()).specifier(), None);
+    }
+}
@@ -0,0 +1,930 @@
+//! Reading and writing of [UCSC chain files], and lifting coordinates between assemblies
+//! with them.
+//!
+//! A chain aligns a region of a reference (`t`, the assembly lifted from) to a region of a
+//! query (`q`, the assembly lifted to) as a series of ungapped blocks. Coordinates are
+//! 0-based and half-open. On the reverse strand, query coordinates refer to the reverse
+//! complement of the query sequence, as in the file format.
+//!
+//! [UCSC chain files]: https://genome.ucsc.edu/goldenPath/help/chain.html
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::chain;
+//! use bio_types::strand::ReqStrand;
+//!
+//! let input = b"chain 1000 chr1 1000 + 100 400 chrA 900 + 0 270 1
+//! 100 50 0
+//! 100 0 20
+//! 50
+//!
+//! ";
+//! let chains = chain::Reader::new(&input[..])
+//!     .records()
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//! let liftover = chain::Liftover::new(chains);
+//!
+//! let lifted = liftover.lift_interval("chr1", 340, 360).unwrap();
+//! assert_eq!((lifted.contig.as_str(), lifted.start, lifted.end), ("chrA", 190, 230));
+//! assert_eq!(lifted.strand, ReqStrand::Forward);
+//! // the region between 200 and 250 is missing from chrA
+//! assert_eq!(
+//!     liftover.lift_position("chr1", 220),
+//!     Err(chain::Unmapped::Deleted)
+//! );
+//! ```
+
+use anyhow::Context;
+use bio_types::strand::ReqStrand;
+use std::collections::HashMap;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::data_structures::interval_tree::ArrayBackedIntervalTree;
+use crate::io::compression::Decoder;
+use crate::io::{bed, gff};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An ungapped block of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    /// Start on the reference.
+    pub tstart: u64,
+    /// Start on the query.
+    pub qstart: u64,
+    /// Length of the block.
+    pub size: u64,
+}
+
+impl Block {
+    /// End on the reference.
+    pub fn tend(&self) -> u64 {
+        self.tstart + self.size
+    }
+
+    /// End on the query.
+    pub fn qend(&self) -> u64 {
+        self.qstart + self.size
+    }
+}
+
+/// A chain, i.e. an alignment of a reference region to a query region.
+///
+/// The aligned regions are given by the blocks, which have to be sorted and must not
+/// overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chain {
+    score: f64,
+    tname: String,
+    tsize: u64,
+    tstrand: ReqStrand,
+    qname: String,
+    qsize: u64,
+    qstrand: ReqStrand,
+    id: u64,
+    blocks: Vec<Block>,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Chain {
+            score: 0.0,
+            tname: String::new(),
+            tsize: 0,
+            tstrand: ReqStrand::Forward,
+            qname: String::new(),
+            qsize: 0,
+            qstrand: ReqStrand::Forward,
+            id: 0,
+            blocks: Vec::new(),
+        }
+    }
+}
+
+impl Chain {
+    /// Create a new, empty chain.
+    pub fn new() -> Self {
+        Chain::default()
+    }
+
+    /// Check if the chain is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tname.is_empty()
+    }
+
+    /// Alignment score of the chain.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Name of the reference sequence.
+    pub fn tname(&self) -> &str {
+        &self.tname
+    }
+
+    /// Length of the reference sequence.
+    pub fn tsize(&self) -> u64 {
+        self.tsize
+    }
+
+    /// Strand of the reference, usually forward.
+    pub fn tstrand(&self) -> ReqStrand {
+        self.tstrand
+    }
+
+    /// Start of the chain on the reference.
+    pub fn tstart(&self) -> u64 {
+        self.blocks.first().map_or(0, |b| b.tstart)
+    }
+
+    /// End of the chain on the reference.
+    pub fn tend(&self) -> u64 {
+        self.blocks.last().map_or(0, Block::tend)
+    }
+
+    /// Name of the query sequence.
+    pub fn qname(&self) -> &str {
+        &self.qname
+    }
+
+    /// Length of the query sequence.
+    pub fn qsize(&self) -> u64 {
+        self.qsize
+    }
+
+    /// Strand of the query.
+    pub fn qstrand(&self) -> ReqStrand {
+        self.qstrand
+    }
+
+    /// Start of the chain on the query, on the query strand.
+    pub fn qstart(&self) -> u64 {
+        self.blocks.first().map_or(0, |b| b.qstart)
+    }
+
+    /// End of the chain on the query, on the query strand.
+    pub fn qend(&self) -> u64 {
+        self.blocks.last().map_or(0, Block::qend)
+    }
+
+    /// Identifier of the chain.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The ungapped blocks of the chain.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn score_mut(&mut self) -> &mut f64 {
+        &mut self.score
+    }
+
+    pub fn tname_mut(&mut self) -> &mut String {
+        &mut self.tname
+    }
+
+    pub fn tsize_mut(&mut self) -> &mut u64 {
+        &mut self.tsize
+    }
+
+    pub fn tstrand_mut(&mut self) -> &mut ReqStrand {
+        &mut self.tstrand
+    }
+
+    pub fn qname_mut(&mut self) -> &mut String {
+        &mut self.qname
+    }
+
+    pub fn qsize_mut(&mut self) -> &mut u64 {
+        &mut self.qsize
+    }
+
+    pub fn qstrand_mut(&mut self) -> &mut ReqStrand {
+        &mut self.qstrand
+    }
+
+    pub fn id_mut(&mut self) -> &mut u64 {
+        &mut self.id
+    }
+
+    pub fn blocks_mut(&mut self) -> &mut Vec<Block> {
+        &mut self.blocks
+    }
+
+    /// The blocks with the reference on the forward strand, and the strand of the query
+    /// relative to it.
+    fn forward_blocks(&self) -> (Vec<Block>, ReqStrand) {
+        match self.tstrand {
+            ReqStrand::Forward => (self.blocks.clone(), self.qstrand),
+            ReqStrand::Reverse => {
+                let blocks = self
+                    .blocks
+                    .iter()
+                    .rev()
+                    .map(|b| Block {
+                        tstart: self.tsize - b.tend(),
+                        qstart: self.qsize - b.qend(),
+                        size: b.size,
+                    })
+                    .collect();
+                (blocks, -self.qstrand)
+            }
+        }
+    }
+}
+
+/// Parse the fields of a chain header line into the given chain, returning start and end
+/// on reference and query.
+fn parse_header(
+    fields: &[&str],
+    chain: &mut Chain,
+) -> std::result::Result<(u64, u64, u64, u64), String> {
+    if fields[0] != "chain" || fields.len() < 12 || fields.len() > 13 {
+        return Err("expected chain header with 12 or 13 fields".to_owned());
+    }
+    let number = |i: usize| {
+        fields[i]
+            .parse::<u64>()
+            .map_err(|_| format!("invalid number: {}", fields[i]))
+    };
+    let strand = |i: usize| match fields[i] {
+        "+" => Ok(ReqStrand::Forward),
+        "-" => Ok(ReqStrand::Reverse),
+        strand => Err(format!("invalid strand: {}", strand)),
+    };
+
+    chain.score = fields[1]
+        .parse()
+        .map_err(|_| format!("invalid score: {}", fields[1]))?;
+    chain.tname = fields[2].to_owned();
+    chain.tsize = number(3)?;
+    chain.tstrand = strand(4)?;
+    chain.qname = fields[7].to_owned();
+    chain.qsize = number(8)?;
+    chain.qstrand = strand(9)?;
+    if fields.len() == 13 {
+        chain.id = number(12)?;
+    }
+    Ok((number(5)?, number(6)?, number(10)?, number(11)?))
+}
+
+/// A reader for chain files.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read chains from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// Read the next line into the buffer, returning false at the end of the input.
+    fn next_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+        Ok(true)
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> Error {
+        Error::Format {
+            line: self.line_no,
+            message: message.into(),
+        }
+    }
+
+    /// Read the next chain into the given `Chain`.
+    /// An empty chain indicates that no more chains can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a header or block line is malformed, or the
+    /// blocks don't span the region given in the header.
+    pub fn read(&mut self, chain: &mut Chain) -> Result<()> {
+        *chain = Chain::new();
+        loop {
+            if !self.next_line()? {
+                return Ok(());
+            }
+            let line = self.line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                break;
+            }
+        }
+
+        let fields: Vec<&str> = self.line.split_whitespace().collect();
+        let (tstart, tend, qstart, qend) =
+            parse_header(&fields, chain).map_err(|message| self.error(message))?;
+
+        let (mut t, mut q) = (tstart, qstart);
+        loop {
+            if !self.next_line()? {
+                return Err(self.error("unexpected end of chain"));
+            }
+            let values = self
+                .line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<std::result::Result<Vec<u64>, _>>()
+                .map_err(|_| self.error("invalid block line"))?;
+            let (size, dt, dq) = match values[..] {
+                [size] => (size, 0, 0),
+                [size, dt, dq] => (size, dt, dq),
+                _ => return Err(self.error("expected block line with 1 or 3 fields")),
+            };
+            chain.blocks.push(Block {
+                tstart: t,
+                qstart: q,
+                size,
+            });
+            t += size + dt;
+            q += size + dq;
+            if values.len() == 1 {
+                break;
+            }
+        }
+        if t != tend || q != qend || tend > chain.tsize || qend > chain.qsize {
+            return Err(self.error("blocks don't match the chain header"));
+        }
+        Ok(())
+    }
+
+    /// Return an iterator over the chains of this file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the chains of a chain file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Chain>;
+
+    fn next(&mut self) -> Option<Result<Chain>> {
+        let mut chain = Chain::new();
+        match self.reader.read(&mut chain) {
+            Ok(()) if chain.is_empty() => None,
+            Ok(()) => Some(Ok(chain)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A writer for chain files.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write a chain.
+    ///
+    /// # Errors
+    ///
+    /// If the chain has no blocks, or the blocks are unsorted or overlap.
+    pub fn write(&mut self, chain: &Chain) -> io::Result<()> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if chain.blocks.is_empty() {
+            return Err(invalid("chain without blocks"));
+        }
+        let mut gaps = Vec::with_capacity(chain.blocks.len() - 1);
+        for pair in chain.blocks.windows(2) {
+            match (
+                pair[1].tstart.checked_sub(pair[0].tend()),
+                pair[1].qstart.checked_sub(pair[0].qend()),
+            ) {
+                (Some(dt), Some(dq)) => gaps.push((dt, dq)),
+                _ => return Err(invalid("unsorted or overlapping blocks")),
+            }
+        }
+
+        writeln!(
+            self.writer,
+            "chain {} {} {} {} {} {} {} {} {} {} {} {}",
+            chain.score,
+            chain.tname,
+            chain.tsize,
+            chain.tstrand.strand_symbol(),
+            chain.tstart(),
+            chain.tend(),
+            chain.qname,
+            chain.qsize,
+            chain.qstrand.strand_symbol(),
+            chain.qstart(),
+            chain.qend(),
+            chain.id
+        )?;
+        for (block, (dt, dq)) in chain.blocks.iter().zip(gaps) {
+            writeln!(self.writer, "{}\t{}\t{}", block.size, dt, dq)?;
+        }
+        writeln!(self.writer, "{}\n", chain.blocks.last().unwrap().size)
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+/// The reason why an interval could not be lifted.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Unmapped {
+    #[error("deleted in new assembly")]
+    Deleted,
+
+    #[error("partially deleted in new assembly: {mapped} of {length} bases mapped")]
+    PartiallyDeleted { mapped: u64, length: u64 },
+
+    #[error("duplicated in new assembly: mapped by {count} chains")]
+    Duplicated { count: usize },
+
+    #[error("split in new assembly: blocks mapped by different chains")]
+    Split,
+
+    #[error("empty interval")]
+    EmptyInterval,
+
+    #[error("invalid record: {0}")]
+    InvalidRecord(String),
+}
+
+/// An interval lifted to the query assembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lifted {
+    /// Name of the query sequence.
+    pub contig: String,
+    /// Start on the forward strand of the query sequence.
+    pub start: u64,
+    /// End on the forward strand of the query sequence.
+    pub end: u64,
+    /// Strand of the query relative to the reference.
+    pub strand: ReqStrand,
+    /// Number of bases of the original interval that are aligned by the chain.
+    pub mapped: u64,
+    /// Index of the chain in [`Liftover::chains`](struct.Liftover.html#method.chains).
+    pub chain: usize,
+}
+
+/// Lifting of coordinates from the reference to the query assembly of a set of chains.
+///
+/// An interval is lifted by a chain if at least a fraction `min_match` (default 0.95) of its
+/// bases are aligned by the chain. The lifted interval spans from the first to the last
+/// aligned base, i.e. it includes insertions in the query, like UCSC `liftOver` does.
+pub struct Liftover {
+    chains: Vec<Chain>,
+    strands: Vec<ReqStrand>,
+    trees: HashMap<String, ArrayBackedIntervalTree<u64, (usize, Block)>>,
+    min_match: f64,
+}
+
+impl Liftover {
+    /// Create a new liftover from the given chains.
+    pub fn new(chains: Vec<Chain>) -> Self {
+        let mut trees: HashMap<String, ArrayBackedIntervalTree<u64, (usize, Block)>> =
+            HashMap::new();
+        let mut strands = Vec::with_capacity(chains.len());
+        for (i, chain) in chains.iter().enumerate() {
+            let (blocks, strand) = chain.forward_blocks();
+            let tree = trees.entry(chain.tname.clone()).or_default();
+            for block in blocks {
+                tree.insert(block.tstart..block.tend(), (i, block));
+            }
+            strands.push(strand);
+        }
+        for tree in trees.values_mut() {
+            tree.index();
+        }
+        Liftover {
+            chains,
+            strands,
+            trees,
+            min_match: 0.95,
+        }
+    }
+
+    /// Read the chains from a given file.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        let chains = Reader::from_file(&path)?
+            .records()
+            .collect::<Result<_>>()
+            .with_context(|| format!("Failed to read chains from {:#?}", path))?;
+        Ok(Liftover::new(chains))
+    }
+
+    /// Set the minimum fraction of bases of an interval that has to be aligned by a chain.
+    ///
+    /// # Panics
+    ///
+    /// If `min_match` is not within `[0, 1]`.
+    pub fn min_match(mut self, min_match: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&min_match),
+            "min_match must be within [0, 1]"
+        );
+        self.min_match = min_match;
+        self
+    }
+
+    /// The chains, in the order they were given.
+    pub fn chains(&self) -> &[Chain] {
+        &self.chains
+    }
+
+    /// Lift the interval by every chain aligning any of its bases, best scoring first.
+    fn lift_any(&self, contig: &str, start: u64, end: u64) -> Vec<Lifted> {
+        let tree = match self.trees.get(contig) {
+            Some(tree) if start < end => tree,
+            _ => return Vec::new(),
+        };
+        let mut lifted: Vec<Lifted> = Vec::new();
+        for entry in tree.find(start..end) {
+            let (chain, block) = *entry.data();
+            let (from, to) = (start.max(block.tstart), end.min(block.tend()));
+            let qstart = block.qstart + from - block.tstart;
+            let qend = qstart + to - from;
+            match lifted.iter_mut().find(|l| l.chain == chain) {
+                Some(l) => {
+                    l.start = l.start.min(qstart);
+                    l.end = l.end.max(qend);
+                    l.mapped += to - from;
+                }
+                None => lifted.push(Lifted {
+                    contig: self.chains[chain].qname.clone(),
+                    start: qstart,
+                    end: qend,
+                    strand: ReqStrand::Forward,
+                    mapped: to - from,
+                    chain,
+                }),
+            }
+        }
+        for l in &mut lifted {
+            l.strand = self.strands[l.chain];
+            if l.strand == ReqStrand::Reverse {
+                let qsize = self.chains[l.chain].qsize;
+                let (start, end) = (qsize - l.end, qsize - l.start);
+                l.start = start;
+                l.end = end;
+            }
+        }
+        lifted.sort_by(|a, b| {
+            let (a, b) = (&self.chains[a.chain], &self.chains[b.chain]);
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        lifted
+    }
+
+    fn passes(&self, lifted: &Lifted, length: u64) -> bool {
+        lifted.mapped as f64 >= self.min_match * length as f64
+    }
+
+    /// Lift the interval `start..end` of the given reference sequence by all chains that
+    /// align enough of it, best scoring chain first.
+    pub fn lift_interval_all(&self, contig: &str, start: u64, end: u64) -> Vec<Lifted> {
+        let mut lifted = self.lift_any(contig, start, end);
+        lifted.retain(|l| self.passes(l, end - start));
+        lifted
+    }
+
+    /// Lift the interval `start..end` of the given reference sequence.
+    ///
+    /// # Errors
+    ///
+    /// If the interval is empty, no chain aligns enough of it, or it is lifted by more than
+    /// one chain.
+    pub fn lift_interval(&self, contig: &str, start: u64, end: u64) -> Result<Lifted, Unmapped> {
+        if start >= end {
+            return Err(Unmapped::EmptyInterval);
+        }
+        let mut lifted = self.lift_any(contig, start, end);
+        let best = match lifted.iter().max_by_key(|l| l.mapped) {
+            Some(best) => best.mapped,
+            None => return Err(Unmapped::Deleted),
+        };
+        lifted.retain(|l| self.passes(l, end - start));
+        match lifted.len() {
+            0 => Err(Unmapped::PartiallyDeleted {
+                mapped: best,
+                length: end - start,
+            }),
+            1 => Ok(lifted.pop().unwrap()),
+            count => Err(Unmapped::Duplicated { count }),
+        }
+    }
+
+    /// Lift a single position of the given reference sequence. The position of the result
+    /// is its `start`.
+    pub fn lift_position(&self, contig: &str, pos: u64) -> Result<Lifted, Unmapped> {
+        self.lift_interval(contig, pos, pos + 1)
+    }
+
+    /// Lift the given range by the given chain, if it aligns any of its bases.
+    fn lift_by_chain(&self, contig: &str, range: &Range<u64>, chain: usize) -> Option<Lifted> {
+        self.lift_any(contig, range.start, range.end)
+            .into_iter()
+            .find(|l| l.chain == chain)
+    }
+
+    /// Lift a BED record. For BED12 records, every block is lifted and all blocks have to
+    /// be lifted by the same chain. The thick region is reduced to its aligned part. The
+    /// strand is flipped if the record is lifted to the reverse strand.
+    ///
+    /// # Errors
+    ///
+    /// If the record or one of its blocks can't be lifted, or the blocks are lifted by
+    /// different chains.
+    pub fn lift_bed(&self, record: &bed::Record) -> Result<bed::Record, Unmapped> {
+        let mut lifted_record = record.clone();
+        let lifted = match record.block_count() {
+            Some(_) => {
+                let blocks = record
+                    .blocks()
+                    .map_err(|e| Unmapped::InvalidRecord(e.to_string()))?;
+                let lifted_blocks = blocks
+                    .iter()
+                    .map(|b| self.lift_interval(record.chrom(), b.start, b.end))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let first = &lifted_blocks[0];
+                if lifted_blocks.iter().any(|l| l.chain != first.chain) {
+                    return Err(Unmapped::Split);
+                }
+                let mut new_blocks: Vec<Range<u64>> =
+                    lifted_blocks.iter().map(|l| l.start..l.end).collect();
+                new_blocks.sort_by_key(|b| b.start);
+
+                let start = new_blocks[0].start;
+                let thick = match (record.thick_start(), record.thick_end()) {
+                    (Some(thick_start), Some(thick_end)) if thick_start < thick_end => self
+                        .lift_by_chain(record.chrom(), &(thick_start..thick_end), first.chain)
+                        .map_or(start..start, |l| l.start..l.end),
+                    _ => start..start,
+                };
+                lifted_record.set_bed12(thick, record.item_rgb().unwrap_or_default(), &new_blocks);
+                Lifted {
+                    start,
+                    end: new_blocks.last().unwrap().end,
+                    mapped: lifted_blocks.iter().map(|l| l.mapped).sum(),
+                    ..first.clone()
+                }
+            }
+            None => self.lift_interval(record.chrom(), record.start(), record.end())?,
+        };
+
+        lifted_record.set_chrom(&lifted.contig);
+        lifted_record.set_start(lifted.start);
+        lifted_record.set_end(lifted.end);
+        if let (Some(strand), ReqStrand::Reverse) = (record.strand(), lifted.strand) {
+            if !strand.is_unknown() {
+                lifted_record.set_strand(-strand);
+            }
+        }
+        Ok(lifted_record)
+    }
+
+    /// Lift a GFF record. The strand is flipped if the record is lifted to the reverse
+    /// strand.
+    ///
+    /// # Errors
+    ///
+    /// If the record can't be lifted.
+    pub fn lift_gff(&self, record: &gff::Record) -> Result<gff::Record, Unmapped> {
+        let lifted = self.lift_interval(
+            record.seqname(),
+            record.start().saturating_sub(1),
+            *record.end(),
+        )?;
+        let mut lifted_record = record.clone();
+        *lifted_record.seqname_mut() = lifted.contig;
+        *lifted_record.start_mut() = lifted.start + 1;
+        *lifted_record.end_mut() = lifted.end;
+        if let (Some(strand), ReqStrand::Reverse) = (record.strand(), lifted.strand) {
+            *lifted_record.strand_mut() = (-strand).strand_symbol().to_owned();
+        }
+        Ok(lifted_record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bio_types::strand::Strand;
+
+    const CHAINS: &[u8] = b"chain 5000 chr1 1000 + 100 400 chrA 900 + 0 270 1
+100\t50\t0
+100\t0\t20
+50
+
+chain 3000 chr1 1000 + 500 600 chrB 300 - 50 150 2
+100
+
+chain 200 chr1 1000 + 550 560 chrC 100 + 0 10 3
+10
+
+";
+
+    fn liftover() -> Liftover {
+        let chains = Reader::new(CHAINS)
+            .records()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        Liftover::new(chains)
+    }
+
+    #[test]
+    fn test_read_write() {
+        let chains: Vec<Chain> = Reader::new(CHAINS)
+            .records()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(chains.len(), 3);
+        let chain = &chains[0];
+        assert_eq!(
+            (chain.tname(), chain.tstart(), chain.tend()),
+            ("chr1", 100, 400)
+        );
+        assert_eq!(
+            (chain.qname(), chain.qstart(), chain.qend()),
+            ("chrA", 0, 270)
+        );
+        assert_eq!(
+            chain.blocks()[2],
+            Block {
+                tstart: 350,
+                qstart: 220,
+                size: 50
+            }
+        );
+        assert_eq!(chains[1].qstrand(), ReqStrand::Reverse);
+
+        let mut writer = Writer::new(Vec::new());
+        for chain in &chains {
+            writer.write(chain).unwrap();
+        }
+        assert_eq!(writer.into_inner().unwrap(), CHAINS);
+
+        let mut reader =
+            Reader::new(&b"chain 1 chr1 1000 + 0 25 chrA 900 + 0 20 1\n10 5 5\n5\n"[..]);
+        assert!(matches!(
+            reader.read(&mut Chain::new()),
+            Err(Error::Format { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_lift_interval() {
+        let liftover = liftover();
+        let lifted = liftover.lift_position("chr1", 150).unwrap();
+        assert_eq!((lifted.contig.as_str(), lifted.start), ("chrA", 50));
+        assert_eq!(liftover.lift_position("chr1", 220), Err(Unmapped::Deleted));
+        assert_eq!(liftover.lift_position("chr2", 220), Err(Unmapped::Deleted));
+
+        let lifted = liftover.lift_position("chr1", 510).unwrap();
+        assert_eq!((lifted.contig.as_str(), lifted.start), ("chrB", 239));
+        assert_eq!(lifted.strand, ReqStrand::Reverse);
+
+        assert_eq!(
+            liftover.lift_interval("chr1", 150, 300),
+            Err(Unmapped::PartiallyDeleted {
+                mapped: 100,
+                length: 150
+            })
+        );
+        assert_eq!(
+            liftover.lift_interval("chr1", 550, 560),
+            Err(Unmapped::Duplicated { count: 2 })
+        );
+        let all = liftover.lift_interval_all("chr1", 550, 560);
+        assert_eq!(all.len(), 2);
+        assert_eq!(
+            (all[0].contig.as_str(), all[0].start, all[0].end),
+            ("chrB", 190, 200)
+        );
+        assert_eq!(all[1].contig, "chrC");
+
+        let liftover = liftover.min_match(0.5);
+        let lifted = liftover.lift_interval("chr1", 150, 300).unwrap();
+        assert_eq!((lifted.start, lifted.end, lifted.mapped), (50, 150, 100));
+        assert_eq!(
+            liftover.lift_interval("chr1", 300, 300),
+            Err(Unmapped::EmptyInterval)
+        );
+    }
+
+    #[test]
+    fn test_lift_bed_gff() {
+        let liftover = liftover();
+        let read_bed = |line: &[u8]| bed::Reader::new(line).records().next().unwrap().unwrap();
+
+        let record = read_bed(b"chr1\t500\t520\tpeak\t0\t+\n");
+        let lifted = liftover.lift_bed(&record).unwrap();
+        assert_eq!(
+            (lifted.chrom(), lifted.start(), lifted.end()),
+            ("chrB", 230, 250)
+        );
+        assert_eq!(lifted.strand(), Some(Strand::Reverse));
+
+        let record =
+            read_bed(b"chr1\t100\t400\ttx\t0\t+\t150\t380\t0\t3\t100,100,40,\t0,150,260,\n");
+        let lifted = liftover.lift_bed(&record).unwrap();
+        let mut buf = Vec::new();
+        {
+            let mut writer = bed::Writer::new(&mut buf);
+            writer.write(&lifted).unwrap();
+        }
+        assert_eq!(
+            buf,
+            b"chrA\t0\t270\ttx\t0\t+\t50\t250\t0\t3\t100,100,40,\t0,100,230,\n"
+        );
+        let record = read_bed(b"chr1\t100\t600\ttx\t0\t+\t100\t100\t0\t2\t100,50,\t0,450,\n");
+        assert_eq!(liftover.lift_bed(&record).unwrap_err(), Unmapped::Split);
+
+        let mut record = gff::Record::new();
+        *record.seqname_mut() = "chr1".to_owned();
+        *record.start_mut() = 501;
+        *record.end_mut() = 520;
+        *record.strand_mut() = "+".to_owned();
+        let lifted = liftover.lift_gff(&record).unwrap();
+        assert_eq!(lifted.seqname(), "chrB");
+        assert_eq!((*lifted.start(), *lifted.end()), (231, 250));
+        assert_eq!(lifted.strand(), Some(Strand::Reverse));
+    }
+}
@@ -0,0 +1,314 @@
+//! Transparent (de)compression of gzip and BGZF files.
+//!
+//! On reading, the compression format is detected from the magic bytes at the start of the
+//! stream, such that plain text, gzip and BGZF input can be handled alike.
+//! On writing, the desired [`Format`] has to be chosen explicitly.
+//!
+//! The readers and writers of the other modules in `bio::io` use these types in their file based
+//! constructors, e.g. [`fasta::Reader::from_file`](crate::io::fasta::Reader::from_file) and
+//! [`fasta::Writer::to_file_with_compression`](crate::io::fasta::Writer::to_file_with_compression).
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::compression::{Decoder, Encoder, Format};
+//! use std::io::{Read, Write};
+//!
+//! let mut encoder = Encoder::new(Vec::new(), Format::Gzip);
+//! encoder.write_all(b">id\nACGT\n").unwrap();
+//! let compressed = encoder.finish().unwrap();
+//!
+//! let mut decoder = Decoder::new(&compressed[..]).unwrap();
+//! assert_eq!(decoder.format(), Format::Gzip);
+//! let mut text = String::new();
+//! decoder.read_to_string(&mut text).unwrap();
+//! assert_eq!(text, ">id\nACGT\n");
+//! ```
+
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::io::bgzf;
+
+/// Compression format of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// No compression.
+    Plain,
+    /// Regular gzip compression.
+    Gzip,
+    /// Blocked gzip compression, as produced by `bgzip`.
+    Bgzf,
+}
+
+impl Format {
+    /// Detect the compression format from the first bytes of a file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bio::io::compression::Format;
+    ///
+    /// assert_eq!(Format::detect(b">id\nACGT\n"), Format::Plain);
+    /// assert_eq!(Format::detect(&[0x1f, 0x8b, 0x08, 0x00]), Format::Gzip);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bgzf::is_bgzf(bytes) {
+            Format::Bgzf
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Format::Gzip
+        } else {
+            Format::Plain
+        }
+    }
+}
+
+/// A reader that transparently decompresses gzip and BGZF input and passes through
+/// anything else.
+pub struct Decoder<R: io::Read> {
+    inner: DecoderInner<R>,
+    format: Format,
+}
+
+/// The wrapped reader, preceded by the bytes that were consumed for format detection.
+type Source<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+enum DecoderInner<R: io::Read> {
+    Plain(io::BufReader<Source<R>>),
+    Gzip(io::BufReader<MultiGzDecoder<io::BufReader<Source<R>>>>),
+}
+
+/// Number of bytes needed to detect the compression format.
+const MAGIC_LEN: usize = 16;
+
+impl<R: io::Read> Decoder<R> {
+    /// Create a new decoder, detecting the compression format from the first bytes of the
+    /// given reader.
+    ///
+    /// # Errors
+    /// If the first bytes can't be read.
+    pub fn new(reader: R) -> io::Result<Self> {
+        let (format, source) = Self::detect(reader)?;
+        Ok(Self::from_bufreader(format, io::BufReader::new(source)))
+    }
+
+    /// Create a new decoder with the given buffer capacity.
+    pub fn with_capacity(capacity: usize, reader: R) -> io::Result<Self> {
+        let (format, source) = Self::detect(reader)?;
+        Ok(Self::from_bufreader(
+            format,
+            io::BufReader::with_capacity(capacity, source),
+        ))
+    }
+
+    /// Read the first bytes of the given reader to detect its format. Short reads, as from
+    /// pipes, are retried until enough bytes are available or the input ends.
+    fn detect(mut reader: R) -> io::Result<(Format, Source<R>)> {
+        let mut magic = vec![0; MAGIC_LEN];
+        let mut len = 0;
+        while len < MAGIC_LEN {
+            match reader.read(&mut magic[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        magic.truncate(len);
+        let format = Format::detect(&magic);
+        Ok((format, io::Cursor::new(magic).chain(reader)))
+    }
+
+    fn from_bufreader(format: Format, reader: io::BufReader<Source<R>>) -> Self {
+        let capacity = reader.capacity();
+        let inner = match format {
+            Format::Plain => DecoderInner::Plain(reader),
+            Format::Gzip | Format::Bgzf => DecoderInner::Gzip(io::BufReader::with_capacity(
+                capacity,
+                MultiGzDecoder::new(reader),
+            )),
+        };
+        Decoder { inner, format }
+    }
+
+    /// The detected compression format.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
+
+impl<R: io::Read> fmt::Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl<R: io::Read> io::Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            DecoderInner::Plain(reader) => reader.read(buf),
+            DecoderInner::Gzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: io::Read> io::BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match &mut self.inner {
+            DecoderInner::Plain(reader) => reader.fill_buf(),
+            DecoderInner::Gzip(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.inner {
+            DecoderInner::Plain(reader) => reader.consume(amt),
+            DecoderInner::Gzip(reader) => reader.consume(amt),
+        }
+    }
+}
+
+/// A writer that compresses its output in the chosen [`Format`].
+///
+/// Compressed output is only complete after calling [`finish`](Encoder::finish), or after the
+/// encoder has been dropped.
+pub enum Encoder<W: io::Write> {
+    /// Uncompressed output.
+    Plain(W),
+    /// Gzip compressed output.
+    Gzip(GzEncoder<W>),
+    /// BGZF compressed output.
+    Bgzf(bgzf::Writer<W>),
+}
+
+impl<W: io::Write> Encoder<W> {
+    /// Create a new encoder with the given format and default compression level.
+    pub fn new(writer: W, format: Format) -> Self {
+        match format {
+            Format::Plain => Encoder::Plain(writer),
+            Format::Gzip => Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default())),
+            Format::Bgzf => Encoder::Bgzf(bgzf::Writer::new(writer)),
+        }
+    }
+
+    /// The compression format of this encoder.
+    pub fn format(&self) -> Format {
+        match self {
+            Encoder::Plain(_) => Format::Plain,
+            Encoder::Gzip(_) => Format::Gzip,
+            Encoder::Bgzf(_) => Format::Bgzf,
+        }
+    }
+
+    /// Write all remaining compressed data and return the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+            Encoder::Gzip(writer) => writer.finish(),
+            Encoder::Bgzf(writer) => writer.finish(),
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Debug for Encoder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encoder")
+            .field("format", &self.format())
+            .finish()
+    }
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(writer) => writer.write(buf),
+            Encoder::Bgzf(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(writer) => writer.flush(),
+            Encoder::Bgzf(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b">id desc\nACCGTAGGCTGA\n";
+
+    fn roundtrip(format: Format) {
+        let mut encoder = Encoder::new(Vec::new(), format);
+        encoder.write_all(TEXT).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(&compressed[..]).unwrap();
+        assert_eq!(decoder.format(), format);
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, TEXT);
+    }
+
+    #[test]
+    fn test_roundtrip_plain() {
+        roundtrip(Format::Plain);
+    }
+
+    #[test]
+    fn test_roundtrip_gzip() {
+        roundtrip(Format::Gzip);
+    }
+
+    #[test]
+    fn test_roundtrip_bgzf() {
+        roundtrip(Format::Bgzf);
+    }
+
+    /// A reader returning a single byte per read, like a slow pipe.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_decoder_short_reads() {
+        for &format in &[Format::Plain, Format::Gzip, Format::Bgzf] {
+            let mut encoder = Encoder::new(Vec::new(), format);
+            encoder.write_all(TEXT).unwrap();
+            let compressed = encoder.finish().unwrap();
+
+            let mut decoder = Decoder::new(Trickle(&compressed)).unwrap();
+            assert_eq!(decoder.format(), format);
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed).unwrap();
+            assert_eq!(decompressed, TEXT);
+        }
+    }
+
+    #[test]
+    fn test_decoder_empty_input() {
+        let mut decoder = Decoder::new(&b""[..]).unwrap();
+        assert_eq!(decoder.format(), Format::Plain);
+        assert!(decoder.fill_buf().unwrap().is_empty());
+    }
+}
@@ -106,6 +106,8 @@
 //!
 //! Random access to FASTA files is facilitated by [`Index`] and [`IndexedReader`]. The FASTA files
 //! must already be indexed with [`samtools faidx`](https://www.htslib.org/doc/faidx.html).
+//! FASTA files compressed with `bgzip` are supported as well, given their BGZF block index
+//! (.gzi), see [`IndexedReader::from_bgzf_file`].
 //!
 //! In this example, we read in the first 10 bases of the sequence named "chr1".
 //!
@@ -138,40 +140,65 @@ use std::io;
 use std::io::prelude::*;
 use std::path::Path;
 
+use crate::io::bgzf;
+use crate::io::compression::{Decoder, Encoder, Format};
 use crate::utils::{Text, TextSlice};
 use anyhow::Context;
 use std::fmt;
+use thiserror::Error;
+
+/// An error that occurs while reading FASTA records. Lines are counted from one and byte
+/// offsets from zero, both referring to the start of the offending line.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line} (byte {offset}): expected '>' at record start")]
+    MissingHeader { line: usize, offset: u64 },
+
+    #[error("line {line} (byte {offset}): header is not valid UTF-8")]
+    InvalidHeader { line: usize, offset: u64 },
+
+    #[error("line {line} (byte {offset}): sequence is not valid UTF-8")]
+    InvalidSequence { line: usize, offset: u64 },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Maximum size of temporary buffer used for reading indexed FASTA files.
 const MAX_FASTA_BUFFER_SIZE: usize = 512;
 
 /// Trait for FASTA readers.
 pub trait FastaRead {
-    fn read(&mut self, record: &mut Record) -> io::Result<()>;
+    fn read(&mut self, record: &mut Record) -> Result<()>;
 }
 
 /// A FASTA reader.
 #[derive(Debug)]
 pub struct Reader<B> {
     reader: B,
-    line: String,
+    buffers: Buffers,
 }
 
-impl Reader<io::BufReader<fs::File>> {
+impl Reader<Decoder<fs::File>> {
     /// Read FASTA from given file path.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
         fs::File::open(&path)
-            .map(Reader::new)
+            .and_then(Decoder::new)
+            .map(Reader::from_bufread)
             .with_context(|| format!("Failed to read fasta from {:#?}", path))
     }
 
-    /// Read FASTA from give file path and a capacity
+    /// Read FASTA from give file path and a capacity.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
     pub fn from_file_with_capacity<P: AsRef<Path> + std::fmt::Debug>(
         capacity: usize,
         path: P,
     ) -> anyhow::Result<Self> {
         fs::File::open(&path)
-            .map(|file| Reader::with_capacity(capacity, file))
+            .and_then(|file| Decoder::with_capacity(capacity, file))
+            .map(Reader::from_bufread)
             .with_context(|| format!("Failed to read fasta from {:#?}", path))
     }
 }
@@ -196,7 +223,7 @@ where
     pub fn new(reader: R) -> Self {
         Reader {
             reader: io::BufReader::new(reader),
-            line: String::new(),
+            buffers: Buffers::default(),
         }
     }
 
@@ -216,7 +243,7 @@ where
     pub fn with_capacity(capacity: usize, reader: R) -> Self {
         Reader {
             reader: io::BufReader::with_capacity(capacity, reader),
-            line: String::new(),
+            buffers: Buffers::default(),
         }
     }
 }
@@ -242,7 +269,7 @@ where
     pub fn from_bufread(bufreader: B) -> Self {
         Reader {
             reader: bufreader,
-            line: String::new(),
+            buffers: Buffers::default(),
         }
     }
 
@@ -272,6 +299,17 @@ where
             error_has_occured: false,
         }
     }
+
+    /// Return a streaming parser over the records of this Fasta file, which yields records
+    /// borrowing from reused internal buffers instead of allocating each record.
+    /// See [`RefRecords`](RefRecords) for an example.
+    pub fn ref_records(self) -> RefRecords<B> {
+        RefRecords {
+            reader: self.reader,
+            buffers: self.buffers,
+            error_has_occured: false,
+        }
+    }
 }
 
 impl<B> FastaRead for Reader<B>
@@ -307,45 +345,103 @@ where
     /// // Check for errors parsing the record
     /// reader
     ///     .read(&mut record)
-    ///     .expect("fasta reader: got an io::Error or could not read_line()");
+    ///     .expect("fasta reader: could not read record");
     ///
     /// assert_eq!(record.id(), "id");
     /// assert_eq!(record.desc().unwrap(), "desc");
     /// assert_eq!(record.seq().to_vec(), b"AAAA");
     /// ```
-    fn read(&mut self, record: &mut Record) -> io::Result<()> {
+    fn read(&mut self, record: &mut Record) -> Result<()> {
         record.clear();
+        if self.buffers.read(&mut self.reader)? {
+            let (id, desc) = self.buffers.header_fields();
+            record.id.push_str(id);
+            record.desc = desc.map(|desc| desc.to_owned());
+            // sequence lines have been checked to be valid UTF-8 while reading
+            record.seq.push_str(utf8(&self.buffers.seq)?);
+        }
+        Ok(())
+    }
+}
+
+/// Interpret the given bytes as UTF-8 text.
+fn utf8(bytes: &[u8]) -> io::Result<&str> {
+    std::str::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The buffers of a FASTA parser along with its position in the input, shared by
+/// [`Reader`](Reader) and [`RefRecords`](RefRecords).
+#[derive(Debug, Default)]
+struct Buffers {
+    line: Vec<u8>,
+    header: String,
+    seq: Vec<u8>,
+    line_number: usize,
+    offset: u64,
+}
+
+impl Buffers {
+    /// Read the next record into the buffers, returning whether there was one. The header of
+    /// the following record is kept in the line buffer.
+    fn read<B: io::BufRead>(&mut self, reader: &mut B) -> Result<bool> {
+        self.header.clear();
+        self.seq.clear();
         if self.line.is_empty() {
-            self.reader.read_line(&mut self.line)?;
+            self.next_line(reader)?;
             if self.line.is_empty() {
-                return Ok(());
+                return Ok(false);
             }
         }
 
-        if !self.line.starts_with('>') {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Expected > at record start.",
-            ));
+        if self.line[0] != b'>' {
+            return Err(Error::MissingHeader {
+                line: self.line_number,
+                offset: self.offset,
+            });
         }
-        let mut header_fields = self.line[1..].trim_end().splitn(2, char::is_whitespace);
-        record.id = header_fields.next().map(|s| s.to_owned()).unwrap();
-        record.desc = header_fields.next().map(|s| s.to_owned());
+        let header =
+            std::str::from_utf8(trim_end(&self.line[1..])).map_err(|_| Error::InvalidHeader {
+                line: self.line_number,
+                offset: self.offset,
+            })?;
+        self.header.push_str(header);
         loop {
-            self.line.clear();
-            self.reader.read_line(&mut self.line)?;
-            if self.line.is_empty() || self.line.starts_with('>') {
+            self.next_line(reader)?;
+            if self.line.is_empty() || self.line[0] == b'>' {
                 break;
             }
-            record.seq.push_str(self.line.trim_end());
+            let seq = trim_end(&self.line);
+            if std::str::from_utf8(seq).is_err() {
+                return Err(Error::InvalidSequence {
+                    line: self.line_number,
+                    offset: self.offset,
+                });
+            }
+            self.seq.extend_from_slice(seq);
         }
 
+        Ok(true)
+    }
+
+    /// Read the next line into the line buffer, keeping track of its position.
+    fn next_line<B: io::BufRead>(&mut self, reader: &mut B) -> io::Result<()> {
+        self.offset += self.line.len() as u64;
+        self.line.clear();
+        if reader.read_until(b'\n', &mut self.line)? > 0 {
+            self.line_number += 1;
+        }
         Ok(())
     }
+
+    /// Split the header into id and optional description.
+    fn header_fields(&self) -> (&str, Option<&str>) {
+        let mut header_fields = self.header.splitn(2, char::is_whitespace);
+        (header_fields.next().unwrap(), header_fields.next())
+    }
 }
 
 /// A FASTA index as created by SAMtools (.fai).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Index {
     inner: Vec<IndexRecord>,
     name_to_rid: collections::HashMap<String, usize>,
@@ -386,6 +482,127 @@ impl Index {
         Self::from_file(&fai_path)
     }
 
+    /// Build a FASTA index by scanning the given FASTA, like `samtools faidx` does.
+    /// For compressed FASTA files, pass a [`Decoder`](crate::io::compression::Decoder), such
+    /// that offsets refer to the uncompressed sequence.
+    ///
+    /// # Errors
+    /// If the FASTA is malformed, contains duplicate sequence names, or if the lines of a
+    /// sequence are wrapped inconsistently. All lines of a sequence except the last one need to
+    /// have the same length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bio::io::fasta::Index;
+    ///
+    /// let fasta: &[u8] = b">chr1\nGTAGGCTGAAAA\nCCCC\n>chr2\nACGT\n";
+    /// let index = Index::build(fasta).unwrap();
+    /// let mut fai = Vec::new();
+    /// index.write(&mut fai).unwrap();
+    /// assert_eq!(fai, b"chr1\t16\t6\t12\t13\nchr2\t4\t30\t4\t5\n");
+    /// ```
+    pub fn build<R: io::Read>(fasta: R) -> io::Result<Self> {
+        let invalid = |msg: &str, line_no: usize| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} (line {})", msg, line_no),
+            )
+        };
+
+        let mut index = Index::default();
+        let mut reader = io::BufReader::new(fasta);
+        let mut line = Vec::new();
+        let mut offset = 0;
+        let mut line_no = 0;
+        let mut current: Option<IndexRecord> = None;
+        // whether the current sequence already had a line shorter than its first one
+        let mut short_line_seen = false;
+
+        loop {
+            line.clear();
+            let line_bytes = reader.read_until(b'\n', &mut line)? as u64;
+            if line_bytes == 0 {
+                break;
+            }
+            line_no += 1;
+            offset += line_bytes;
+
+            if line[0] == b'>' {
+                if let Some(record) = current.take() {
+                    index.push(record)?;
+                }
+                let name = line[1..]
+                    .split(|c| c.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                let name = String::from_utf8(name.to_vec())
+                    .map_err(|_| invalid("Invalid UTF-8 in FASTA header.", line_no))?;
+                current = Some(IndexRecord {
+                    name,
+                    len: 0,
+                    offset,
+                    line_bases: 0,
+                    line_bytes: 0,
+                });
+                short_line_seen = false;
+                continue;
+            }
+
+            let record = current
+                .as_mut()
+                .ok_or_else(|| invalid("Expected > at record start.", line_no))?;
+            let line_bases = line
+                .iter()
+                .rposition(|c| !c.is_ascii_whitespace())
+                .map_or(0, |pos| pos + 1) as u64;
+            if line_bases == 0 {
+                // empty lines may only occur at the end of a sequence
+                short_line_seen = true;
+                continue;
+            }
+            if short_line_seen {
+                return Err(invalid(
+                    "Inconsistent line length in FASTA sequence.",
+                    line_no,
+                ));
+            }
+            if record.line_bases == 0 {
+                record.line_bases = line_bases;
+                record.line_bytes = line_bytes;
+            } else if line_bases > record.line_bases
+                || (line_bases == record.line_bases
+                    && line_bytes != record.line_bytes
+                    && line.ends_with(b"\n"))
+            {
+                return Err(invalid(
+                    "Inconsistent line length in FASTA sequence.",
+                    line_no,
+                ));
+            } else if line_bases < record.line_bases {
+                short_line_seen = true;
+            }
+            record.len += line_bases;
+        }
+        if let Some(record) = current {
+            index.push(record)?;
+        }
+
+        Ok(index)
+    }
+
+    /// Write the index in .fai format.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        for record in &self.inner {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                record.name, record.len, record.offset, record.line_bases, record.line_bytes
+            )?;
+        }
+        Ok(())
+    }
+
     /// Return a vector of sequences described in the index.
     pub fn sequences(&self) -> Vec<Sequence> {
         // sort kv pairs by rid to preserve order
@@ -397,6 +614,26 @@ impl Index {
             })
             .collect()
     }
+
+    /// Ensure that the given sequence name is not yet part of the index.
+    fn check_name(&self, name: &str) -> io::Result<()> {
+        if self.name_to_rid.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Duplicate sequence name in FASTA: {}.", name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Append a record, ensuring that sequence names stay unique.
+    fn push(&mut self, record: IndexRecord) -> io::Result<()> {
+        self.check_name(&record.name)?;
+        self.name_to_rid
+            .insert(record.name.clone(), self.inner.len());
+        self.inner.push(record);
+        Ok(())
+    }
 }
 
 /// A FASTA reader with an index as created by SAMtools (.fai).
@@ -421,6 +658,56 @@ impl IndexedReader<fs::File> {
     }
 }
 
+impl IndexedReader<bgzf::Reader<fs::File>> {
+    /// Read from a given path to a BGZF compressed FASTA file. This assumes the index
+    /// ref.fasta.gz.fai and the BGZF block index ref.fasta.gz.gzi to be present for
+    /// FASTA ref.fasta.gz, as created by `bgzip -i` and `samtools faidx`.
+    pub fn from_bgzf_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        let index = Index::with_fasta_file(path)?;
+        let gzi = bgzf::GziIndex::with_bgzf_file(path)?;
+        fs::File::open(&path)
+            .map(|f| Self::with_index(bgzf::Reader::with_index(f, gzi), index))
+            .map_err(csv::Error::from)
+            .with_context(|| format!("Failed to read fasta from {:#?}", path))
+    }
+}
+
+impl<R: io::Read + io::Seek> IndexedReader<bgzf::Reader<R>> {
+    /// Read from a BGZF compressed FASTA, its index and its BGZF block index, all given as
+    /// `io::Read`. FASTA has to be `io::Seek` in addition. Offsets in the index refer to the
+    /// uncompressed sequence and are translated into BGZF virtual offsets with the block index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bio::io::bgzf;
+    /// use bio::io::fasta::IndexedReader;
+    /// use std::io::{Cursor, Write};
+    ///
+    /// let mut writer = bgzf::Writer::new(Vec::new());
+    /// writer.write_all(b">chr1\nGTAGGCTGAAAA\nCCCC").unwrap();
+    /// writer.flush().unwrap();
+    /// let mut gzi = Vec::new();
+    /// writer.index().write(&mut gzi).unwrap();
+    /// let fasta = writer.finish().unwrap();
+    ///
+    /// let fai: &[u8] = b"chr1\t16\t6\t12\t13";
+    /// let mut faidx = IndexedReader::new_bgzf(Cursor::new(fasta), fai, &gzi[..]).unwrap();
+    /// faidx.fetch("chr1", 10, 14).unwrap();
+    /// let mut seq = Vec::new();
+    /// faidx.read(&mut seq).unwrap();
+    /// assert_eq!(seq, b"AACC");
+    /// ```
+    pub fn new_bgzf<I: io::Read, G: io::Read>(fasta: R, fai: I, gzi: G) -> csv::Result<Self> {
+        let index = Index::new(fai)?;
+        let gzi = bgzf::GziIndex::new(gzi)?;
+        Ok(Self::with_index(
+            bgzf::Reader::with_index(fasta, gzi),
+            index,
+        ))
+    }
+}
+
 impl<R: io::Read + io::Seek> IndexedReader<R> {
     /// Read from a FASTA and its index, both given as `io::Read`. FASTA has to
     /// be `io::Seek` in addition.
@@ -793,6 +1080,9 @@ impl<'a, R: io::Read + io::Seek + 'a> Iterator for IndexedReaderIterator<'a, R>
 #[derive(Debug)]
 pub struct Writer<W: io::Write> {
     writer: io::BufWriter<W>,
+    line_width: Option<usize>,
+    index: Option<Index>,
+    offset: u64,
 }
 
 impl Writer<fs::File> {
@@ -808,24 +1098,95 @@ impl Writer<fs::File> {
     }
 }
 
+impl Writer<Encoder<fs::File>> {
+    /// Write to the given file path, compressing the output in the given format.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bio::io::compression::Format;
+    /// use bio::io::fasta::{Reader, Writer};
+    ///
+    /// let path = std::env::temp_dir().join("to_file_with_compression.fa.gz");
+    /// {
+    ///     let mut writer = Writer::to_file_with_compression(&path, Format::Bgzf).unwrap();
+    ///     writer.write("id", None, b"ACGT").unwrap();
+    /// }
+    /// let record = Reader::from_file(&path).unwrap().records().next().unwrap().unwrap();
+    /// assert_eq!(record.seq(), b"ACGT");
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn to_file_with_compression<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+        fs::File::create(path).map(|file| Writer::new(Encoder::new(file, format)))
+    }
+}
+
 impl<W: io::Write> Writer<W> {
     /// Create a new Fasta writer.
     pub fn new(writer: W) -> Self {
-        Writer {
-            writer: io::BufWriter::new(writer),
-        }
+        Self::from_bufwriter(io::BufWriter::new(writer))
     }
 
     /// Create a new Fasta writer with a capacity of write buffer
     pub fn with_capacity(capacity: usize, writer: W) -> Self {
-        Writer {
-            writer: io::BufWriter::with_capacity(capacity, writer),
-        }
+        Self::from_bufwriter(io::BufWriter::with_capacity(capacity, writer))
     }
 
     /// Create a new Fasta writer with a given BufWriter
     pub fn from_bufwriter(bufwriter: io::BufWriter<W>) -> Self {
-        Writer { writer: bufwriter }
+        Writer {
+            writer: bufwriter,
+            line_width: None,
+            index: None,
+            offset: 0,
+        }
+    }
+
+    /// Wrap sequences into lines of at most the given number of bases.
+    /// By default, each sequence is written on a single line.
+    ///
+    /// # Panics
+    /// If `line_width` is zero.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        assert!(line_width > 0, "line width must be positive");
+        self.line_width = Some(line_width);
+        self
+    }
+
+    /// Build a FASTA index (.fai) of all records while writing them. The index can be
+    /// obtained via [`index`](Writer::index). Offsets refer to the uncompressed output.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bio::io::fasta::{IndexedReader, Writer};
+    /// use std::fs;
+    ///
+    /// let path = std::env::temp_dir().join("track_index.fa");
+    /// {
+    ///     let mut writer = Writer::to_file(&path).unwrap().line_width(4).track_index();
+    ///     writer.write("chr1", Some("desc"), b"GTAGGCTGAAAACCCC").unwrap();
+    ///     writer.write("chr2", None, b"ACG").unwrap();
+    ///     let mut fai_path = path.clone().into_os_string();
+    ///     fai_path.push(".fai");
+    ///     let index = writer.index().unwrap();
+    ///     index.write(fs::File::create(fai_path).unwrap()).unwrap();
+    /// }
+    ///
+    /// let mut reader = IndexedReader::from_file(&path).unwrap();
+    /// reader.fetch("chr1", 3, 9).unwrap();
+    /// let mut seq = Vec::new();
+    /// reader.read(&mut seq).unwrap();
+    /// assert_eq!(seq, b"GGCTGA");
+    /// # fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn track_index(mut self) -> Self {
+        self.index = Some(Index::default());
+        self
+    }
+
+    /// The index of all records written so far, if enabled via
+    /// [`track_index`](Writer::track_index).
+    pub fn index(&self) -> Option<&Index> {
+        self.index.as_ref()
     }
 
     /// Directly write a [`fasta::Record`](struct.Record.html).
@@ -862,16 +1223,54 @@ impl<W: io::Write> Writer<W> {
     }
 
     /// Write a Fasta record with given id, optional description and sequence.
+    ///
+    /// # Errors
+    /// If there is an issue writing to the `Writer`, or if the index is tracked and the id
+    /// has been written before.
     pub fn write(&mut self, id: &str, desc: Option<&str>, seq: TextSlice<'_>) -> io::Result<()> {
+        // reject duplicates before anything is written
+        if let Some(index) = self.index.as_ref() {
+            index.check_name(id)?;
+        }
         self.writer.write_all(b">")?;
         self.writer.write_all(id.as_bytes())?;
+        self.offset += id.len() as u64 + 2;
         if let Some(desc) = desc {
             self.writer.write_all(b" ")?;
             self.writer.write_all(desc.as_bytes())?;
+            self.offset += desc.len() as u64 + 1;
         }
         self.writer.write_all(b"\n")?;
-        self.writer.write_all(seq)?;
-        self.writer.write_all(b"\n")?;
+        let seq_offset = self.offset;
+
+        let line_bases = match self.line_width {
+            Some(line_width) if seq.len() > line_width => line_width,
+            _ => seq.len(),
+        };
+        if seq.is_empty() {
+            self.writer.write_all(b"\n")?;
+            self.offset += 1;
+        } else {
+            for line in seq.chunks(line_bases) {
+                self.writer.write_all(line)?;
+                self.writer.write_all(b"\n")?;
+                self.offset += line.len() as u64 + 1;
+            }
+        }
+
+        if let Some(index) = self.index.as_mut() {
+            index.push(IndexRecord {
+                name: id.to_owned(),
+                len: seq.len() as u64,
+                offset: seq_offset,
+                line_bases: line_bases as u64,
+                line_bytes: if seq.is_empty() {
+                    0
+                } else {
+                    line_bases as u64 + 1
+                },
+            })?;
+        }
 
         Ok(())
     }
@@ -1021,9 +1420,9 @@ impl<B> Iterator for Records<B>
 where
     B: io::BufRead,
 {
-    type Item = io::Result<Record>;
+    type Item = Result<Record>;
 
-    fn next(&mut self) -> Option<io::Result<Record>> {
+    fn next(&mut self) -> Option<Result<Record>> {
         if self.error_has_occured {
             None
         } else {
@@ -1040,6 +1439,107 @@ where
     }
 }
 
+/// A FASTA record borrowing its data from the buffers of a [`RefRecords`](RefRecords)
+/// parser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefRecord<'a> {
+    id: &'a str,
+    desc: Option<&'a str>,
+    seq: &'a [u8],
+}
+
+impl<'a> RefRecord<'a> {
+    /// Return the id of the record.
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Return descriptions if present.
+    pub fn desc(&self) -> Option<&'a str> {
+        self.desc
+    }
+
+    /// Return the sequence of the record.
+    pub fn seq(&self) -> TextSlice<'a> {
+        self.seq
+    }
+
+    /// Copy the borrowed data into an owned [`Record`](Record).
+    /// Invalid UTF-8 in the sequence is replaced by `U+FFFD`.
+    pub fn to_owned(&self) -> Record {
+        Record {
+            id: self.id.to_owned(),
+            desc: self.desc.map(|desc| desc.to_owned()),
+            seq: String::from_utf8_lossy(self.seq).into_owned(),
+        }
+    }
+}
+
+/// A streaming parser over the records of a Fasta file, yielding [`RefRecord`](RefRecord)s
+/// that borrow from internal buffers. The buffers are reused for every record, such that no
+/// allocations happen per record once they have grown to the size of the largest record.
+///
+/// As records borrow from the parser, this can't implement `Iterator`. Instead, records are
+/// obtained with the [`next`](RefRecords::next) method in a `while let` loop.
+///
+/// # Example
+///
+/// ```rust
+/// use bio::io::fasta::Reader;
+///
+/// let fasta: &[u8] = b">id1 desc\nACGT\nGG\n>id2\nTTTT\n";
+/// let mut records = Reader::new(fasta).ref_records();
+/// let mut total_len = 0;
+/// while let Some(record) = records.next() {
+///     let record = record.unwrap();
+///     total_len += record.seq().len();
+/// }
+/// assert_eq!(total_len, 10);
+/// ```
+#[derive(Debug)]
+pub struct RefRecords<B> {
+    reader: B,
+    buffers: Buffers,
+    error_has_occured: bool,
+}
+
+impl<B> RefRecords<B>
+where
+    B: io::BufRead,
+{
+    /// Parse the next record, returning `None` at the end of the input.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<RefRecord<'_>>> {
+        if self.error_has_occured {
+            return None;
+        }
+        match self.buffers.read(&mut self.reader) {
+            Ok(false) => None,
+            Ok(true) => {
+                let (id, desc) = self.buffers.header_fields();
+                Some(Ok(RefRecord {
+                    id,
+                    desc,
+                    seq: &self.buffers.seq,
+                }))
+            }
+            Err(err) => {
+                self.error_has_occured = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Strip trailing whitespace, including line breaks, from the given line.
+fn trim_end(line: &[u8]) -> &[u8] {
+    let len = line
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(0, |pos| pos + 1);
+    &line[..len]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1180,6 +1680,42 @@ ATTGTTGTTTTA
         );
     }
 
+    #[test]
+    fn test_ref_records() {
+        let expected: Vec<Record> = Reader::new(FASTA_FILE)
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let mut records = Reader::new(FASTA_FILE).ref_records();
+        let mut actual = Vec::new();
+        while let Some(record) = records.next() {
+            actual.push(record.unwrap().to_owned());
+        }
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.id(), e.id());
+            assert_eq!(a.desc(), e.desc());
+            assert_eq!(a.seq(), e.seq());
+        }
+
+        // continue after reading the first record with the owning reader
+        let mut reader = Reader::new(FASTA_FILE);
+        let mut record = Record::new();
+        reader.read(&mut record).unwrap();
+        let mut records = reader.ref_records();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(record.id(), "id2");
+        assert_eq!(record.desc(), None);
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_ref_records_wrong_header() {
+        let mut records = Reader::new(&b"!test\nACGTA\n"[..]).ref_records();
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+    }
+
     #[test]
     fn test_reader_wrong_header() {
         let mut reader = Reader::new(&b"!test\nACGTA\n"[..]);
@@ -1190,6 +1726,55 @@ ATTGTTGTTTTA
         );
     }
 
+    #[test]
+    fn test_readers_reject_invalid_sequence() {
+        let fasta = b">id\nACGT\nAC\xffGT\n";
+        let mut records = Reader::new(&fasta[..]).records();
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::InvalidSequence { line: 3, offset: 9 }))
+        ));
+        let mut records = Reader::new(&fasta[..]).ref_records();
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::InvalidSequence { line: 3, offset: 9 }))
+        ));
+    }
+
+    #[test]
+    fn test_reader_error_position() {
+        let mut records = Reader::new(&b"ACGT\n>id\nACGT\n"[..]).records();
+        let err = records.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::MissingHeader { line: 1, offset: 0 }));
+        assert_eq!(
+            err.to_string(),
+            "line 1 (byte 0): expected '>' at record start"
+        );
+
+        let fasta = b">id1\nACGT\n>id2\nAC\nGT\n>\xffid3\nACGT\n";
+        let mut records = Reader::new(&fasta[..]).ref_records();
+        assert_eq!(records.next().unwrap().unwrap().id(), "id1");
+        assert_eq!(records.next().unwrap().unwrap().id(), "id2");
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::InvalidHeader {
+                line: 6,
+                offset: 21
+            })
+        ));
+
+        let mut records = Reader::new(&fasta[..]).records();
+        assert_eq!(records.next().unwrap().unwrap().id(), "id1");
+        assert_eq!(records.next().unwrap().unwrap().id(), "id2");
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::InvalidHeader {
+                line: 6,
+                offset: 21
+            })
+        ));
+    }
+
     #[test]
     fn test_reader_no_id() {
         let mut reader = Reader::new(&b">\nACGTA\n"[..]);
@@ -1299,6 +1884,48 @@ ATTGTTGTTTTA
         _test_indexed_reader_extreme_whitespace(_read_buffer);
     }
 
+    #[test]
+    fn test_indexed_reader_bgzf() {
+        // write the sequences often enough to span several BGZF blocks
+        let mut fasta = Vec::new();
+        let mut fai = Vec::new();
+        let seq_line = b"ACCGTAGGCTGACCGTAGGCTGAACGTAGGCTGAAAGTAGGCTGAAAACCCC\n";
+        for i in 0..3 {
+            let header = format!(">seq{}\n", i);
+            fasta.extend_from_slice(header.as_bytes());
+            writeln!(fai, "seq{}\t{}\t{}\t52\t53", i, 52 * 2000, fasta.len()).unwrap();
+            for _ in 0..2000 {
+                fasta.extend_from_slice(seq_line);
+            }
+        }
+        let mut writer = bgzf::Writer::new(Vec::new());
+        writer.write_all(&fasta).unwrap();
+        writer.flush().unwrap();
+        let mut gzi = Vec::new();
+        writer.index().write(&mut gzi).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut plain = IndexedReader::new(io::Cursor::new(&fasta), &fai[..]).unwrap();
+        let mut compressed_reader =
+            IndexedReader::new_bgzf(io::Cursor::new(&compressed), &fai[..], &gzi[..]).unwrap();
+        for &(name, start, stop) in &[
+            ("seq2", 100, 200),
+            ("seq0", 0, 52 * 2000),
+            ("seq1", 64_000, 67_000),
+            ("seq0", 1, 5),
+            ("seq2", 52 * 2000 - 3, 52 * 2000),
+        ] {
+            assert_eq!(
+                _read_buffer(&mut compressed_reader, name, start, stop).unwrap(),
+                _read_buffer(&mut plain, name, start, stop).unwrap()
+            );
+            assert_eq!(
+                _read_iter(&mut compressed_reader, name, start, stop).unwrap(),
+                _read_buffer(&mut plain, name, start, stop).unwrap()
+            );
+        }
+    }
+
     #[test]
     fn test_indexed_reader_crlf() {
         _test_indexed_reader(&FASTA_FILE_CRLF, &FAI_FILE_CRLF, _read_buffer);
@@ -1686,6 +2313,96 @@ ATTGTTGTTTTA
         assert_eq!(writer.writer.get_ref(), &WRITE_FASTA_FILE);
     }
 
+    #[test]
+    fn test_index_build() {
+        let index = Index::build(FASTA_FILE).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(fai, FAI_FILE);
+
+        let index = Index::build(FASTA_FILE_CRLF).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(
+            fai,
+            FAI_FILE_CRLF
+                .iter()
+                .filter(|&&c| c != b'\r')
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+
+        let index = Index::build(FASTA_FILE_NO_TRAILING_LF).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(fai, [FAI_FILE_NO_TRAILING_LF, b"\n"].concat());
+    }
+
+    #[test]
+    fn test_index_build_inconsistent_wrapping() {
+        // a short line in the middle of a sequence
+        assert!(Index::build(&b">id\nACGT\nAC\nACGT\n"[..]).is_err());
+        // a line longer than the first one
+        assert!(Index::build(&b">id\nACGT\nACGTA\n"[..]).is_err());
+        // an empty line in the middle of a sequence
+        assert!(Index::build(&b">id\nACGT\n\nACGT\n"[..]).is_err());
+        // a sequence without header
+        assert!(Index::build(&b"ACGT\n"[..]).is_err());
+        // duplicate names
+        assert!(Index::build(&b">id\nACGT\n>id\nACGT\n"[..]).is_err());
+        // trailing empty lines are fine
+        assert!(Index::build(&b">id\nACGT\nAC\n\n>id2\nA\n"[..]).is_ok());
+    }
+
+    #[test]
+    fn test_writer_line_width_index() {
+        let mut writer = Writer::new(Vec::new()).line_width(12).track_index();
+        writer
+            .write(
+                "id",
+                Some("desc"),
+                b"ACCGTAGGCTGACCGTAGGCTGAACGTAGGCTGAAAGTAGGCTGAAAACCCC",
+            )
+            .unwrap();
+        writer
+            .write("id2", None, b"ATTGTTGTTTTAATTGTTGTTTTAATTGTTGTTTTAGGGG")
+            .unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.writer.get_ref(), &FASTA_FILE);
+
+        let mut fai = Vec::new();
+        writer.index().unwrap().write(&mut fai).unwrap();
+        assert_eq!(fai, FAI_FILE);
+
+        assert!(writer.write("id", None, b"ACGT").is_err());
+    }
+
+    #[test]
+    fn test_writer_index_duplicate_name() {
+        let mut writer = Writer::new(Vec::new()).track_index();
+        writer.write("a", None, b"ACGT").unwrap();
+        assert!(writer.write("a", None, b"GG").is_err());
+        writer.flush().unwrap();
+        assert_eq!(writer.writer.get_ref(), b">a\nACGT\n");
+        assert_eq!(writer.index().unwrap().sequences().len(), 1);
+    }
+
+    #[test]
+    fn test_writer_index_matches_build() {
+        let mut writer = Writer::new(Vec::new()).line_width(3).track_index();
+        writer.write("a", None, b"ACGTACGTA").unwrap();
+        writer.write("b", Some("x y"), b"").unwrap();
+        writer.write("c", None, b"AC").unwrap();
+        writer.flush().unwrap();
+
+        let built = Index::build(&writer.writer.get_ref()[..]).unwrap();
+        let mut expected = Vec::new();
+        built.write(&mut expected).unwrap();
+        let mut actual = Vec::new();
+        writer.index().unwrap().write(&mut actual).unwrap();
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_display_record_no_desc_id_without_space_after() {
         let fasta: &'static [u8] = b">id\nACGT\n";
@@ -1769,13 +2486,30 @@ ATTGTTGTTTTA
         assert!(Writer::to_file_with_capacity(100, path).is_ok());
     }
 
+    #[test]
+    fn test_reader_from_gzip_file() {
+        let file = tempfile::NamedTempFile::new().expect("Could not create temp file");
+        {
+            let mut encoder = Encoder::new(fs::File::create(file.path()).unwrap(), Format::Gzip);
+            encoder.write_all(FASTA_FILE).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        let reader = Reader::from_file(file.path()).unwrap();
+        let ids: Vec<String> = reader
+            .records()
+            .map(|r| r.unwrap().id().to_owned())
+            .collect();
+        assert_eq!(ids, vec!["id", "id2"]);
+    }
+
     #[test]
     fn test_write_record() {
         let path = Path::new("test.fa");
         let file = fs::File::create(path).unwrap();
         {
             let handle = io::BufWriter::new(file);
-            let mut writer = Writer { writer: handle };
+            let mut writer = Writer::from_bufwriter(handle);
             let record = Record::with_attrs("id", Some("desc"), b"ACGT");
 
             let write_result = writer.write_record(&record);
@@ -102,18 +102,24 @@
 //! ```
 
 use anyhow::Context;
+use std::cmp::min;
+use std::collections;
 use std::convert::AsRef;
 use std::fmt;
 use std::fs;
 use std::io;
 use std::io::prelude::*;
+use std::mem;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+/// An error that occurs while reading FastQ records. Lines are counted from one and byte
+/// offsets from zero, both referring to the start of the offending line, which is the header
+/// of the record for errors concerning a record as a whole.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("expected '@' at record start")]
-    MissingAt,
+    #[error("line {line} (byte {offset}): expected '@' at record start")]
+    MissingAt { line: usize, offset: u64 },
 
     #[error("can't open {path} file: {source}")]
     FileOpen { path: PathBuf, source: io::Error },
@@ -121,13 +127,58 @@ pub enum Error {
     #[error("can't read input")]
     ReadError(#[from] io::Error),
 
-    #[error("Incomplete record. Each FastQ record has to consist of 4 lines: header, sequence, separator and qualities.")]
-    IncompleteRecord,
+    #[error("line {line} (byte {offset}): incomplete record. Each FastQ record has to consist of at least 4 lines: header, sequence, separator and qualities.")]
+    IncompleteRecord { line: usize, offset: u64 },
+
+    #[error("line {line} (byte {offset}): record {id} has {seq_len} bases but {qual_len} quality scores")]
+    LengthMismatch {
+        line: usize,
+        offset: u64,
+        id: String,
+        seq_len: usize,
+        qual_len: usize,
+    },
+
+    #[error("line {line} (byte {offset}): header is not valid UTF-8")]
+    InvalidHeader { line: usize, offset: u64 },
+
+    #[error("line {line} (byte {offset}): sequence is not valid UTF-8")]
+    InvalidSequence { line: usize, offset: u64 },
+
+    #[error("line {line} (byte {offset}): qualities are not valid UTF-8")]
+    InvalidQuality { line: usize, offset: u64 },
+
+    #[error("inconsistent line length in record {id}")]
+    InconsistentLineLength { id: String },
+
+    #[error("duplicate record name: {0}")]
+    DuplicateName(String),
+
+    #[error("unknown record name: {0}")]
+    UnknownRecord(String),
+
+    #[error("invalid index of record {0}: zero bases per line")]
+    InvalidIndex(String),
+
+    #[error("invalid interval {start}..{stop} for record of length {len}")]
+    InvalidInterval { start: u64, stop: u64, len: u64 },
+
+    #[error("mate names of read pair {index} don't match: {first} and {second}")]
+    MateMismatch {
+        index: usize,
+        first: String,
+        second: String,
+    },
+
+    #[error("read pair {index} is missing a mate")]
+    MissingMate { index: usize },
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 use bio_types::sequence::SequenceRead;
 
+use crate::io::compression::{Decoder, Encoder, Format};
+use crate::stats::{LogProb, PHREDProb, Prob};
 use crate::utils::TextSlice;
 
 /// Trait for FastQ readers.
@@ -139,18 +190,20 @@ pub trait FastqRead {
 #[derive(Debug)]
 pub struct Reader<B> {
     reader: B,
-    line_buffer: String,
+    buffers: Buffers,
 }
 
-impl Reader<io::BufReader<fs::File>> {
+impl Reader<Decoder<fs::File>> {
     /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
         fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
             .map_err(|e| Error::FileOpen {
                 path: path.as_ref().to_owned(),
                 source: e,
             })
-            .map(Reader::new)
+            .map(Reader::from_bufread)
             .with_context(|| format!("Failed to read fastq from {:#?}", path))
     }
 }
@@ -160,7 +213,7 @@ impl<R: io::Read> Reader<io::BufReader<R>> {
     pub fn new(reader: R) -> Self {
         Reader {
             reader: io::BufReader::new(reader),
-            line_buffer: String::new(),
+            buffers: Buffers::default(),
         }
     }
 
@@ -168,7 +221,7 @@ impl<R: io::Read> Reader<io::BufReader<R>> {
     pub fn with_capacity(capacity: usize, reader: R) -> Self {
         Reader {
             reader: io::BufReader::with_capacity(capacity, reader),
-            line_buffer: String::new(),
+            buffers: Buffers::default(),
         }
     }
 }
@@ -181,7 +234,7 @@ where
     pub fn from_bufread(bufreader: B) -> Self {
         Reader {
             reader: bufreader,
-            line_buffer: String::new(),
+            buffers: Buffers::default(),
         }
     }
 
@@ -208,6 +261,17 @@ where
     pub fn records(self) -> Records<B> {
         Records { reader: self }
     }
+
+    /// Return a streaming parser over the records of this FastQ file, which yields records
+    /// borrowing from reused internal buffers instead of allocating each record.
+    /// See [`RefRecords`](RefRecords) for an example.
+    pub fn ref_records(self) -> RefRecords<B> {
+        RefRecords {
+            reader: self.reader,
+            buffers: self.buffers,
+            error_has_occured: false,
+        }
+    }
 }
 
 impl<B> FastqRead for Reader<B>
@@ -224,13 +288,15 @@ where
     /// [records](Reader::records) iterator.
     ///
     /// FastQ files with wrapped sequence and quality strings are allowed.
+    /// Regular 4-line records are parsed line by line. For wrapped records, all lines up to
+    /// the `+` separator are taken as sequence, and quality lines are read until they
+    /// cover the whole sequence, such that the qualities may be wrapped differently.
+    /// Records whose sequence and qualities differ in length are rejected.
     ///
     /// # Errors
     ///
     /// This function will return an error if the record is incomplete,
     /// syntax is violated or any form of I/O error is encountered.
-    /// Additionally, if the FastQ file has line-wrapped records, and the wrapping is not
-    /// consistent between the sequence and quality string for a record, parsing will fail.
     ///
     /// # Example
     ///
@@ -254,44 +320,139 @@ where
     /// ```
     fn read(&mut self, record: &mut Record) -> Result<()> {
         record.clear();
-        self.line_buffer.clear();
+        if self.buffers.read(&mut self.reader)? {
+            let (id, desc) = self.buffers.header_fields();
+            record.id.push_str(id);
+            record.desc = desc.map(|desc| desc.to_owned());
+            // sequence and quality lines have been checked to be valid UTF-8 while reading
+            record.seq.push_str(utf8(&self.buffers.seq)?);
+            record.qual.push_str(utf8(&self.buffers.qual)?);
+        }
+        Ok(())
+    }
+}
 
-        self.reader.read_line(&mut self.line_buffer)?;
+/// Interpret the given bytes as UTF-8 text.
+fn utf8(bytes: &[u8]) -> io::Result<&str> {
+    std::str::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The given line without trailing whitespace, if it is valid UTF-8.
+fn text(line: &[u8]) -> Option<&str> {
+    std::str::from_utf8(trim_end(line)).ok()
+}
+
+/// The buffers of a FastQ parser along with its position in the input, shared by
+/// [`Reader`](Reader) and [`RefRecords`](RefRecords).
+#[derive(Debug, Default)]
+struct Buffers {
+    line: Vec<u8>,
+    header: String,
+    seq: Vec<u8>,
+    qual: Vec<u8>,
+    line_number: usize,
+    offset: u64,
+}
 
-        if !self.line_buffer.is_empty() {
-            if !self.line_buffer.starts_with('@') {
-                return Err(Error::MissingAt);
+impl Buffers {
+    /// Read the next record into the buffers, returning whether there was one.
+    ///
+    /// All lines up to the `+` separator are taken as sequence. A single sequence line is
+    /// followed by a single quality line, whereas the qualities of wrapped sequences are read
+    /// until they cover the whole sequence.
+    fn read<B: io::BufRead>(&mut self, reader: &mut B) -> Result<bool> {
+        self.header.clear();
+        self.seq.clear();
+        self.qual.clear();
+
+        if !self.next_line(reader)? {
+            return Ok(false);
+        }
+        let (line, offset) = (self.line_number, self.offset);
+        if self.line[0] != b'@' {
+            return Err(Error::MissingAt { line, offset });
+        }
+        let header = text(&self.line[1..]).ok_or(Error::InvalidHeader { line, offset })?;
+        self.header.push_str(header);
+
+        let mut seq_lines = 0;
+        loop {
+            if !self.next_line(reader)? {
+                return Err(Error::IncompleteRecord { line, offset });
             }
-            let mut header_fields = self.line_buffer[1..].trim_end().splitn(2, ' ');
-            record.id = header_fields.next().unwrap_or_default().to_owned();
-            record.desc = header_fields.next().map(|s| s.to_owned());
-            self.line_buffer.clear();
-
-            self.reader.read_line(&mut self.line_buffer)?;
-
-            let mut lines_read = 0;
-            while !self.line_buffer.is_empty() && !self.line_buffer.starts_with('+') {
-                record.seq.push_str(self.line_buffer.trim_end());
-                self.line_buffer.clear();
-                self.reader.read_line(&mut self.line_buffer)?;
-                lines_read += 1;
+            if self.line[0] == b'+' {
+                break;
             }
+            let seq = text(&self.line).ok_or(Error::InvalidSequence {
+                line: self.line_number,
+                offset: self.offset,
+            })?;
+            self.seq.extend_from_slice(seq.as_bytes());
+            seq_lines += 1;
+        }
 
-            for _ in 0..lines_read {
-                self.line_buffer.clear();
-                self.reader
-                    .read_line(&mut self.line_buffer)
-                    .map_err(Error::ReadError)?;
-                record.qual.push_str(self.line_buffer.trim_end());
+        if seq_lines <= 1 {
+            // fast path: a regular 4-line record
+            if self.next_line(reader)? {
+                self.push_qual()?;
             }
-
-            if record.qual.is_empty() {
-                return Err(Error::IncompleteRecord);
+        } else {
+            // wrapped record: the qualities may begin with '@', hence they are read until
+            // they cover the sequence instead of up to the next header
+            while self.qual.len() < self.seq.len() {
+                if !self.next_line(reader)? {
+                    return Err(Error::IncompleteRecord { line, offset });
+                }
+                self.push_qual()?;
             }
         }
 
+        if self.qual.is_empty() {
+            return Err(Error::IncompleteRecord { line, offset });
+        }
+        if self.qual.len() != self.seq.len() {
+            return Err(Error::LengthMismatch {
+                line,
+                offset,
+                id: self.header_fields().0.to_owned(),
+                seq_len: self.seq.len(),
+                qual_len: self.qual.len(),
+            });
+        }
+        Ok(true)
+    }
+
+    /// Append the current line to the qualities.
+    fn push_qual(&mut self) -> Result<()> {
+        let qual = text(&self.line).ok_or(Error::InvalidQuality {
+            line: self.line_number,
+            offset: self.offset,
+        })?;
+        self.qual.extend_from_slice(qual.as_bytes());
         Ok(())
     }
+
+    /// Read the next line into the line buffer, keeping track of its position. Return false
+    /// at the end of the input.
+    fn next_line<B: io::BufRead>(&mut self, reader: &mut B) -> io::Result<bool> {
+        self.offset += self.line.len() as u64;
+        self.line.clear();
+        if reader.read_until(b'\n', &mut self.line)? > 0 {
+            self.line_number += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Split the header into id and optional description.
+    fn header_fields(&self) -> (&str, Option<&str>) {
+        let mut header_fields = self.header.splitn(2, ' ');
+        (
+            header_fields.next().unwrap_or_default(),
+            header_fields.next(),
+        )
+    }
 }
 
 /// A FastQ record.
@@ -415,6 +576,38 @@ impl Record {
         self.qual.trim_end().as_bytes()
     }
 
+    /// Convert the base qualities in place from the given encoding to another one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bio::io::fastq::{QualityEncoding, Record};
+    ///
+    /// let mut record = Record::with_attrs("read1", None, b"ACGT", b"hhB@");
+    /// record.convert_qual(QualityEncoding::Illumina15, QualityEncoding::Sanger);
+    /// assert_eq!(record.qual(), b"II#!");
+    /// ```
+    pub fn convert_qual(&mut self, from: QualityEncoding, to: QualityEncoding) {
+        if from == to {
+            return;
+        }
+        let mut qual = mem::take(&mut self.qual).into_bytes();
+        for q in qual.iter_mut() {
+            *q = to.convert(*q, from);
+        }
+        self.qual = String::from_utf8(qual).expect("bug: encoded qualities are always ASCII");
+    }
+
+    /// Return the probabilities that the base calls are wrong, on PHRED scale.
+    pub fn phred_probs(&self, encoding: QualityEncoding) -> impl Iterator<Item = PHREDProb> + '_ {
+        self.qual().iter().map(move |&q| encoding.phred_prob(q))
+    }
+
+    /// Return the log-scaled probabilities that the base calls are wrong.
+    pub fn log_probs(&self, encoding: QualityEncoding) -> impl Iterator<Item = LogProb> + '_ {
+        self.qual().iter().map(move |&q| encoding.log_prob(q))
+    }
+
     /// Clear the record.
     fn clear(&mut self) {
         self.id.clear();
@@ -424,6 +617,129 @@ impl Record {
     }
 }
 
+/// Encoding of base qualities in FastQ files.
+///
+/// See [Cock et al. (2010)](https://doi.org/10.1093/nar/gkp1137) for an overview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityEncoding {
+    /// PHRED scores with ASCII offset 33, also used by Illumina 1.8+.
+    Sanger,
+    /// Solexa scores with ASCII offset 64, ranging from -5 to 40.
+    Solexa,
+    /// PHRED scores with ASCII offset 64, ranging from 0 to 40.
+    Illumina13,
+    /// PHRED scores with ASCII offset 64, ranging from 3 to 40 (2 marks low quality read ends).
+    Illumina15,
+}
+
+impl QualityEncoding {
+    /// The ASCII offset of the encoding.
+    pub fn offset(self) -> u8 {
+        match self {
+            QualityEncoding::Sanger => 33,
+            _ => 64,
+        }
+    }
+
+    /// Detect the encoding from the qualities of the first `n` of the given records.
+    /// Returns `None` if the records contain no qualities or qualities outside of the printable
+    /// ASCII range.
+    ///
+    /// As the encodings overlap, detection is based on the smallest quality character, such
+    /// that e.g. Sanger encoded data without any quality below 26 can't be told apart from
+    /// Illumina 1.3+ data. Scanning enough records makes this unlikely.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bio::io::fastq::{QualityEncoding, Record};
+    ///
+    /// let records = vec![
+    ///     Record::with_attrs("read1", None, b"ACGT", b"hhhB"),
+    ///     Record::with_attrs("read2", None, b"ACGT", b"Ihhh"),
+    /// ];
+    /// assert_eq!(
+    ///     QualityEncoding::detect(&records, 1000),
+    ///     Some(QualityEncoding::Illumina15)
+    /// );
+    /// ```
+    pub fn detect<'a, I>(records: I, n: usize) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Record>,
+    {
+        let (min, max) = records
+            .into_iter()
+            .take(n)
+            .flat_map(|record| record.qual().iter().copied())
+            .fold((u8::MAX, u8::MIN), |(min, max), q| (min.min(q), max.max(q)));
+
+        if min > max || min < b'!' || max > b'~' {
+            // no qualities or non-printable ones
+            None
+        } else if min < b';' {
+            Some(QualityEncoding::Sanger)
+        } else if min < b'@' {
+            Some(QualityEncoding::Solexa)
+        } else if min < b'B' {
+            Some(QualityEncoding::Illumina13)
+        } else {
+            Some(QualityEncoding::Illumina15)
+        }
+    }
+
+    /// Decode the given quality character into a PHRED score, rounding converted Solexa scores.
+    pub fn phred(self, qual: u8) -> u8 {
+        match self {
+            QualityEncoding::Solexa => {
+                let solexa = f64::from(qual) - f64::from(self.offset());
+                (10.0 * (10.0f64.powf(solexa / 10.0) + 1.0).log10()).round() as u8
+            }
+            _ => qual.saturating_sub(self.offset()),
+        }
+    }
+
+    /// Encode the given PHRED score as quality character, capped at the highest printable
+    /// ASCII character.
+    pub fn encode(self, phred: u8) -> u8 {
+        let max = b'~' - self.offset();
+        match self {
+            QualityEncoding::Solexa => {
+                let solexa = 10.0 * (10.0f64.powf(f64::from(phred) / 10.0) - 1.0).log10();
+                // a PHRED score of 0 has no Solexa equivalent, -5 is the lowest Solexa score
+                (solexa.round().max(-5.0).min(f64::from(max)) + f64::from(self.offset())) as u8
+            }
+            _ => phred.min(max) + self.offset(),
+        }
+    }
+
+    /// Convert the given quality character to this encoding from the given one.
+    pub fn convert(self, qual: u8, from: QualityEncoding) -> u8 {
+        if self == from {
+            qual
+        } else {
+            self.encode(from.phred(qual))
+        }
+    }
+
+    /// Return the probability that the base call with the given quality character is wrong,
+    /// on PHRED scale. Solexa scores are converted exactly, without rounding.
+    pub fn phred_prob(self, qual: u8) -> PHREDProb {
+        match self {
+            QualityEncoding::Solexa => {
+                let solexa = f64::from(qual) - f64::from(self.offset());
+                PHREDProb::from(Prob(1.0 / (1.0 + 10.0f64.powf(solexa / 10.0))))
+            }
+            _ => PHREDProb(f64::from(self.phred(qual))),
+        }
+    }
+
+    /// Return the log-scaled probability that the base call with the given quality character
+    /// is wrong.
+    pub fn log_prob(self, qual: u8) -> LogProb {
+        LogProb::from(self.phred_prob(qual))
+    }
+}
+
 impl fmt::Display for Record {
     /// Allows for using `Record` in a given formatter `f`. In general this is for
     /// creating a `String` representation of a `Record` and, optionally, writing it to
@@ -508,6 +824,662 @@ where
     }
 }
 
+/// A FastQ record borrowing its data from the buffers of a [`RefRecords`](RefRecords)
+/// parser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefRecord<'a> {
+    id: &'a str,
+    desc: Option<&'a str>,
+    seq: &'a [u8],
+    qual: &'a [u8],
+}
+
+impl<'a> RefRecord<'a> {
+    /// Return the id of the record.
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Return descriptions if present.
+    pub fn desc(&self) -> Option<&'a str> {
+        self.desc
+    }
+
+    /// Return the sequence of the record.
+    pub fn seq(&self) -> TextSlice<'a> {
+        self.seq
+    }
+
+    /// Return the base qualities of the record.
+    pub fn qual(&self) -> &'a [u8] {
+        self.qual
+    }
+
+    /// Copy the borrowed data into an owned [`Record`](Record).
+    /// Invalid UTF-8 in sequence or qualities is replaced by `U+FFFD`.
+    pub fn to_owned(&self) -> Record {
+        Record {
+            id: self.id.to_owned(),
+            desc: self.desc.map(|desc| desc.to_owned()),
+            seq: String::from_utf8_lossy(self.seq).into_owned(),
+            qual: String::from_utf8_lossy(self.qual).into_owned(),
+        }
+    }
+}
+
+/// A streaming parser over the records of a FastQ file, yielding [`RefRecord`](RefRecord)s
+/// that borrow from internal buffers. The buffers are reused for every record, such that no
+/// allocations happen per record once they have grown to the size of the largest record.
+/// Records are parsed like with [`Reader::read`](Reader::read).
+///
+/// As records borrow from the parser, this can't implement `Iterator`. Instead, records are
+/// obtained with the [`next`](RefRecords::next) method in a `while let` loop.
+///
+/// # Example
+///
+/// ```rust
+/// use bio::io::fastq::Reader;
+///
+/// let fq: &[u8] = b"@id1 desc\nACGT\n+\nIIII\n@id2\nGGCCA\n+\nIIIII\n";
+/// let mut records = Reader::new(fq).ref_records();
+/// let mut nb_bases = 0;
+/// while let Some(record) = records.next() {
+///     let record = record.unwrap();
+///     nb_bases += record.seq().len();
+/// }
+/// assert_eq!(nb_bases, 9);
+/// ```
+#[derive(Debug)]
+pub struct RefRecords<B> {
+    reader: B,
+    buffers: Buffers,
+    error_has_occured: bool,
+}
+
+impl<B> RefRecords<B>
+where
+    B: io::BufRead,
+{
+    /// Parse the next record, returning `None` at the end of the input or after an error.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<RefRecord<'_>>> {
+        if self.error_has_occured {
+            return None;
+        }
+        match self.buffers.read(&mut self.reader) {
+            Ok(false) => None,
+            Ok(true) => {
+                let (id, desc) = self.buffers.header_fields();
+                Some(Ok(RefRecord {
+                    id,
+                    desc,
+                    seq: &self.buffers.seq,
+                    qual: &self.buffers.qual,
+                }))
+            }
+            Err(err) => {
+                self.error_has_occured = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Strip trailing whitespace, including line breaks, from the given line.
+fn trim_end(line: &[u8]) -> &[u8] {
+    &line[..count_bases(line) as usize]
+}
+
+/// Return the name shared by both mates of a read pair, i.e. the record id without a trailing
+/// `/1` or `/2`. Illumina style comments like `1:N:0:ATCACG` are part of the description and
+/// hence ignored anyway.
+///
+/// # Example
+///
+/// ```rust
+/// use bio::io::fastq::mate_name;
+///
+/// assert_eq!(mate_name("read42/1"), "read42");
+/// assert_eq!(mate_name("read42/2"), "read42");
+/// assert_eq!(mate_name("read42"), "read42");
+/// ```
+pub fn mate_name(id: &str) -> &str {
+    id.strip_suffix("/1")
+        .or_else(|| id.strip_suffix("/2"))
+        .unwrap_or(id)
+}
+
+/// A reader for paired-end FastQ data, either given as two files (R1 and R2) or as a single
+/// interleaved file in which each record is directly followed by its mate.
+///
+/// Mate names are validated with [`mate_name`](mate_name): reading fails with
+/// [`Error::MateMismatch`](Error::MateMismatch) if the two records of a pair don't belong
+/// together, and with [`Error::MissingMate`](Error::MissingMate) if one of the inputs ends early.
+///
+/// # Example
+///
+/// ```rust
+/// use bio::io::fastq::{PairedReader, Reader};
+///
+/// let r1: &[u8] = b"@read1/1\nACGT\n+\nIIII\n@read2/1\nGGGG\n+\nIIII\n";
+/// let r2: &[u8] = b"@read1/2\nTTTT\n+\nJJJJ\n@read2/2\nCCCC\n+\nJJJJ\n";
+/// let reader = PairedReader::new(Reader::new(r1), Reader::new(r2));
+/// for result in reader.records() {
+///     let (first, second) = result.unwrap();
+///     assert_eq!(first.seq().len(), second.seq().len());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PairedReader<B1, B2 = B1> {
+    first: Reader<B1>,
+    second: Option<Reader<B2>>,
+    index: usize,
+}
+
+impl PairedReader<Decoder<fs::File>, Decoder<fs::File>> {
+    /// Read pairs from two given files, holding the first and second mates respectively.
+    pub fn from_files<P1, P2>(first: P1, second: P2) -> anyhow::Result<Self>
+    where
+        P1: AsRef<Path> + std::fmt::Debug,
+        P2: AsRef<Path> + std::fmt::Debug,
+    {
+        Ok(PairedReader::new(
+            Reader::from_file(first)?,
+            Reader::from_file(second)?,
+        ))
+    }
+
+    /// Read pairs from a given interleaved file.
+    pub fn interleaved_from_file<P: AsRef<Path> + std::fmt::Debug>(
+        path: P,
+    ) -> anyhow::Result<Self> {
+        Ok(PairedReader::interleaved(Reader::from_file(path)?))
+    }
+}
+
+impl<B> PairedReader<B, B>
+where
+    B: io::BufRead,
+{
+    /// Read pairs from a single interleaved reader.
+    pub fn interleaved(reader: Reader<B>) -> Self {
+        PairedReader {
+            first: reader,
+            second: None,
+            index: 0,
+        }
+    }
+}
+
+impl<B1, B2> PairedReader<B1, B2>
+where
+    B1: io::BufRead,
+    B2: io::BufRead,
+{
+    /// Read pairs from two readers, holding the first and second mates respectively.
+    pub fn new(first: Reader<B1>, second: Reader<B2>) -> Self {
+        PairedReader {
+            first,
+            second: Some(second),
+            index: 0,
+        }
+    }
+
+    /// Read the next pair into the given records. Empty records indicate that no more pairs
+    /// can be read.
+    ///
+    /// # Errors
+    /// In addition to the errors of [`Reader::read`](Reader::read), this fails if the mates
+    /// don't have matching names or if one of them is missing. The error names the 0-based
+    /// index of the offending pair.
+    pub fn read(&mut self, first: &mut Record, second: &mut Record) -> Result<()> {
+        self.first.read(first)?;
+        match self.second.as_mut() {
+            Some(reader) => reader.read(second)?,
+            None if first.is_empty() => second.clear(),
+            None => self.first.read(second)?,
+        }
+
+        if first.is_empty() && second.is_empty() {
+            return Ok(());
+        }
+        if first.is_empty() || second.is_empty() {
+            return Err(Error::MissingMate { index: self.index });
+        }
+        if mate_name(first.id()) != mate_name(second.id()) {
+            return Err(Error::MateMismatch {
+                index: self.index,
+                first: first.id().to_owned(),
+                second: second.id().to_owned(),
+            });
+        }
+        self.index += 1;
+
+        Ok(())
+    }
+
+    /// Return an iterator over the pairs of this reader.
+    pub fn records(self) -> PairedRecords<B1, B2> {
+        PairedRecords { reader: self }
+    }
+}
+
+/// An iterator over the read pairs of a [`PairedReader`](PairedReader).
+#[derive(Debug)]
+pub struct PairedRecords<B1, B2 = B1> {
+    reader: PairedReader<B1, B2>,
+}
+
+impl<B1, B2> Iterator for PairedRecords<B1, B2>
+where
+    B1: io::BufRead,
+    B2: io::BufRead,
+{
+    type Item = Result<(Record, Record)>;
+
+    fn next(&mut self) -> Option<Result<(Record, Record)>> {
+        let (mut first, mut second) = (Record::new(), Record::new());
+        match self.reader.read(&mut first, &mut second) {
+            Ok(()) if first.is_empty() => None,
+            Ok(()) => Some(Ok((first, second))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A FastQ index as created by `samtools fqidx` (.fai).
+///
+/// In addition to the five columns of a FASTA index (name, length, offset, line bases and
+/// line bytes), every entry holds the offset of the record's qualities.
+/// Wrapped records are supported as long as sequence and qualities are wrapped identically.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    inner: Vec<IndexRecord>,
+    name_to_rid: collections::HashMap<String, usize>,
+}
+
+impl Index {
+    /// Open a FastQ index from a given `io::Read` instance.
+    pub fn new<R: io::Read>(fai: R) -> csv::Result<Self> {
+        let mut inner = vec![];
+        let mut name_to_rid = collections::HashMap::new();
+
+        let mut fai_reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(fai);
+        for (rid, row) in fai_reader.deserialize().enumerate() {
+            let record: IndexRecord = row?;
+            name_to_rid.insert(record.name.clone(), rid);
+            inner.push(record);
+        }
+        Ok(Index { inner, name_to_rid })
+    }
+
+    /// Open a FastQ index from a given file path.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        fs::File::open(&path)
+            .map_err(csv::Error::from)
+            .and_then(Self::new)
+            .with_context(|| format!("Failed to read fastq index from {:#?}", path))
+    }
+
+    /// Open a FastQ index given the corresponding FastQ file path.
+    /// That is, for reads.fastq we expect reads.fastq.fai.
+    pub fn with_fastq_file<P: AsRef<Path>>(fastq_path: &P) -> anyhow::Result<Self> {
+        let mut fai_path = fastq_path.as_ref().as_os_str().to_owned();
+        fai_path.push(".fai");
+
+        Self::from_file(&fai_path)
+    }
+
+    /// Build a FastQ index by scanning the given FastQ, like `samtools fqidx` does.
+    ///
+    /// # Errors
+    /// If the FastQ is malformed, contains duplicate record names, or if the lines of a record
+    /// are wrapped inconsistently. All sequence lines of a record except the last one need to
+    /// have the same length, and the qualities have to be wrapped like the sequence.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bio::io::fastq::Index;
+    ///
+    /// let fastq: &[u8] = b"@read1 desc\nACGT\n+\nIIII\n@read2\nGGCCA\n+\nJJJJJ\n";
+    /// let index = Index::build(fastq).unwrap();
+    /// let mut fai = Vec::new();
+    /// index.write(&mut fai).unwrap();
+    /// assert_eq!(fai, b"read1\t4\t12\t4\t5\t19\nread2\t5\t31\t5\t6\t39\n");
+    /// ```
+    pub fn build<R: io::Read>(fastq: R) -> Result<Self> {
+        let mut index = Index::default();
+        let mut reader = io::BufReader::new(fastq);
+        let mut line = Vec::new();
+        let mut offset = 0;
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            let header_bytes = reader.read_until(b'\n', &mut line)? as u64;
+            if header_bytes == 0 {
+                break;
+            }
+            line_number += 1;
+            let (record_line, record_offset) = (line_number, offset);
+            let incomplete = || Error::IncompleteRecord {
+                line: record_line,
+                offset: record_offset,
+            };
+            offset += header_bytes;
+            if line.iter().all(|c| c.is_ascii_whitespace()) {
+                // tolerate trailing empty lines
+                continue;
+            }
+            if line[0] != b'@' {
+                return Err(Error::MissingAt {
+                    line: record_line,
+                    offset: record_offset,
+                });
+            }
+            let name = line[1..]
+                .split(|c| c.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default();
+            let name = String::from_utf8(name.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let mut record = IndexRecord {
+                name,
+                len: 0,
+                offset,
+                line_bases: 0,
+                line_bytes: 0,
+                qual_offset: 0,
+            };
+
+            // sequence lines, up to the separator
+            let mut seq_lines = 0;
+            let mut short_line_seen = false;
+            loop {
+                line.clear();
+                let line_bytes = reader.read_until(b'\n', &mut line)? as u64;
+                if line_bytes == 0 {
+                    return Err(incomplete());
+                }
+                line_number += 1;
+                offset += line_bytes;
+                if line[0] == b'+' {
+                    break;
+                }
+                seq_lines += 1;
+                let line_bases = count_bases(&line);
+                if short_line_seen {
+                    return Err(Error::InconsistentLineLength { id: record.name });
+                }
+                if seq_lines == 1 {
+                    record.line_bases = line_bases;
+                    record.line_bytes = line_bytes;
+                } else if line_bases > record.line_bases
+                    || (line_bases == record.line_bases && line_bytes != record.line_bytes)
+                {
+                    return Err(Error::InconsistentLineLength { id: record.name });
+                }
+                if line_bases < record.line_bases || line_bases == 0 {
+                    short_line_seen = true;
+                }
+                record.len += line_bases;
+            }
+            record.qual_offset = offset;
+
+            // qualities have to be wrapped exactly like the sequence
+            let mut qual_len = 0;
+            for i in 0..seq_lines {
+                line.clear();
+                let line_bytes = reader.read_until(b'\n', &mut line)? as u64;
+                if line_bytes == 0 {
+                    return Err(incomplete());
+                }
+                line_number += 1;
+                offset += line_bytes;
+                let line_bases = count_bases(&line);
+                if line_bases != min(record.line_bases, record.len - qual_len)
+                    || (i + 1 < seq_lines && line_bytes != record.line_bytes)
+                {
+                    return Err(Error::InconsistentLineLength { id: record.name });
+                }
+                qual_len += line_bases;
+            }
+
+            index.push(record)?;
+        }
+
+        Ok(index)
+    }
+
+    /// Write the index in .fai format.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        for record in &self.inner {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                record.name,
+                record.len,
+                record.offset,
+                record.line_bases,
+                record.line_bytes,
+                record.qual_offset
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Return the names of the indexed records, in the order of the FastQ file.
+    pub fn names(&self) -> Vec<&str> {
+        self.inner
+            .iter()
+            .map(|record| record.name.as_str())
+            .collect()
+    }
+
+    /// Return the sequence length of the record with the given name.
+    pub fn seq_len(&self, name: &str) -> Option<u64> {
+        self.name_to_rid.get(name).map(|&rid| self.inner[rid].len)
+    }
+
+    /// Append a record, ensuring that record names stay unique.
+    fn push(&mut self, record: IndexRecord) -> Result<()> {
+        if self.name_to_rid.contains_key(&record.name) {
+            return Err(Error::DuplicateName(record.name));
+        }
+        self.name_to_rid
+            .insert(record.name.clone(), self.inner.len());
+        self.inner.push(record);
+        Ok(())
+    }
+}
+
+/// Number of bases on a line, i.e. its length without trailing whitespace.
+fn count_bases(line: &[u8]) -> u64 {
+    line.iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(0, |pos| pos + 1) as u64
+}
+
+/// Record of a FastQ index.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct IndexRecord {
+    name: String,
+    len: u64,
+    offset: u64,
+    line_bases: u64,
+    line_bytes: u64,
+    qual_offset: u64,
+}
+
+/// A FastQ reader with an index as created by `samtools fqidx` (.fai).
+///
+/// # Example
+///
+/// ```rust
+/// use bio::io::fastq::{Index, IndexedReader, Record};
+/// use std::io::Cursor;
+///
+/// let fastq: &[u8] = b"@read1\nACGTACGT\n+\nIIIIJJJJ\n@read2\nGGGG\n+\n!!!!\n";
+/// let index = Index::build(fastq).unwrap();
+/// let mut reader = IndexedReader::with_index(Cursor::new(fastq), index);
+///
+/// let mut record = Record::new();
+/// reader.fetch_record("read2", &mut record).unwrap();
+/// assert_eq!(record.seq(), b"GGGG");
+///
+/// let (mut seq, mut qual) = (Vec::new(), Vec::new());
+/// reader.fetch("read1", 2, 6, &mut seq, &mut qual).unwrap();
+/// assert_eq!(seq, b"GTAC");
+/// assert_eq!(qual, b"IIJJ");
+/// ```
+#[derive(Debug)]
+pub struct IndexedReader<R: io::Read + io::Seek> {
+    reader: io::BufReader<R>,
+    pub index: Index,
+}
+
+impl IndexedReader<fs::File> {
+    /// Read from a given file path. This assumes the index reads.fastq.fai to be
+    /// present for FastQ reads.fastq.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        let index = Index::with_fastq_file(path)?;
+        fs::File::open(&path)
+            .map(|f| Self::with_index(f, index))
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .with_context(|| format!("Failed to read fastq from {:#?}", path))
+    }
+}
+
+impl<R: io::Read + io::Seek> IndexedReader<R> {
+    /// Read from a FastQ and its index, both given as `io::Read`. FastQ has to
+    /// be `io::Seek` in addition.
+    pub fn new<I: io::Read>(fastq: R, fai: I) -> csv::Result<Self> {
+        let index = Index::new(fai)?;
+        Ok(Self::with_index(fastq, index))
+    }
+
+    /// Read from a FastQ and its index, the first given as `io::Read`, the
+    /// second given as index object.
+    pub fn with_index(fastq: R, index: Index) -> Self {
+        IndexedReader {
+            reader: io::BufReader::new(fastq),
+            index,
+        }
+    }
+
+    /// Read the whole record with the given name into `record`.
+    /// As the index does not store descriptions, the description of the record is left empty.
+    ///
+    /// # Errors
+    /// If `name` does not exist within the index, or the FastQ is truncated.
+    pub fn fetch_record(&mut self, name: &str, record: &mut Record) -> Result<()> {
+        let idx = self.idx(name)?;
+        let (mut seq, mut qual) = (Vec::new(), Vec::new());
+        self.read_range(&idx, idx.offset, 0, idx.len, &mut seq)?;
+        self.read_range(&idx, idx.qual_offset, 0, idx.len, &mut qual)?;
+
+        record.clear();
+        record.id.push_str(&idx.name);
+        record.seq =
+            String::from_utf8(seq).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        record.qual =
+            String::from_utf8(qual).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(())
+    }
+
+    /// Read the interval `[start, stop)` of the sequence and qualities of the record with the
+    /// given name into `seq` and `qual`. `start` and `stop` are 0-based.
+    ///
+    /// # Errors
+    /// If `name` does not exist within the index, the interval is invalid, or the FastQ is
+    /// truncated.
+    pub fn fetch(
+        &mut self,
+        name: &str,
+        start: u64,
+        stop: u64,
+        seq: &mut Vec<u8>,
+        qual: &mut Vec<u8>,
+    ) -> Result<()> {
+        let idx = self.idx(name)?;
+        if start > stop || stop > idx.len {
+            return Err(Error::InvalidInterval {
+                start,
+                stop,
+                len: idx.len,
+            });
+        }
+        self.read_range(&idx, idx.offset, start, stop, seq)?;
+        self.read_range(&idx, idx.qual_offset, start, stop, qual)
+    }
+
+    /// Return the IndexRecord for the given record name.
+    fn idx(&self, name: &str) -> Result<IndexRecord> {
+        let idx = self
+            .index
+            .name_to_rid
+            .get(name)
+            .map(|&rid| self.index.inner[rid].clone())
+            .ok_or_else(|| Error::UnknownRecord(name.to_owned()))?;
+        // the line length is needed to locate bases in non-empty records
+        if idx.len > 0 && idx.line_bases == 0 {
+            return Err(Error::InvalidIndex(idx.name));
+        }
+        Ok(idx)
+    }
+
+    /// Read the bases `[start, stop)` of the (sequence or quality) block beginning at `offset`
+    /// into `buf`, skipping line breaks.
+    fn read_range(
+        &mut self,
+        idx: &IndexRecord,
+        offset: u64,
+        start: u64,
+        stop: u64,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        buf.clear();
+        if start == stop {
+            return Ok(());
+        }
+        let line_start = start / idx.line_bases * idx.line_bytes;
+        self.reader.seek(io::SeekFrom::Start(
+            offset + line_start + start % idx.line_bases,
+        ))?;
+
+        let mut bases_left = (stop - start) as usize;
+        while bases_left > 0 {
+            let src = self.reader.fill_buf()?;
+            if src.is_empty() {
+                return Err(Error::ReadError(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "FastQ file is truncated.",
+                )));
+            }
+            let mut consumed = 0;
+            for &c in src {
+                if bases_left == 0 {
+                    break;
+                }
+                consumed += 1;
+                if c != b'\n' && c != b'\r' {
+                    buf.push(c);
+                    bases_left -= 1;
+                }
+            }
+            self.reader.consume(consumed);
+        }
+        Ok(())
+    }
+}
+
 /// A FastQ writer.
 #[derive(Debug)]
 pub struct Writer<W: io::Write> {
@@ -527,6 +1499,13 @@ impl Writer<fs::File> {
     }
 }
 
+impl Writer<Encoder<fs::File>> {
+    /// Write to the given file path, compressing the output in the given format.
+    pub fn to_file_with_compression<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+        fs::File::create(path).map(|file| Writer::new(Encoder::new(file, format)))
+    }
+}
+
 impl<W: io::Write> Writer<W> {
     /// Write to a given `io::Write`.
     pub fn new(writer: W) -> Self {
@@ -575,6 +1554,14 @@ impl<W: io::Write> Writer<W> {
         Ok(())
     }
 
+    /// Write a read pair in interleaved fashion, i.e. the first mate directly followed by the
+    /// second one, such that the output can be read with
+    /// [`PairedReader::interleaved`](PairedReader::interleaved).
+    pub fn write_pair(&mut self, first: &Record, second: &Record) -> io::Result<()> {
+        self.write_record(first)?;
+        self.write_record(second)
+    }
+
     /// Flush the writer, ensuring that everything is written.
     pub fn flush(&mut self) -> io::Result<()> {
         self.writer.flush()
@@ -632,6 +1619,64 @@ IIIIIIJJJJJJ
         }
     }
 
+    #[test]
+    fn test_ref_records() {
+        let fq: &'static [u8] =
+            b"@id description\nACGT\nGGGG\nC\n+\n@@@@\n!!!!$\n@id2\nACGT\n+\nIIII\n";
+        let expected: Vec<Record> = Reader::new(fq).records().map(|r| r.unwrap()).collect();
+        let mut records = Reader::new(fq).ref_records();
+        let mut actual = Vec::new();
+        while let Some(record) = records.next() {
+            actual.push(record.unwrap().to_owned());
+        }
+        assert_eq!(actual, expected);
+
+        let mut records = Reader::new(FASTQ_FILE).ref_records();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(record.id(), "id");
+        assert_eq!(record.desc(), Some("desc"));
+        assert_eq!(record.seq(), b"ACCGTAGGCTGA");
+        assert_eq!(record.qual(), b"IIIIIIJJJJJJ");
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_ref_records_errors() {
+        let mut records = Reader::new(&b"id\nACGT\n+\nIIII\n@id\nA\n+\nI\n"[..]).ref_records();
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::MissingAt { line: 1, offset: 0 }))
+        ));
+        assert!(records.next().is_none());
+        let mut records = Reader::new(&b"@id\nA\n+\nI\n@id2\nACGT\n+\n"[..]).ref_records();
+        assert!(records.next().unwrap().is_ok());
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::IncompleteRecord {
+                line: 5,
+                offset: 10
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_readers_reject_invalid_utf8() {
+        let fq: &[u8] = b"@id\nAC\xffT\n+\nIIII\n";
+        let mut record = Record::new();
+        assert!(matches!(
+            Reader::new(fq).read(&mut record),
+            Err(Error::InvalidSequence { line: 2, offset: 4 })
+        ));
+        let fq: &[u8] = b"@id\nACGT\n+\nII\xffI\n";
+        assert!(matches!(
+            Reader::new(fq).ref_records().next(),
+            Some(Err(Error::InvalidQuality {
+                line: 4,
+                offset: 11
+            }))
+        ));
+    }
+
     #[test]
     fn test_display_record_no_desc_id_without_space_after() {
         let fq: &'static [u8] = b"@id\nACGT\n+\n!!!!\n";
@@ -765,7 +1810,7 @@ IIIIIIJJJJJJ
 
         let error = reader.read(&mut record).unwrap_err();
 
-        assert!(matches!(error, Error::MissingAt))
+        assert!(matches!(error, Error::MissingAt { line: 1, offset: 0 }))
     }
 
     #[test]
@@ -776,7 +1821,10 @@ IIIIIIJJJJJJ
 
         let error = reader.read(&mut record).unwrap_err();
 
-        assert!(matches!(error, Error::IncompleteRecord))
+        assert!(matches!(
+            error,
+            Error::IncompleteRecord { line: 1, offset: 0 }
+        ))
     }
 
     #[test]
@@ -814,15 +1862,97 @@ IIIIIIJJJJJJ
     }
 
     #[test]
-    fn test_read_wrapped_record_with_inconsistent_wrapping_errors() {
-        let fq: &'static [u8] = b"@id description\nACGT\nGGGG\nC\n+\n@@@@\n!!!!$\n@id2 description\nACGT\nGGGG\nC\n+\n@@@@\n!!!!\n$\n@id3 desc1 desc2\nAAA\nAAA\nAA\n+\n^^^\n^^^\n^^\n";
-        let mut reader = Reader::new(fq);
+    fn test_read_wrapped_record_with_different_quality_wrapping_is_handled() {
+        let fq: &'static [u8] = b"@id description\nACGT\nGGGG\nC\n+\n@@@@\n!!!!$\n@id2 description\nACGT\nGGGG\nC\n+\n@@@@\n!!!!\n$\n@id3 desc1 desc2\nAAA\nAAA\nAA\n+\n^^^^^^^^\n";
+        let records: Vec<Record> = Reader::new(fq).records().map(|r| r.unwrap()).collect();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0],
+            Record::with_attrs("id", Some("description"), b"ACGTGGGGC", b"@@@@!!!!$")
+        );
+        assert_eq!(
+            records[1],
+            Record::with_attrs("id2", Some("description"), b"ACGTGGGGC", b"@@@@!!!!$")
+        );
+        assert_eq!(
+            records[2],
+            Record::with_attrs("id3", Some("desc1 desc2"), b"AAAAAAAA", b"^^^^^^^^")
+        );
+    }
+
+    #[test]
+    fn test_read_single_line_sequence_with_wrapped_quality_errors() {
+        let fq: &'static [u8] = b"@r1\nACGT\n+\nII\nII\n@r2\nGG\n+\nII\n";
+        let mut records = Reader::new(fq).records();
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::LengthMismatch {
+                seq_len: 4,
+                qual_len: 2,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::MissingAt {
+                line: 5,
+                offset: 14
+            }))
+        ));
+
+        let mut records = Reader::new(fq).ref_records();
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::LengthMismatch { .. }))
+        ));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_read_short_quality_does_not_consume_next_record() {
+        let fq: &'static [u8] = b"@r1\nACGTACGTAC\n+\nIIII\n@r2\nGG\n+\nII\n";
+        let mut records = Reader::new(fq).records();
+        let error = records.next().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::LengthMismatch {
+                seq_len: 10,
+                qual_len: 4,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "line 1 (byte 0): record r1 has 10 bases but 4 quality scores"
+        );
+        let record = records.next().unwrap().unwrap();
+        assert_eq!((record.id(), record.seq()), ("r2", &b"GG"[..]));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_read_wrapped_record_with_quality_lines_starting_with_at() {
+        let fq: &'static [u8] = b"@id\nACGTAC\nGTA\n+\n@IIIII\n@II\n@id2\nAC\n+\nII\n";
+        let records: Vec<Record> = Reader::new(fq).records().map(|r| r.unwrap()).collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].qual(), b"@IIIII@II");
+        assert_eq!(records[1].id(), "id2");
+    }
 
+    #[test]
+    fn test_read_wrapped_record_with_truncated_quality_raises_err() {
+        let fq: &'static [u8] = b"@id\nACGT\nACGT\n+\nIIII\n";
+        let mut reader = Reader::new(fq);
         let mut record = Record::new();
-        reader.read(&mut record).unwrap();
+
         let error = reader.read(&mut record).unwrap_err();
 
-        assert!(matches!(error, Error::MissingAt))
+        assert!(matches!(
+            error,
+            Error::IncompleteRecord { line: 1, offset: 0 }
+        ))
     }
 
     #[test]
@@ -832,7 +1962,10 @@ IIIIIIJJJJJJ
 
         let error = records.next().unwrap().unwrap_err();
 
-        assert!(matches!(error, Error::IncompleteRecord));
+        assert!(matches!(
+            error,
+            Error::IncompleteRecord { line: 1, offset: 0 }
+        ));
     }
 
     #[test]
@@ -937,7 +2070,44 @@ IIIIIIJJJJJJ
         let mut record = Record::new();
         let err = reader.read(&mut record).unwrap_err();
 
-        assert!(matches!(err, Error::IncompleteRecord))
+        assert!(matches!(
+            err,
+            Error::IncompleteRecord { line: 1, offset: 0 }
+        ))
+    }
+
+    #[test]
+    fn test_compressed_file_roundtrip() {
+        for format in &[Format::Gzip, Format::Bgzf] {
+            let file = tempfile::NamedTempFile::new().expect("Could not create temp file");
+            {
+                let mut writer = Writer::to_file_with_compression(file.path(), *format).unwrap();
+                writer
+                    .write("id", Some("desc"), b"ACCGTAGGCTGA", b"IIIIIIJJJJJJ")
+                    .unwrap();
+            }
+            let mut compressed = Vec::new();
+            fs::File::open(file.path())
+                .unwrap()
+                .read_to_end(&mut compressed)
+                .unwrap();
+            assert_eq!(Format::detect(&compressed), *format);
+
+            let records: Vec<Record> = Reader::from_file(file.path())
+                .unwrap()
+                .records()
+                .map(|r| r.unwrap())
+                .collect();
+            assert_eq!(
+                records,
+                vec![Record::with_attrs(
+                    "id",
+                    Some("desc"),
+                    b"ACCGTAGGCTGA",
+                    b"IIIIIIJJJJJJ"
+                )]
+            );
+        }
     }
 
     #[test]
@@ -977,4 +2147,276 @@ IIIIIIJJJJJJ
         assert!(fs::remove_file(path).is_ok());
         assert_eq!(actual, expected)
     }
+
+    const FASTQ_FILE_WRAPPED: &[u8] = b"@read1 desc
+ACGTA
+CGTAC
+GT
++read1
+IIIII
+JJJJJ
+KK
+@read2
+GGCC
++
+@@!!
+";
+    const FAI_FILE_WRAPPED: &[u8] = b"read1\t12\t12\t5\t6\t34\nread2\t4\t56\t4\t5\t63\n";
+
+    #[test]
+    fn test_index_build() {
+        let index = Index::build(FASTQ_FILE).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(fai, b"id\t12\t9\t12\t13\t24\n");
+
+        let index = Index::build(FASTQ_FILE_WRAPPED).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(fai, FAI_FILE_WRAPPED);
+        assert_eq!(index.names(), vec!["read1", "read2"]);
+        assert_eq!(index.seq_len("read1"), Some(12));
+        assert_eq!(index.seq_len("read3"), None);
+
+        let index = Index::new(FAI_FILE_WRAPPED).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(fai, FAI_FILE_WRAPPED);
+    }
+
+    #[test]
+    fn test_index_build_invalid() {
+        for fastq in &[
+            &b"@id\nACGT\nAC\nACGT\n+\nIIII\nII\nIIII\n"[..],
+            b"@id\nACGT\nAC\n+\nII\nIIII\n",
+            b"@id\nACGT\nAC\n+\nIIIIII\n",
+        ] {
+            assert!(matches!(
+                Index::build(*fastq),
+                Err(Error::InconsistentLineLength { .. })
+            ));
+        }
+        assert!(matches!(
+            Index::build(&b"@id\nACGT\n+\nIIII\n@id\nA\n+\nI\n"[..]),
+            Err(Error::DuplicateName(_))
+        ));
+        assert!(matches!(
+            Index::build(&b"@id\nACGT\n+\nII"[..]),
+            Err(Error::InconsistentLineLength { .. })
+        ));
+        assert!(matches!(
+            Index::build(&b"@id\nACGT\n"[..]),
+            Err(Error::IncompleteRecord { line: 1, offset: 0 })
+        ));
+        assert!(matches!(
+            Index::build(&b"id\nACGT\n+\nIIII\n"[..]),
+            Err(Error::MissingAt { line: 1, offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_indexed_reader() {
+        let mut reader =
+            IndexedReader::new(io::Cursor::new(FASTQ_FILE_WRAPPED), FAI_FILE_WRAPPED).unwrap();
+
+        let mut record = Record::new();
+        reader.fetch_record("read1", &mut record).unwrap();
+        assert_eq!(record.id(), "read1");
+        assert_eq!(record.desc(), None);
+        assert_eq!(record.seq(), b"ACGTACGTACGT");
+        assert_eq!(record.qual(), b"IIIIIJJJJJKK");
+        reader.fetch_record("read2", &mut record).unwrap();
+        assert_eq!(record.seq(), b"GGCC");
+        assert_eq!(record.qual(), b"@@!!");
+
+        let (mut seq, mut qual) = (Vec::new(), Vec::new());
+        reader.fetch("read1", 3, 11, &mut seq, &mut qual).unwrap();
+        assert_eq!(seq, b"TACGTACG");
+        assert_eq!(qual, b"IIJJJJJK");
+        reader.fetch("read2", 2, 2, &mut seq, &mut qual).unwrap();
+        assert!(seq.is_empty() && qual.is_empty());
+
+        assert!(matches!(
+            reader.fetch("read1", 5, 13, &mut seq, &mut qual),
+            Err(Error::InvalidInterval { .. })
+        ));
+        assert!(matches!(
+            reader.fetch_record("read3", &mut record),
+            Err(Error::UnknownRecord(_))
+        ));
+    }
+
+    #[test]
+    fn test_indexed_reader_invalid_index() {
+        let fai: &[u8] = b"read1\t12\t12\t0\t6\t34\n";
+        let mut reader = IndexedReader::new(io::Cursor::new(FASTQ_FILE_WRAPPED), fai).unwrap();
+        let mut record = Record::new();
+        assert!(matches!(
+            reader.fetch_record("read1", &mut record),
+            Err(Error::InvalidIndex(_))
+        ));
+    }
+
+    #[test]
+    fn test_indexed_reader_truncated() {
+        let index = Index::build(FASTQ_FILE).unwrap();
+        let mut reader = IndexedReader::with_index(io::Cursor::new(&FASTQ_FILE[..30]), index);
+        let mut record = Record::new();
+        assert!(matches!(
+            reader.fetch_record("id", &mut record),
+            Err(Error::ReadError(_))
+        ));
+    }
+
+    const FASTQ_R1: &[u8] = b"@pair1/1 1:N:0:ATCACG\nACGT\n+\nIIII\n@pair2/1\nGGGG\n+\nJJJJ\n";
+    const FASTQ_R2: &[u8] = b"@pair1/2 2:N:0:ATCACG\nTTTT\n+\nKKKK\n@pair2/2\nCCCC\n+\nLLLL\n";
+
+    #[test]
+    fn test_paired_reader() {
+        let reader = PairedReader::new(Reader::new(FASTQ_R1), Reader::new(FASTQ_R2));
+        let pairs: Vec<(Record, Record)> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].0.id(), "pair1/1");
+        assert_eq!(pairs[0].1.id(), "pair1/2");
+        assert_eq!(pairs[1].0.seq(), b"GGGG");
+        assert_eq!(pairs[1].1.seq(), b"CCCC");
+    }
+
+    #[test]
+    fn test_paired_reader_interleaved_roundtrip() {
+        let r1: Vec<Record> = Reader::new(FASTQ_R1)
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let r2: Vec<Record> = Reader::new(FASTQ_R2)
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let mut writer = Writer::new(Vec::new());
+        for (first, second) in r1.iter().zip(r2.iter()) {
+            writer.write_pair(first, second).unwrap();
+        }
+        writer.flush().unwrap();
+        let interleaved = writer.writer.get_ref().clone();
+
+        let reader = PairedReader::interleaved(Reader::new(&interleaved[..]));
+        let pairs: Vec<(Record, Record)> = reader.records().map(|r| r.unwrap()).collect();
+        let expected: Vec<(Record, Record)> = r1.into_iter().zip(r2.into_iter()).collect();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn test_paired_reader_mate_mismatch() {
+        let r2: &[u8] = b"@pair1/2\nTTTT\n+\nKKKK\n@pair3/2\nCCCC\n+\nLLLL\n";
+        let mut records = PairedReader::new(Reader::new(FASTQ_R1), Reader::new(r2)).records();
+        assert!(records.next().unwrap().is_ok());
+        match records.next().unwrap() {
+            Err(Error::MateMismatch {
+                index,
+                first,
+                second,
+            }) => {
+                assert_eq!(index, 1);
+                assert_eq!(first, "pair2/1");
+                assert_eq!(second, "pair3/2");
+            }
+            other => panic!("expected mate mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_paired_reader_missing_mate() {
+        let r2: &[u8] = b"@pair1/2\nTTTT\n+\nKKKK\n";
+        let mut records = PairedReader::new(Reader::new(FASTQ_R1), Reader::new(r2)).records();
+        assert!(records.next().unwrap().is_ok());
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::MissingMate { index: 1 })
+        ));
+
+        let mut records = PairedReader::interleaved(Reader::new(FASTQ_R1)).records();
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::MateMismatch { index: 0, .. })
+        ));
+        let interleaved: &[u8] = b"@pair1/1\nACGT\n+\nIIII\n";
+        let mut records = PairedReader::interleaved(Reader::new(interleaved)).records();
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(Error::MissingMate { index: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_quality_encoding_detect() {
+        let detect = |quals: &[&[u8]]| {
+            let records: Vec<Record> = quals
+                .iter()
+                .map(|qual| Record::with_attrs("id", None, &vec![b'A'; qual.len()], qual))
+                .collect();
+            QualityEncoding::detect(&records, 10)
+        };
+        assert_eq!(detect(&[b"IIII", b"#!II"]), Some(QualityEncoding::Sanger));
+        assert_eq!(detect(&[b"hhhh", b";;hh"]), Some(QualityEncoding::Solexa));
+        assert_eq!(
+            detect(&[b"hhhh", b"@Ahh"]),
+            Some(QualityEncoding::Illumina13)
+        );
+        assert_eq!(
+            detect(&[b"hhhh", b"BBhh"]),
+            Some(QualityEncoding::Illumina15)
+        );
+        assert_eq!(detect(&[]), None);
+        assert_eq!(detect(&[b"II\x7f"]), None);
+
+        // only the first n records are scanned
+        let records = vec![
+            Record::with_attrs("id1", None, b"AC", b"hB"),
+            Record::with_attrs("id2", None, b"AC", b"!!"),
+        ];
+        assert_eq!(
+            QualityEncoding::detect(&records, 1),
+            Some(QualityEncoding::Illumina15)
+        );
+        assert_eq!(
+            QualityEncoding::detect(&records, 2),
+            Some(QualityEncoding::Sanger)
+        );
+    }
+
+    #[test]
+    fn test_quality_encoding_convert() {
+        let mut record = Record::with_attrs("id", None, b"ACGTA", b"!+5?~");
+        record.convert_qual(QualityEncoding::Sanger, QualityEncoding::Illumina13);
+        assert_eq!(record.qual(), b"@JT^~");
+        record.convert_qual(QualityEncoding::Illumina13, QualityEncoding::Sanger);
+        assert_eq!(record.qual(), b"!+5?_");
+
+        // Solexa and PHRED scores only differ for low qualities
+        let mut record = Record::with_attrs("id", None, b"ACGT", b";@Jh");
+        record.convert_qual(QualityEncoding::Solexa, QualityEncoding::Sanger);
+        assert_eq!(record.qual(), b"\"$+I");
+        record.convert_qual(QualityEncoding::Sanger, QualityEncoding::Solexa);
+        assert_eq!(record.qual(), b";@Jh");
+        assert_eq!(QualityEncoding::Solexa.encode(0), b';');
+    }
+
+    #[test]
+    fn test_quality_probs() {
+        let record = Record::with_attrs("id", None, b"ACG", b"!+5");
+        let probs: Vec<PHREDProb> = record.phred_probs(QualityEncoding::Sanger).collect();
+        assert_eq!(
+            probs,
+            vec![PHREDProb(0.0), PHREDProb(10.0), PHREDProb(20.0)]
+        );
+        let probs: Vec<LogProb> = record.log_probs(QualityEncoding::Sanger).collect();
+        assert_relative_eq!(*probs[1], 0.1f64.ln(), epsilon = 1e-10);
+
+        // Solexa score 0 means an error probability of 0.5
+        assert_relative_eq!(
+            *Prob::from(QualityEncoding::Solexa.phred_prob(b'@')),
+            0.5,
+            epsilon = 1e-10
+        );
+    }
 }
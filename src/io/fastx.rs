@@ -0,0 +1,452 @@
+//! A common interface to FASTA and FASTQ records, and a reader that detects the format of its
+//! input.
+//!
+//! The [`SequenceRecord`] trait is implemented by the records of both formats, such that code
+//! can be written once for either of them. The [`Reader`] inspects the first byte of its
+//! (transparently decompressed) input, i.e. `>` for FASTA or `@` for FASTQ, and yields
+//! [`Record`]s wrapping the record of the detected format.
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::fastx::{Format, Reader, SequenceRecord};
+//!
+//! fn total_len<R: SequenceRecord>(records: &[R]) -> usize {
+//!     records.iter().map(|record| record.seq().len()).sum()
+//! }
+//!
+//! let fastq: &[u8] = b"@read1\nACGT\n+\nIIII\n@read2\nGG\n+\nII\n";
+//! let reader = Reader::new(fastq).unwrap();
+//! assert_eq!(reader.format(), Format::Fastq);
+//! let records = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+//! assert_eq!(total_len(&records), 6);
+//! assert_eq!(records[0].qual(), Some(&b"IIII"[..]));
+//!
+//! let fasta: &[u8] = b">chr1\nACGT\nACGT\n";
+//! let records = Reader::new(fasta)
+//!     .unwrap()
+//!     .records()
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//! assert_eq!(total_len(&records), 8);
+//! assert_eq!(records[0].qual(), None);
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use thiserror::Error;
+
+use crate::io::compression::Decoder;
+use crate::io::fasta::{self, FastaRead};
+use crate::io::fastq::{self, FastqRead};
+use crate::utils::TextSlice;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("expected '>' or '@' at start of input, found {0:?}")]
+    UnknownFormat(char),
+
+    #[error(transparent)]
+    Fasta(#[from] fasta::Error),
+
+    #[error(transparent)]
+    Fastq(#[from] fastq::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A sequence record with an id, an optional description, a sequence and optional qualities.
+pub trait SequenceRecord {
+    /// Return the id of the record.
+    fn id(&self) -> &str;
+
+    /// Return the description if present.
+    fn desc(&self) -> Option<&str>;
+
+    /// Return the sequence of the record.
+    fn seq(&self) -> TextSlice<'_>;
+
+    /// Return the base qualities if the format provides them.
+    fn qual(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl SequenceRecord for fasta::Record {
+    fn id(&self) -> &str {
+        self.id()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        self.desc()
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        self.seq()
+    }
+}
+
+impl SequenceRecord for fastq::Record {
+    fn id(&self) -> &str {
+        self.id()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        self.desc()
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        self.seq()
+    }
+
+    fn qual(&self) -> Option<&[u8]> {
+        Some(self.qual())
+    }
+}
+
+impl<'a> SequenceRecord for fasta::RefRecord<'a> {
+    fn id(&self) -> &str {
+        self.id()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        self.desc()
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        self.seq()
+    }
+}
+
+impl<'a> SequenceRecord for fastq::RefRecord<'a> {
+    fn id(&self) -> &str {
+        self.id()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        self.desc()
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        self.seq()
+    }
+
+    fn qual(&self) -> Option<&[u8]> {
+        Some(self.qual())
+    }
+}
+
+impl<T: SequenceRecord + ?Sized> SequenceRecord for Box<T> {
+    fn id(&self) -> &str {
+        (**self).id()
+    }
+
+    fn desc(&self) -> Option<&str> {
+        (**self).desc()
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        (**self).seq()
+    }
+
+    fn qual(&self) -> Option<&[u8]> {
+        (**self).qual()
+    }
+}
+
+/// The sequence format detected by a [`Reader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Fasta,
+    Fastq,
+}
+
+/// A record of either format.
+#[derive(Debug, Clone)]
+pub enum Record {
+    Fasta(fasta::Record),
+    Fastq(fastq::Record),
+}
+
+impl Record {
+    /// The format of the record.
+    pub fn format(&self) -> Format {
+        match self {
+            Record::Fasta(_) => Format::Fasta,
+            Record::Fastq(_) => Format::Fastq,
+        }
+    }
+
+    /// Convert into a boxed trait object.
+    pub fn into_boxed(self) -> Box<dyn SequenceRecord> {
+        match self {
+            Record::Fasta(record) => Box::new(record),
+            Record::Fastq(record) => Box::new(record),
+        }
+    }
+}
+
+impl SequenceRecord for Record {
+    fn id(&self) -> &str {
+        match self {
+            Record::Fasta(record) => record.id(),
+            Record::Fastq(record) => record.id(),
+        }
+    }
+
+    fn desc(&self) -> Option<&str> {
+        match self {
+            Record::Fasta(record) => record.desc(),
+            Record::Fastq(record) => record.desc(),
+        }
+    }
+
+    fn seq(&self) -> TextSlice<'_> {
+        match self {
+            Record::Fasta(record) => record.seq(),
+            Record::Fastq(record) => record.seq(),
+        }
+    }
+
+    fn qual(&self) -> Option<&[u8]> {
+        match self {
+            Record::Fasta(_) => None,
+            Record::Fastq(record) => Some(record.qual()),
+        }
+    }
+}
+
+impl From<fasta::Record> for Record {
+    fn from(record: fasta::Record) -> Self {
+        Record::Fasta(record)
+    }
+}
+
+impl From<fastq::Record> for Record {
+    fn from(record: fastq::Record) -> Self {
+        Record::Fastq(record)
+    }
+}
+
+impl fmt::Display for Record {
+    /// Write the record in its own format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Fasta(record) => record.fmt(f),
+            Record::Fastq(record) => record.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Inner<B> {
+    Fasta(fasta::Reader<B>),
+    Fastq(fastq::Reader<B>),
+}
+
+/// A reader for FASTA or FASTQ input, detecting the format from the first record.
+#[derive(Debug)]
+pub struct Reader<B> {
+    inner: Inner<B>,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read FASTA or FASTQ from the given file path.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(&path)
+            .map_err(|source| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source,
+            })
+            .and_then(|file| Decoder::new(file).map_err(Error::from))
+            .and_then(Reader::from_bufread)
+            .with_context(|| format!("Failed to read sequences from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<Decoder<R>> {
+    /// Create a new reader given an instance of `io::Read`.
+    /// Gzip and BGZF compressed input is detected and decompressed transparently.
+    ///
+    /// # Errors
+    /// If the input can't be read or starts with neither `>` nor `@`.
+    pub fn new(reader: R) -> Result<Self> {
+        Reader::from_bufread(Decoder::new(reader)?)
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    /// Leading whitespace is skipped. Empty input is treated as FASTA without records.
+    ///
+    /// # Errors
+    /// If the input can't be read or starts with neither `>` nor `@`.
+    pub fn from_bufread(mut bufreader: B) -> Result<Self> {
+        let first = loop {
+            let buf = bufreader.fill_buf()?;
+            if buf.is_empty() {
+                break None;
+            }
+            match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(pos) => {
+                    let first = buf[pos];
+                    bufreader.consume(pos);
+                    break Some(first);
+                }
+                None => {
+                    let len = buf.len();
+                    bufreader.consume(len);
+                }
+            }
+        };
+        let inner = match first {
+            None | Some(b'>') => Inner::Fasta(fasta::Reader::from_bufread(bufreader)),
+            Some(b'@') => Inner::Fastq(fastq::Reader::from_bufread(bufreader)),
+            Some(other) => return Err(Error::UnknownFormat(other as char)),
+        };
+        Ok(Reader { inner })
+    }
+
+    /// The detected format.
+    pub fn format(&self) -> Format {
+        match self.inner {
+            Inner::Fasta(_) => Format::Fasta,
+            Inner::Fastq(_) => Format::Fastq,
+        }
+    }
+
+    /// Read the next record, returning `None` at the end of the input.
+    pub fn read(&mut self) -> Result<Option<Record>> {
+        match &mut self.inner {
+            Inner::Fasta(reader) => {
+                let mut record = fasta::Record::new();
+                reader.read(&mut record)?;
+                Ok(if record.is_empty() {
+                    None
+                } else {
+                    Some(Record::Fasta(record))
+                })
+            }
+            Inner::Fastq(reader) => {
+                let mut record = fastq::Record::new();
+                reader.read(&mut record)?;
+                Ok(if record.is_empty() {
+                    None
+                } else {
+                    Some(Record::Fastq(record))
+                })
+            }
+        }
+    }
+
+    /// Return an iterator over the records.
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occured: false,
+        }
+    }
+}
+
+/// An iterator over the records of a FASTA or FASTQ file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occured: bool,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        if self.error_has_occured {
+            return None;
+        }
+        match self.reader.read() {
+            Ok(record) => record.map(Ok),
+            Err(err) => {
+                self.error_has_occured = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::compression::{Encoder, Format as Compression};
+    use std::io::Write;
+
+    const FASTA: &[u8] = b">id1 desc\nACGT\nAC\n>id2\nTTT\n";
+    const FASTQ: &[u8] = b"@id1 desc\nACGTAC\n+\nIIIIII\n@id2\nTTT\n+\n###\n";
+
+    fn read_all(input: &[u8]) -> (Format, Vec<Record>) {
+        let reader = Reader::new(input).unwrap();
+        let format = reader.format();
+        let records = reader.records().collect::<Result<Vec<_>>>().unwrap();
+        (format, records)
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let (format, fasta) = read_all(FASTA);
+        assert_eq!(format, Format::Fasta);
+        let (format, fastq) = read_all(FASTQ);
+        assert_eq!(format, Format::Fastq);
+
+        assert_eq!(fasta.len(), 2);
+        assert_eq!(fastq.len(), 2);
+        for (a, b) in fasta.iter().zip(&fastq) {
+            assert_eq!(a.id(), b.id());
+            assert_eq!(a.desc(), b.desc());
+            assert_eq!(a.seq(), b.seq());
+        }
+        assert_eq!(fasta[0].qual(), None);
+        assert_eq!(fastq[1].qual(), Some(&b"###"[..]));
+        assert_eq!(fastq[1].format(), Format::Fastq);
+        assert_eq!(fastq[1].to_string(), "@id2\nTTT\n+\n###\n");
+
+        let boxed = fastq
+            .into_iter()
+            .map(Record::into_boxed)
+            .collect::<Vec<_>>();
+        assert_eq!(boxed[0].seq(), b"ACGTAC");
+    }
+
+    #[test]
+    fn test_compressed_and_whitespace() {
+        let mut encoder = Encoder::new(Vec::new(), Compression::Gzip);
+        encoder.write_all(b"\n\n").unwrap();
+        encoder.write_all(FASTQ).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let (format, records) = read_all(&compressed);
+        assert_eq!(format, Format::Fastq);
+        assert_eq!(records.len(), 2);
+
+        let (format, records) = read_all(b"");
+        assert_eq!(format, Format::Fasta);
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(matches!(
+            Reader::new(&b"ACGT\n"[..]),
+            Err(Error::UnknownFormat('A'))
+        ));
+        let mut records = Reader::new(&b"@id\nACGT\n"[..]).unwrap().records();
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+    }
+}
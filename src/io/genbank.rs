@@ -0,0 +1,1661 @@
+//! Reading and writing of annotated sequences in [GenBank] and [EMBL] flat file format.
+//!
+//! Records contain the most commonly used header fields (name, molecule type, topology,
+//! definition, accessions, version, keywords, source organism and taxonomy), the feature
+//! table and the sequence. References and comments are skipped when reading.
+//! Feature locations are parsed into a [`Location`](Location) tree, supporting `join`,
+//! `order`, `complement`, remote accessions, sites between two bases and fuzzy (`<`/`>`) ends.
+//!
+//! [GenBank]: https://www.ncbi.nlm.nih.gov/genbank/release/current/
+//! [EMBL]: https://ftp.ebi.ac.uk/pub/databases/embl/doc/usrman.txt
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::genbank;
+//!
+//! let gb: &'static [u8] = b"LOCUS       example                   12 bp    DNA     linear   SYN 01-JAN-2020
+//! DEFINITION  An example.
+//! FEATURES             Location/Qualifiers
+//!      CDS             complement(<1..9)
+//!                      /gene=\"exA\"
+//! ORIGIN
+//!         1 atgaaatagc cc
+//! //
+//! ";
+//! let reader = genbank::Reader::new(gb);
+//! for result in reader.records() {
+//!     let record = result.expect("Error reading record.");
+//!     assert_eq!(record.seq(), b"atgaaatagccc");
+//!     let cds = &record.features()[0];
+//!     assert_eq!(cds.qualifier("gene"), Some("exA"));
+//!     assert_eq!(cds.location().range(), Some(0..9));
+//!
+//!     let mut writer = genbank::Writer::new(Vec::new(), genbank::Format::Embl);
+//!     writer.write(&record).expect("Error writing record.");
+//! }
+//! ```
+
+use anyhow::Context;
+use std::cmp::{max, min};
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use bio_types::strand::Strand;
+
+use crate::io::compression::Decoder;
+use crate::io::gff;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("invalid feature location: {0}")]
+    InvalidLocation(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Width of the key column of the feature table (including the leading indentation).
+const FEATURE_KEY_WIDTH: usize = 21;
+/// Width of the location and qualifier column of the feature table.
+const FEATURE_VALUE_WIDTH: usize = 58;
+/// Width of the header values of GenBank and EMBL files.
+const HEADER_VALUE_WIDTH: usize = 67;
+
+/// Qualifiers whose values are written without quotes.
+const UNQUOTED_QUALIFIERS: [&str; 13] = [
+    "anticodon",
+    "citation",
+    "codon_start",
+    "compare",
+    "direction",
+    "estimated_length",
+    "mod_base",
+    "number",
+    "rpt_type",
+    "rpt_unit_range",
+    "tag_peptide",
+    "transl_except",
+    "transl_table",
+];
+
+/// The flat file flavour of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    GenBank,
+    Embl,
+}
+
+/// The topology of a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Linear,
+    Circular,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Linear
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Linear => write!(f, "linear"),
+            Topology::Circular => write!(f, "circular"),
+        }
+    }
+}
+
+/// The location of a feature. Coordinates are 0-based and half-open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// A contiguous range of bases, e.g. `<10..20` or `7`. The flags denote that the
+    /// feature extends beyond the given start (`<`) or end (`>`).
+    Span {
+        start: u64,
+        end: u64,
+        partial_start: bool,
+        partial_end: bool,
+    },
+    /// A site between two adjacent bases, e.g. `10^11`, given as the number of bases
+    /// left of the site.
+    Between(u64),
+    /// The reverse complement of the contained location.
+    Complement(Box<Location>),
+    /// Locations that are joined into one contiguous sequence, e.g. the exons of a CDS.
+    Join(Vec<Location>),
+    /// Locations in the given order, without implying that they are joined.
+    Order(Vec<Location>),
+    /// A location on another sequence, e.g. `J00194.1:100..202`.
+    Remote {
+        accession: String,
+        location: Box<Location>,
+    },
+}
+
+impl Location {
+    /// The smallest range enclosing all local parts of this location,
+    /// or `None` if the location only refers to other sequences.
+    pub fn range(&self) -> Option<Range<u64>> {
+        match self {
+            Location::Span { start, end, .. } => Some(*start..*end),
+            Location::Between(pos) => Some(*pos..*pos),
+            Location::Complement(inner) => inner.range(),
+            Location::Join(parts) | Location::Order(parts) => parts
+                .iter()
+                .filter_map(|part| part.range())
+                .reduce(|acc, range| min(acc.start, range.start)..max(acc.end, range.end)),
+            Location::Remote { .. } => None,
+        }
+    }
+
+    /// The strand of this location. Mixed strands yield `Strand::Unknown`.
+    pub fn strand(&self) -> Strand {
+        match self {
+            Location::Span { .. } | Location::Between(_) => Strand::Forward,
+            Location::Complement(inner) => match inner.strand() {
+                Strand::Forward => Strand::Reverse,
+                Strand::Reverse => Strand::Forward,
+                Strand::Unknown => Strand::Unknown,
+            },
+            Location::Join(parts) | Location::Order(parts) => {
+                let mut strands = parts.iter().map(|part| part.strand());
+                match strands.next() {
+                    Some(first) if strands.all(|strand| strand == first) => first,
+                    _ => Strand::Unknown,
+                }
+            }
+            Location::Remote { location, .. } => location.strand(),
+        }
+    }
+
+    /// The local ranges of this location in the order in which they are read, i.e.
+    /// from last to first for complemented locations. Remote parts are skipped.
+    pub fn spans(&self) -> Vec<Range<u64>> {
+        let mut spans = Vec::new();
+        self.push_spans(&mut spans);
+        spans
+    }
+
+    fn push_spans(&self, spans: &mut Vec<Range<u64>>) {
+        match self {
+            Location::Span { start, end, .. } => spans.push(*start..*end),
+            Location::Between(pos) => spans.push(*pos..*pos),
+            Location::Complement(inner) => {
+                let first = spans.len();
+                inner.push_spans(spans);
+                spans[first..].reverse();
+            }
+            Location::Join(parts) | Location::Order(parts) => {
+                for part in parts {
+                    part.push_spans(spans);
+                }
+            }
+            Location::Remote { .. } => (),
+        }
+    }
+}
+
+impl FromStr for Location {
+    type Err = Error;
+
+    /// Parse a location as given in the feature table. Whitespace is ignored.
+    fn from_str(s: &str) -> Result<Self> {
+        let location: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        parse_location(&location)
+    }
+}
+
+fn parse_location(s: &str) -> Result<Location> {
+    let invalid = || Error::InvalidLocation(s.to_owned());
+    if let Some(inner) = strip_operator(s, "complement") {
+        return Ok(Location::Complement(Box::new(parse_location(inner)?)));
+    }
+    if let Some(inner) = strip_operator(s, "join") {
+        return Ok(Location::Join(parse_location_list(inner)?));
+    }
+    if let Some(inner) = strip_operator(s, "order") {
+        return Ok(Location::Order(parse_location_list(inner)?));
+    }
+    if let Some(colon) = s.find(':') {
+        let accession = &s[..colon];
+        if accession.is_empty() || accession.contains('(') {
+            return Err(invalid());
+        }
+        return Ok(Location::Remote {
+            accession: accession.to_owned(),
+            location: Box::new(parse_location(&s[colon + 1..])?),
+        });
+    }
+    if let Some(caret) = s.find('^') {
+        let left = s[..caret].parse::<u64>().map_err(|_| invalid())?;
+        let right = s[caret + 1..].parse::<u64>().map_err(|_| invalid())?;
+        if right != left + 1 {
+            return Err(invalid());
+        }
+        return Ok(Location::Between(left));
+    }
+    let (first, last) = match s.find("..") {
+        Some(dots) => (&s[..dots], &s[dots + 2..]),
+        None => (s, s),
+    };
+    let (start_marker, start) = parse_position(first).ok_or_else(invalid)?;
+    let (end_marker, end) = parse_position(last).ok_or_else(invalid)?;
+    if start == 0 || start > end {
+        return Err(invalid());
+    }
+    let (partial_start, partial_end) = if first.len() == s.len() {
+        // a single base can only be fuzzy on one side
+        (start_marker == Some('<'), end_marker == Some('>'))
+    } else {
+        (start_marker.is_some(), end_marker.is_some())
+    };
+    Ok(Location::Span {
+        start: start - 1,
+        end,
+        partial_start,
+        partial_end,
+    })
+}
+
+/// Parse a position, returning its fuzzy marker (`<` or `>`) if present and its value.
+fn parse_position(s: &str) -> Option<(Option<char>, u64)> {
+    let (marker, digits) = match s.strip_prefix(&['<', '>'][..]) {
+        Some(digits) => (s.chars().next(), digits),
+        None => (None, s),
+    };
+    digits.parse().ok().map(|pos| (marker, pos))
+}
+
+/// Return the arguments of `s` if it has the form `operator(...)`.
+fn strip_operator<'a>(s: &'a str, operator: &str) -> Option<&'a str> {
+    let inner = s
+        .strip_prefix(operator)?
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    // the opening parenthesis has to be closed by the last character
+    let mut depth = 0i32;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        if depth < 0 {
+            return None;
+        }
+    }
+    Some(inner)
+}
+
+/// Parse a comma separated list of locations, ignoring commas in nested operators.
+fn parse_location_list(s: &str) -> Result<Vec<Location>> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut part_start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(parse_location(&s[part_start..i])?);
+                part_start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(parse_location(&s[part_start..])?);
+    Ok(parts)
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_list(f: &mut fmt::Formatter<'_>, name: &str, parts: &[Location]) -> fmt::Result {
+            write!(f, "{}(", name)?;
+            for (i, part) in parts.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", part)?;
+            }
+            write!(f, ")")
+        }
+
+        match self {
+            Location::Span {
+                start,
+                end,
+                partial_start,
+                partial_end,
+            } => {
+                let start_marker = if *partial_start { "<" } else { "" };
+                let end_marker = if *partial_end { ">" } else { "" };
+                if *end == start + 1 && !(*partial_start && *partial_end) {
+                    write!(f, "{}{}{}", start_marker, end_marker, end)
+                } else {
+                    write!(f, "{}{}..{}{}", start_marker, start + 1, end_marker, end)
+                }
+            }
+            Location::Between(pos) => write!(f, "{}^{}", pos, pos + 1),
+            Location::Complement(inner) => write!(f, "complement({})", inner),
+            Location::Join(parts) => write_list(f, "join", parts),
+            Location::Order(parts) => write_list(f, "order", parts),
+            Location::Remote {
+                accession,
+                location,
+            } => write!(f, "{}:{}", accession, location),
+        }
+    }
+}
+
+/// A feature of the feature table, e.g. a gene or CDS.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, MutGetters)]
+pub struct Feature {
+    /// The feature key, e.g. `CDS`.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    kind: String,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    location: Location,
+    /// Qualifiers in order of appearance. Qualifiers without a value (e.g. `/pseudo`)
+    /// have `None` as value.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    qualifiers: Vec<(String, Option<String>)>,
+}
+
+impl Feature {
+    /// Create a new feature without qualifiers.
+    pub fn new(kind: &str, location: Location) -> Self {
+        Feature {
+            kind: kind.to_owned(),
+            location,
+            qualifiers: Vec::new(),
+        }
+    }
+
+    /// Return the value of the first qualifier with the given key.
+    pub fn qualifier(&self, key: &str) -> Option<&str> {
+        self.qualifiers
+            .iter()
+            .find(|(k, v)| k == key && v.is_some())
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Iterate over the values of all qualifiers with the given key.
+    /// Qualifiers without a value are skipped.
+    pub fn qualifier_values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.qualifiers
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .filter_map(|(_, v)| v.as_deref())
+    }
+
+    /// Return true if a qualifier with the given key is present, with or without value.
+    pub fn has_qualifier(&self, key: &str) -> bool {
+        self.qualifiers.iter().any(|(k, _)| k == key)
+    }
+
+    /// Convert this feature into GFF records on the given sequence, one per local span of
+    /// the location, in the order in which they are read. Qualifiers become attributes;
+    /// qualifiers without a value are set to `true`. For `CDS` features, the phase of each
+    /// span is derived from `/codon_start`. A site between two bases is reported as the
+    /// two flanking bases.
+    pub fn to_gff(&self, seqname: &str) -> Vec<gff::Record> {
+        let strand = match self.location.strand() {
+            Strand::Forward => "+",
+            Strand::Reverse => "-",
+            Strand::Unknown => ".",
+        };
+        let codon_start = if self.kind == "CDS" {
+            Some(
+                self.qualifier("codon_start")
+                    .and_then(|c| c.parse::<i64>().ok())
+                    .unwrap_or(1),
+            )
+        } else {
+            None
+        };
+
+        let mut records = Vec::new();
+        let mut coding_len = 0i64;
+        for span in self.location.spans() {
+            let mut record = gff::Record::new();
+            *record.seqname_mut() = seqname.to_owned();
+            *record.source_mut() = ".".to_owned();
+            *record.feature_type_mut() = self.kind.clone();
+            if span.start == span.end {
+                *record.start_mut() = span.start;
+                *record.end_mut() = span.end + 1;
+            } else {
+                *record.start_mut() = span.start + 1;
+                *record.end_mut() = span.end;
+            }
+            *record.strand_mut() = strand.to_owned();
+            *record.frame_mut() = match codon_start {
+                Some(codon_start) => {
+                    ((3 - (coding_len - (codon_start - 1)).rem_euclid(3)) % 3).to_string()
+                }
+                None => ".".to_owned(),
+            };
+            for (key, value) in &self.qualifiers {
+                record.attributes_mut().insert(
+                    key.clone(),
+                    value.clone().unwrap_or_else(|| "true".to_owned()),
+                );
+            }
+            coding_len += (span.end - span.start) as i64;
+            records.push(record);
+        }
+        records
+    }
+}
+
+/// A GenBank or EMBL record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters, MutGetters)]
+pub struct Record {
+    /// The locus name (GenBank) or entry name (EMBL).
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    name: String,
+    /// The molecule type, e.g. `DNA` or `mRNA`.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    molecule_type: String,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    topology: Topology,
+    /// The database division, e.g. `BCT` or `SYN`.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    division: String,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    date: String,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    definition: String,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    accessions: Vec<String>,
+    /// The accession with sequence version, e.g. `L09137.2`.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    version: Option<String>,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    keywords: Vec<String>,
+    /// Free text description of the source organism (GenBank `SOURCE` line).
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    source: String,
+    /// The scientific name of the source organism.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    organism: String,
+    /// The taxonomic lineage of the source organism.
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    taxonomy: Vec<String>,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    features: Vec<Feature>,
+    #[get = "pub"]
+    #[get_mut = "pub"]
+    seq: Vec<u8>,
+}
+
+impl Record {
+    /// Create a new, empty record.
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.features.is_empty() && self.seq.is_empty()
+    }
+
+    /// Return the length of the sequence.
+    pub fn len(&self) -> usize {
+        self.seq.len()
+    }
+
+    /// Convert all features into GFF records, using the record name as sequence name.
+    pub fn to_gff(&self) -> Vec<gff::Record> {
+        self.features
+            .iter()
+            .flat_map(|feature| feature.to_gff(&self.name))
+            .collect()
+    }
+}
+
+/// Accumulates the lines of a feature table into features.
+#[derive(Debug, Default)]
+struct FeatureTable {
+    features: Vec<Feature>,
+    current: Option<PendingFeature>,
+}
+
+#[derive(Debug)]
+struct PendingFeature {
+    line: usize,
+    kind: String,
+    location: String,
+    qualifiers: Vec<(String, Option<String>)>,
+    /// Whether the value of the last qualifier is an unterminated quoted string.
+    open_quote: bool,
+}
+
+impl FeatureTable {
+    /// Process a line of the feature table. The line is given without the leading five
+    /// columns, i.e. starting with the feature key.
+    fn push_line(&mut self, line: &str, line_no: usize) -> Result<()> {
+        let key_width = FEATURE_KEY_WIDTH - 5;
+        let key = line.get(..key_width).unwrap_or(line).trim();
+        let value = line.get(key_width..).unwrap_or("").trim_end();
+
+        if let Some(feature) = self.current.as_mut() {
+            if feature.open_quote {
+                let (key, raw) = feature.qualifiers.last_mut().unwrap();
+                let raw = raw.as_mut().unwrap();
+                if key.as_str() != "translation" {
+                    raw.push(' ');
+                }
+                raw.push_str(value);
+                feature.open_quote = !is_closed_quote(raw);
+                return Ok(());
+            }
+        }
+
+        if !key.is_empty() {
+            self.finish_feature()?;
+            self.current = Some(PendingFeature {
+                line: line_no,
+                kind: key.to_owned(),
+                location: value.trim().to_owned(),
+                qualifiers: Vec::new(),
+                open_quote: false,
+            });
+            return Ok(());
+        }
+
+        let feature = self.current.as_mut().ok_or_else(|| Error::Format {
+            line: line_no,
+            message: "feature table continuation line without feature".to_owned(),
+        })?;
+        let value = value.trim_start();
+        if let Some(qualifier) = value.strip_prefix('/') {
+            let (key, raw) = match qualifier.find('=') {
+                Some(eq) => (&qualifier[..eq], Some(qualifier[eq + 1..].to_owned())),
+                None => (qualifier, None),
+            };
+            feature.open_quote =
+                matches!(&raw, Some(raw) if raw.starts_with('"') && !is_closed_quote(raw));
+            feature.qualifiers.push((key.to_owned(), raw));
+        } else if let Some((_, Some(raw))) = feature.qualifiers.last_mut() {
+            raw.push(' ');
+            raw.push_str(value);
+        } else if feature.qualifiers.is_empty() {
+            feature.location.push_str(value);
+        } else {
+            return Err(Error::Format {
+                line: line_no,
+                message: format!("unexpected continuation of qualifier: {}", value),
+            });
+        }
+        Ok(())
+    }
+
+    fn finish_feature(&mut self) -> Result<()> {
+        if let Some(feature) = self.current.take() {
+            if feature.open_quote {
+                return Err(Error::Format {
+                    line: feature.line,
+                    message: format!("unterminated qualifier value in {} feature", feature.kind),
+                });
+            }
+            let location =
+                feature
+                    .location
+                    .parse::<Location>()
+                    .map_err(|e: Error| Error::Format {
+                        line: feature.line,
+                        message: e.to_string(),
+                    })?;
+            let qualifiers = feature
+                .qualifiers
+                .into_iter()
+                .map(|(key, raw)| (key, raw.map(|raw| unquote(&raw))))
+                .collect();
+            self.features.push(Feature {
+                kind: feature.kind,
+                location,
+                qualifiers,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<Feature>> {
+        self.finish_feature()?;
+        Ok(self.features)
+    }
+}
+
+/// Check whether a quoted qualifier value is terminated. Quotes inside the value are
+/// escaped by doubling them, hence the value is closed if it contains an even number of quotes.
+fn is_closed_quote(raw: &str) -> bool {
+    raw.len() >= 2 && raw.ends_with('"') && raw.bytes().filter(|&b| b == b'"').count() % 2 == 0
+}
+
+fn unquote(raw: &str) -> String {
+    match raw.strip_prefix('"').and_then(|raw| raw.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => raw.to_owned(),
+    }
+}
+
+/// Split a `;` separated list (e.g. keywords or taxonomy), dropping the terminal period.
+fn split_list(text: &str) -> Vec<String> {
+    let text = text.trim().trim_end_matches('.');
+    text.split(';')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_owned())
+        .collect()
+}
+
+fn append_text(text: &mut String, line: &str) {
+    let line = line.trim();
+    if !text.is_empty() && !line.is_empty() {
+        text.push(' ');
+    }
+    text.push_str(line);
+}
+
+fn push_seq_line(seq: &mut Vec<u8>, line: &str) {
+    seq.extend(
+        line.bytes()
+            .filter(|b| !b.is_ascii_digit() && !b.is_ascii_whitespace()),
+    );
+}
+
+/// Sections of a GenBank record that may continue over multiple lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Definition,
+    Accession,
+    Keywords,
+    Source,
+    Organism,
+    Features,
+    Origin,
+    Other,
+}
+
+/// A GenBank and EMBL reader. The format is detected for each record.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read GenBank from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// Read the next line into the line buffer, returning false at the end of the input.
+    fn next_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+        let trimmed = self.line.trim_end_matches(&['\n', '\r'][..]).len();
+        self.line.truncate(trimmed);
+        Ok(true)
+    }
+
+    fn format_error(&self, message: &str) -> Error {
+        Error::Format {
+            line: self.line_no,
+            message: message.to_owned(),
+        }
+    }
+
+    /// Read the next record into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the record is not terminated by `//`, a
+    /// header line is malformed, a feature location can't be parsed or the sequence
+    /// length differs from the length given in the header.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        *record = Record::new();
+        loop {
+            if !self.next_line()? {
+                return Ok(());
+            }
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+
+        if self.line.starts_with("LOCUS") {
+            self.read_genbank(record)
+        } else if self.line.starts_with("ID ") {
+            self.read_embl(record)
+        } else {
+            Err(self.format_error("expected record to start with LOCUS or ID line"))
+        }
+    }
+
+    fn read_genbank(&mut self, record: &mut Record) -> Result<()> {
+        let len = self.parse_locus(record)?;
+        let mut section = Section::Other;
+        let mut keywords = String::new();
+        let mut taxonomy = String::new();
+        let mut features = FeatureTable::default();
+
+        loop {
+            if !self.next_line()? {
+                return Err(self.format_error("unexpected end of record, expected //"));
+            }
+            let line = self.line.as_str();
+            if line.starts_with("//") {
+                break;
+            }
+            let key = line.get(..12).unwrap_or(line).trim();
+            let value = line.get(12..).unwrap_or("").trim();
+            if !line.starts_with(' ') {
+                section = match key {
+                    "DEFINITION" => {
+                        append_text(&mut record.definition, value);
+                        Section::Definition
+                    }
+                    "ACCESSION" => {
+                        record
+                            .accessions
+                            .extend(value.split_whitespace().map(|a| a.to_owned()));
+                        Section::Accession
+                    }
+                    "VERSION" => {
+                        record.version = value.split_whitespace().next().map(|v| v.to_owned());
+                        Section::Other
+                    }
+                    "KEYWORDS" => {
+                        append_text(&mut keywords, value);
+                        Section::Keywords
+                    }
+                    "SOURCE" => {
+                        append_text(&mut record.source, value);
+                        Section::Source
+                    }
+                    "FEATURES" => Section::Features,
+                    "ORIGIN" => Section::Origin,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+            match section {
+                Section::Features => {
+                    features.push_line(line.get(5..).unwrap_or(""), self.line_no)?
+                }
+                Section::Origin => push_seq_line(&mut record.seq, line),
+                _ if !key.is_empty() => {
+                    if key == "ORGANISM" {
+                        append_text(&mut record.organism, value);
+                        section = Section::Organism;
+                    } else {
+                        section = Section::Other;
+                    }
+                }
+                Section::Definition => append_text(&mut record.definition, value),
+                Section::Accession => record
+                    .accessions
+                    .extend(value.split_whitespace().map(|a| a.to_owned())),
+                Section::Keywords => append_text(&mut keywords, value),
+                Section::Source => append_text(&mut record.source, value),
+                Section::Organism => append_text(&mut taxonomy, value),
+                Section::Other => (),
+            }
+        }
+
+        record.keywords = split_list(&keywords);
+        record.taxonomy = split_list(&taxonomy);
+        record.features = features.finish()?;
+        self.check_len(record, len)
+    }
+
+    /// Parse the LOCUS line, returning the sequence length.
+    fn parse_locus(&self, record: &mut Record) -> Result<u64> {
+        let fields: Vec<&str> = self.line.split_whitespace().skip(1).collect();
+        if fields.len() < 3 || !(fields[2] == "bp" || fields[2] == "aa") {
+            return Err(self.format_error("expected LOCUS line of the form 'LOCUS name length bp'"));
+        }
+        record.name = fields[0].to_owned();
+        let len = fields[1]
+            .parse()
+            .map_err(|_| self.format_error("invalid sequence length in LOCUS line"))?;
+        let mut rest = &fields[3..];
+        if let Some(&molecule_type) = rest.first() {
+            if !is_topology(molecule_type) {
+                record.molecule_type = molecule_type.to_owned();
+                rest = &rest[1..];
+            }
+        }
+        if let Some(&topology) = rest.first() {
+            if is_topology(topology) {
+                record.topology = parse_topology(topology);
+                rest = &rest[1..];
+            }
+        }
+        for field in rest {
+            if field.contains('-') {
+                record.date = (*field).to_owned();
+            } else {
+                record.division = (*field).to_owned();
+            }
+        }
+        Ok(len)
+    }
+
+    fn read_embl(&mut self, record: &mut Record) -> Result<()> {
+        let (len, sequence_version) = self.parse_id(record)?;
+        let mut description = String::new();
+        let mut keywords = String::new();
+        let mut taxonomy = String::new();
+        let mut features = FeatureTable::default();
+        let mut in_sequence = false;
+
+        loop {
+            if !self.next_line()? {
+                return Err(self.format_error("unexpected end of record, expected //"));
+            }
+            let line = self.line.as_str();
+            if line.starts_with("//") {
+                break;
+            }
+            if in_sequence && line.starts_with(' ') {
+                push_seq_line(&mut record.seq, line);
+                continue;
+            }
+            let value = line.get(5..).unwrap_or("").trim();
+            match line.get(..2).unwrap_or(line) {
+                "AC" => record.accessions.extend(
+                    value
+                        .split(';')
+                        .map(|a| a.trim())
+                        .filter(|a| !a.is_empty())
+                        .map(|a| a.to_owned()),
+                ),
+                "SV" => record.version = Some(value.to_owned()),
+                "DT" if record.date.is_empty() => {
+                    record.date = value.split_whitespace().next().unwrap_or("").to_owned()
+                }
+                "DE" => append_text(&mut description, value),
+                "KW" => append_text(&mut keywords, value),
+                "OS" => append_text(&mut record.organism, value),
+                "OC" => append_text(&mut taxonomy, value),
+                "FT" => features.push_line(line.get(5..).unwrap_or(""), self.line_no)?,
+                "SQ" => in_sequence = true,
+                _ => (),
+            }
+        }
+
+        if record.version.is_none() {
+            if let Some(sequence_version) = sequence_version {
+                let accession = record.accessions.first().unwrap_or(&record.name);
+                record.version = Some(format!("{}.{}", accession, sequence_version));
+            }
+        }
+        record.definition = description;
+        record.source = record.organism.clone();
+        record.keywords = split_list(&keywords);
+        record.taxonomy = split_list(&taxonomy);
+        record.features = features.finish()?;
+        self.check_len(record, len)
+    }
+
+    /// Parse the ID line, returning the sequence length and the sequence version if present.
+    fn parse_id(&self, record: &mut Record) -> Result<(u64, Option<String>)> {
+        let line = self.line.get(5..).unwrap_or("").trim();
+        let mut fields = line.split(';').map(|field| field.trim());
+        let mut first = fields.next().unwrap_or("").split_whitespace();
+        record.name = first
+            .next()
+            .ok_or_else(|| self.format_error("missing entry name in ID line"))?
+            .to_owned();
+
+        let mut len = None;
+        let mut sequence_version = None;
+        let mut others: Vec<&str> = first.collect();
+        for field in fields {
+            if let Some(version) = field.strip_prefix("SV ") {
+                sequence_version = Some(version.trim().to_owned());
+            } else if is_topology(field) {
+                record.topology = parse_topology(field);
+            } else if field.ends_with("BP.") || field.ends_with("AA.") {
+                len = field.split_whitespace().next().and_then(|l| l.parse().ok());
+            } else if !field.is_empty() {
+                others.push(field);
+            }
+        }
+        let len = len.ok_or_else(|| self.format_error("missing sequence length in ID line"))?;
+        record.molecule_type = others
+            .iter()
+            .find(|field| field.contains("DNA") || field.contains("RNA") || **field == "protein")
+            .or_else(|| others.first())
+            .map_or_else(String::new, |field| (*field).to_owned());
+        if others.len() > 1 {
+            record.division = others[others.len() - 1].to_owned();
+        }
+        Ok((len, sequence_version))
+    }
+
+    fn check_len(&self, record: &Record, len: u64) -> Result<()> {
+        if !record.seq.is_empty() && record.seq.len() as u64 != len {
+            return Err(Error::Format {
+                line: self.line_no,
+                message: format!(
+                    "sequence of record {} has length {}, but the header states {}",
+                    record.name,
+                    record.seq.len(),
+                    len
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Return an iterator over the records of this file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+fn is_topology(field: &str) -> bool {
+    field.eq_ignore_ascii_case("linear") || field.eq_ignore_ascii_case("circular")
+}
+
+fn parse_topology(field: &str) -> Topology {
+    if field.eq_ignore_ascii_case("circular") {
+        Topology::Circular
+    } else {
+        Topology::Linear
+    }
+}
+
+/// An iterator over the records of a GenBank or EMBL file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::new();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Greedily break text at spaces into lines of at most `width` characters.
+/// Words longer than `width` are not broken.
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut words = text.split(' ');
+    let mut line = words.next().unwrap_or("").to_owned();
+    for word in words {
+        if line.len() + 1 + word.len() <= width {
+            line.push(' ');
+            line.push_str(word);
+        } else {
+            lines.push(line);
+            line = word.to_owned();
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Break a location after commas into lines of at most `width` characters where possible.
+fn wrap_location(location: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for part in location.split_inclusive(',') {
+        if !line.is_empty() && line.len() + part.len() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        line.push_str(part);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Format a qualifier and break it into lines of at most `width` characters.
+fn wrap_qualifier(key: &str, value: Option<&str>, width: usize) -> Vec<String> {
+    let value = match value {
+        None => return vec![format!("/{}", key)],
+        Some(value) if UNQUOTED_QUALIFIERS.contains(&key) => {
+            return wrap_words(&format!("/{}={}", key, value), width)
+        }
+        Some(value) => value,
+    };
+    let qualifier = format!("/{}=\"{}\"", key, value.replace('"', "\"\""));
+    if key == "translation" {
+        qualifier
+            .as_bytes()
+            .chunks(width)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect()
+    } else {
+        wrap_words(&qualifier, width)
+    }
+}
+
+/// A GenBank or EMBL writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+    format: Format,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path in the given format.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+        fs::File::create(path).map(|file| Writer::new(file, format))
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write` in the given format.
+    pub fn new(writer: W, format: Format) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+            format,
+        }
+    }
+
+    /// Write a record.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self.format {
+            Format::GenBank => self.write_genbank(record),
+            Format::Embl => self.write_embl(record),
+        }
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Write a header field, wrapping the value at spaces.
+    fn write_field(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let (key_width, continuation) = match self.format {
+            Format::GenBank => (12, " "),
+            Format::Embl => (5, key),
+        };
+        for (i, line) in wrap_words(value, HEADER_VALUE_WIDTH).iter().enumerate() {
+            let key = if i == 0 { key } else { continuation };
+            writeln!(
+                self.writer,
+                "{}",
+                format!("{:<width$}{}", key, line, width = key_width).trim_end()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_features(&mut self, prefix: &str, features: &[Feature]) -> io::Result<()> {
+        for feature in features {
+            let location = wrap_location(&feature.location.to_string(), FEATURE_VALUE_WIDTH);
+            let qualifiers = feature.qualifiers.iter().flat_map(|(key, value)| {
+                wrap_qualifier(key, value.as_deref(), FEATURE_VALUE_WIDTH)
+            });
+            for (i, line) in location.into_iter().chain(qualifiers).enumerate() {
+                let key = if i == 0 { feature.kind.as_str() } else { "" };
+                writeln!(
+                    self.writer,
+                    "{:<width$}{}",
+                    format!("{}   {}", prefix, key),
+                    line,
+                    width = FEATURE_KEY_WIDTH
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_genbank(&mut self, record: &Record) -> io::Result<()> {
+        let locus = format!(
+            "LOCUS       {:<16} {:>11} bp    {:<6}  {:<8} {:<3} {}",
+            record.name,
+            record.seq.len(),
+            record.molecule_type,
+            record.topology,
+            record.division,
+            record.date
+        );
+        writeln!(self.writer, "{}", locus.trim_end())?;
+        self.write_field("DEFINITION", &record.definition)?;
+        if !record.accessions.is_empty() {
+            self.write_field("ACCESSION", &record.accessions.join(" "))?;
+        }
+        if let Some(version) = &record.version {
+            self.write_field("VERSION", version)?;
+        }
+        self.write_field("KEYWORDS", &format!("{}.", record.keywords.join("; ")))?;
+        if !record.source.is_empty() {
+            self.write_field("SOURCE", &record.source)?;
+        }
+        if !record.organism.is_empty() {
+            self.write_field("  ORGANISM", &record.organism)?;
+            if !record.taxonomy.is_empty() {
+                self.write_field("", &format!("{}.", record.taxonomy.join("; ")))?;
+            }
+        }
+        writeln!(self.writer, "FEATURES             Location/Qualifiers")?;
+        self.write_features("  ", &record.features)?;
+        writeln!(self.writer, "ORIGIN")?;
+        for (i, line) in record.seq.chunks(60).enumerate() {
+            write!(self.writer, "{:>9}", i * 60 + 1)?;
+            for group in line.chunks(10) {
+                self.writer.write_all(b" ")?;
+                self.writer.write_all(group)?;
+            }
+            self.writer.write_all(b"\n")?;
+        }
+        writeln!(self.writer, "//")
+    }
+
+    fn write_embl(&mut self, record: &Record) -> io::Result<()> {
+        let sequence_version = record
+            .version
+            .as_ref()
+            .and_then(|version| version.rsplit('.').next())
+            .map_or_else(String::new, |version| format!(" SV {};", version));
+        writeln!(
+            self.writer,
+            "ID   {};{} {}; {}; STD; {}; {} BP.",
+            record.name,
+            sequence_version,
+            record.topology,
+            record.molecule_type,
+            record.division,
+            record.seq.len()
+        )?;
+        writeln!(self.writer, "XX")?;
+        if !record.accessions.is_empty() {
+            self.write_field("AC", &format!("{};", record.accessions.join("; ")))?;
+            writeln!(self.writer, "XX")?;
+        }
+        if !record.date.is_empty() {
+            self.write_field("DT", &record.date)?;
+            writeln!(self.writer, "XX")?;
+        }
+        self.write_field("DE", &record.definition)?;
+        writeln!(self.writer, "XX")?;
+        self.write_field("KW", &format!("{}.", record.keywords.join("; ")))?;
+        writeln!(self.writer, "XX")?;
+        if !record.organism.is_empty() {
+            self.write_field("OS", &record.organism)?;
+            if !record.taxonomy.is_empty() {
+                self.write_field("OC", &format!("{}.", record.taxonomy.join("; ")))?;
+            }
+            writeln!(self.writer, "XX")?;
+        }
+        writeln!(self.writer, "FH   Key             Location/Qualifiers")?;
+        writeln!(self.writer, "FH")?;
+        self.write_features("FT", &record.features)?;
+        writeln!(self.writer, "XX")?;
+
+        let count = |bases: &[u8]| record.seq.iter().filter(|&b| bases.contains(b)).count();
+        let (a, c, g, t) = (count(b"Aa"), count(b"Cc"), count(b"Gg"), count(b"Tt"));
+        writeln!(
+            self.writer,
+            "SQ   Sequence {} BP; {} A; {} C; {} G; {} T; {} other;",
+            record.seq.len(),
+            a,
+            c,
+            g,
+            t,
+            record.seq.len() - a - c - g - t
+        )?;
+        for (i, line) in record.seq.chunks(60).enumerate() {
+            let groups: Vec<String> = line
+                .chunks(10)
+                .map(|group| String::from_utf8_lossy(group).into_owned())
+                .collect();
+            writeln!(
+                self.writer,
+                "     {:<65}{:>10}",
+                groups.join(" "),
+                i * 60 + line.len()
+            )?;
+        }
+        writeln!(self.writer, "//")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENBANK_FILE: &[u8] =
+        b"LOCUS       pEX                       72 bp    DNA     circular SYN 15-JUN-2010
+DEFINITION  Cloning vector pEX, complete
+            sequence.
+ACCESSION   L09137 X02514
+VERSION     L09137.2
+KEYWORDS    cloning vector; synthetic.
+SOURCE      Cloning vector pEX
+  ORGANISM  Cloning vector pEX
+            other sequences; artificial sequences; vectors.
+REFERENCE   1  (bases 1 to 72)
+  AUTHORS   Doe,J.
+  TITLE     A vector
+FEATURES             Location/Qualifiers
+     source          1..72
+                     /organism=\"Cloning vector pEX\"
+                     /mol_type=\"other DNA\"
+     CDS             join(<1..12,
+                     20..>30)
+                     /codon_start=2
+                     /gene=\"exA\"
+                     /note=\"a note that spans
+                     two lines with \"\"quotes\"\"\"
+                     /translation=\"MKV
+                     LA\"
+                     /pseudo
+     misc_feature    complement(40^41)
+ORIGIN
+        1 atgaaagtac tggcataaag cgcgcgttta aacgtacgta cgtacgtacg tacgtacgta
+       61 cgtacgtacg ta
+//
+";
+
+    const EMBL_FILE: &[u8] = b"ID   X56734; SV 1; linear; mRNA; STD; PLN; 20 BP.
+XX
+AC   X56734; S46826;
+XX
+DT   12-SEP-1991 (Rel. 29, Created)
+DT   25-NOV-2005 (Rel. 85, Last updated, Version 11)
+XX
+DE   Trifolium repens mRNA for
+DE   beta-glucosidase
+XX
+KW   beta-glucosidase.
+XX
+OS   Trifolium repens (white clover)
+OC   Eukaryota; Viridiplantae.
+XX
+FH   Key             Location/Qualifiers
+FH
+FT   CDS             complement(3..17)
+FT                   /product=\"beta-glucosidase\"
+FT                   /transl_table=11
+XX
+SQ   Sequence 20 BP; 6 A; 4 C; 4 G; 6 T; 0 other;
+     aaacaaacca aatatggatt                                                20
+//
+";
+
+    #[test]
+    fn test_read_genbank() {
+        let record = Reader::new(GENBANK_FILE).records().next().unwrap().unwrap();
+
+        assert_eq!(record.name(), "pEX");
+        assert_eq!(record.molecule_type(), "DNA");
+        assert_eq!(*record.topology(), Topology::Circular);
+        assert_eq!(record.division(), "SYN");
+        assert_eq!(record.date(), "15-JUN-2010");
+        assert_eq!(
+            record.definition(),
+            "Cloning vector pEX, complete sequence."
+        );
+        assert_eq!(record.accessions(), &["L09137", "X02514"]);
+        assert_eq!(record.version().as_deref(), Some("L09137.2"));
+        assert_eq!(record.keywords(), &["cloning vector", "synthetic"]);
+        assert_eq!(record.source(), "Cloning vector pEX");
+        assert_eq!(record.organism(), "Cloning vector pEX");
+        assert_eq!(
+            record.taxonomy(),
+            &["other sequences", "artificial sequences", "vectors"]
+        );
+        assert_eq!(record.len(), 72);
+        assert!(record.seq().starts_with(b"atgaaagtac"));
+        assert!(record.seq().ends_with(b"cgtacgtacgta"));
+
+        let features = record.features();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].kind(), "source");
+        assert_eq!(features[0].qualifier("mol_type"), Some("other DNA"));
+
+        let cds = &features[1];
+        assert_eq!(
+            cds.location(),
+            &Location::Join(vec![
+                Location::Span {
+                    start: 0,
+                    end: 12,
+                    partial_start: true,
+                    partial_end: false
+                },
+                Location::Span {
+                    start: 19,
+                    end: 30,
+                    partial_start: false,
+                    partial_end: true
+                },
+            ])
+        );
+        assert_eq!(cds.qualifier("codon_start"), Some("2"));
+        assert_eq!(
+            cds.qualifier("note"),
+            Some("a note that spans two lines with \"quotes\"")
+        );
+        assert_eq!(cds.qualifier("translation"), Some("MKVLA"));
+        assert!(cds.has_qualifier("pseudo"));
+        assert_eq!(cds.qualifier("pseudo"), None);
+
+        assert_eq!(
+            features[2].location(),
+            &Location::Complement(Box::new(Location::Between(40)))
+        );
+    }
+
+    #[test]
+    fn test_read_embl() {
+        let record = Reader::new(EMBL_FILE).records().next().unwrap().unwrap();
+
+        assert_eq!(record.name(), "X56734");
+        assert_eq!(record.molecule_type(), "mRNA");
+        assert_eq!(*record.topology(), Topology::Linear);
+        assert_eq!(record.division(), "PLN");
+        assert_eq!(record.date(), "12-SEP-1991");
+        assert_eq!(
+            record.definition(),
+            "Trifolium repens mRNA for beta-glucosidase"
+        );
+        assert_eq!(record.accessions(), &["X56734", "S46826"]);
+        assert_eq!(record.version().as_deref(), Some("X56734.1"));
+        assert_eq!(record.keywords(), &["beta-glucosidase"]);
+        assert_eq!(record.organism(), "Trifolium repens (white clover)");
+        assert_eq!(record.taxonomy(), &["Eukaryota", "Viridiplantae"]);
+        assert_eq!(record.seq(), b"aaacaaaccaaatatggatt");
+
+        let cds = &record.features()[0];
+        assert_eq!(cds.kind(), "CDS");
+        assert_eq!(cds.location().range(), Some(2..17));
+        assert_eq!(cds.location().strand(), Strand::Reverse);
+        assert_eq!(cds.qualifier("product"), Some("beta-glucosidase"));
+        assert_eq!(cds.qualifier("transl_table"), Some("11"));
+    }
+
+    #[test]
+    fn test_read_multiple_records() {
+        let mut input = GENBANK_FILE.to_vec();
+        input.extend_from_slice(EMBL_FILE);
+        input.extend_from_slice(b"\n");
+        input.extend_from_slice(GENBANK_FILE);
+        let names: Vec<String> = Reader::new(&input[..])
+            .records()
+            .map(|record| record.unwrap().name().to_owned())
+            .collect();
+        assert_eq!(names, ["pEX", "X56734", "pEX"]);
+    }
+
+    #[test]
+    fn test_genbank_roundtrip() {
+        let record = Reader::new(GENBANK_FILE).records().next().unwrap().unwrap();
+        let mut writer = Writer::new(Vec::new(), Format::GenBank);
+        writer.write(&record).unwrap();
+        writer.flush().unwrap();
+        let output = writer.writer.into_inner().unwrap();
+
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(
+            lines[0],
+            "LOCUS       pEX                       72 bp    DNA     circular SYN 15-JUN-2010"
+        );
+        assert!(lines.contains(&"     CDS             join(<1..12,20..>30)"));
+        assert!(lines.contains(&"                     /codon_start=2"));
+        assert!(lines.contains(
+            &"        1 atgaaagtac tggcataaag cgcgcgttta aacgtacgta cgtacgtacg tacgtacgta"
+        ));
+
+        let reread = Reader::new(&output[..]).records().next().unwrap().unwrap();
+        assert_eq!(reread, record);
+    }
+
+    #[test]
+    fn test_embl_roundtrip() {
+        let record = Reader::new(GENBANK_FILE).records().next().unwrap().unwrap();
+        let mut writer = Writer::new(Vec::new(), Format::Embl);
+        writer.write(&record).unwrap();
+        writer.flush().unwrap();
+        let output = writer.writer.into_inner().unwrap();
+
+        let text = std::str::from_utf8(&output).unwrap();
+        assert!(text.starts_with("ID   pEX; SV 2; circular; DNA; STD; SYN; 72 BP.\n"));
+        assert!(text.contains("\nSQ   Sequence 72 BP; 22 A; 15 C; 18 G; 17 T; 0 other;\n"));
+
+        let reread = Reader::new(&output[..]).records().next().unwrap().unwrap();
+        assert_eq!(reread.features(), record.features());
+        assert_eq!(reread.seq(), record.seq());
+        assert_eq!(reread.definition(), record.definition());
+        assert_eq!(reread.accessions(), record.accessions());
+        assert_eq!(reread.version(), record.version());
+        assert_eq!(reread.taxonomy(), record.taxonomy());
+    }
+
+    #[test]
+    fn test_write_wraps_long_qualifiers() {
+        let mut feature = Feature::new("CDS", "1..12".parse().unwrap());
+        let note = "word ".repeat(30).trim_end().to_owned();
+        let translation = "M".repeat(100);
+        feature
+            .qualifiers_mut()
+            .push(("note".to_owned(), Some(note.clone())));
+        feature
+            .qualifiers_mut()
+            .push(("translation".to_owned(), Some(translation.clone())));
+        let mut record = Record::new();
+        *record.name_mut() = "long".to_owned();
+        *record.seq_mut() = b"atgatgatgtaa".to_vec();
+        record.features_mut().push(feature);
+
+        let mut writer = Writer::new(Vec::new(), Format::GenBank);
+        writer.write(&record).unwrap();
+        writer.flush().unwrap();
+        let output = writer.writer.into_inner().unwrap();
+        assert!(std::str::from_utf8(&output)
+            .unwrap()
+            .lines()
+            .all(|line| line.len() <= 80));
+
+        let reread = Reader::new(&output[..]).records().next().unwrap().unwrap();
+        assert_eq!(reread.features()[0].qualifier("note"), Some(note.as_str()));
+        assert_eq!(
+            reread.features()[0].qualifier("translation"),
+            Some(translation.as_str())
+        );
+    }
+
+    #[test]
+    fn test_location_parse_and_display() {
+        for location in &[
+            "467",
+            "<1",
+            "340..565",
+            "<345..500",
+            "<1..>888",
+            "102^103",
+            "complement(34..126)",
+            "join(12..78,134..202)",
+            "complement(join(2691..4571,4918..5163))",
+            "join(complement(4918..5163),complement(2691..4571))",
+            "order(1..10,20..30)",
+            "J00194.1:100..202",
+            "join(1..100,J00194.1:100..202)",
+        ] {
+            let parsed: Location = location.parse().unwrap();
+            assert_eq!(&parsed.to_string(), location);
+        }
+        assert_eq!(
+            "join( 1..10 ,\n 20..30 )"
+                .parse::<Location>()
+                .unwrap()
+                .to_string(),
+            "join(1..10,20..30)"
+        );
+    }
+
+    #[test]
+    fn test_location_invalid() {
+        for location in &[
+            "",
+            "0..10",
+            "10..5",
+            "1..",
+            "5^7",
+            "join(1..10",
+            "complement(1..2),complement(3..4)",
+            "foo(1..2)",
+            ":1..2",
+        ] {
+            assert!(
+                matches!(location.parse::<Location>(), Err(Error::InvalidLocation(_))),
+                "{}",
+                location
+            );
+        }
+    }
+
+    #[test]
+    fn test_location_range_strand_and_spans() {
+        let location: Location = "complement(join(1..10,20..30))".parse().unwrap();
+        assert_eq!(location.range(), Some(0..30));
+        assert_eq!(location.strand(), Strand::Reverse);
+        assert_eq!(location.spans(), vec![19..30, 0..10]);
+
+        let mixed: Location = "order(1..10,complement(20..30))".parse().unwrap();
+        assert!(mixed.strand().is_unknown());
+
+        let remote: Location = "J00194.1:100..202".parse().unwrap();
+        assert_eq!(remote.range(), None);
+        assert!(remote.spans().is_empty());
+    }
+
+    #[test]
+    fn test_feature_to_gff() {
+        let record = Reader::new(GENBANK_FILE).records().next().unwrap().unwrap();
+        let cds = record.features()[1].to_gff(record.name());
+        assert_eq!(cds.len(), 2);
+        assert_eq!(cds[0].seqname(), "pEX");
+        assert_eq!(cds[0].feature_type(), "CDS");
+        assert_eq!((*cds[0].start(), *cds[0].end()), (1, 12));
+        assert_eq!((*cds[1].start(), *cds[1].end()), (20, 30));
+        assert_eq!(cds[0].strand(), Some(Strand::Forward));
+        // codon_start=2 skips one base, leaving 11 coding bases in the first exon
+        assert_eq!(cds[0].frame(), "1");
+        assert_eq!(cds[1].frame(), "1");
+        assert_eq!(cds[0].attributes().get("gene"), Some(&"exA".to_owned()));
+        assert_eq!(cds[0].attributes().get("pseudo"), Some(&"true".to_owned()));
+
+        let site = record.features()[2].to_gff(record.name());
+        assert_eq!((*site[0].start(), *site[0].end()), (40, 41));
+        assert_eq!(site[0].strand(), Some(Strand::Reverse));
+        assert_eq!(site[0].frame(), ".");
+
+        assert_eq!(record.to_gff().len(), 4);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let missing_end = &GENBANK_FILE[..GENBANK_FILE.len() - 3];
+        assert!(matches!(
+            Reader::new(missing_end).records().next().unwrap(),
+            Err(Error::Format { .. })
+        ));
+
+        assert!(matches!(
+            Reader::new(&b"FOO bar\n"[..]).records().next().unwrap(),
+            Err(Error::Format { line: 1, .. })
+        ));
+
+        let bad_location = b"LOCUS       x 4 bp DNA\nFEATURES             Location/Qualifiers\n     gene            4..1\nORIGIN\n        1 acgt\n//\n";
+        assert!(matches!(
+            Reader::new(&bad_location[..]).records().next().unwrap(),
+            Err(Error::Format { line: 3, .. })
+        ));
+
+        let wrong_len = b"LOCUS       x 5 bp DNA\nORIGIN\n        1 acgt\n//\n";
+        assert!(matches!(
+            Reader::new(&wrong_len[..]).records().next().unwrap(),
+            Err(Error::Format { .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(Reader::new(&b"\n\n"[..]).records().next().is_none());
+    }
+}
@@ -0,0 +1,1082 @@
+//! Reading and writing of sequence graphs in the [GFA] format, versions 1 and 2.
+//!
+//! A graph consists of segments (sequences), links between segment ends, containments of
+//! segments in others, and paths and walks through the graph. Optional fields are typed
+//! like SAM tags. A graph can be converted into a petgraph `StableDiGraph` with
+//! [`Gfa::to_graph`](struct.Gfa.html#method.to_graph), and a partial order alignment graph
+//! can be exported with [`Gfa::from_poa_graph`](struct.Gfa.html#method.from_poa_graph),
+//! e.g. for inspection in [Bandage].
+//!
+//! GFA 2 edges are read as links if they are dovetail overlaps and as containments if one
+//! segment is contained in the other, with the edge identifier as `ID` tag. Ordered groups
+//! are read as paths. Fragments, gaps, unordered groups and comments are skipped.
+//!
+//! [GFA]: https://github.com/GFA-spec/GFA-spec
+//! [Bandage]: https://rrwick.github.io/Bandage/
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::gfa;
+//!
+//! let input = b"H\tVN:Z:1.0
+//! S\ts1\tACGTACGT
+//! S\ts2\tTACGGA
+//! L\ts1\t+\ts2\t+\t3M
+//! P\tp1\ts1+,s2+\t3M
+//! ";
+//! let graph = gfa::Reader::new(&input[..]).read_gfa().unwrap();
+//! assert_eq!(graph.segments.len(), 2);
+//! assert_eq!(graph.paths[0].steps[1].0, "s2");
+//!
+//! let mut writer = gfa::Writer::new(Vec::new()).version(gfa::Version::Gfa2);
+//! writer.write(&graph).unwrap();
+//! assert!(String::from_utf8(writer.into_inner().unwrap())
+//!     .unwrap()
+//!     .contains("E\t*\ts1+\ts2+\t5\t8$\t0\t3\t3M\n"));
+//! ```
+
+use anyhow::Context;
+use bio_types::strand::ReqStrand;
+use petgraph::stable_graph::StableDiGraph;
+use std::collections::{HashMap, HashSet};
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path as FilePath, PathBuf};
+use thiserror::Error;
+
+use crate::alignment::poa::POAGraph;
+use crate::io::compression::Decoder;
+use crate::io::sam::{parse_tag, Cigar, CigarString, TagValue};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("unknown segment {0}")]
+    UnknownSegment(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Optional fields of a GFA line.
+pub type Tags = Vec<(String, TagValue)>;
+
+/// A graph of segments as nodes and links as edges, with node indices in the order of the
+/// segments.
+pub type SequenceGraph = StableDiGraph<Segment, Link>;
+
+/// The version of the GFA format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Gfa1,
+    Gfa2,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for Version {
+    fn default() -> Self {
+        Version::Gfa1
+    }
+}
+
+fn find_tag<'a>(tags: &'a [(String, TagValue)], tag: &str) -> Option<&'a TagValue> {
+    tags.iter().find(|(t, _)| t == tag).map(|(_, value)| value)
+}
+
+/// A segment, i.e. a sequence of the graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    /// The sequence, or `None` if it is not stored in the file (`*`).
+    pub sequence: Option<Vec<u8>>,
+    pub tags: Tags,
+}
+
+impl Segment {
+    /// The length of the segment, given by its sequence or the `LN` tag.
+    pub fn length(&self) -> Option<u64> {
+        match (&self.sequence, self.tag("LN")) {
+            (Some(sequence), _) => Some(sequence.len() as u64),
+            (None, Some(TagValue::Int(len))) if *len >= 0 => Some(*len as u64),
+            _ => None,
+        }
+    }
+
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A link from the end of one oriented segment to the start of another, i.e. a dovetail
+/// overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub from: String,
+    pub from_orient: ReqStrand,
+    pub to: String,
+    pub to_orient: ReqStrand,
+    /// The alignment of the overlap, with `from` as reference. Empty if unavailable.
+    pub overlap: CigarString,
+    pub tags: Tags,
+}
+
+impl Link {
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A segment contained in another one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Containment {
+    pub container: String,
+    pub container_orient: ReqStrand,
+    pub contained: String,
+    pub contained_orient: ReqStrand,
+    /// The leftmost position of the contained segment in the container (0-based).
+    pub pos: u64,
+    /// The alignment of the overlap, with `container` as reference. Empty if unavailable.
+    pub overlap: CigarString,
+    pub tags: Tags,
+}
+
+impl Containment {
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A named path through oriented segments.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    pub name: String,
+    pub steps: Vec<(String, ReqStrand)>,
+    /// The overlaps between consecutive steps. Empty if unavailable.
+    pub overlaps: Vec<CigarString>,
+    pub tags: Tags,
+}
+
+impl Path {
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A walk of a haplotype through oriented segments (GFA 1.1).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Walk {
+    pub sample: String,
+    pub haplotype: u64,
+    pub sequence: String,
+    /// Start of the walk on the sequence, if given.
+    pub start: Option<u64>,
+    /// End of the walk on the sequence, if given.
+    pub end: Option<u64>,
+    pub steps: Vec<(String, ReqStrand)>,
+    pub tags: Tags,
+}
+
+impl Walk {
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A line of a GFA file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Header(Tags),
+    Segment(Segment),
+    Link(Link),
+    Containment(Containment),
+    Path(Path),
+    Walk(Walk),
+}
+
+/// A sequence graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gfa {
+    /// The fields of the header lines.
+    pub header: Tags,
+    pub segments: Vec<Segment>,
+    pub links: Vec<Link>,
+    pub containments: Vec<Containment>,
+    pub paths: Vec<Path>,
+    pub walks: Vec<Walk>,
+}
+
+impl Gfa {
+    /// Create a new, empty graph.
+    pub fn new() -> Self {
+        Gfa::default()
+    }
+
+    /// Add a record to the graph.
+    pub fn push(&mut self, record: Record) {
+        match record {
+            Record::Header(tags) => self.header.extend(tags),
+            Record::Segment(segment) => self.segments.push(segment),
+            Record::Link(link) => self.links.push(link),
+            Record::Containment(containment) => self.containments.push(containment),
+            Record::Path(path) => self.paths.push(path),
+            Record::Walk(walk) => self.walks.push(walk),
+        }
+    }
+
+    /// Return the segment with the given name.
+    pub fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.name == name)
+    }
+
+    /// Convert into a directed graph with a node per segment and an edge per link, from
+    /// `from` to `to`. The node indices follow the order of the segments. Containments,
+    /// paths and walks are not part of the graph.
+    ///
+    /// # Errors
+    ///
+    /// If a link refers to an unknown segment.
+    pub fn to_graph(&self) -> Result<SequenceGraph> {
+        let mut graph = SequenceGraph::with_capacity(self.segments.len(), self.links.len());
+        let mut nodes = HashMap::new();
+        for segment in &self.segments {
+            nodes.insert(segment.name.as_str(), graph.add_node(segment.clone()));
+        }
+        let node = |name: &str| {
+            nodes
+                .get(name)
+                .copied()
+                .ok_or_else(|| Error::UnknownSegment(name.to_owned()))
+        };
+        for link in &self.links {
+            graph.add_edge(node(&link.from)?, node(&link.to)?, link.clone());
+        }
+        Ok(graph)
+    }
+
+    /// Export a partial order alignment graph, with a segment per node, named by the node
+    /// index, and a link per edge. The edge weights, i.e. the number of sequences
+    /// supporting an edge, are stored as `RC` (read count) tags of the links.
+    pub fn from_poa_graph(graph: &POAGraph) -> Self {
+        let mut gfa = Gfa::new();
+        gfa.header
+            .push(("VN".to_owned(), TagValue::String("1.0".to_owned())));
+        for (i, node) in graph.raw_nodes().iter().enumerate() {
+            gfa.segments.push(Segment {
+                name: i.to_string(),
+                sequence: Some(vec![node.weight]),
+                tags: Vec::new(),
+            });
+        }
+        for edge in graph.raw_edges() {
+            gfa.links.push(Link {
+                from: edge.source().index().to_string(),
+                from_orient: ReqStrand::Forward,
+                to: edge.target().index().to_string(),
+                to_orient: ReqStrand::Forward,
+                overlap: CigarString(vec![Cigar::Match(0)]),
+                tags: vec![("RC".to_owned(), TagValue::Int(edge.weight as i64))],
+            });
+        }
+        gfa
+    }
+}
+
+fn orientation(value: &str) -> std::result::Result<ReqStrand, String> {
+    match value {
+        "+" => Ok(ReqStrand::Forward),
+        "-" => Ok(ReqStrand::Reverse),
+        _ => Err(format!("invalid orientation: {}", value)),
+    }
+}
+
+/// Parse a segment reference with trailing orientation, e.g. `s1+`.
+fn step(value: &str) -> std::result::Result<(String, ReqStrand), String> {
+    match value.char_indices().last() {
+        Some((i, _)) if i > 0 => Ok((value[..i].to_owned(), orientation(&value[i..])?)),
+        _ => Err(format!("invalid segment reference: {}", value)),
+    }
+}
+
+/// Parse the steps of a walk, e.g. `>s1<s2`.
+fn walk_steps(value: &str) -> std::result::Result<Vec<(String, ReqStrand)>, String> {
+    let invalid = || format!("invalid walk: {}", value);
+    let mut steps: Vec<(String, ReqStrand)> = Vec::new();
+    for c in value.chars() {
+        match c {
+            '>' => steps.push((String::new(), ReqStrand::Forward)),
+            '<' => steps.push((String::new(), ReqStrand::Reverse)),
+            _ => steps.last_mut().ok_or_else(invalid)?.0.push(c),
+        }
+    }
+    if steps.is_empty() || steps.iter().any(|(name, _)| name.is_empty()) {
+        return Err(invalid());
+    }
+    Ok(steps)
+}
+
+fn number(value: &str, name: &str) -> std::result::Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {}: {}", name, value))
+}
+
+/// Parse an optional position, given as `*` if missing.
+fn optional_number(value: &str, name: &str) -> std::result::Result<Option<u64>, String> {
+    match value {
+        "*" => Ok(None),
+        _ => number(value, name).map(Some),
+    }
+}
+
+/// Parse a GFA 2 position, returning whether it is marked as the end of the segment.
+fn position(value: &str) -> std::result::Result<(u64, bool), String> {
+    match value.strip_suffix('$') {
+        Some(value) => Ok((number(value, "position")?, true)),
+        None => Ok((number(value, "position")?, false)),
+    }
+}
+
+fn cigar(value: &str) -> std::result::Result<CigarString, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid overlap: {}", value))
+}
+
+/// Parse a GFA 2 alignment. Trace alignments are dropped.
+fn alignment(value: &str) -> std::result::Result<CigarString, String> {
+    if value.bytes().all(|c| c.is_ascii_digit() || c == b',') {
+        return Ok(CigarString::default());
+    }
+    cigar(value)
+}
+
+/// Swap reference and query of an alignment.
+fn invert(cigar: CigarString) -> CigarString {
+    let ops = cigar
+        .0
+        .into_iter()
+        .map(|op| match op {
+            Cigar::Ins(len) => Cigar::Del(len),
+            Cigar::Del(len) => Cigar::Ins(len),
+            op => op,
+        })
+        .collect();
+    CigarString(ops)
+}
+
+fn tags(fields: &[&str]) -> std::result::Result<Tags, String> {
+    fields
+        .iter()
+        .map(|field| parse_tag(field).ok_or_else(|| format!("invalid optional field: {}", field)))
+        .collect()
+}
+
+/// Parse a line, returning `None` for lines that are skipped.
+fn parse_line(
+    fields: &[&str],
+    version: Option<Version>,
+) -> std::result::Result<Option<Record>, String> {
+    let min_fields = match fields[0] {
+        "H" => 1,
+        "S" | "O" => 3,
+        "P" => 4,
+        "L" => 6,
+        "C" | "W" => 7,
+        "E" => 9,
+        _ => return Ok(None),
+    };
+    if fields.len() < min_fields {
+        return Err(format!(
+            "expected at least {} fields in {} line, found {}",
+            min_fields,
+            fields[0],
+            fields.len()
+        ));
+    }
+
+    let record = match fields[0] {
+        "H" => Record::Header(tags(&fields[1..])?),
+        "S" => {
+            let gfa2 = match version {
+                Some(version) => version == Version::Gfa2,
+                // sequences of GFA 1 segments can't be numbers
+                None => fields.len() >= 4 && fields[2].bytes().all(|c| c.is_ascii_digit()),
+            };
+            let (sequence, tag_fields) = if gfa2 {
+                if fields.len() < 4 {
+                    return Err("expected at least 4 fields in S line".to_owned());
+                }
+                (fields[3], &fields[4..])
+            } else {
+                (fields[2], &fields[3..])
+            };
+            let mut segment = Segment {
+                name: fields[1].to_owned(),
+                sequence: match sequence {
+                    "*" => None,
+                    _ => Some(sequence.as_bytes().to_vec()),
+                },
+                tags: tags(tag_fields)?,
+            };
+            if gfa2 && segment.length().is_none() {
+                let length = number(fields[2], "segment length")?;
+                segment
+                    .tags
+                    .push(("LN".to_owned(), TagValue::Int(length as i64)));
+            }
+            Record::Segment(segment)
+        }
+        "L" => Record::Link(Link {
+            from: fields[1].to_owned(),
+            from_orient: orientation(fields[2])?,
+            to: fields[3].to_owned(),
+            to_orient: orientation(fields[4])?,
+            overlap: cigar(fields[5])?,
+            tags: tags(&fields[6..])?,
+        }),
+        "C" => Record::Containment(Containment {
+            container: fields[1].to_owned(),
+            container_orient: orientation(fields[2])?,
+            contained: fields[3].to_owned(),
+            contained_orient: orientation(fields[4])?,
+            pos: number(fields[5], "position")?,
+            overlap: cigar(fields[6])?,
+            tags: tags(&fields[7..])?,
+        }),
+        "P" => Record::Path(Path {
+            name: fields[1].to_owned(),
+            steps: fields[2]
+                .split(',')
+                .map(step)
+                .collect::<std::result::Result<_, _>>()?,
+            overlaps: match fields[3] {
+                "*" => Vec::new(),
+                overlaps => overlaps
+                    .split(',')
+                    .map(cigar)
+                    .collect::<std::result::Result<_, _>>()?,
+            },
+            tags: tags(&fields[4..])?,
+        }),
+        "W" => Record::Walk(Walk {
+            sample: fields[1].to_owned(),
+            haplotype: number(fields[2], "haplotype index")?,
+            sequence: fields[3].to_owned(),
+            start: optional_number(fields[4], "start")?,
+            end: optional_number(fields[5], "end")?,
+            steps: walk_steps(fields[6])?,
+            tags: tags(&fields[7..])?,
+        }),
+        "E" => parse_edge(fields)?,
+        "O" => Record::Path(Path {
+            name: fields[1].to_owned(),
+            steps: fields[2]
+                .split(' ')
+                .map(step)
+                .collect::<std::result::Result<_, _>>()?,
+            overlaps: Vec::new(),
+            tags: tags(&fields[3..])?,
+        }),
+        _ => unreachable!(),
+    };
+    Ok(Some(record))
+}
+
+/// Parse a GFA 2 edge into a link or containment.
+fn parse_edge(fields: &[&str]) -> std::result::Result<Record, String> {
+    let (from, from_orient) = step(fields[2])?;
+    let (to, to_orient) = step(fields[3])?;
+    let (begin1, end1) = (position(fields[4])?, position(fields[5])?);
+    let (begin2, end2) = (position(fields[6])?, position(fields[7])?);
+    let overlap = alignment(fields[8])?;
+    let mut tags = tags(&fields[9..])?;
+    if fields[1] != "*" {
+        tags.insert(0, ("ID".to_owned(), TagValue::String(fields[1].to_owned())));
+    }
+
+    let at_end1 = match from_orient {
+        ReqStrand::Forward => end1.1,
+        ReqStrand::Reverse => begin1.0 == 0,
+    };
+    let at_start2 = match to_orient {
+        ReqStrand::Forward => begin2.0 == 0,
+        ReqStrand::Reverse => end2.1,
+    };
+    if at_end1 && at_start2 {
+        Ok(Record::Link(Link {
+            from,
+            from_orient,
+            to,
+            to_orient,
+            overlap,
+            tags,
+        }))
+    } else if begin2.0 == 0 && end2.1 {
+        Ok(Record::Containment(Containment {
+            container: from,
+            container_orient: from_orient,
+            contained: to,
+            contained_orient: to_orient,
+            pos: begin1.0,
+            overlap,
+            tags,
+        }))
+    } else if begin1.0 == 0 && end1.1 {
+        Ok(Record::Containment(Containment {
+            container: to,
+            container_orient: to_orient,
+            contained: from,
+            contained_orient: from_orient,
+            pos: begin2.0,
+            overlap: invert(overlap),
+            tags,
+        }))
+    } else {
+        Err("edges that are neither dovetails nor containments are not supported".to_owned())
+    }
+}
+
+/// A GFA reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line: String,
+    line_no: usize,
+    version: Option<Version>,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<FilePath> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read GFA from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line: String::new(),
+            line_no: 0,
+            version: None,
+        }
+    }
+
+    /// The version given in the header (`VN` tag), if any has been read yet.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Read the next record, or `None` at the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line has too few fields, a field can't be
+    /// parsed, or a GFA 2 edge is an internal overlap.
+    pub fn read(&mut self) -> Result<Option<Record>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_no += 1;
+            let line = self.line.trim_end_matches(&['\n', '\r'][..]);
+            let fields: Vec<&str> = line.split('\t').collect();
+            let record = parse_line(&fields, self.version).map_err(|message| Error::Format {
+                line: self.line_no,
+                message,
+            })?;
+            match record {
+                Some(Record::Header(ref tags)) => {
+                    if let Some(TagValue::String(version)) = find_tag(tags, "VN") {
+                        self.version = Some(if version.starts_with('2') {
+                            Version::Gfa2
+                        } else {
+                            Version::Gfa1
+                        });
+                    }
+                    return Ok(record);
+                }
+                Some(record) => return Ok(Some(record)),
+                None => continue,
+            }
+        }
+    }
+
+    /// Return an iterator over the records of this file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+
+    /// Read the whole graph. References to GFA 2 edges are removed from paths.
+    pub fn read_gfa(self) -> Result<Gfa> {
+        let mut gfa = Gfa::new();
+        for record in self.records() {
+            gfa.push(record?);
+        }
+
+        let segments: HashSet<&str> = gfa.segments.iter().map(|s| s.name.as_str()).collect();
+        let edges: HashSet<String> = gfa
+            .links
+            .iter()
+            .map(|l| &l.tags)
+            .chain(gfa.containments.iter().map(|c| &c.tags))
+            .filter_map(|tags| match find_tag(tags, "ID") {
+                Some(TagValue::String(id)) if !segments.contains(id.as_str()) => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        if !edges.is_empty() {
+            for path in &mut gfa.paths {
+                path.steps.retain(|(name, _)| !edges.contains(name));
+            }
+        }
+        Ok(gfa)
+    }
+}
+
+/// An iterator over the records of a GFA file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        self.reader.read().transpose()
+    }
+}
+
+/// Format a GFA 2 position, marking the end of the segment with `$`.
+fn format_position(pos: u64, len: u64) -> String {
+    if pos == len {
+        format!("{}$", pos)
+    } else {
+        pos.to_string()
+    }
+}
+
+/// A GFA writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+    version: Version,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<FilePath>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write GFA 1 to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+            version: Version::Gfa1,
+        }
+    }
+
+    /// Set the version of the format to write.
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    fn write_tags(&mut self, tags: &[(String, TagValue)], skip: &str) -> io::Result<()> {
+        for (tag, value) in tags.iter().filter(|(tag, _)| tag != skip) {
+            write!(self.writer, "\t{}:{}", tag, value)?;
+        }
+        writeln!(self.writer)
+    }
+
+    /// Write a graph. The version in the header is set according to the written format.
+    ///
+    /// # Errors
+    ///
+    /// When writing GFA 2, if the graph contains walks, or a link or containment refers to
+    /// a segment of unknown length.
+    pub fn write(&mut self, gfa: &Gfa) -> io::Result<()> {
+        let version = match (self.version, find_tag(&gfa.header, "VN")) {
+            (Version::Gfa1, Some(TagValue::String(v))) if v.starts_with('1') => v.as_str(),
+            (Version::Gfa1, _) => "1.0",
+            (Version::Gfa2, _) => "2.0",
+        };
+        write!(self.writer, "H\tVN:Z:{}", version)?;
+        self.write_tags(&gfa.header, "VN")?;
+        match self.version {
+            Version::Gfa1 => self.write_gfa1(gfa),
+            Version::Gfa2 => self.write_gfa2(gfa),
+        }
+    }
+
+    fn write_gfa1(&mut self, gfa: &Gfa) -> io::Result<()> {
+        for segment in &gfa.segments {
+            write!(self.writer, "S\t{}\t", segment.name)?;
+            match &segment.sequence {
+                Some(sequence) => self.writer.write_all(sequence)?,
+                None => write!(self.writer, "*")?,
+            }
+            self.write_tags(&segment.tags, "")?;
+        }
+        for link in &gfa.links {
+            write!(
+                self.writer,
+                "L\t{}\t{}\t{}\t{}\t{}",
+                link.from,
+                link.from_orient.strand_symbol(),
+                link.to,
+                link.to_orient.strand_symbol(),
+                link.overlap
+            )?;
+            self.write_tags(&link.tags, "")?;
+        }
+        for containment in &gfa.containments {
+            write!(
+                self.writer,
+                "C\t{}\t{}\t{}\t{}\t{}\t{}",
+                containment.container,
+                containment.container_orient.strand_symbol(),
+                containment.contained,
+                containment.contained_orient.strand_symbol(),
+                containment.pos,
+                containment.overlap
+            )?;
+            self.write_tags(&containment.tags, "")?;
+        }
+        for path in &gfa.paths {
+            let steps: Vec<String> = path
+                .steps
+                .iter()
+                .map(|(name, orient)| format!("{}{}", name, orient.strand_symbol()))
+                .collect();
+            let overlaps: Vec<String> = path.overlaps.iter().map(|o| o.to_string()).collect();
+            write!(
+                self.writer,
+                "P\t{}\t{}\t{}",
+                path.name,
+                steps.join(","),
+                if overlaps.is_empty() {
+                    "*".to_owned()
+                } else {
+                    overlaps.join(",")
+                }
+            )?;
+            self.write_tags(&path.tags, "")?;
+        }
+        for walk in &gfa.walks {
+            let optional = |pos: Option<u64>| pos.map_or_else(|| "*".to_owned(), |p| p.to_string());
+            write!(
+                self.writer,
+                "W\t{}\t{}\t{}\t{}\t{}\t",
+                walk.sample,
+                walk.haplotype,
+                walk.sequence,
+                optional(walk.start),
+                optional(walk.end)
+            )?;
+            for (name, orient) in &walk.steps {
+                let direction = match orient {
+                    ReqStrand::Forward => '>',
+                    ReqStrand::Reverse => '<',
+                };
+                write!(self.writer, "{}{}", direction, name)?;
+            }
+            self.write_tags(&walk.tags, "")?;
+        }
+        Ok(())
+    }
+
+    fn write_gfa2(&mut self, gfa: &Gfa) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if !gfa.walks.is_empty() {
+            return Err(invalid("walks can't be written as GFA 2".to_owned()));
+        }
+        let lengths: HashMap<&str, u64> = gfa
+            .segments
+            .iter()
+            .filter_map(|s| s.length().map(|len| (s.name.as_str(), len)))
+            .collect();
+        let length = |name: &str| {
+            lengths
+                .get(name)
+                .copied()
+                .ok_or_else(|| invalid(format!("unknown length of segment {}", name)))
+        };
+        let id = |tags: &[(String, TagValue)]| match find_tag(tags, "ID") {
+            Some(TagValue::String(id)) => id.clone(),
+            _ => "*".to_owned(),
+        };
+
+        for segment in &gfa.segments {
+            write!(
+                self.writer,
+                "S\t{}\t{}\t",
+                segment.name,
+                length(&segment.name)?
+            )?;
+            match &segment.sequence {
+                Some(sequence) => self.writer.write_all(sequence)?,
+                None => write!(self.writer, "*")?,
+            }
+            self.write_tags(&segment.tags, "LN")?;
+        }
+        for link in &gfa.links {
+            let (len1, len2) = (length(&link.from)?, length(&link.to)?);
+            let (overlap1, overlap2) = (link.overlap.reference_len(), link.overlap.query_len());
+            let (begin1, end1) = match link.from_orient {
+                ReqStrand::Forward => (len1.saturating_sub(overlap1), len1),
+                ReqStrand::Reverse => (0, overlap1),
+            };
+            let (begin2, end2) = match link.to_orient {
+                ReqStrand::Forward => (0, overlap2),
+                ReqStrand::Reverse => (len2.saturating_sub(overlap2), len2),
+            };
+            write!(
+                self.writer,
+                "E\t{}\t{}{}\t{}{}\t{}\t{}\t{}\t{}\t{}",
+                id(&link.tags),
+                link.from,
+                link.from_orient.strand_symbol(),
+                link.to,
+                link.to_orient.strand_symbol(),
+                format_position(begin1, len1),
+                format_position(end1, len1),
+                format_position(begin2, len2),
+                format_position(end2, len2),
+                link.overlap
+            )?;
+            self.write_tags(&link.tags, "ID")?;
+        }
+        for containment in &gfa.containments {
+            let len1 = length(&containment.container)?;
+            let len2 = length(&containment.contained)?;
+            let covered = if containment.overlap.0.is_empty() {
+                len2
+            } else {
+                containment.overlap.reference_len()
+            };
+            write!(
+                self.writer,
+                "E\t{}\t{}{}\t{}{}\t{}\t{}\t0\t{}\t{}",
+                id(&containment.tags),
+                containment.container,
+                containment.container_orient.strand_symbol(),
+                containment.contained,
+                containment.contained_orient.strand_symbol(),
+                format_position(containment.pos, len1),
+                format_position(containment.pos + covered, len1),
+                format_position(len2, len2),
+                containment.overlap
+            )?;
+            self.write_tags(&containment.tags, "ID")?;
+        }
+        for path in &gfa.paths {
+            let steps: Vec<String> = path
+                .steps
+                .iter()
+                .map(|(name, orient)| format!("{}{}", name, orient.strand_symbol()))
+                .collect();
+            write!(self.writer, "O\t{}\t{}", path.name, steps.join(" "))?;
+            self.write_tags(&path.tags, "")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alignment::pairwise::Scoring;
+    use crate::alignment::poa::Aligner;
+    use petgraph::graph::NodeIndex;
+
+    const GFA1: &[u8] = b"H\tVN:Z:1.0
+S\ts1\tACGTACGT\tRC:i:10
+S\ts2\t*\tLN:i:100
+S\ts3\tCGTA
+L\ts1\t+\ts2\t-\t4M
+L\ts2\t-\ts3\t+\t*\tID:Z:l2
+C\ts1\t+\ts3\t+\t1\t4M
+P\tp1\ts1+,s2-,s3+\t4M,*
+W\tNA12878\t1\tchr1\t0\t*\t>s1<s2>s3
+";
+
+    const GFA2: &[u8] = b"H\tVN:Z:2.0
+S\ts1\t8\tACGTACGT
+S\ts2\t100\t*
+S\ts3\t4\tCGTA
+# an edge and a containment
+E\te1\ts1+\ts2-\t4\t8$\t96\t100$\t4M
+E\t*\ts2-\ts3+\t0\t0\t0\t0\t*
+E\te3\ts1+\ts3+\t1\t5\t0\t4$\t4M
+O\tp1\ts1+ e1+ s2- s3+
+";
+
+    #[test]
+    fn test_gfa1() {
+        let gfa = Reader::new(GFA1).read_gfa().unwrap();
+        assert_eq!(gfa.segments.len(), 3);
+        assert_eq!(
+            gfa.segment("s1").unwrap().tag("RC"),
+            Some(&TagValue::Int(10))
+        );
+        assert_eq!(gfa.segment("s2").unwrap().sequence, None);
+        assert_eq!(gfa.segment("s2").unwrap().length(), Some(100));
+        let link = &gfa.links[0];
+        assert_eq!((link.from.as_str(), link.to.as_str()), ("s1", "s2"));
+        assert_eq!(link.to_orient, ReqStrand::Reverse);
+        assert_eq!(link.overlap, CigarString(vec![Cigar::Match(4)]));
+        assert!(gfa.links[1].overlap.0.is_empty());
+        assert_eq!(gfa.containments[0].pos, 1);
+        assert_eq!(gfa.paths[0].steps.len(), 3);
+        assert_eq!(gfa.paths[0].overlaps.len(), 2);
+        let walk = &gfa.walks[0];
+        assert_eq!((walk.start, walk.end), (Some(0), None));
+        assert_eq!(walk.steps[1], ("s2".to_owned(), ReqStrand::Reverse));
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write(&gfa).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), GFA1);
+
+        let mut writer = Writer::new(Vec::new()).version(Version::Gfa2);
+        assert!(writer.write(&gfa).is_err());
+
+        let mut reader = Reader::new(&b"S\ts1\tACGT\nL\ts1\t+\ts2\t?\t*\n"[..]);
+        assert!(reader.read().unwrap().is_some());
+        assert!(matches!(reader.read(), Err(Error::Format { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_gfa2() {
+        let gfa = Reader::new(GFA2).read_gfa().unwrap();
+        assert_eq!(gfa.segments.len(), 3);
+        assert_eq!(gfa.segment("s2").unwrap().length(), Some(100));
+        assert_eq!(gfa.links.len(), 2);
+        assert_eq!(
+            gfa.links[0].tag("ID"),
+            Some(&TagValue::String("e1".to_owned()))
+        );
+        assert_eq!(gfa.links[1].from_orient, ReqStrand::Reverse);
+        assert_eq!(gfa.containments.len(), 1);
+        let containment = &gfa.containments[0];
+        assert_eq!(
+            (
+                containment.container.as_str(),
+                containment.contained.as_str()
+            ),
+            ("s1", "s3")
+        );
+        assert_eq!(containment.pos, 1);
+        let steps: Vec<&str> = gfa.paths[0].steps.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(steps, ["s1", "s2", "s3"]);
+
+        let mut writer = Writer::new(Vec::new()).version(Version::Gfa2);
+        writer.write(&gfa).unwrap();
+        let expected: Vec<u8> = GFA2
+            .split(|&c| c == b'\n')
+            .filter(|line| !line.starts_with(b"#") && !line.is_empty())
+            .map(|line| match line {
+                b"O\tp1\ts1+ e1+ s2- s3+" => &b"O\tp1\ts1+ s2- s3+"[..],
+                line => line,
+            })
+            .flat_map(|line| line.iter().chain(b"\n"))
+            .copied()
+            .collect();
+        assert_eq!(
+            String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write(&gfa).unwrap();
+        let gfa1 = Reader::new(&writer.into_inner().unwrap()[..])
+            .read_gfa()
+            .unwrap();
+        assert_eq!(gfa1.links, gfa.links);
+        assert_eq!(gfa1.containments, gfa.containments);
+
+        let mut reader = Reader::new(&b"E\t*\ts1+\ts2+\t1\t2\t1\t2\t*\n"[..]);
+        assert!(matches!(reader.read(), Err(Error::Format { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_graph() {
+        let scoring = Scoring::new(-1, 0, |a: u8, b: u8| if a == b { 1i32 } else { -1i32 });
+        let mut aligner = Aligner::new(scoring, b"ACGT");
+        aligner.global(b"ACCT").add_to_graph();
+        let poa = aligner.graph();
+
+        let gfa = Gfa::from_poa_graph(poa);
+        assert_eq!(gfa.segments.len(), poa.node_count());
+        assert_eq!(gfa.links.len(), poa.edge_count());
+        assert_eq!(gfa.segments[4].sequence, Some(b"C".to_vec()));
+
+        let graph = gfa.to_graph().unwrap();
+        assert_eq!(graph.node_count(), poa.node_count());
+        assert_eq!(graph.edge_count(), poa.edge_count());
+        let node = NodeIndex::new(1);
+        assert_eq!(graph[node].name, "1");
+        assert_eq!(graph.neighbors(node).count(), 2);
+
+        let mut gfa = Gfa::new();
+        gfa.links.push(gfa1_link("s1", "s2"));
+        assert!(matches!(gfa.to_graph(), Err(Error::UnknownSegment(_))));
+    }
+
+    fn gfa1_link(from: &str, to: &str) -> Link {
+        Link {
+            from: from.to_owned(),
+            from_orient: ReqStrand::Forward,
+            to: to.to_owned(),
+            to_orient: ReqStrand::Forward,
+            overlap: CigarString::default(),
+            tags: Vec::new(),
+        }
+    }
+}
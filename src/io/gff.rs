@@ -9,6 +9,10 @@
 //! [GTF2]: http://mblab.wustl.edu/GTF2.html (not supported)
 //! [GFF3]: http://gmod.org/wiki/GFF3#GFF3_Format
 //!
+//! Records can be assembled into gene models with [`FeatureTree`](FeatureTree).
+//! GFF3 directives are available as [`Directive`](Directive)s, and attribute keys and values
+//! are percent-decoded and -encoded.
+//!
 //! # Example
 //!
 //! ```
@@ -28,14 +32,22 @@ use anyhow::Context;
 use itertools::Itertools;
 use multimap::MultiMap;
 use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::convert::AsRef;
+use std::fmt;
 use std::fs;
 use std::io;
+use std::io::prelude::*;
 use std::path::Path;
 use std::str::FromStr;
+use thiserror::Error;
 
 use bio_types::strand::Strand;
 
+use crate::io::compression::Decoder;
+use crate::io::fasta;
+
 /// `GffType`
 ///
 /// We have three format in the GFF family.
@@ -91,20 +103,263 @@ impl GffType {
     }
 }
 
+/// A GFF3 directive (`##` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// `##gff-version`
+    GffVersion(String),
+    /// `##sequence-region` with 1-based, inclusive coordinates.
+    SequenceRegion { seqid: String, start: u64, end: u64 },
+    /// `##feature-ontology`
+    FeatureOntology(String),
+    /// `##attribute-ontology`
+    AttributeOntology(String),
+    /// `##source-ontology`
+    SourceOntology(String),
+    /// `##species`
+    Species(String),
+    /// `##genome-build`
+    GenomeBuild { source: String, name: String },
+    /// `###`, indicating that all forward references to features have been resolved.
+    ResolutionBarrier,
+    /// `##FASTA`, indicating that the rest of the file contains sequences in FASTA format.
+    Fasta,
+    /// Any other or malformed directive, given by name and the remainder of the line.
+    Other { name: String, value: String },
+}
+
+impl FromStr for Directive {
+    type Err = String;
+
+    /// Parse a directive line including the leading `##`.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line
+            .strip_prefix("##")
+            .ok_or_else(|| format!("Directive '{}' does not start with '##'.", line))?
+            .trim_end();
+        let (name, value) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim_start()),
+            None => (line, ""),
+        };
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        Ok(match (name, fields.as_slice()) {
+            ("#", []) => Directive::ResolutionBarrier,
+            ("FASTA", []) => Directive::Fasta,
+            ("gff-version", [version]) => Directive::GffVersion((*version).to_owned()),
+            ("feature-ontology", [uri]) => Directive::FeatureOntology((*uri).to_owned()),
+            ("attribute-ontology", [uri]) => Directive::AttributeOntology((*uri).to_owned()),
+            ("source-ontology", [uri]) => Directive::SourceOntology((*uri).to_owned()),
+            ("species", [uri]) => Directive::Species((*uri).to_owned()),
+            ("genome-build", [source, build]) => Directive::GenomeBuild {
+                source: (*source).to_owned(),
+                name: (*build).to_owned(),
+            },
+            ("sequence-region", [seqid, start, end]) => match (start.parse(), end.parse()) {
+                (Ok(start), Ok(end)) => Directive::SequenceRegion {
+                    seqid: (*seqid).to_owned(),
+                    start,
+                    end,
+                },
+                _ => Directive::Other {
+                    name: name.to_owned(),
+                    value: value.to_owned(),
+                },
+            },
+            _ => Directive::Other {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Directive::GffVersion(version) => write!(f, "##gff-version {}", version),
+            Directive::SequenceRegion { seqid, start, end } => {
+                write!(f, "##sequence-region {} {} {}", seqid, start, end)
+            }
+            Directive::FeatureOntology(uri) => write!(f, "##feature-ontology {}", uri),
+            Directive::AttributeOntology(uri) => write!(f, "##attribute-ontology {}", uri),
+            Directive::SourceOntology(uri) => write!(f, "##source-ontology {}", uri),
+            Directive::Species(uri) => write!(f, "##species {}", uri),
+            Directive::GenomeBuild { source, name } => {
+                write!(f, "##genome-build {} {}", source, name)
+            }
+            Directive::ResolutionBarrier => write!(f, "###"),
+            Directive::Fasta => write!(f, "##FASTA"),
+            Directive::Other { name, value } if value.is_empty() => write!(f, "##{}", name),
+            Directive::Other { name, value } => write!(f, "##{} {}", name, value),
+        }
+    }
+}
+
+/// Characters that are percent-encoded in GFF3 attribute keys and values.
+fn needs_escape(c: char) -> bool {
+    matches!(c, ';' | '=' | ',' | '&' | '%') || c.is_control()
+}
+
+/// Percent-encode the reserved characters of a GFF3 attribute key or value.
+fn escape(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(needs_escape) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        if needs_escape(c) {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                escaped.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Decode percent-encoded characters. Invalid escape sequences are kept as they are.
+fn unescape(s: &str) -> String {
+    if !s.contains('%') {
+        return s.to_owned();
+    }
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| {
+            std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// An error that occurs while reading GFF records. Lines are counted from one and byte
+/// offsets from zero, both referring to the start of the offending line.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line} (byte {offset}): {message}")]
+    Format {
+        line: usize,
+        offset: u64,
+        message: String,
+    },
+
+    #[error("line {line} (byte {offset}): truncated record with {fields} fields, expected 9")]
+    TruncatedRecord {
+        line: usize,
+        offset: u64,
+        fields: usize,
+    },
+
+    #[error("line {line} (byte {offset}): start {start} is greater than end {end}")]
+    InvalidCoordinates {
+        line: usize,
+        offset: u64,
+        start: u64,
+        end: u64,
+    },
+
+    #[error("line {line} (byte {offset}): invalid strand {strand:?}")]
+    InvalidStrand {
+        line: usize,
+        offset: u64,
+        strand: String,
+    },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Reads the lines of a GFF file up to an embedded FASTA section, collecting directives
+/// and skipping comments and blank lines on the way.
+#[derive(Debug)]
+struct Lines<R: io::Read> {
+    reader: io::BufReader<R>,
+    line: Vec<u8>,
+    line_number: usize,
+    offset: u64,
+    directives: Vec<Directive>,
+    fasta: bool,
+}
+
+impl<R: io::Read> Lines<R> {
+    fn new(reader: R) -> Self {
+        Lines {
+            reader: io::BufReader::new(reader),
+            line: Vec::new(),
+            line_number: 0,
+            offset: 0,
+            directives: Vec::new(),
+            fasta: false,
+        }
+    }
+
+    /// Read the next feature line into the line buffer, returning `false` once all features
+    /// have been read.
+    fn next_line(&mut self) -> io::Result<bool> {
+        loop {
+            self.offset += self.line.len() as u64;
+            self.line.clear();
+            if self.fasta {
+                return Ok(false);
+            }
+            // FASTA sections may also start without ##FASTA directive
+            match self.reader.fill_buf()?.first() {
+                None => return Ok(false),
+                Some(b'>') => {
+                    self.fasta = true;
+                    return Ok(false);
+                }
+                Some(_) => (),
+            }
+            self.reader.read_until(b'\n', &mut self.line)?;
+            self.line_number += 1;
+            if self.line.starts_with(b"##") {
+                let directive: Directive = String::from_utf8_lossy(&self.line).parse().unwrap();
+                self.fasta = directive == Directive::Fasta;
+                self.directives.push(directive);
+            } else if !self.line.starts_with(b"#") && !self.line.iter().all(u8::is_ascii_whitespace)
+            {
+                return Ok(true);
+            }
+        }
+    }
+}
+
 /// A GFF reader.
+///
+/// Directives are collected while reading and comments are skipped. A trailing FASTA
+/// section (GFF3 `##FASTA` directive) can be read with [`into_fasta`](Reader::into_fasta).
 #[derive(Debug)]
 pub struct Reader<R: io::Read> {
-    inner: csv::Reader<R>,
+    inner: Lines<R>,
     gff_type: GffType,
 }
 
-impl Reader<fs::File> {
+impl Reader<Decoder<fs::File>> {
     /// Read GFF from given file path in given format.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(
         path: P,
         fileformat: GffType,
     ) -> anyhow::Result<Self> {
         fs::File::open(&path)
+            .and_then(Decoder::new)
             .map(|f| Reader::new(f, fileformat))
             .with_context(|| format!("Failed to read GFF from {:#?}", path))
     }
@@ -114,16 +369,15 @@ impl<R: io::Read> Reader<R> {
     /// Create a new GFF reader given an instance of `io::Read`, in given format.
     pub fn new(reader: R, fileformat: GffType) -> Self {
         Reader {
-            inner: csv::ReaderBuilder::new()
-                .delimiter(b'\t')
-                .has_headers(false)
-                .comment(Some(b'#'))
-                .from_reader(reader),
+            inner: Lines::new(reader),
             gff_type: fileformat,
         }
     }
 
     /// Iterate over all records.
+    ///
+    /// Records with fewer than nine fields, a start greater than the end or a strand other
+    /// than `+`, `-`, `.` or `?` are reported as errors.
     pub fn records(&mut self) -> Records<'_, R> {
         let (delim, term, vdelim) = self.gff_type.separator();
         let r = format!(
@@ -133,79 +387,145 @@ impl<R: io::Read> Reader<R> {
         );
         let attribute_re = Regex::new(&r).unwrap();
         Records {
-            inner: self.inner.deserialize(),
+            lines: &mut self.inner,
             attribute_re,
             value_delim: vdelim as char,
+            unescape: self.gff_type == GffType::GFF3,
         }
     }
-}
 
-type GffRecordInner = (
-    String,
-    String,
-    String,
-    u64,
-    u64,
-    String,
-    String,
-    String,
-    String,
-);
+    /// The directives read so far.
+    pub fn directives(&self) -> &[Directive] {
+        &self.inner.directives
+    }
+
+    /// Skip all remaining records and return a FASTA reader for the sequences following the
+    /// records, or `None` if there are none.
+    pub fn into_fasta(self) -> io::Result<Option<fasta::Reader<io::BufReader<R>>>> {
+        let mut lines = self.inner;
+        while lines.next_line()? {}
+        Ok(if lines.fasta {
+            Some(fasta::Reader::from_bufread(lines.reader))
+        } else {
+            None
+        })
+    }
+
+    /// Read all records and assemble them into a feature hierarchy.
+    /// See [`FeatureTree::from_records`](FeatureTree::from_records) for details.
+    pub fn feature_tree(&mut self) -> Result<FeatureTree, HierarchyError> {
+        let gff_type = self.gff_type;
+        FeatureTree::from_records(self.records(), gff_type)
+    }
+}
 
 /// An iterator over the records of a GFF file.
 pub struct Records<'a, R: io::Read> {
-    inner: csv::DeserializeRecordsIter<'a, R, GffRecordInner>,
+    lines: &'a mut Lines<R>,
     attribute_re: Regex,
     value_delim: char,
+    unescape: bool,
 }
 
-impl<'a, R: io::Read> Iterator for Records<'a, R> {
-    type Item = csv::Result<Record>;
-
-    fn next(&mut self) -> Option<csv::Result<Record>> {
-        self.inner.next().map(|res| {
-            res.map(
-                |(
-                    seqname,
-                    source,
-                    feature_type,
-                    start,
-                    end,
-                    score,
-                    strand,
-                    frame,
-                    raw_attributes,
-                )| {
-                    let trim_quotes = |s: &str| s.trim_matches('\'').trim_matches('"').to_owned();
-                    let mut attributes = MultiMap::new();
-                    for caps in self.attribute_re.captures_iter(&raw_attributes) {
-                        for value in caps["value"].split(self.value_delim) {
-                            attributes.insert(trim_quotes(&caps["key"]), trim_quotes(value));
-                        }
-                    }
-                    Record {
-                        seqname,
-                        source,
-                        feature_type,
-                        start,
-                        end,
-                        score,
-                        strand,
-                        frame,
-                        attributes,
-                    }
-                },
-            )
+impl<'a, R: io::Read> Records<'a, R> {
+    /// Parse the current line of the reader.
+    fn parse(&self) -> Result<Record> {
+        let (line, offset) = (self.lines.line_number, self.lines.offset);
+        let format_error = |message: String| Error::Format {
+            line,
+            offset,
+            message,
+        };
+        let text = std::str::from_utf8(&self.lines.line)
+            .map_err(|_| format_error("record is not valid UTF-8".to_owned()))?;
+        let fields: Vec<&str> = text
+            .trim_end_matches(&['\r', '\n'][..])
+            .split('\t')
+            .collect();
+        if fields.len() < 9 {
+            return Err(Error::TruncatedRecord {
+                line,
+                offset,
+                fields: fields.len(),
+            });
+        }
+        if fields.len() > 9 {
+            return Err(format_error(format!(
+                "expected 9 fields, found {}",
+                fields.len()
+            )));
+        }
+        let position = |name: &str, value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format_error(format!("invalid {} position {:?}", name, value)))
+        };
+        let start = position("start", fields[3])?;
+        let end = position("end", fields[4])?;
+        if start > end {
+            return Err(Error::InvalidCoordinates {
+                line,
+                offset,
+                start,
+                end,
+            });
+        }
+        if !matches!(fields[6], "+" | "-" | "." | "?") {
+            return Err(Error::InvalidStrand {
+                line,
+                offset,
+                strand: fields[6].to_owned(),
+            });
+        }
+
+        let unescape_values = self.unescape;
+        let trim_quotes = |s: &str| {
+            let s = s.trim_matches('\'').trim_matches('"');
+            if unescape_values {
+                unescape(s)
+            } else {
+                s.to_owned()
+            }
+        };
+        let mut attributes = MultiMap::new();
+        for caps in self.attribute_re.captures_iter(fields[8]) {
+            for value in caps["value"].split(self.value_delim) {
+                attributes.insert(trim_quotes(&caps["key"]), trim_quotes(value));
+            }
+        }
+        Ok(Record {
+            seqname: fields[0].to_owned(),
+            source: fields[1].to_owned(),
+            feature_type: fields[2].to_owned(),
+            start,
+            end,
+            score: fields[5].to_owned(),
+            strand: fields[6].to_owned(),
+            frame: fields[7].to_owned(),
+            attributes,
         })
     }
 }
 
+impl<'a, R: io::Read> Iterator for Records<'a, R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        match self.lines.next_line() {
+            Ok(true) => Some(self.parse()),
+            Ok(false) => None,
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
 /// A GFF writer.
 #[derive(Debug)]
 pub struct Writer<W: io::Write> {
     inner: csv::Writer<W>,
     delimiter: char,
     terminator: String,
+    gff_type: GffType,
 }
 
 impl Writer<fs::File> {
@@ -228,19 +548,34 @@ impl<W: io::Write> Writer<W> {
                 .from_writer(writer),
             delimiter: delim as char,
             terminator: String::from_utf8(vec![termi]).unwrap(),
+            gff_type: fileformat,
         }
     }
 
     /// Write a given GFF record.
+    /// In GFF3, all values of an attribute are written and reserved characters are
+    /// percent-encoded.
     pub fn write(&mut self, record: &Record) -> csv::Result<()> {
-        let attributes = if !record.attributes.is_empty() {
+        let attributes = if record.attributes.is_empty() {
+            "".to_owned()
+        } else if self.gff_type == GffType::GFF3 {
+            record
+                .attributes
+                .iter_all()
+                .map(|(key, values)| {
+                    format!(
+                        "{}={}",
+                        escape(key),
+                        values.iter().map(|value| escape(value)).join(",")
+                    )
+                })
+                .join(";")
+        } else {
             record
                 .attributes
                 .iter()
                 .map(|(a, b)| format!("{}{}{}", a, self.delimiter, b))
                 .join(&self.terminator)
-        } else {
-            "".to_owned()
         };
 
         self.inner.serialize((
@@ -255,6 +590,21 @@ impl<W: io::Write> Writer<W> {
             attributes,
         ))
     }
+
+    /// Write a directive line, e.g. `##gff-version 3`.
+    pub fn write_directive(&mut self, directive: &Directive) -> csv::Result<()> {
+        self.inner.write_record(&[directive.to_string()])
+    }
+
+    /// Write the `##FASTA` directive and return a FASTA writer for the sequences
+    /// following the records.
+    pub fn into_fasta(mut self) -> io::Result<fasta::Writer<W>> {
+        self.write_directive(&Directive::Fasta)?;
+        self.inner
+            .into_inner()
+            .map(fasta::Writer::new)
+            .map_err(|e| e.into_error())
+    }
 }
 
 /// A GFF record
@@ -385,6 +735,461 @@ impl Record {
     }
 }
 
+/// An error that occurs while assembling GFF records into a feature hierarchy.
+#[derive(Error, Debug)]
+pub enum HierarchyError {
+    #[error("can't read GFF record")]
+    Read(#[from] Error),
+
+    #[error("feature {feature} references unknown feature {parent}")]
+    Orphan { feature: String, parent: String },
+
+    #[error("features form a cycle involving {0}")]
+    Cycle(String),
+
+    #[error("records of feature {0} disagree on sequence, type, strand or parent")]
+    Inconsistent(String),
+
+    #[error("feature {0} lacks a gene_id attribute")]
+    MissingGeneId(String),
+}
+
+/// A feature of a [`FeatureTree`](FeatureTree), assembled from one or more GFF records
+/// sharing an ID (e.g. the segments of a CDS).
+#[derive(Debug, Clone)]
+pub struct Feature {
+    id: Option<String>,
+    records: Vec<Record>,
+    parents: Vec<usize>,
+    children: Vec<usize>,
+    derives_from: Vec<usize>,
+    derivatives: Vec<usize>,
+}
+
+impl Feature {
+    fn new(id: Option<String>, record: Record) -> Self {
+        Feature {
+            id,
+            records: vec![record],
+            parents: Vec::new(),
+            children: Vec::new(),
+            derives_from: Vec::new(),
+            derivatives: Vec::new(),
+        }
+    }
+
+    /// ID of the feature, `None` for features without `ID` attribute.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The records of the feature, in the order they were read.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Sequence name of the feature.
+    pub fn seqname(&self) -> &str {
+        self.records[0].seqname()
+    }
+
+    /// Type of the feature.
+    pub fn feature_type(&self) -> &str {
+        self.records[0].feature_type()
+    }
+
+    /// Smallest start position of all records of the feature (1-based).
+    pub fn start(&self) -> u64 {
+        self.records.iter().map(|r| r.start).min().unwrap()
+    }
+
+    /// Largest end position of all records of the feature.
+    pub fn end(&self) -> u64 {
+        self.records.iter().map(|r| r.end).max().unwrap()
+    }
+
+    /// Strand of the feature.
+    pub fn strand(&self) -> Option<Strand> {
+        self.records[0].strand()
+    }
+
+    /// Indices of the parent features.
+    pub fn parents(&self) -> &[usize] {
+        &self.parents
+    }
+
+    /// Indices of the child features.
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    /// Indices of the features this feature derives from (`Derives_from` attribute).
+    pub fn derives_from(&self) -> &[usize] {
+        &self.derives_from
+    }
+
+    /// Indices of the features deriving from this feature.
+    pub fn derivatives(&self) -> &[usize] {
+        &self.derivatives
+    }
+
+    /// A human readable name of the feature for error messages.
+    fn describe(&self) -> String {
+        match &self.id {
+            Some(id) => id.clone(),
+            None => format!(
+                "{} at {}:{}-{}",
+                self.feature_type(),
+                self.seqname(),
+                self.start(),
+                self.end()
+            ),
+        }
+    }
+
+    fn is_consistent(&self, record: &Record) -> bool {
+        let first = &self.records[0];
+        first.seqname == record.seqname
+            && first.feature_type == record.feature_type
+            && first.strand == record.strand
+    }
+}
+
+/// Features of a GFF file linked into a hierarchy, e.g. gene → mRNA → exon/CDS.
+///
+/// Since a feature may have multiple parents, the hierarchy is a directed acyclic graph.
+/// Features are stored in input order and referenced by their index.
+///
+/// # Example
+///
+/// ```
+/// use bio::io::gff;
+///
+/// let gff3 = b"ctg1\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene1
+/// ctg1\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA1;Parent=gene1
+/// ctg1\t.\texon\t1050\t1500\t.\t+\t.\tParent=mRNA1
+/// ctg1\t.\texon\t3000\t9000\t.\t+\t.\tParent=mRNA1
+/// ";
+/// let mut reader = gff::Reader::new(&gff3[..], gff::GffType::GFF3);
+/// let tree = reader.feature_tree().unwrap();
+/// assert_eq!(tree.roots().len(), 1);
+/// let mrna = tree.get("mRNA1").unwrap();
+/// assert_eq!(tree.children(mrna).count(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FeatureTree {
+    features: Vec<Feature>,
+    ids: HashMap<String, usize>,
+    roots: Vec<usize>,
+}
+
+impl FeatureTree {
+    /// Assemble the given records into a feature tree.
+    ///
+    /// For `GffType::GTF2` and `GffType::GFF2`, features are grouped via their `gene_id`
+    /// and `transcript_id` attributes, and missing gene and transcript features are
+    /// inferred from their children. Genes and transcripts have separate ID namespaces; if
+    /// they share an ID, [`get`](FeatureTree::get) returns the gene. Otherwise, the GFF3
+    /// `ID`, `Parent` and `Derives_from` attributes are used, and records sharing an `ID`
+    /// are merged into one feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record can't be read, a parent is unknown, the features form a
+    /// cycle, records of a feature disagree, or a GTF record lacks a `gene_id`.
+    pub fn from_records<I>(records: I, gff_type: GffType) -> Result<Self, HierarchyError>
+    where
+        I: IntoIterator<Item = Result<Record>>,
+    {
+        let mut tree = FeatureTree::default();
+        match gff_type {
+            GffType::GTF2 | GffType::GFF2 => tree.group_gtf(records)?,
+            _ => tree.link_gff3(records)?,
+        }
+        tree.check_acyclic()?;
+        tree.roots = (0..tree.features.len())
+            .filter(|&i| tree.features[i].parents.is_empty())
+            .collect();
+        Ok(tree)
+    }
+
+    /// Number of features.
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Return true if there are no features.
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// All features, in input order.
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    /// Return the feature with the given index.
+    pub fn feature(&self, index: usize) -> &Feature {
+        &self.features[index]
+    }
+
+    /// Return the index of the feature with the given ID.
+    pub fn index(&self, id: &str) -> Option<usize> {
+        self.ids.get(id).copied()
+    }
+
+    /// Return the feature with the given ID.
+    pub fn get(&self, id: &str) -> Option<&Feature> {
+        self.index(id).map(|i| &self.features[i])
+    }
+
+    /// Indices of the features without parent.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Iterate over the children of the given feature.
+    pub fn children<'a>(&'a self, feature: &'a Feature) -> impl Iterator<Item = &'a Feature> {
+        feature.children.iter().map(move |&i| &self.features[i])
+    }
+
+    /// Iterate over the parents of the given feature.
+    pub fn parents<'a>(&'a self, feature: &'a Feature) -> impl Iterator<Item = &'a Feature> {
+        feature.parents.iter().map(move |&i| &self.features[i])
+    }
+
+    /// Indices of all descendants of the feature with the given index in depth-first
+    /// order. Features reachable via multiple paths are reported once.
+    pub fn descendants(&self, index: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.features.len()];
+        let mut descendants = Vec::new();
+        let mut stack: Vec<usize> = self.features[index]
+            .children
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !visited[i] {
+                visited[i] = true;
+                descendants.push(i);
+                stack.extend(self.features[i].children.iter().rev());
+            }
+        }
+        descendants
+    }
+
+    fn push(&mut self, id: Option<String>, record: Record) -> usize {
+        let index = self.features.len();
+        if let Some(id) = &id {
+            self.ids.entry(id.clone()).or_insert(index);
+        }
+        self.features.push(Feature::new(id, record));
+        index
+    }
+
+    fn link(&mut self, parent: usize, child: usize) {
+        if !self.features[child].parents.contains(&parent) {
+            self.features[child].parents.push(parent);
+            self.features[parent].children.push(child);
+        }
+    }
+
+    fn link_gff3<I>(&mut self, records: I) -> Result<(), HierarchyError>
+    where
+        I: IntoIterator<Item = Result<Record>>,
+    {
+        for record in records {
+            let record = record?;
+            match record.attributes.get("ID").cloned() {
+                Some(id) => match self.index(&id) {
+                    Some(i) => {
+                        if !self.features[i].is_consistent(&record) {
+                            return Err(HierarchyError::Inconsistent(id));
+                        }
+                        self.features[i].records.push(record);
+                    }
+                    None => {
+                        self.push(Some(id), record);
+                    }
+                },
+                None => {
+                    self.push(None, record);
+                }
+            }
+        }
+
+        for child in 0..self.features.len() {
+            for key in &["Parent", "Derives_from"] {
+                let targets: Vec<String> = self.features[child]
+                    .records
+                    .iter()
+                    .filter_map(|r| r.attributes.get_vec(*key))
+                    .flatten()
+                    .unique()
+                    .cloned()
+                    .collect();
+                for target in targets {
+                    let parent = self.index(&target).ok_or_else(|| HierarchyError::Orphan {
+                        feature: self.features[child].describe(),
+                        parent: target.clone(),
+                    })?;
+                    if *key == "Parent" {
+                        self.link(parent, child);
+                    } else if !self.features[child].derives_from.contains(&parent) {
+                        self.features[child].derives_from.push(parent);
+                        self.features[parent].derivatives.push(child);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn group_gtf<I>(&mut self, records: I) -> Result<(), HierarchyError>
+    where
+        I: IntoIterator<Item = Result<Record>>,
+    {
+        // gene and transcript features by type and ID
+        let mut groups = HashMap::new();
+        // gene and transcript features that have not been seen in the input yet
+        let mut inferred = HashSet::new();
+        for record in records {
+            let record = record?;
+            let gene_id = match record.attributes.get("gene_id") {
+                Some(gene_id) => gene_id.clone(),
+                None => {
+                    return Err(HierarchyError::MissingGeneId(
+                        Feature::new(None, record).describe(),
+                    ))
+                }
+            };
+            let transcript_id = record.attributes.get("transcript_id").cloned();
+
+            let gene =
+                self.gtf_group(&gene_id, "gene", &record, None, &mut groups, &mut inferred)?;
+            if record.feature_type == "gene" {
+                continue;
+            }
+            let parent = match transcript_id {
+                Some(transcript_id) => {
+                    let transcript = self.gtf_group(
+                        &transcript_id,
+                        "transcript",
+                        &record,
+                        Some(gene),
+                        &mut groups,
+                        &mut inferred,
+                    )?;
+                    if record.feature_type == "transcript" {
+                        continue;
+                    }
+                    transcript
+                }
+                None => gene,
+            };
+            let child = self.push(None, record);
+            self.link(parent, child);
+        }
+        Ok(())
+    }
+
+    /// Return the index of the gene or transcript with the given ID, creating it if needed.
+    /// Features that are only implied by their children span all of them.
+    fn gtf_group(
+        &mut self,
+        id: &str,
+        feature_type: &'static str,
+        record: &Record,
+        gene: Option<usize>,
+        groups: &mut HashMap<(&'static str, String), usize>,
+        inferred: &mut HashSet<usize>,
+    ) -> Result<usize, HierarchyError> {
+        let is_group_record = record.feature_type == feature_type;
+        let index = match groups.get(&(feature_type, id.to_owned())).copied() {
+            Some(i) => {
+                let first = &self.features[i].records[0];
+                if first.seqname != record.seqname || first.strand != record.strand {
+                    return Err(HierarchyError::Inconsistent(id.to_owned()));
+                }
+                if is_group_record && inferred.remove(&i) {
+                    self.features[i].records = vec![record.clone()];
+                } else if is_group_record {
+                    self.features[i].records.push(record.clone());
+                } else if inferred.contains(&i) {
+                    let inferred_record = &mut self.features[i].records[0];
+                    inferred_record.start = inferred_record.start.min(record.start);
+                    inferred_record.end = inferred_record.end.max(record.end);
+                }
+                i
+            }
+            None => {
+                let group_record = if is_group_record {
+                    record.clone()
+                } else {
+                    let mut group_record = record.clone();
+                    group_record.feature_type = feature_type.to_owned();
+                    group_record.score = ".".to_owned();
+                    group_record.frame = ".".to_owned();
+                    group_record.attributes.retain(|key, _| {
+                        key == "gene_id" || (key == "transcript_id" && gene.is_some())
+                    });
+                    group_record
+                };
+                let index = self.push(Some(id.to_owned()), group_record);
+                groups.insert((feature_type, id.to_owned()), index);
+                if !is_group_record {
+                    inferred.insert(index);
+                }
+                index
+            }
+        };
+        if let Some(gene) = gene {
+            match self.features[index].parents.first() {
+                Some(&parent) if parent != gene => {
+                    return Err(HierarchyError::Inconsistent(id.to_owned()))
+                }
+                _ => self.link(gene, index),
+            }
+        }
+        Ok(index)
+    }
+
+    fn check_acyclic(&self) -> Result<(), HierarchyError> {
+        // 0: unvisited, 1: on the current path, 2: done
+        let mut state = vec![0u8; self.features.len()];
+        for start in 0..self.features.len() {
+            if state[start] != 0 {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            state[start] = 1;
+            while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+                match self.features[node].children.get(*next) {
+                    Some(&child) => {
+                        *next += 1;
+                        match state[child] {
+                            0 => {
+                                state[child] = 1;
+                                stack.push((child, 0));
+                            }
+                            1 => {
+                                return Err(HierarchyError::Cycle(self.features[child].describe()))
+                            }
+                            _ => (),
+                        }
+                    }
+                    None => {
+                        state[node] = 2;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -635,4 +1440,334 @@ P0A7B8\tUniProtKB\tChain\t2\t176\t50\t+\t.\tID PRO_0000148105
             Err("String 'xtf9' is not a valid GFFType (GFF/GTF format version).".to_string())
         )
     }
+
+    const GFF3_GENE: &[u8] = b"ctg1\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene1
+ctg1\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg1\t.\tmRNA\t1300\t9000\t.\t+\t.\tID=mRNA2;Parent=gene1
+ctg1\t.\texon\t1300\t1500\t.\t+\t.\tID=exon1;Parent=mRNA1,mRNA2
+ctg1\t.\texon\t3000\t9000\t.\t+\t.\tParent=mRNA1
+ctg1\t.\tCDS\t1301\t1500\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tCDS\t3000\t3902\t.\t+\t1\tID=cds1;Parent=mRNA1
+ctg1\t.\tpolypeptide\t1301\t3902\t.\t+\t.\tID=pp1;Derives_from=mRNA1
+";
+
+    #[test]
+    fn test_feature_tree_gff3() {
+        let mut reader = Reader::new(GFF3_GENE, GffType::GFF3);
+        let tree = reader.feature_tree().unwrap();
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.roots(), &[0, 6]);
+
+        let gene = tree.get("gene1").unwrap();
+        let mrnas: Vec<_> = tree.children(gene).map(|f| f.id().unwrap()).collect();
+        assert_eq!(mrnas, vec!["mRNA1", "mRNA2"]);
+
+        let exon = tree.get("exon1").unwrap();
+        let parents: Vec<_> = tree.parents(exon).map(|f| f.id().unwrap()).collect();
+        assert_eq!(parents, vec!["mRNA1", "mRNA2"]);
+
+        let cds = tree.get("cds1").unwrap();
+        assert_eq!(cds.records().len(), 2);
+        assert_eq!((cds.start(), cds.end()), (1301, 3902));
+        assert_eq!(cds.strand(), Some(Strand::Forward));
+
+        let mrna1 = tree.index("mRNA1").unwrap();
+        assert_eq!(tree.feature(mrna1).children().len(), 3);
+        assert!(tree
+            .children(tree.feature(mrna1))
+            .any(|f| f.id().is_none() && f.feature_type() == "exon"));
+        let pp = tree.get("pp1").unwrap();
+        assert_eq!(pp.derives_from(), &[mrna1]);
+        assert_eq!(
+            tree.feature(mrna1).derivatives(),
+            &[tree.index("pp1").unwrap()]
+        );
+
+        // the shared exon is reported once
+        assert_eq!(tree.descendants(0), vec![1, 3, 4, 5, 2]);
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let read = |gff: &[u8]| {
+            Reader::new(gff, GffType::GFF3)
+                .records()
+                .collect::<Result<Vec<_>>>()
+        };
+        let reversed = b"##gff-version 3\n# comment\nctg1\t.\tgene\t10\t1\t.\t+\t.\tID=a\n";
+        let err = read(reversed).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidCoordinates {
+                line: 3,
+                offset: 26,
+                start: 10,
+                end: 1
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 3 (byte 26): start 10 is greater than end 1"
+        );
+        assert!(matches!(
+            read(b"ctg1\t.\tgene\t1\t10\t.\tx\t.\tID=a\n"),
+            Err(Error::InvalidStrand {
+                line: 1,
+                offset: 0,
+                strand
+            }) if strand == "x"
+        ));
+        assert!(matches!(
+            read(b"ctg1\t.\tgene\t1\t10\t.\t+\t.\tID=a\n\nctg1\t.\tgene\t1\t10\n"),
+            Err(Error::TruncatedRecord {
+                line: 3,
+                offset: 29,
+                fields: 5
+            })
+        ));
+        assert!(matches!(
+            read(b"ctg1\t.\tgene\tone\t10\t.\t+\t.\tID=a\n"),
+            Err(Error::Format { line: 1, .. })
+        ));
+        assert!(matches!(
+            Reader::new(&reversed[..], GffType::GFF3).feature_tree(),
+            Err(HierarchyError::Read(Error::InvalidCoordinates {
+                line: 3,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_feature_tree_errors() {
+        let orphan = b"ctg1\t.\texon\t1\t10\t.\t+\t.\tParent=mRNA1\n";
+        match Reader::new(&orphan[..], GffType::GFF3).feature_tree() {
+            Err(HierarchyError::Orphan { feature, parent }) => {
+                assert_eq!(feature, "exon at ctg1:1-10");
+                assert_eq!(parent, "mRNA1");
+            }
+            r => panic!("expected orphan error, got {:?}", r),
+        }
+
+        let cycle = b"ctg1\t.\tgene\t1\t10\t.\t+\t.\tID=a;Parent=b
+ctg1\t.\tmRNA\t1\t10\t.\t+\t.\tID=b;Parent=a
+";
+        assert!(matches!(
+            Reader::new(&cycle[..], GffType::GFF3).feature_tree(),
+            Err(HierarchyError::Cycle(_))
+        ));
+
+        let inconsistent = b"ctg1\t.\tCDS\t1\t10\t.\t+\t0\tID=a
+ctg2\t.\tCDS\t20\t30\t.\t+\t0\tID=a
+";
+        assert!(matches!(
+            Reader::new(&inconsistent[..], GffType::GFF3).feature_tree(),
+            Err(HierarchyError::Inconsistent(id)) if id == "a"
+        ));
+
+        let no_gene = b"chr1\t.\texon\t1\t10\t.\t+\t.\ttranscript_id \"t1\";\n";
+        assert!(matches!(
+            Reader::new(&no_gene[..], GffType::GTF2).feature_tree(),
+            Err(HierarchyError::MissingGeneId(_))
+        ));
+    }
+
+    #[test]
+    fn test_feature_tree_gtf() {
+        let mut reader = Reader::new(GTF_FILE_2, GffType::GTF2);
+        let tree = reader.feature_tree().unwrap();
+        assert_eq!(tree.len(), 2);
+        let gene = tree.get("ENSG00000223972.5").unwrap();
+        assert_eq!(gene.feature_type(), "gene");
+        let transcript = tree.get("ENST00000456328.2").unwrap();
+        assert_eq!(transcript.parents(), &[0]);
+        assert_eq!(tree.roots(), &[0]);
+    }
+
+    #[test]
+    fn test_feature_tree_gtf_inferred() {
+        let gtf = b"chr1\t.\texon\t100\t200\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t1\";
+chr1\t.\texon\t300\t400\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t1\";
+chr1\t.\texon\t150\t500\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t2\";
+chr1\t.\tCDS\t150\t200\t.\t-\t0\tgene_id \"g1\"; transcript_id \"t1\";
+";
+        let tree = Reader::new(&gtf[..], GffType::GTF2).feature_tree().unwrap();
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.roots(), &[0]);
+        let gene = tree.get("g1").unwrap();
+        assert_eq!(
+            (gene.feature_type(), gene.start(), gene.end()),
+            ("gene", 100, 500)
+        );
+        assert_eq!(gene.strand(), Some(Strand::Reverse));
+        assert!(gene.records()[0]
+            .attributes()
+            .get("transcript_id")
+            .is_none());
+        let t1 = tree.get("t1").unwrap();
+        assert_eq!(
+            (t1.feature_type(), t1.start(), t1.end()),
+            ("transcript", 100, 400)
+        );
+        assert_eq!(tree.children(t1).count(), 3);
+        assert_eq!(tree.children(gene).count(), 2);
+
+        // genes and transcripts may share an ID
+        let gtf = b"chr1\t.\texon\t100\t200\t.\t+\t.\tgene_id \"x\"; transcript_id \"x\";\n";
+        let tree = Reader::new(&gtf[..], GffType::GTF2).feature_tree().unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.roots(), &[0]);
+        assert_eq!(tree.get("x").unwrap().feature_type(), "gene");
+        assert_eq!(tree.feature(1).feature_type(), "transcript");
+        assert_eq!(tree.feature(1).parents(), &[0]);
+        assert_eq!(tree.feature(2).parents(), &[1]);
+
+        // transcripts can't switch genes
+        let gtf = b"chr1\t.\texon\t100\t200\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t1\";
+chr1\t.\texon\t300\t400\t.\t-\t.\tgene_id \"g2\"; transcript_id \"t1\";
+";
+        assert!(matches!(
+            Reader::new(&gtf[..], GffType::GTF2).feature_tree(),
+            Err(HierarchyError::Inconsistent(_))
+        ));
+    }
+
+    const GFF3_WITH_DIRECTIVES: &[u8] = b"##gff-version 3.1.26
+##sequence-region ctg123 1 1497228
+# a comment
+##species https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?id=9606
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene1;Note=A%3BB%2CC%3DD%26E%25,second;Alias=tab%09here%ZZ
+###
+ctg123\t.\tgene\t10000\t11000\t.\t+\t.\tID=gene2
+##FASTA
+>ctg123
+ACGT
+ACGT
+>ctg124
+TTTT
+";
+
+    #[test]
+    fn test_reader_format_error() {
+        let gff = b"##gff-version 3\n# comment\n\nctg1\t.\tgene\tone\t10\t.\t+\t.\tID=a\n";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        assert!(matches!(
+            reader.records().next().unwrap(),
+            Err(Error::Format { line: 4, .. })
+        ));
+        let mut reader = Reader::new(&b"ctg1\t.\tgene\t1\t10\n"[..], GffType::GFF3);
+        assert!(matches!(
+            reader.records().next().unwrap(),
+            Err(Error::TruncatedRecord { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_directives() {
+        let mut reader = Reader::new(GFF3_WITH_DIRECTIVES, GffType::GFF3);
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            reader.directives(),
+            &[
+                Directive::GffVersion("3.1.26".to_owned()),
+                Directive::SequenceRegion {
+                    seqid: "ctg123".to_owned(),
+                    start: 1,
+                    end: 1497228
+                },
+                Directive::Species(
+                    "https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?id=9606".to_owned()
+                ),
+                Directive::ResolutionBarrier,
+                Directive::Fasta,
+            ]
+        );
+        for directive in reader.directives() {
+            assert_eq!(
+                &directive.to_string().parse::<Directive>().unwrap(),
+                directive
+            );
+        }
+        assert_eq!(
+            "##sequence-region ctg1 x 10".parse::<Directive>().unwrap(),
+            Directive::Other {
+                name: "sequence-region".to_owned(),
+                value: "ctg1 x 10".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn test_embedded_fasta() {
+        let mut reader = Reader::new(GFF3_WITH_DIRECTIVES, GffType::GFF3);
+        assert_eq!(reader.records().next().unwrap().unwrap().start(), &1000);
+        let fasta = reader.into_fasta().unwrap().unwrap();
+        let seqs: Vec<_> = fasta.records().map(|r| r.unwrap()).collect();
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[0].id(), "ctg123");
+        assert_eq!(seqs[0].seq(), b"ACGTACGT");
+
+        // FASTA sections may start without directive
+        let gff = b"ctg1\t.\tgene\t1\t4\t.\t+\t.\tID=gene1\n>ctg1\nACGT\n";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        assert_eq!(reader.records().count(), 1);
+        let fasta = reader.into_fasta().unwrap().unwrap();
+        assert_eq!(fasta.records().next().unwrap().unwrap().seq(), b"ACGT");
+
+        assert!(Reader::new(GFF_FILE, GffType::GFF3)
+            .into_fasta()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_attribute_escaping() {
+        let mut reader = Reader::new(GFF3_WITH_DIRECTIVES, GffType::GFF3);
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(
+            record.attributes().get_vec("Note").unwrap(),
+            &["A;B,C=D&E%", "second"]
+        );
+        // invalid escape sequences are kept
+        assert_eq!(record.attributes().get("Alias").unwrap(), "tab\there%ZZ");
+
+        let mut attributes = MultiMap::new();
+        attributes.insert("Note".to_owned(), "A;B,C=D&E%\tF".to_owned());
+        attributes.insert("Note".to_owned(), "second".to_owned());
+        let mut record = Record::new();
+        *record.attributes_mut() = attributes;
+        let mut writer = Writer::new(vec![], GffType::GFF3);
+        writer.write(&record).unwrap();
+        let output = writer.inner.into_inner().unwrap();
+        assert!(output.ends_with(b"\tNote=A%3BB%2CC%3DD%26E%25%09F,second\n"));
+
+        let mut reader = Reader::new(&output[..], GffType::GFF3);
+        let parsed = reader.records().next().unwrap().unwrap();
+        assert_eq!(parsed.attributes(), record.attributes());
+    }
+
+    #[test]
+    fn test_write_directives_and_fasta() {
+        let mut output = Vec::new();
+        let mut writer = Writer::new(&mut output, GffType::GFF3);
+        writer
+            .write_directive(&Directive::GffVersion("3".to_owned()))
+            .unwrap();
+        writer
+            .write_directive(&Directive::SequenceRegion {
+                seqid: "ctg1".to_owned(),
+                start: 1,
+                end: 4,
+            })
+            .unwrap();
+        let mut fasta = writer.into_fasta().unwrap();
+        fasta.write("ctg1", None, b"ACGT").unwrap();
+        fasta.flush().unwrap();
+        drop(fasta);
+        assert_eq!(
+            output,
+            b"##gff-version 3\n##sequence-region ctg1 1 4\n##FASTA\n>ctg1\nACGT\n"
+        );
+    }
 }
@@ -1,8 +1,24 @@
 //! Readers and writers for common bioinformatics file formats.
 
 pub mod bed;
+pub mod bedgraph;
+pub mod bgzf;
+pub mod blast;
+pub mod chain;
+pub mod compression;
 pub mod fasta;
 pub mod fastq;
+pub mod fastx;
+pub mod genbank;
+pub mod gfa;
 pub mod gff;
+pub mod msa;
 #[cfg(feature = "phylogeny")]
 pub mod newick;
+pub mod paf;
+pub mod parallel;
+pub mod sam;
+pub mod transcript;
+pub mod twobit;
+pub mod vcf;
+pub mod wig;
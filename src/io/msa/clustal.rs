@@ -0,0 +1,317 @@
+//! Reading and writing of alignments in the Clustal format, as produced by Clustal W/X,
+//! Clustal Omega, MUSCLE and T-Coffee.
+//!
+//! Conservation lines are skipped while reading. The writer computes them from the
+//! columns: `*` marks fully conserved columns, `:` and `.` columns whose residues all
+//! belong to one of the strong or weak amino acid groups of Clustal.
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::msa::{clustal, MsaRead};
+//!
+//! let input = b"CLUSTAL O(1.2.4) multiple sequence alignment
+//!
+//! seq1      MKV-LA 5
+//! seq2      MRVTLA 6
+//!           *:* **
+//! ";
+//! let mut reader = clustal::Reader::new(&input[..]);
+//! let msa = reader.alignments().next().unwrap().unwrap();
+//! assert_eq!(msa.names(), ["seq1", "seq2"]);
+//! assert_eq!(msa.column(3), b"-T");
+//! ```
+
+use anyhow::Context;
+use std::collections::HashMap;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use super::{invalid_name, is_gap, Alignments, Error, Lines, Msa, MsaRead, Result};
+use crate::io::compression::Decoder;
+
+/// The first words of the header lines written by the common Clustal-producing tools.
+const HEADERS: [&str; 4] = ["CLUSTAL", "MUSCLE", "PROBCONS", "T-COFFEE"];
+
+/// Amino acid groups used for `:` in conservation lines.
+const STRONG_GROUPS: [&[u8]; 9] = [
+    b"STA", b"NEQK", b"NHQK", b"NDEQ", b"QHRK", b"MILV", b"MILF", b"HY", b"FYW",
+];
+
+/// Amino acid groups used for `.` in conservation lines.
+const WEAK_GROUPS: [&[u8]; 11] = [
+    b"CSA", b"ATV", b"SAG", b"STNK", b"STPA", b"SGND", b"SNDEQK", b"NDEQHK", b"NEQHRK", b"FVLIM",
+    b"HFY",
+];
+
+/// A Clustal reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    lines: Lines<B>,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read Clustal alignment from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            lines: Lines::new(bufreader),
+        }
+    }
+
+    /// Return an iterator over the alignments of this file, i.e. the single alignment it
+    /// contains.
+    pub fn alignments(self) -> Alignments<Self> {
+        Alignments { reader: self }
+    }
+}
+
+impl<B: io::BufRead> MsaRead for Reader<B> {
+    /// Read the alignment. As a Clustal file contains a single alignment, reading again
+    /// yields an empty alignment.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the header is missing, a sequence line is
+    /// malformed or the rows have different lengths.
+    fn read(&mut self, msa: &mut Msa) -> Result<()> {
+        msa.clear();
+        match self.lines.next_nonblank()? {
+            None => return Ok(()),
+            Some(line) if HEADERS.iter().any(|h| line.starts_with(h)) => (),
+            Some(_) => return Err(self.lines.error("missing Clustal header")),
+        }
+
+        let mut names = Vec::new();
+        let mut rows: HashMap<String, Vec<u8>> = HashMap::new();
+        while let Some(line) = self.lines.next_line()? {
+            // conservation lines are indented
+            if line.trim().is_empty() || line.starts_with(char::is_whitespace) {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 2
+                || fields.len() > 3
+                || (fields.len() == 3 && fields[2].parse::<usize>().is_err())
+            {
+                let message = format!("invalid sequence line '{}'", line);
+                return Err(self.lines.error(message));
+            }
+            match rows.get_mut(fields[0]) {
+                Some(row) => row.extend_from_slice(fields[1].as_bytes()),
+                None => {
+                    names.push(fields[0].to_owned());
+                    rows.insert(fields[0].to_owned(), fields[1].as_bytes().to_vec());
+                }
+            }
+        }
+
+        for name in names {
+            msa.push(&name, &rows[&name])?;
+        }
+        Ok(())
+    }
+}
+
+/// The conservation symbol of an alignment column.
+fn conservation(column: &[u8]) -> u8 {
+    if column.is_empty() || column.iter().any(|&c| is_gap(c)) {
+        return b' ';
+    }
+    let column: Vec<u8> = column.iter().map(u8::to_ascii_uppercase).collect();
+    let in_group = |group: &&[u8]| column.iter().all(|c| group.contains(c));
+    if column.iter().all(|&c| c == column[0]) {
+        b'*'
+    } else if STRONG_GROUPS.iter().any(in_group) {
+        b':'
+    } else if WEAK_GROUPS.iter().any(in_group) {
+        b'.'
+    } else {
+        b' '
+    }
+}
+
+/// A Clustal writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+    line_width: usize,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`, with 60 columns per block.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+            line_width: 60,
+        }
+    }
+
+    /// Set the number of columns per block.
+    ///
+    /// # Panics
+    ///
+    /// If `line_width` is zero.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        assert!(line_width > 0, "line width must be positive");
+        self.line_width = line_width;
+        self
+    }
+
+    /// Write an alignment.
+    ///
+    /// # Errors
+    ///
+    /// If a sequence name contains whitespace.
+    pub fn write(&mut self, msa: &Msa) -> io::Result<()> {
+        if let Some(name) = msa
+            .names()
+            .iter()
+            .find(|n| n.is_empty() || n.contains(char::is_whitespace))
+        {
+            return Err(invalid_name(
+                name,
+                "names must be non-empty and without whitespace",
+            ));
+        }
+
+        writeln!(self.writer, "CLUSTAL W multiple sequence alignment\n")?;
+        let label_width = msa.names().iter().map(String::len).max().unwrap_or(0) + 4;
+        let conservation: Vec<u8> = msa.columns().map(|c| conservation(&c)).collect();
+        for start in (0..msa.width()).step_by(self.line_width) {
+            let end = (start + self.line_width).min(msa.width());
+            for (name, row) in msa.rows() {
+                write!(self.writer, "{:width$}", name, width = label_width)?;
+                self.writer.write_all(&row[start..end])?;
+                writeln!(self.writer)?;
+            }
+            write!(self.writer, "{:width$}", "", width = label_width)?;
+            self.writer.write_all(&conservation[start..end])?;
+            writeln!(self.writer, "\n")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLUSTAL: &[u8] = b"CLUSTAL W (1.83) multiple sequence alignment
+
+
+human      MKVLAAGIVA-LLLA 14
+mouse      MKILSAGLVA-VLLA 14
+fly        MRVLT-GIVSQTLLS 14
+           *::*: *:*.  **.
+
+human      QPW 17
+mouse      QPW 17
+fly        EPW 17
+            **
+";
+
+    #[test]
+    fn test_read() {
+        let mut reader = Reader::new(CLUSTAL);
+        let mut msa = Msa::new();
+        reader.read(&mut msa).unwrap();
+        assert_eq!(msa.names(), ["human", "mouse", "fly"]);
+        assert_eq!(msa.width(), 18);
+        assert_eq!(msa.row(2), b"MRVLT-GIVSQTLLSEPW");
+        reader.read(&mut msa).unwrap();
+        assert!(msa.is_empty());
+
+        let mut reader = Reader::new(&b"CLUSTAL\n\nseq1 AC GT\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 3, .. })
+        ));
+        let mut reader = Reader::new(&b">seq1\nACGT\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_write() {
+        let msa = Reader::new(CLUSTAL).alignments().next().unwrap().unwrap();
+        let mut writer = Writer::new(Vec::new()).line_width(10);
+        writer.write(&msa).unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "CLUSTAL W multiple sequence alignment
+
+human    MKVLAAGIVA
+mouse    MKILSAGLVA
+fly      MRVLT-GIVS
+         *::*: *:*:
+
+human    -LLLAQPW
+mouse    -VLLAQPW
+fly      QTLLSEPW
+           **::**
+
+"
+        );
+        let reread = Reader::new(&output[..])
+            .alignments()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reread, msa);
+    }
+
+    #[test]
+    fn test_conservation() {
+        assert_eq!(conservation(b"AAa"), b'*');
+        assert_eq!(conservation(b"STA"), b':');
+        assert_eq!(conservation(b"CS"), b'.');
+        assert_eq!(conservation(b"AW"), b' ');
+        assert_eq!(conservation(b"A-"), b' ');
+    }
+}
@@ -0,0 +1,216 @@
+//! Reading and writing of alignments as aligned FASTA, i.e. FASTA records of equal length
+//! including gap characters.
+//!
+//! Record descriptions are kept as `DE` sequence annotations (`#=GS <name> DE <text>` in
+//! Stockholm) and written back as descriptions.
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::msa::{fasta, MsaRead};
+//!
+//! let input = b">seq1 first
+//! AC-GT
+//! >seq2
+//! ACTGT
+//! ";
+//! let mut reader = fasta::Reader::new(&input[..]);
+//! let msa = reader.alignments().next().unwrap().unwrap();
+//! assert_eq!(msa.width(), 5);
+//! assert_eq!(msa.sequence_annotations[0].2, "first");
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{Alignments, Error, Msa, MsaRead, Result};
+use crate::io::compression::Decoder;
+use crate::io::fasta::{self, FastaRead};
+
+/// An aligned FASTA reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: fasta::Reader<B>,
+    done: bool,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read aligned FASTA from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: fasta::Reader::from_bufread(bufreader),
+            done: false,
+        }
+    }
+
+    /// Return an iterator over the alignments of this file, i.e. the single alignment it
+    /// contains.
+    pub fn alignments(self) -> Alignments<Self> {
+        Alignments { reader: self }
+    }
+}
+
+impl<B: io::BufRead> MsaRead for Reader<B> {
+    /// Read all records as one alignment. Reading again yields an empty alignment.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the input is not valid FASTA or the records
+    /// have different lengths.
+    fn read(&mut self, msa: &mut Msa) -> Result<()> {
+        msa.clear();
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+        let mut record = fasta::Record::new();
+        loop {
+            self.reader.read(&mut record)?;
+            if record.is_empty() {
+                return Ok(());
+            }
+            msa.push(record.id(), record.seq())?;
+            if let Some(desc) = record.desc() {
+                msa.sequence_annotations.push((
+                    record.id().to_owned(),
+                    "DE".to_owned(),
+                    desc.to_owned(),
+                ));
+            }
+        }
+    }
+}
+
+/// An aligned FASTA writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: fasta::Writer<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`, with each row on a single line.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: fasta::Writer::new(writer),
+        }
+    }
+
+    /// Wrap rows into lines of at most the given number of columns.
+    ///
+    /// # Panics
+    ///
+    /// If `line_width` is zero.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.writer = self.writer.line_width(line_width);
+        self
+    }
+
+    /// Write an alignment, with `DE` sequence annotations as descriptions.
+    pub fn write(&mut self, msa: &Msa) -> io::Result<()> {
+        for (name, row) in msa.rows() {
+            self.writer
+                .write(name, msa.sequence_annotation(name, "DE"), row)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::msa::{clustal, stockholm};
+
+    #[test]
+    fn test_roundtrip() {
+        let input = b">seq1 first sequence\nAC-GT\nAC\n>seq2\nACTGTA.\n";
+        let msa = Reader::new(&input[..])
+            .alignments()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(msa.row(0), b"AC-GTAC");
+
+        let mut output = Vec::new();
+        {
+            let mut writer = Writer::new(&mut output).line_width(4);
+            writer.write(&msa).unwrap();
+        }
+        assert_eq!(
+            output,
+            b">seq1 first sequence\nAC-G\nTAC\n>seq2\nACTG\nTA.\n".to_vec()
+        );
+
+        // description is carried over to Stockholm
+        let mut writer = stockholm::Writer::new(Vec::new());
+        writer.write(&msa).unwrap();
+        let stockholm = writer.into_inner().unwrap();
+        let reread = stockholm::Reader::new(&stockholm[..])
+            .alignments()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reread, msa);
+
+        let mut writer = clustal::Writer::new(Vec::new());
+        writer.write(&msa).unwrap();
+        let clustal = writer.into_inner().unwrap();
+        let reread = clustal::Reader::new(&clustal[..])
+            .alignments()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reread.row(1), msa.row(1));
+    }
+
+    #[test]
+    fn test_ragged() {
+        let mut reader = Reader::new(&b">seq1\nACGT\n>seq2\nACG\n"[..]);
+        let mut msa = Msa::new();
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::RowLength {
+                expected: 4,
+                found: 3,
+                ..
+            })
+        ));
+    }
+}
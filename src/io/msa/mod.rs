@@ -0,0 +1,390 @@
+//! Reading and writing of multiple sequence alignments.
+//!
+//! All formats share the in-memory [`Msa`](struct.Msa.html) type, holding named rows of equal
+//! length. Readers for the [Stockholm](stockholm/index.html), [Clustal](clustal/index.html),
+//! [PHYLIP](phylip/index.html) and [aligned FASTA](fasta/index.html) formats implement the
+//! [`MsaRead`](trait.MsaRead.html) trait, which fills an `Msa` with the next alignment of the
+//! input.
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::msa::{clustal, stockholm, MsaRead};
+//!
+//! let input = b"# STOCKHOLM 1.0
+//! #=GF ID example
+//! seq1 AC-GT
+//! seq2 ACTGT
+//! #=GC SS_cons ..<>.
+//! //
+//! ";
+//! let mut reader = stockholm::Reader::new(&input[..]);
+//! let mut msa = bio::io::msa::Msa::new();
+//! reader.read(&mut msa).unwrap();
+//! assert_eq!(msa.len(), 2);
+//! assert_eq!(msa.column(2), b"-T");
+//! assert_eq!(msa.ungapped(0), b"ACGT");
+//!
+//! let mut writer = clustal::Writer::new(Vec::new());
+//! writer.write(&msa).unwrap();
+//! let output = writer.into_inner().unwrap();
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "CLUSTAL W multiple sequence alignment\n\nseq1    AC-GT\nseq2    ACTGT\n        ** **\n\n"
+//! );
+//! ```
+
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::utils::TextSlice;
+
+pub mod clustal;
+pub mod fasta;
+pub mod phylip;
+pub mod stockholm;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error(transparent)]
+    Fasta(#[from] crate::io::fasta::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("row '{name}' has {found} columns, expected {expected}")]
+    RowLength {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Return whether the given character denotes a gap, i.e. is one of `-`, `.` or `~`.
+pub fn is_gap(c: u8) -> bool {
+    matches!(c, b'-' | b'.' | b'~')
+}
+
+/// A multiple sequence alignment: named rows of equal length, with optional
+/// Stockholm-style markup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Msa {
+    names: Vec<String>,
+    rows: Vec<Vec<u8>>,
+    /// Annotations of the whole alignment (`#=GF`), as `(feature, text)`.
+    pub file_annotations: Vec<(String, String)>,
+    /// Annotations of single sequences (`#=GS`), as `(name, feature, text)`.
+    pub sequence_annotations: Vec<(String, String, String)>,
+    /// Annotations of the columns (`#=GC`), as `(feature, markup)` with one character
+    /// per column.
+    pub column_annotations: Vec<(String, Vec<u8>)>,
+    /// Annotations of the residues of single sequences (`#=GR`), as
+    /// `(name, feature, markup)` with one character per column.
+    pub residue_annotations: Vec<(String, String, Vec<u8>)>,
+}
+
+impl Msa {
+    /// Create an empty alignment.
+    pub fn new() -> Self {
+        Msa::default()
+    }
+
+    /// Append a row.
+    ///
+    /// # Errors
+    ///
+    /// If the row has a different length than the rows already present.
+    pub fn push(&mut self, name: &str, row: TextSlice<'_>) -> Result<()> {
+        if !self.rows.is_empty() && row.len() != self.width() {
+            return Err(Error::RowLength {
+                name: name.to_owned(),
+                expected: self.width(),
+                found: row.len(),
+            });
+        }
+        self.names.push(name.to_owned());
+        self.rows.push(row.to_vec());
+        Ok(())
+    }
+
+    /// Remove all rows and annotations.
+    pub fn clear(&mut self) {
+        *self = Msa::default();
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Return whether the alignment has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// The row names, in order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The name of the `i`-th row.
+    pub fn name(&self, i: usize) -> &str {
+        &self.names[i]
+    }
+
+    /// The `i`-th row, including gaps.
+    pub fn row(&self, i: usize) -> TextSlice<'_> {
+        &self.rows[i]
+    }
+
+    /// The index of the first row with the given name.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// The first row with the given name.
+    pub fn get(&self, name: &str) -> Option<TextSlice<'_>> {
+        self.index(name).map(|i| self.row(i))
+    }
+
+    /// Iterate over the rows and their names.
+    pub fn rows(&self) -> impl Iterator<Item = (&str, TextSlice<'_>)> + '_ {
+        self.names
+            .iter()
+            .zip(&self.rows)
+            .map(|(name, row)| (name.as_str(), row.as_slice()))
+    }
+
+    /// The `j`-th column, with one character per row.
+    pub fn column(&self, j: usize) -> Vec<u8> {
+        self.rows.iter().map(|row| row[j]).collect()
+    }
+
+    /// Iterate over the columns.
+    pub fn columns(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.width()).map(move |j| self.column(j))
+    }
+
+    /// The number of gaps in the `j`-th column.
+    pub fn gap_count(&self, j: usize) -> usize {
+        self.rows.iter().filter(|row| is_gap(row[j])).count()
+    }
+
+    /// Return whether the `j`-th column consists of gaps only.
+    pub fn is_gap_column(&self, j: usize) -> bool {
+        self.gap_count(j) == self.len()
+    }
+
+    /// The `i`-th row with gaps removed.
+    pub fn ungapped(&self, i: usize) -> Vec<u8> {
+        self.rows[i]
+            .iter()
+            .copied()
+            .filter(|&c| !is_gap(c))
+            .collect()
+    }
+
+    /// The 0-based position, within the ungapped `i`-th row, of the residue in column `j`,
+    /// or `None` if the row has a gap there.
+    pub fn residue_index(&self, i: usize, j: usize) -> Option<usize> {
+        let row = &self.rows[i];
+        if is_gap(row[j]) {
+            None
+        } else {
+            Some(row[..j].iter().filter(|&&c| !is_gap(c)).count())
+        }
+    }
+
+    /// Remove the columns consisting of gaps only, along with their column and residue
+    /// markup.
+    pub fn remove_gap_columns(&mut self) {
+        let keep: Vec<bool> = (0..self.width()).map(|j| !self.is_gap_column(j)).collect();
+        let retain = |seq: &mut Vec<u8>| {
+            let mut j = 0;
+            seq.retain(|_| {
+                j += 1;
+                keep.get(j - 1).copied().unwrap_or(true)
+            });
+        };
+        self.rows.iter_mut().for_each(retain);
+        self.column_annotations
+            .iter_mut()
+            .for_each(|(_, markup)| retain(markup));
+        self.residue_annotations
+            .iter_mut()
+            .for_each(|(_, _, markup)| retain(markup));
+    }
+
+    /// The text of the first `#=GS` annotation of the given row and feature.
+    pub(crate) fn sequence_annotation(&self, name: &str, feature: &str) -> Option<&str> {
+        self.sequence_annotations
+            .iter()
+            .find(|(n, f, _)| n == name && f == feature)
+            .map(|(_, _, text)| text.as_str())
+    }
+}
+
+/// A trait for readers of multiple sequence alignments.
+pub trait MsaRead {
+    /// Read the next alignment into the given `Msa`, replacing its content.
+    /// An empty alignment indicates that no more alignments can be read.
+    fn read(&mut self, msa: &mut Msa) -> Result<()>;
+}
+
+/// An iterator over the alignments of a reader.
+#[derive(Debug)]
+pub struct Alignments<R> {
+    reader: R,
+}
+
+impl<R: MsaRead> Iterator for Alignments<R> {
+    type Item = Result<Msa>;
+
+    fn next(&mut self) -> Option<Result<Msa>> {
+        let mut msa = Msa::new();
+        match self.reader.read(&mut msa) {
+            Ok(()) if msa.is_empty() => None,
+            Ok(()) => Some(Ok(msa)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Line-based input with line numbers for error messages.
+#[derive(Debug)]
+struct Lines<B> {
+    reader: B,
+    line: String,
+    line_no: usize,
+}
+
+impl<B: io::BufRead> Lines<B> {
+    fn new(reader: B) -> Self {
+        Lines {
+            reader,
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// The next line without its line terminator, or `None` at the end of the input.
+    fn next_line(&mut self) -> Result<Option<&str>> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        Ok(Some(self.line.trim_end_matches(&['\n', '\r'][..])))
+    }
+
+    /// The next line that is not blank.
+    fn next_nonblank(&mut self) -> Result<Option<&str>> {
+        loop {
+            if self.next_line()?.is_none() {
+                return Ok(None);
+            }
+            if !self.line.trim().is_empty() {
+                return Ok(Some(self.line.trim_end()));
+            }
+        }
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> Error {
+        Error::Format {
+            line: self.line_no,
+            message: message.into(),
+        }
+    }
+}
+
+/// The error returned by writers for names that can't be represented in their format.
+fn invalid_name(name: &str, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("can't write sequence name '{}': {}", name, reason),
+    )
+}
+
+/// The first column or residue markup whose length differs from the alignment width, with
+/// its length.
+fn ragged_markup(msa: &Msa) -> Option<(&str, usize)> {
+    let width = msa.width();
+    msa.column_annotations
+        .iter()
+        .map(|(feature, markup)| (feature.as_str(), markup.len()))
+        .chain(
+            msa.residue_annotations
+                .iter()
+                .map(|(_, feature, markup)| (feature.as_str(), markup.len())),
+        )
+        .find(|&(_, len)| len != width)
+}
+
+/// Ensure that all markup spans the alignment before writing.
+fn check_width(msa: &Msa) -> io::Result<()> {
+    match ragged_markup(msa) {
+        Some((feature, len)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "markup '{}' has {} columns, expected {}",
+                feature,
+                len,
+                msa.width()
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Msa {
+        let mut msa = Msa::new();
+        msa.push("a", b"AC--GT").unwrap();
+        msa.push("b", b"A---GA").unwrap();
+        msa.column_annotations
+            .push(("SS_cons".to_owned(), b"<<..>>".to_vec()));
+        msa
+    }
+
+    #[test]
+    fn test_msa() {
+        let mut msa = example();
+        assert_eq!(msa.len(), 2);
+        assert_eq!(msa.width(), 6);
+        assert_eq!(msa.get("b"), Some(&b"A---GA"[..]));
+        assert_eq!(msa.columns().collect::<Vec<_>>()[1], b"C-");
+        assert_eq!(msa.gap_count(1), 1);
+        assert!(msa.is_gap_column(2));
+        assert_eq!(msa.ungapped(1), b"AGA");
+        assert_eq!(msa.residue_index(0, 4), Some(2));
+        assert_eq!(msa.residue_index(1, 1), None);
+        assert!(matches!(
+            msa.push("c", b"ACGT"),
+            Err(Error::RowLength {
+                expected: 6,
+                found: 4,
+                ..
+            })
+        ));
+
+        msa.remove_gap_columns();
+        assert_eq!(msa.row(0), b"ACGT");
+        assert_eq!(msa.column_annotations[0].1, b"<<>>");
+    }
+}
@@ -0,0 +1,417 @@
+//! Reading and writing of alignments in the [PHYLIP] format.
+//!
+//! A PHYLIP file starts with the number of sequences and columns, followed either by each
+//! complete sequence ([`Layout::Sequential`](enum.Layout.html)) or by blocks holding a
+//! stretch of every sequence ([`Layout::Interleaved`](enum.Layout.html)), where only the
+//! first block carries the names. Names are separated from the sequences by whitespace, or,
+//! in strict mode, padded to exactly 10 characters. Whitespace within sequences is ignored.
+//!
+//! [PHYLIP]: https://evolution.genetics.washington.edu/phylip/doc/sequence.html
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::msa::phylip::{Layout, Reader, Writer};
+//! use bio::io::msa::MsaRead;
+//!
+//! let input = b" 2 12
+//! seq1 ACGTACGTAC
+//! seq2 ACGTTCGTAC
+//!
+//! GT
+//! G-
+//! ";
+//! let mut reader = Reader::new(&input[..]).layout(Layout::Interleaved);
+//! let msa = reader.alignments().next().unwrap().unwrap();
+//! assert_eq!(msa.row(1), b"ACGTTCGTACG-");
+//!
+//! let mut writer = Writer::new(Vec::new()).layout(Layout::Sequential).strict(true);
+//! writer.write(&msa).unwrap();
+//! assert_eq!(
+//!     writer.into_inner().unwrap(),
+//!     b" 2 12\nseq1      ACGTACGTACGT\nseq2      ACGTTCGTACG-\n"
+//! );
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use super::{invalid_name, Alignments, Error, Lines, Msa, MsaRead, Result};
+use crate::io::compression::Decoder;
+
+/// The width of names in strict PHYLIP.
+const STRICT_NAME_WIDTH: usize = 10;
+
+/// The arrangement of the sequences in a PHYLIP file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Each sequence is given completely, possibly over several lines, before the next.
+    Sequential,
+    /// The sequences are given in blocks, each holding one line per sequence.
+    Interleaved,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Interleaved
+    }
+}
+
+/// A PHYLIP reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    lines: Lines<B>,
+    layout: Layout,
+    strict: bool,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read PHYLIP alignment from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`. By default, the
+    /// input is expected to be interleaved, with names separated by whitespace.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            lines: Lines::new(bufreader),
+            layout: Layout::default(),
+            strict: false,
+        }
+    }
+
+    /// Set the layout of the input.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Read names as the first 10 characters of a line, which may include spaces, instead of
+    /// the first word.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Return an iterator over the alignments of this file.
+    pub fn alignments(self) -> Alignments<Self> {
+        Alignments { reader: self }
+    }
+
+    /// The next non-blank line, failing at the end of the input.
+    fn expect_line(&mut self, count: usize, expected: usize) -> Result<&str> {
+        if self.lines.next_nonblank()?.is_none() {
+            let message = format!(
+                "unexpected end of input after {} of {} sequences",
+                count, expected
+            );
+            return Err(self.lines.error(message));
+        }
+        Ok(self.lines.line.trim_end())
+    }
+}
+
+/// Split a line into the name and the residues of the sequence, removing whitespace from
+/// the latter.
+fn split_name(line: &str, strict: bool) -> (String, Vec<u8>) {
+    let (name, seq) = if strict {
+        let end = line
+            .char_indices()
+            .nth(STRICT_NAME_WIDTH)
+            .map_or(line.len(), |(i, _)| i);
+        (line[..end].trim(), &line[end..])
+    } else {
+        let line = line.trim_start();
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        (&line[..end], &line[end..])
+    };
+    (name.to_owned(), residues(seq))
+}
+
+/// The residues of a sequence line, without whitespace.
+fn residues(line: &str) -> Vec<u8> {
+    line.bytes().filter(|c| !c.is_ascii_whitespace()).collect()
+}
+
+impl<B: io::BufRead> MsaRead for Reader<B> {
+    /// Read the next alignment, i.e. the next data set of the file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the header is malformed, the input ends early
+    /// or a sequence is longer than announced.
+    fn read(&mut self, msa: &mut Msa) -> Result<()> {
+        msa.clear();
+        let header = match self.lines.next_nonblank()? {
+            None => return Ok(()),
+            Some(line) => line.to_owned(),
+        };
+        let counts: Vec<usize> = header
+            .split_whitespace()
+            .take(2)
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| self.lines.error(format!("invalid header '{}'", header)))?;
+        if counts.len() != 2 {
+            return Err(self.lines.error(format!("invalid header '{}'", header)));
+        }
+        let (count, width) = (counts[0], counts[1]);
+
+        // the count is not trusted for pre-allocation, as it may be arbitrarily large
+        let mut rows: Vec<(String, Vec<u8>)> = Vec::new();
+        match self.layout {
+            Layout::Sequential => {
+                for i in 0..count {
+                    let strict = self.strict;
+                    let (name, mut seq) = split_name(self.expect_line(i, count)?, strict);
+                    while seq.len() < width {
+                        let line = self.expect_line(i, count)?;
+                        seq.extend(residues(line));
+                    }
+                    rows.push((name, seq));
+                }
+            }
+            Layout::Interleaved => {
+                for i in 0..count {
+                    let strict = self.strict;
+                    rows.push(split_name(self.expect_line(i, count)?, strict));
+                }
+                while rows.iter().any(|(_, seq)| seq.len() < width) {
+                    for (i, (_, seq)) in rows.iter_mut().enumerate() {
+                        seq.extend(residues(self.expect_line(i, count)?));
+                    }
+                }
+            }
+        }
+
+        for (name, seq) in rows {
+            if seq.len() != width {
+                let message = format!(
+                    "sequence '{}' has {} columns, expected {}",
+                    name,
+                    seq.len(),
+                    width
+                );
+                return Err(self.lines.error(message));
+            }
+            msa.push(&name, &seq)?;
+        }
+        Ok(())
+    }
+}
+
+/// A PHYLIP writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+    layout: Layout,
+    strict: bool,
+    line_width: usize,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`. By default, the output is interleaved with 60 columns
+    /// per line, and names are separated from the sequences by whitespace.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+            layout: Layout::default(),
+            strict: false,
+            line_width: 60,
+        }
+    }
+
+    /// Set the layout of the output. Sequential output puts every sequence on one line.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Pad names to exactly 10 characters, as required by the original PHYLIP programs.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the number of columns per line of interleaved output.
+    ///
+    /// # Panics
+    ///
+    /// If `line_width` is zero.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        assert!(line_width > 0, "line width must be positive");
+        self.line_width = line_width;
+        self
+    }
+
+    /// Write an alignment.
+    ///
+    /// # Errors
+    ///
+    /// If a name is longer than 10 characters in strict mode, or contains whitespace
+    /// otherwise.
+    pub fn write(&mut self, msa: &Msa) -> io::Result<()> {
+        for name in msa.names() {
+            if self.strict && name.chars().count() > STRICT_NAME_WIDTH {
+                return Err(invalid_name(
+                    name,
+                    "strict names have at most 10 characters",
+                ));
+            }
+            if !self.strict && (name.is_empty() || name.contains(char::is_whitespace)) {
+                return Err(invalid_name(
+                    name,
+                    "names must be non-empty and without whitespace",
+                ));
+            }
+        }
+        let label_width = if self.strict {
+            STRICT_NAME_WIDTH
+        } else {
+            msa.names().iter().map(String::len).max().unwrap_or(0) + 1
+        };
+
+        writeln!(self.writer, " {} {}", msa.len(), msa.width())?;
+        let first_width = match self.layout {
+            Layout::Sequential => msa.width(),
+            Layout::Interleaved => self.line_width.min(msa.width()),
+        };
+        for (name, row) in msa.rows() {
+            write!(self.writer, "{:width$}", name, width = label_width)?;
+            self.writer.write_all(&row[..first_width])?;
+            writeln!(self.writer)?;
+        }
+        for start in (first_width..msa.width()).step_by(self.line_width) {
+            let end = (start + self.line_width).min(msa.width());
+            writeln!(self.writer)?;
+            for (_, row) in msa.rows() {
+                self.writer.write_all(&row[start..end])?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Msa {
+        let mut msa = Msa::new();
+        msa.push("Turkey", b"AAGCTNGGGCATTTCAGGGTGAGCCCGGGCAATACAGGGTAT")
+            .unwrap();
+        msa.push("Salmo gair", b"AAGCCTTGGCAGTGCAGGGTGAGCCGTGGCCGGGCACGGTAT")
+            .unwrap();
+        msa.push("H. Sapiens", b"ACCGGTTGGCCGTTCAGGGTACAGGTTGGCCGTTCAGGGTAA")
+            .unwrap();
+        msa
+    }
+
+    #[test]
+    fn test_read_sequential_strict() {
+        let input = b"3 42
+Turkey    AAGCTNGGGC ATTTCAGGGT
+GAGCCCGGGCAATACAGGGTAT
+Salmo gairAAGCCTTGGC AGTGCAGGGT GAGCCGTGGCCGGGCACGGTAT
+H. SapiensACCGGTTGGCCGTTCAGGGTACAGGTTGGCCGTTCAGGGTAA
+";
+        let mut reader = Reader::new(&input[..])
+            .layout(Layout::Sequential)
+            .strict(true);
+        let mut msa = Msa::new();
+        reader.read(&mut msa).unwrap();
+        assert_eq!(msa, example());
+        reader.read(&mut msa).unwrap();
+        assert!(msa.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut msa = Msa::new();
+        msa.push("seq1", b"ACGTACGTACGTA").unwrap();
+        msa.push("seq2", b"ACGT-CGTACG-A").unwrap();
+
+        let mut writer = Writer::new(Vec::new()).line_width(5);
+        writer.write(&msa).unwrap();
+        writer.write(&msa).unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            " 2 13\nseq1 ACGTA\nseq2 ACGT-\n\nCGTAC\nCGTAC\n\nGTA\nG-A\n".repeat(2)
+        );
+        let alignments: Vec<Msa> = Reader::new(&output[..])
+            .alignments()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(alignments, vec![msa.clone(), msa]);
+
+        let strict = Writer::new(Vec::new()).strict(true).write(&example());
+        assert!(strict.is_ok());
+        let relaxed = Writer::new(Vec::new()).write(&example());
+        assert_eq!(relaxed.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut msa = Msa::new();
+        let mut reader = Reader::new(&b"2 x\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 1, .. })
+        ));
+        let mut reader = Reader::new(&b"2 4\nseq1 ACGT\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 2, .. })
+        ));
+        let mut reader = Reader::new(&b"2 4\nseq1 ACGT\nseq2 ACGTA\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 3, .. })
+        ));
+        let mut reader = Reader::new(&b"99999999999999 4\nseq1 ACGT\n"[..]);
+        assert!(matches!(reader.read(&mut msa), Err(Error::Format { .. })));
+    }
+}
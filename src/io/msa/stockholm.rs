@@ -0,0 +1,406 @@
+//! Reading and writing of alignments in the [Stockholm] format, as used by Pfam and Rfam.
+//!
+//! Per-file (`#=GF`), per-sequence (`#=GS`), per-column (`#=GC`) and per-residue (`#=GR`)
+//! markup is kept in the corresponding fields of [`Msa`](../struct.Msa.html). Interleaved
+//! blocks are joined while reading; the writer puts every row on a single line.
+//!
+//! [Stockholm]: https://en.wikipedia.org/wiki/Stockholm_format
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::msa::{stockholm, MsaRead};
+//!
+//! let input = b"# STOCKHOLM 1.0
+//! #=GS seq1 AC P12345
+//! seq1         AC-G
+//! seq2         ACUG
+//! #=GR seq2 SS ..<>
+//!
+//! seq1         U
+//! seq2         U
+//! #=GR seq2 SS .
+//! //
+//! ";
+//! let reader = stockholm::Reader::new(&input[..]);
+//! let msa = reader.alignments().next().unwrap().unwrap();
+//! assert_eq!(msa.row(0), b"AC-GU");
+//! assert_eq!(msa.residue_annotations[0].2, b"..<>.");
+//! ```
+
+use anyhow::Context;
+use std::collections::HashMap;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use super::{
+    check_width, invalid_name, ragged_markup, Alignments, Error, Lines, Msa, MsaRead, Result,
+};
+use crate::io::compression::Decoder;
+
+/// A Stockholm reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    lines: Lines<B>,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read Stockholm alignment from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            lines: Lines::new(bufreader),
+        }
+    }
+
+    /// Return an iterator over the alignments of this file.
+    pub fn alignments(self) -> Alignments<Self> {
+        Alignments { reader: self }
+    }
+}
+
+/// Split off the first whitespace-delimited word of the given text.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (&text[..end], text[end..].trim())
+}
+
+impl<B: io::BufRead> MsaRead for Reader<B> {
+    /// Read the next alignment, up to its `//` terminator.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `# STOCKHOLM` header or the `//` terminator
+    /// is missing, or if rows or markup have different lengths.
+    fn read(&mut self, msa: &mut Msa) -> Result<()> {
+        msa.clear();
+        match self.lines.next_nonblank()? {
+            None => return Ok(()),
+            Some(line) if line.starts_with("# STOCKHOLM") => (),
+            Some(_) => return Err(self.lines.error("missing '# STOCKHOLM' header")),
+        }
+
+        let mut names = Vec::new();
+        let mut rows: HashMap<String, Vec<u8>> = HashMap::new();
+        loop {
+            let line = match self.lines.next_line()? {
+                Some(line) => line,
+                None => return Err(self.lines.error("missing '//' terminator")),
+            };
+            if line.trim() == "//" {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(markup) = line.strip_prefix("#=G") {
+                let (kind, rest) = markup.split_at(1.min(markup.len()));
+                let (first, rest) = split_word(rest);
+                match kind {
+                    "F" => msa
+                        .file_annotations
+                        .push((first.to_owned(), rest.to_owned())),
+                    "S" => {
+                        let (feature, text) = split_word(rest);
+                        msa.sequence_annotations.push((
+                            first.to_owned(),
+                            feature.to_owned(),
+                            text.to_owned(),
+                        ));
+                    }
+                    "C" => {
+                        let markup = rest.as_bytes();
+                        match msa.column_annotations.iter_mut().find(|(f, _)| f == first) {
+                            Some((_, existing)) => existing.extend_from_slice(markup),
+                            None => msa
+                                .column_annotations
+                                .push((first.to_owned(), markup.to_vec())),
+                        }
+                    }
+                    "R" => {
+                        let (feature, markup) = split_word(rest);
+                        match msa
+                            .residue_annotations
+                            .iter_mut()
+                            .find(|(n, f, _)| n == first && f == feature)
+                        {
+                            Some((_, _, existing)) => existing.extend_from_slice(markup.as_bytes()),
+                            None => msa.residue_annotations.push((
+                                first.to_owned(),
+                                feature.to_owned(),
+                                markup.as_bytes().to_vec(),
+                            )),
+                        }
+                    }
+                    _ => {
+                        let message = format!("unknown markup line '{}'", line);
+                        return Err(self.lines.error(message));
+                    }
+                }
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let (name, seq) = split_word(line);
+            if seq.contains(char::is_whitespace) {
+                let message = format!("sequence '{}' contains whitespace", name);
+                return Err(self.lines.error(message));
+            }
+            match rows.get_mut(name) {
+                Some(row) => row.extend_from_slice(seq.as_bytes()),
+                None => {
+                    names.push(name.to_owned());
+                    rows.insert(name.to_owned(), seq.as_bytes().to_vec());
+                }
+            }
+        }
+
+        for name in names {
+            msa.push(&name, &rows[&name])?;
+        }
+        if let Some((feature, len)) = ragged_markup(msa) {
+            let message = format!(
+                "markup '{}' has {} columns, expected {}",
+                feature,
+                len,
+                msa.width()
+            );
+            return Err(self.lines.error(message));
+        }
+        Ok(())
+    }
+}
+
+/// A Stockholm writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write an alignment, including its markup and the `//` terminator.
+    ///
+    /// # Errors
+    ///
+    /// If a sequence name contains whitespace or markup does not span all columns.
+    pub fn write(&mut self, msa: &Msa) -> io::Result<()> {
+        check_width(msa)?;
+        if let Some(name) = msa
+            .names()
+            .iter()
+            .find(|n| n.is_empty() || n.contains(char::is_whitespace))
+        {
+            return Err(invalid_name(
+                name,
+                "names must be non-empty and without whitespace",
+            ));
+        }
+
+        writeln!(self.writer, "# STOCKHOLM 1.0")?;
+        for (feature, text) in &msa.file_annotations {
+            writeln!(self.writer, "#=GF {} {}", feature, text)?;
+        }
+        for (name, feature, text) in &msa.sequence_annotations {
+            writeln!(self.writer, "#=GS {} {} {}", name, feature, text)?;
+        }
+        if !msa.file_annotations.is_empty() || !msa.sequence_annotations.is_empty() {
+            writeln!(self.writer)?;
+        }
+
+        let labels = msa
+            .names()
+            .iter()
+            .map(String::len)
+            .chain(
+                msa.residue_annotations
+                    .iter()
+                    .map(|(name, feature, _)| name.len() + feature.len() + 6),
+            )
+            .chain(
+                msa.column_annotations
+                    .iter()
+                    .map(|(feature, _)| feature.len() + 5),
+            );
+        let width = labels.max().unwrap_or(0) + 1;
+
+        for (name, row) in msa.rows() {
+            write!(self.writer, "{:width$}", name, width = width)?;
+            self.writer.write_all(row)?;
+            writeln!(self.writer)?;
+            for (_, feature, markup) in msa.residue_annotations.iter().filter(|(n, _, _)| n == name)
+            {
+                let label = format!("#=GR {} {}", name, feature);
+                write!(self.writer, "{:width$}", label, width = width)?;
+                self.writer.write_all(markup)?;
+                writeln!(self.writer)?;
+            }
+        }
+        for (feature, markup) in &msa.column_annotations {
+            let label = format!("#=GC {}", feature);
+            write!(self.writer, "{:width$}", label, width = width)?;
+            self.writer.write_all(markup)?;
+            writeln!(self.writer)?;
+        }
+        writeln!(self.writer, "//")
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STOCKHOLM: &[u8] = b"# STOCKHOLM 1.0
+#=GF ID    trna
+#=GF AC RF00005
+# a comment
+#=GS seq1 DE first sequence
+
+seq1           GCG-GAUU
+seq2           GCGCGA.U
+#=GR seq2 SS   <<<..>>>
+#=GC SS_cons   <<<..>>>
+
+seq1           UA
+seq2           UA
+#=GR seq2 SS   ..
+#=GC SS_cons   ..
+//
+# STOCKHOLM 1.0
+seq3 AC
+//
+";
+
+    #[test]
+    fn test_read() {
+        let alignments: Vec<Msa> = Reader::new(STOCKHOLM)
+            .alignments()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(alignments.len(), 2);
+        let msa = &alignments[0];
+        assert_eq!(msa.names(), ["seq1", "seq2"]);
+        assert_eq!(msa.row(1), b"GCGCGA.UUA");
+        assert_eq!(
+            msa.file_annotations,
+            vec![
+                ("ID".to_owned(), "trna".to_owned()),
+                ("AC".to_owned(), "RF00005".to_owned())
+            ]
+        );
+        assert_eq!(
+            msa.sequence_annotation("seq1", "DE"),
+            Some("first sequence")
+        );
+        assert_eq!(msa.column_annotations[0].1, b"<<<..>>>..");
+        assert_eq!(msa.residue_annotations[0].0, "seq2");
+        assert_eq!(alignments[1].row(0), b"AC");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let msa = Reader::new(STOCKHOLM).alignments().next().unwrap().unwrap();
+        let mut writer = Writer::new(Vec::new());
+        writer.write(&msa).unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "# STOCKHOLM 1.0
+#=GF ID trna
+#=GF AC RF00005
+#=GS seq1 DE first sequence
+
+seq1         GCG-GAUUUA
+seq2         GCGCGA.UUA
+#=GR seq2 SS <<<..>>>..
+#=GC SS_cons <<<..>>>..
+//
+"
+        );
+        let reread = Reader::new(&output[..])
+            .alignments()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reread, msa);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut msa = Msa::new();
+        let mut reader = Reader::new(&b"seq1 ACGT\n//\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 1, .. })
+        ));
+
+        let mut reader = Reader::new(&b"# STOCKHOLM 1.0\nseq1 ACGT\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 2, .. })
+        ));
+
+        let mut reader = Reader::new(&b"# STOCKHOLM 1.0\nseq1 ACGT\nseq2 AC\n//\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::RowLength { .. })
+        ));
+
+        let mut reader = Reader::new(&b"# STOCKHOLM 1.0\nseq1 ACGT\n#=GC SS_cons ..\n//\n"[..]);
+        assert!(matches!(
+            reader.read(&mut msa),
+            Err(Error::Format { line: 4, .. })
+        ));
+    }
+}
@@ -3,7 +3,13 @@
 // This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! A struct to read phylogenetic trees in the Newick format.
+//! Functions to read and write phylogenetic trees in the Newick format.
+//!
+//! Comments in square brackets are accepted anywhere a branch length may
+//! appear. Comments following the [NHX](https://en.wikipedia.org/wiki/Newick_format#New_Hampshire_X_format)
+//! convention (`[&&NHX:key=value:...]`) can be retrieved as node
+//! [`Attributes`](type.Attributes.html) with `from_string_nhx` and written
+//! back with a [`Writer`](struct.Writer.html).
 //!
 //!  # Example
 //!
@@ -18,11 +24,28 @@
 //!      println!("{}", taxon.weight);
 //!  }
 //!  ```
+//!
+//!  Trees can be written back, here with branch lengths rounded to two
+//!  decimals and their NHX annotations preserved. The branch length of the
+//!  root is not part of a `Tree`, hence it is lost (see [`Writer`](struct.Writer.html)):
+//!
+//!  ```
+//!  use bio::io::newick;
+//!
+//!  let (tree, attributes) =
+//!      newick::from_string_nhx("(A:0.123,B:0.2)C:0.5[&&NHX:B=100];").unwrap();
+//!  let mut writer = newick::Writer::new(Vec::new()).precision(Some(2));
+//!  writer.write_nhx(&tree, &attributes).unwrap();
+//!  assert_eq!(writer.into_inner(), b"(A:0.12,B:0.20)C[&&NHX:B=100];\n");
+//!  ```
 
 use bio_types::phylogeny::{Tree, TreeGraph};
 use pest::iterators::Pair;
 use pest::Parser;
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -48,6 +71,10 @@ pub enum Error {
 }
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// NHX attributes of the nodes of a tree, as `(key, value)` pairs in their
+/// order of appearance. Nodes without an NHX comment have no entry.
+pub type Attributes = HashMap<NodeIndex, Vec<(String, String)>>;
+
 /// The parser is automagically derived from the `newick.pest` grammar
 /// file
 #[derive(Parser)]
@@ -64,18 +91,47 @@ enum TreeValue {
     Link {
         weight: f32,
         node: Box<TreeValue>,
+        attributes: Vec<(String, String)>,
     },
 }
 
+/// Removes the enclosing single quotes of a label, if any, and unescapes
+/// the doubled quotes inside.
+fn unquote(label: &str) -> String {
+    if label.len() >= 2 && label.starts_with('\'') && label.ends_with('\'') {
+        label[1..label.len() - 1].replace("''", "'")
+    } else {
+        label.to_owned()
+    }
+}
+
+/// Extracts the `key=value` pairs of an NHX comment (`[&&NHX:...]`); other
+/// comments are ignored.
+fn parse_nhx(comment: &str) -> Vec<(String, String)> {
+    match comment
+        .strip_prefix("[&&NHX")
+        .and_then(|c| c.strip_suffix(']'))
+    {
+        Some(fields) => fields
+            .split(':')
+            .filter_map(|field| {
+                let mut pair = field.splitn(2, '=');
+                Some((pair.next()?.to_owned(), pair.next()?.to_owned()))
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
 /// Given a string representing a Newick tree, tries to parse it and
-/// returns the `TreeValue` of the root
-fn parse_newick_file(content: &str) -> Result<TreeValue> {
+/// returns the `TreeValue` of the root along with its NHX attributes
+fn parse_newick_file(content: &str) -> Result<(TreeValue, Vec<(String, String)>)> {
     fn parse_value(pair: Pair<Rule>) -> TreeValue {
         match pair.as_rule() {
             Rule::Leaf => {
                 let name = pair.into_inner().next().unwrap().as_str();
                 TreeValue::Node {
-                    name: Some(name.into()),
+                    name: Some(unquote(name)),
                     children: None,
                 }
             }
@@ -89,48 +145,30 @@ fn parse_newick_file(content: &str) -> Result<TreeValue> {
                         .map(parse_value)
                         .collect(),
                 );
-                let name = if let Some(clade) = inner_rules.next() {
-                    Some(clade.as_str().into())
-                } else {
-                    None
-                };
+                let name = inner_rules.next().map(|clade| unquote(clade.as_str()));
                 TreeValue::Node { children, name }
             }
 
             Rule::Branch => {
-                fn get_weight(mut inner: pest::iterators::Pairs<Rule>) -> f32 {
-                    if let Some(weight) = inner.next() {
-                        weight.as_str().parse::<f32>().unwrap()
-                    } else {
-                        f32::NAN
+                let mut node = TreeValue::Node {
+                    name: None,
+                    children: None,
+                };
+                let mut weight = f32::NAN;
+                let mut attributes = Vec::new();
+                for inner in pair.into_inner() {
+                    match inner.as_rule() {
+                        Rule::SubTree => node = parse_value(inner),
+                        Rule::float => weight = inner.as_str().parse::<f32>().unwrap(),
+                        Rule::Comment => attributes = parse_nhx(inner.as_str()),
+                        _ => unreachable!(),
                     }
                 }
 
-                let mut inner = pair.into_inner();
-                let (node, weight) = if let Some(next) = inner.next() {
-                    match next.as_rule() {
-                        Rule::SubTree => (parse_value(next), get_weight(inner)),
-                        _ => (
-                            TreeValue::Node {
-                                name: None,
-                                children: None,
-                            },
-                            next.as_str().parse::<f32>().unwrap(),
-                        ),
-                    }
-                } else {
-                    (
-                        TreeValue::Node {
-                            name: None,
-                            children: None,
-                        },
-                        get_weight(inner),
-                    )
-                };
-
                 TreeValue::Link {
                     weight,
                     node: Box::new(node),
+                    attributes,
                 }
             }
 
@@ -142,21 +180,29 @@ fn parse_newick_file(content: &str) -> Result<TreeValue> {
             | Rule::BranchSet
             | Rule::float
             | Rule::safe
-            | Rule::name => unreachable!(),
+            | Rule::quoted
+            | Rule::name
+            | Rule::Comment
+            | Rule::comment_text => unreachable!(),
         }
     }
 
-    let root = NewickParser::parse(Rule::Tree, &content)
-        .map_err(Error::ParsingError)?
-        .next()
-        .unwrap();
+    let mut pairs = NewickParser::parse(Rule::Tree, content).map_err(Error::ParsingError)?;
+    let root = pairs.next().unwrap();
+    let attributes = pairs
+        .find(|pair| pair.as_rule() == Rule::Comment)
+        .map_or_else(Vec::new, |comment| parse_nhx(comment.as_str()));
 
-    Ok(parse_value(root))
+    Ok((parse_value(root), attributes))
 }
 
-/// Convert an intermediary `TreeValue` to the public `Tree` type
-fn newick_to_graph(root: TreeValue) -> Result<Tree> {
-    fn add_node(g: &mut TreeGraph, t: TreeValue) -> NodeIndex {
+/// Convert an intermediary `TreeValue` to the public `Tree` type and the
+/// NHX attributes of its nodes
+fn newick_to_graph(
+    root: TreeValue,
+    root_attributes: Vec<(String, String)>,
+) -> Result<(Tree, Attributes)> {
+    fn add_node(g: &mut TreeGraph, attrs: &mut Attributes, t: TreeValue) -> NodeIndex {
         match t {
             TreeValue::Node { name, children } => {
                 let node_id = g.add_node(name.unwrap_or("N/A".into()).into());
@@ -164,9 +210,16 @@ fn newick_to_graph(root: TreeValue) -> Result<Tree> {
                     for child in children {
                         match child {
                             TreeValue::Node { .. } => unimplemented!(),
-                            TreeValue::Link { weight, node } => {
-                                let child_id = add_node(g, *node);
+                            TreeValue::Link {
+                                weight,
+                                node,
+                                attributes,
+                            } => {
+                                let child_id = add_node(g, attrs, *node);
                                 g.add_edge(node_id, child_id, weight);
+                                if !attributes.is_empty() {
+                                    attrs.insert(child_id, attributes);
+                                }
                             }
                         }
                     }
@@ -178,21 +231,36 @@ fn newick_to_graph(root: TreeValue) -> Result<Tree> {
     }
 
     let mut g = TreeGraph::new();
-    add_node(&mut g, root);
+    let mut attributes = Attributes::new();
+    let root_id = add_node(&mut g, &mut attributes, root);
+    if !root_attributes.is_empty() {
+        attributes.insert(root_id, root_attributes);
+    }
 
-    Ok(Tree { g })
+    Ok((Tree { g }, attributes))
 }
 
 /// Reads a tree from an `&str`-compatible type
 pub fn from_string<S: AsRef<str>>(content: S) -> Result<Tree> {
-    let raw_tree = parse_newick_file(content.as_ref())?;
-    newick_to_graph(raw_tree)
+    from_string_nhx(content).map(|(tree, _)| tree)
+}
+
+/// Reads a tree and the NHX attributes of its nodes from an
+/// `&str`-compatible type
+pub fn from_string_nhx<S: AsRef<str>>(content: S) -> Result<(Tree, Attributes)> {
+    let (raw_tree, root_attributes) = parse_newick_file(content.as_ref())?;
+    newick_to_graph(raw_tree, root_attributes)
 }
 
 /// Reads a tree from a file
 pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Tree> {
+    from_file_nhx(path).map(|(tree, _)| tree)
+}
+
+/// Reads a tree and the NHX attributes of its nodes from a file
+pub fn from_file_nhx<P: AsRef<Path>>(path: P) -> Result<(Tree, Attributes)> {
     fs::File::open(&path)
-        .map(read)
+        .map(read_nhx)
         .map_err(|e| Error::OpenFile {
             filename: path.as_ref().to_owned(),
             source: e,
@@ -201,10 +269,270 @@ pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Tree> {
 
 /// Reads a tree from any type implementing `io::Read`
 pub fn read<R: io::Read>(reader: R) -> Result<Tree> {
+    read_nhx(reader).map(|(tree, _)| tree)
+}
+
+/// Reads a tree and the NHX attributes of its nodes from any type
+/// implementing `io::Read`
+pub fn read_nhx<R: io::Read>(reader: R) -> Result<(Tree, Attributes)> {
     let content_bytes = reader
         .bytes()
         .collect::<Result<Vec<_>, _>>()
         .map_err(Error::Read)?;
     let content_str = std::str::from_utf8(&content_bytes).map_err(Error::InvalidContent)?;
-    from_string(&content_str)
+    from_string_nhx(&content_str)
+}
+
+/// When to enclose node labels in single quotes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quoting {
+    /// Quote labels containing whitespace or characters with a meaning in
+    /// Newick (`()[]':;,`).
+    Needed,
+    /// Quote every label.
+    Always,
+    /// Never quote labels, writing them verbatim.
+    Never,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for Quoting {
+    fn default() -> Self {
+        Quoting::Needed
+    }
+}
+
+/// A Newick writer.
+///
+/// Nodes labelled `N/A`, as created by the parser for unnamed nodes, are
+/// written without a label, and branches with a `NaN` weight without a
+/// length. Children are written in the order their edges were added.
+///
+/// A `Tree` has no edge leading to its root, so the parser drops a branch
+/// length given for the root, e.g. the `0.5` in `(A,B)C:0.5;`, and the root
+/// is always written without one. Its NHX attributes are kept.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: W,
+    precision: Option<usize>,
+    quoting: Quoting,
+}
+
+impl Writer<fs::File> {
+    /// Write to the given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::File::create(&path)
+            .map(Writer::new)
+            .map_err(|e| Error::OpenFile {
+                filename: path.as_ref().to_owned(),
+                source: e,
+            })
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to the given `io::Write`, with branch lengths in their shortest
+    /// representation and labels quoted when needed.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            precision: None,
+            quoting: Quoting::default(),
+        }
+    }
+
+    /// Set the number of decimals of branch lengths, or `None` for the
+    /// shortest representation that parses back to the same value.
+    pub fn precision(mut self, precision: Option<usize>) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Set when labels are enclosed in single quotes.
+    pub fn quoting(mut self, quoting: Quoting) -> Self {
+        self.quoting = quoting;
+        self
+    }
+
+    /// Write a tree, terminated by `;` and a newline.
+    pub fn write(&mut self, tree: &Tree) -> io::Result<()> {
+        self.write_nhx(tree, &Attributes::new())
+    }
+
+    /// Write a tree along with the NHX attributes of its nodes.
+    pub fn write_nhx(&mut self, tree: &Tree, attributes: &Attributes) -> io::Result<()> {
+        let mut out = String::new();
+        if let Some(root) = tree.g.node_indices().find(|&n| {
+            tree.g
+                .neighbors_directed(n, Direction::Incoming)
+                .next()
+                .is_none()
+        }) {
+            self.format_node(&mut out, tree, attributes, root, None);
+        }
+        out.push_str(";\n");
+        self.writer.write_all(out.as_bytes())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Unwrap the writer, returning the underlying `io::Write`.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn format_node(
+        &self,
+        out: &mut String,
+        tree: &Tree,
+        attributes: &Attributes,
+        node: NodeIndex,
+        weight: Option<f32>,
+    ) {
+        let mut edges: Vec<_> = tree.g.edges_directed(node, Direction::Outgoing).collect();
+        if !edges.is_empty() {
+            edges.sort_by_key(|edge| edge.id());
+            out.push('(');
+            for (i, edge) in edges.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                self.format_node(out, tree, attributes, edge.target(), Some(*edge.weight()));
+            }
+            out.push(')');
+        }
+
+        let label = &tree.g[node];
+        if label != "N/A" {
+            out.push_str(&self.format_label(label));
+        }
+        if let Some(weight) = weight.filter(|w| !w.is_nan()) {
+            out.push(':');
+            match self.precision {
+                Some(precision) => out.push_str(&format!("{:.*}", precision, weight)),
+                None => out.push_str(&weight.to_string()),
+            }
+        }
+        if let Some(fields) = attributes.get(&node).filter(|fields| !fields.is_empty()) {
+            out.push_str("[&&NHX");
+            for (key, value) in fields {
+                out.push_str(&format!(":{}={}", key, value));
+            }
+            out.push(']');
+        }
+    }
+
+    fn format_label(&self, label: &str) -> String {
+        let needed = label.is_empty()
+            || label
+                .chars()
+                .any(|c| c.is_whitespace() || "()[]':;,".contains(c));
+        match self.quoting {
+            Quoting::Never => label.to_owned(),
+            Quoting::Needed if !needed => label.to_owned(),
+            _ => format!("'{}'", label.replace('\'', "''")),
+        }
+    }
+}
+
+/// Writes a tree to a Newick string, with default settings.
+pub fn to_string(tree: &Tree) -> String {
+    let mut writer = Writer::new(Vec::new());
+    writer.write(tree).unwrap();
+    let mut out = String::from_utf8(writer.into_inner()).unwrap();
+    out.pop();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let newick = "(A:0.1,B:0.2,(C:0.3,D:0.4)E:0.5)F;";
+        let tree = from_string(newick).unwrap();
+        assert_eq!(to_string(&tree), newick);
+
+        let newick = "((,B),'my taxon':1e-3);";
+        let tree = from_string(newick).unwrap();
+        assert!(tree.g.node_weights().any(|n| n == "my taxon"));
+        assert_eq!(to_string(&tree), "((,B),'my taxon':0.001);");
+
+        // the branch length of the root is not kept
+        let tree = from_string("(A,B)C:0.5;").unwrap();
+        assert_eq!(to_string(&tree), "(A,B)C;");
+    }
+
+    #[test]
+    fn test_quoting_and_precision() {
+        let tree = from_string("(A:0.123456,'B''s':2)'root node';").unwrap();
+        assert!(tree.g.node_weights().any(|n| n == "B's"));
+
+        let mut writer = Writer::new(Vec::new()).precision(Some(3));
+        writer.write(&tree).unwrap();
+        assert_eq!(
+            writer.into_inner(),
+            b"(A:0.123,'B''s':2.000)'root node';\n".to_vec()
+        );
+
+        let mut writer = Writer::new(Vec::new())
+            .precision(Some(0))
+            .quoting(Quoting::Always);
+        writer.write(&tree).unwrap();
+        assert_eq!(
+            writer.into_inner(),
+            b"('A':0,'B''s':2)'root node';\n".to_vec()
+        );
+
+        let mut writer = Writer::new(Vec::new()).quoting(Quoting::Never);
+        writer.write(&tree).unwrap();
+        assert_eq!(
+            writer.into_inner(),
+            b"(A:0.123456,B's:2)root node;\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_nhx() {
+        let newick = "((A:0.1[&&NHX:S=human],B:0.2[&&NHX:S=chimp:B=95])AB:0.3[&&NHX:B=100],\
+                      C[comment])root[&&NHX:S=primates];";
+        let (tree, attributes) = from_string_nhx(newick).unwrap();
+        let node = |name: &str| tree.g.node_indices().find(|&n| tree.g[n] == name).unwrap();
+        assert_eq!(
+            attributes[&node("B")],
+            vec![
+                ("S".to_owned(), "chimp".to_owned()),
+                ("B".to_owned(), "95".to_owned())
+            ]
+        );
+        assert_eq!(
+            attributes[&node("AB")],
+            vec![("B".to_owned(), "100".to_owned())]
+        );
+        assert_eq!(
+            attributes[&node("root")],
+            vec![("S".to_owned(), "primates".to_owned())]
+        );
+        assert!(!attributes.contains_key(&node("C")));
+
+        // plain parsing ignores comments
+        assert_eq!(from_string(newick).unwrap().g.node_count(), 5);
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_nhx(&tree, &attributes).unwrap();
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            written,
+            "((A:0.1[&&NHX:S=human],B:0.2[&&NHX:S=chimp:B=95])AB:0.3[&&NHX:B=100],C)\
+             root[&&NHX:S=primates];\n"
+        );
+        let (reparsed, reattributes) = from_string_nhx(&written).unwrap();
+        assert_eq!(reparsed.g.node_count(), tree.g.node_count());
+        assert_eq!(reattributes, attributes);
+    }
 }
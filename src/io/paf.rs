@@ -0,0 +1,724 @@
+//! Reading and writing of pairwise mappings in the [PAF] format, as produced by minimap2.
+//!
+//! Records hold the twelve mandatory columns and the optional SAM-style fields, with typed
+//! access to the CIGAR (`cg`), alignment type (`tp`) and edit distance (`NM`). Coordinates
+//! are 0-based and half-open. A record with a CIGAR can be converted into an
+//! [`Alignment`](../../alignment/struct.Alignment.html), e.g. to re-score it with
+//! [`Scoring::score_alignment`](../../alignment/pairwise/struct.Scoring.html#method.score_alignment).
+//!
+//! [PAF]: https://github.com/lh3/miniasm/blob/master/PAF.md
+//!
+//! # Example
+//!
+//! ```
+//! use bio::alignment::pairwise::Scoring;
+//! use bio::io::paf;
+//!
+//! let query = b"ACGTTACGT";
+//! let target = b"GGACGTACGTCC";
+//! let input = b"q1\t9\t0\t9\t+\tt1\t12\t2\t10\t7\t9\t60\ttp:A:P\tcg:Z:4M1I4M\n";
+//! let reader = paf::Reader::new(&input[..]);
+//! for record in reader.records() {
+//!     let record = record.unwrap();
+//!     assert_eq!(record.alignment_type(), Some(paf::AlignmentType::Primary));
+//!     let alignment = record.alignment().unwrap();
+//!     let scoring = Scoring::from_scores(-2, -1, 1, -1);
+//!     assert_eq!(scoring.score_alignment(&alignment, query, target), 5);
+//! }
+//! ```
+
+use anyhow::Context;
+use bio_types::strand::ReqStrand;
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::alignment::{Alignment, AlignmentMode, AlignmentOperation};
+use crate::io::compression::Decoder;
+use crate::io::sam::{parse_tag, Cigar, CigarString, TagValue};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("invalid CIGAR string: {0}")]
+    InvalidCigar(String),
+
+    #[error("record has no CIGAR (cg tag)")]
+    MissingCigar,
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The type of an alignment, as given by the `tp` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignmentType {
+    /// A primary alignment (`P`).
+    Primary,
+    /// A secondary alignment (`S`).
+    Secondary,
+    /// An inversion (`I` or `i`).
+    Inversion,
+}
+
+/// A PAF record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    qname: String,
+    qlen: u64,
+    qstart: u64,
+    qend: u64,
+    strand: ReqStrand,
+    tname: String,
+    tlen: u64,
+    tstart: u64,
+    tend: u64,
+    matches: u64,
+    block_len: u64,
+    mapq: u8,
+    tags: Vec<(String, TagValue)>,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Record {
+            qname: String::new(),
+            qlen: 0,
+            qstart: 0,
+            qend: 0,
+            strand: ReqStrand::Forward,
+            tname: String::new(),
+            tlen: 0,
+            tstart: 0,
+            tend: 0,
+            matches: 0,
+            block_len: 0,
+            mapq: 255,
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl Record {
+    /// Create a new, empty record. The mapping quality is 255 (unavailable).
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    /// Convert a pairwise alignment of a query (x) against a target (y) into a record.
+    /// For the reverse strand, the alignment has to be computed on the reverse complement
+    /// of the query.
+    ///
+    /// Matches and mismatches are reported as `=` and `X` in the CIGAR (`cg`), the edit
+    /// distance as `NM` and the score as `AS`. Clip operations are not part of the CIGAR.
+    pub fn from_alignment(
+        alignment: &Alignment,
+        qname: &str,
+        tname: &str,
+        strand: ReqStrand,
+    ) -> Self {
+        let mut cigar = CigarString::default();
+        let mut matches = 0;
+        let mut edit_distance = 0;
+        for op in &alignment.operations {
+            let op = match op {
+                AlignmentOperation::Match => {
+                    matches += 1;
+                    Cigar::Equal(1)
+                }
+                AlignmentOperation::Subst => Cigar::Diff(1),
+                AlignmentOperation::Ins => Cigar::Ins(1),
+                AlignmentOperation::Del => Cigar::Del(1),
+                AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_) => continue,
+            };
+            if op.char() != '=' {
+                edit_distance += 1;
+            }
+            cigar.push(op);
+        }
+        let (qstart, qend) = match strand {
+            ReqStrand::Forward => (alignment.xstart, alignment.xend),
+            ReqStrand::Reverse => (
+                alignment.xlen - alignment.xend,
+                alignment.xlen - alignment.xstart,
+            ),
+        };
+
+        let mut record = Record {
+            qname: qname.to_owned(),
+            qlen: alignment.xlen as u64,
+            qstart: qstart as u64,
+            qend: qend as u64,
+            strand,
+            tname: tname.to_owned(),
+            tlen: alignment.ylen as u64,
+            tstart: alignment.ystart as u64,
+            tend: alignment.yend as u64,
+            matches,
+            block_len: cigar.0.iter().map(|op| op.len() as u64).sum(),
+            ..Record::new()
+        };
+        record.push_tag("NM", TagValue::Int(edit_distance));
+        record.push_tag("AS", TagValue::Int(alignment.score as i64));
+        record.set_cigar(&cigar);
+        record
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.qname.is_empty()
+    }
+
+    /// Name of the query sequence.
+    pub fn qname(&self) -> &str {
+        &self.qname
+    }
+
+    /// Length of the query sequence.
+    pub fn qlen(&self) -> u64 {
+        self.qlen
+    }
+
+    /// Start of the alignment on the query (0-based, inclusive).
+    pub fn qstart(&self) -> u64 {
+        self.qstart
+    }
+
+    /// End of the alignment on the query (0-based, exclusive).
+    pub fn qend(&self) -> u64 {
+        self.qend
+    }
+
+    /// Relative strand of query and target.
+    pub fn strand(&self) -> ReqStrand {
+        self.strand
+    }
+
+    /// Name of the target sequence.
+    pub fn tname(&self) -> &str {
+        &self.tname
+    }
+
+    /// Length of the target sequence.
+    pub fn tlen(&self) -> u64 {
+        self.tlen
+    }
+
+    /// Start of the alignment on the target (0-based, inclusive).
+    pub fn tstart(&self) -> u64 {
+        self.tstart
+    }
+
+    /// End of the alignment on the target (0-based, exclusive).
+    pub fn tend(&self) -> u64 {
+        self.tend
+    }
+
+    /// Number of matching bases in the alignment.
+    pub fn matches(&self) -> u64 {
+        self.matches
+    }
+
+    /// Number of bases, including gaps, in the alignment.
+    pub fn block_len(&self) -> u64 {
+        self.block_len
+    }
+
+    /// Mapping quality, 255 if unavailable.
+    pub fn mapq(&self) -> u8 {
+        self.mapq
+    }
+
+    /// The optional fields in order of appearance.
+    pub fn tags(&self) -> &[(String, TagValue)] {
+        &self.tags
+    }
+
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        self.tags
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, value)| value)
+    }
+
+    /// Set the optional field with the given tag, replacing an existing value.
+    pub fn push_tag(&mut self, tag: &str, value: TagValue) {
+        match self.tags.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, existing)) => *existing = value,
+            None => self.tags.push((tag.to_owned(), value)),
+        }
+    }
+
+    /// Remove the optional field with the given tag, returning its value.
+    pub fn remove_tag(&mut self, tag: &str) -> Option<TagValue> {
+        let i = self.tags.iter().position(|(t, _)| t == tag)?;
+        Some(self.tags.remove(i).1)
+    }
+
+    /// The CIGAR of the alignment (`cg` tag), if present.
+    pub fn cigar(&self) -> Result<Option<CigarString>> {
+        match self.tag("cg") {
+            Some(TagValue::String(cigar)) => cigar
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::InvalidCigar(cigar.clone())),
+            Some(value) => Err(Error::InvalidCigar(value.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the CIGAR of the alignment (`cg` tag).
+    pub fn set_cigar(&mut self, cigar: &CigarString) {
+        self.push_tag("cg", TagValue::String(cigar.to_string()));
+    }
+
+    /// The type of the alignment (`tp` tag), if present and valid.
+    pub fn alignment_type(&self) -> Option<AlignmentType> {
+        match self.tag("tp") {
+            Some(TagValue::Char(b'P')) => Some(AlignmentType::Primary),
+            Some(TagValue::Char(b'S')) => Some(AlignmentType::Secondary),
+            Some(TagValue::Char(b'I')) | Some(TagValue::Char(b'i')) => {
+                Some(AlignmentType::Inversion)
+            }
+            _ => None,
+        }
+    }
+
+    /// The edit distance of the alignment (`NM` tag), if present and valid.
+    pub fn edit_distance(&self) -> Option<u64> {
+        match self.tag("NM") {
+            Some(TagValue::Int(nm)) if *nm >= 0 => Some(*nm as u64),
+            _ => None,
+        }
+    }
+
+    /// Convert the CIGAR into an alignment of the query (x) against the target (y). For the
+    /// reverse strand, x refers to the reverse complement of the query.
+    ///
+    /// `M` operations become matches, as PAF does not tell them apart from mismatches; the
+    /// CIGAR of `minimap2 --eqx` distinguishes them. Skipped regions (`N`) become deletions.
+    /// The score is taken from the `AS` tag, or 0 if absent.
+    ///
+    /// # Errors
+    ///
+    /// If the record has no CIGAR, the CIGAR contains clips or padding, or it does not span
+    /// the aligned query and target intervals.
+    pub fn alignment(&self) -> Result<Alignment> {
+        let cigar = self.cigar()?.ok_or(Error::MissingCigar)?;
+        let invalid = || Error::InvalidCigar(cigar.to_string());
+        let mut operations = Vec::new();
+        for op in &cigar.0 {
+            let operation = match op {
+                Cigar::Match(_) | Cigar::Equal(_) => AlignmentOperation::Match,
+                Cigar::Diff(_) => AlignmentOperation::Subst,
+                Cigar::Ins(_) => AlignmentOperation::Ins,
+                Cigar::Del(_) | Cigar::RefSkip(_) => AlignmentOperation::Del,
+                Cigar::SoftClip(_) | Cigar::HardClip(_) | Cigar::Pad(_) => return Err(invalid()),
+            };
+            for _ in 0..op.len() {
+                operations.push(operation);
+            }
+        }
+        let query_len = self.qend.checked_sub(self.qstart).ok_or_else(invalid)?;
+        let target_len = self.tend.checked_sub(self.tstart).ok_or_else(invalid)?;
+        if cigar.query_len() != query_len
+            || cigar.reference_len() != target_len
+            || self.qend > self.qlen
+        {
+            return Err(invalid());
+        }
+
+        let (xstart, xend) = match self.strand {
+            ReqStrand::Forward => (self.qstart, self.qend),
+            ReqStrand::Reverse => (self.qlen - self.qend, self.qlen - self.qstart),
+        };
+        let score = match self.tag("AS") {
+            Some(TagValue::Int(score)) => *score as i32,
+            _ => 0,
+        };
+        Ok(Alignment {
+            score,
+            xstart: xstart as usize,
+            xend: xend as usize,
+            xlen: self.qlen as usize,
+            ystart: self.tstart as usize,
+            yend: self.tend as usize,
+            ylen: self.tlen as usize,
+            operations,
+            mode: AlignmentMode::Local,
+        })
+    }
+
+    pub fn qname_mut(&mut self) -> &mut String {
+        &mut self.qname
+    }
+
+    pub fn qlen_mut(&mut self) -> &mut u64 {
+        &mut self.qlen
+    }
+
+    pub fn qstart_mut(&mut self) -> &mut u64 {
+        &mut self.qstart
+    }
+
+    pub fn qend_mut(&mut self) -> &mut u64 {
+        &mut self.qend
+    }
+
+    pub fn strand_mut(&mut self) -> &mut ReqStrand {
+        &mut self.strand
+    }
+
+    pub fn tname_mut(&mut self) -> &mut String {
+        &mut self.tname
+    }
+
+    pub fn tlen_mut(&mut self) -> &mut u64 {
+        &mut self.tlen
+    }
+
+    pub fn tstart_mut(&mut self) -> &mut u64 {
+        &mut self.tstart
+    }
+
+    pub fn tend_mut(&mut self) -> &mut u64 {
+        &mut self.tend
+    }
+
+    pub fn matches_mut(&mut self) -> &mut u64 {
+        &mut self.matches
+    }
+
+    pub fn block_len_mut(&mut self) -> &mut u64 {
+        &mut self.block_len
+    }
+
+    pub fn mapq_mut(&mut self) -> &mut u8 {
+        &mut self.mapq
+    }
+}
+
+impl FromStr for Record {
+    type Err = String;
+
+    /// Parse a record from a PAF line without line terminator.
+    fn from_str(line: &str) -> std::result::Result<Self, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 12 {
+            return Err(format!(
+                "expected at least 12 tab separated fields, found {}",
+                fields.len()
+            ));
+        }
+        fn number<T: FromStr>(value: &str, name: &str) -> std::result::Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid {}: {}", name, value))
+        }
+
+        let mut record = Record {
+            qname: fields[0].to_owned(),
+            qlen: number(fields[1], "query length")?,
+            qstart: number(fields[2], "query start")?,
+            qend: number(fields[3], "query end")?,
+            strand: match fields[4] {
+                "+" => ReqStrand::Forward,
+                "-" => ReqStrand::Reverse,
+                strand => return Err(format!("invalid strand: {}", strand)),
+            },
+            tname: fields[5].to_owned(),
+            tlen: number(fields[6], "target length")?,
+            tstart: number(fields[7], "target start")?,
+            tend: number(fields[8], "target end")?,
+            matches: number(fields[9], "number of matches")?,
+            block_len: number(fields[10], "alignment block length")?,
+            mapq: number(fields[11], "mapping quality")?,
+            tags: Vec::new(),
+        };
+        if record.qname.is_empty() {
+            return Err("empty query name".to_owned());
+        }
+        for field in &fields[12..] {
+            match parse_tag(field) {
+                Some(tag) => record.tags.push(tag),
+                None => return Err(format!("invalid optional field: {}", field)),
+            }
+        }
+        Ok(record)
+    }
+}
+
+impl fmt::Display for Record {
+    /// Format the record as a PAF line without line terminator.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.qname,
+            self.qlen,
+            self.qstart,
+            self.qend,
+            self.strand.strand_symbol(),
+            self.tname,
+            self.tlen,
+            self.tstart,
+            self.tend,
+            self.matches,
+            self.block_len,
+            self.mapq
+        )?;
+        for (tag, value) in &self.tags {
+            write!(f, "\t{}:{}", tag, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A PAF reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read PAF from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// Read the next record into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line has too few fields or a field can't be
+    /// parsed.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                *record = Record::new();
+                return Ok(());
+            }
+            self.line_no += 1;
+            let line = self.line.trim_end_matches(&['\n', '\r'][..]);
+            if line.is_empty() {
+                continue;
+            }
+            *record = line.parse().map_err(|message| Error::Format {
+                line: self.line_no,
+                message,
+            })?;
+            return Ok(());
+        }
+    }
+
+    /// Return an iterator over the records of this PAF file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the records of a PAF file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::new();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A PAF writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write a record.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alignment::pairwise::{Aligner, Scoring};
+    use crate::alphabets::dna;
+
+    const PAF_FILE: &[u8] = b"read1\t20\t2\t18\t-\tchr1\t100\t40\t56\t14\t17\t60\tNM:i:3\tAS:i:20\ttp:A:P\tcg:Z:5M1D6M1I4M
+read2\t50\t0\t50\t+\tchr2\t1000\t10\t60\t50\t50\t255\ttp:A:S
+
+read3\t10\t0\t10\t+\tchr2\t1000\t0\t10\t10\t10\t0\tcg:Z:10=
+";
+
+    #[test]
+    fn test_read() {
+        let records: Vec<Record> = Reader::new(PAF_FILE)
+            .records()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        let record = &records[0];
+        assert_eq!(record.qname(), "read1");
+        assert_eq!((record.qstart(), record.qend()), (2, 18));
+        assert_eq!(record.strand(), ReqStrand::Reverse);
+        assert_eq!(
+            (record.tname(), record.tstart(), record.tend()),
+            ("chr1", 40, 56)
+        );
+        assert_eq!(
+            (record.matches(), record.block_len(), record.mapq()),
+            (14, 17, 60)
+        );
+        assert_eq!(record.edit_distance(), Some(3));
+        assert_eq!(record.alignment_type(), Some(AlignmentType::Primary));
+        assert_eq!(record.cigar().unwrap().unwrap().to_string(), "5M1D6M1I4M");
+        assert_eq!(records[1].alignment_type(), Some(AlignmentType::Secondary));
+        assert_eq!(records[1].cigar().unwrap(), None);
+        assert!(matches!(records[1].alignment(), Err(Error::MissingCigar)));
+
+        let alignment = record.alignment().unwrap();
+        assert_eq!((alignment.xstart, alignment.xend), (2, 18));
+        assert_eq!((alignment.ystart, alignment.yend), (40, 56));
+        assert_eq!(alignment.operations.len(), 17);
+        assert_eq!(alignment.score, 20);
+
+        for paf in &[
+            &b"read1\t20\t18\t2\t-\tchr1\t100\t40\t56\t14\t16\t60\tcg:Z:16M\n"[..],
+            &b"read1\t20\t2\t18\t+\tchr1\t100\t56\t40\t14\t16\t60\tcg:Z:16M\n"[..],
+        ] {
+            let record = Reader::new(*paf).records().next().unwrap().unwrap();
+            assert!(matches!(record.alignment(), Err(Error::InvalidCigar(_))));
+        }
+
+        let mut reader = Reader::new(&b"read1\t20\t2\t18\t*\tchr1\t100\t40\t56\t14\t17\t60\n"[..]);
+        assert!(matches!(
+            reader.read(&mut Record::new()),
+            Err(Error::Format { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut writer = Writer::new(Vec::new());
+        for record in Reader::new(PAF_FILE).records() {
+            writer.write(&record.unwrap()).unwrap();
+        }
+        let output = writer.into_inner().unwrap();
+        let expected: Vec<u8> = PAF_FILE
+            .split(|&c| c == b'\n')
+            .filter(|line| !line.is_empty())
+            .flat_map(|line| line.iter().chain(b"\n"))
+            .copied()
+            .collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_alignment_rescoring() {
+        let query = b"TTACGTAGGCATCAG";
+        let target = b"GGGGCTGATGCCTACGTAAGGGG";
+        let revcomp = dna::revcomp(&query[..]);
+        let scoring = Scoring::from_scores(-3, -1, 2, -2);
+        let mut aligner = Aligner::with_scoring(scoring.clone());
+        let alignment = aligner.local(&revcomp, target);
+
+        let record = Record::from_alignment(&alignment, "q", "t", ReqStrand::Reverse);
+        assert_eq!(record.qlen(), query.len() as u64);
+        assert_eq!(
+            record.qend() - record.qstart(),
+            (alignment.xend - alignment.xstart) as u64
+        );
+        assert_eq!(record.qstart(), (query.len() - alignment.xend) as u64);
+
+        let parsed: Record = record.to_string().parse().unwrap();
+        assert_eq!(parsed, record);
+        let converted = parsed.alignment().unwrap();
+        assert_eq!(converted.operations, alignment.operations);
+        assert_eq!(
+            (converted.xstart, converted.ystart),
+            (alignment.xstart, alignment.ystart)
+        );
+        assert_eq!(
+            scoring.score_alignment(&converted, &revcomp, target),
+            alignment.score
+        );
+    }
+}
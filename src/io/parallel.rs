@@ -0,0 +1,614 @@
+//! Multi-threaded parsing of FASTA and FastQ files.
+//!
+//! A single thread reads the input and splits it into chunks of whole records, which are then
+//! parsed, and optionally processed further, on a pool of worker threads.
+//! Results are handed out as batches of records, either in the order of the input
+//! ([`Order::Preserved`](Order::Preserved)) or as soon as they are ready
+//! ([`Order::Unordered`](Order::Unordered)). Batches are plain vectors, such that they can be
+//! passed on to e.g. `rayon` for further parallel processing.
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::parallel::{Order, Reader};
+//!
+//! let fq: &[u8] = b"@id1\nACGT\n+\nIIII\n@id2\nGGCCA\n+\nIIIII\n@id3\nTT\n+\nII\n";
+//! let batches = Reader::fastq(fq, 2)
+//!     .chunk_size(16)
+//!     .order(Order::Preserved)
+//!     .process(|records| records.iter().map(|r| r.seq().len()).sum::<usize>());
+//!
+//! let mut nb_bases = 0;
+//! for batch in batches {
+//!     nb_bases += batch.unwrap();
+//! }
+//! assert_eq!(nb_bases, 11);
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::prelude::*;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::io::{fasta, fastq};
+
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Order in which batches are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Batches are returned in the order of the input.
+    Preserved,
+    /// Batches are returned as soon as they are ready.
+    Unordered,
+}
+
+/// A record based file format that can be split into chunks and parsed in parallel.
+pub trait ChunkFormat {
+    type Record: Send + 'static;
+    type Error: From<io::Error> + Send + 'static;
+
+    /// Return the end of the last complete record in the given buffer, or `None` if the buffer
+    /// does not contain a complete record.
+    fn last_record_end(buf: &[u8]) -> Option<usize>;
+
+    /// Parse all records of the given chunk, which is preceded by `line` lines and `offset`
+    /// bytes of input. Positions reported in errors refer to the whole input.
+    fn parse(chunk: &[u8], line: usize, offset: u64) -> Result<Vec<Self::Record>, Self::Error>;
+}
+
+/// The FASTA format, yielding [`fasta::Record`](crate::io::fasta::Record)s.
+#[derive(Debug, Clone, Copy)]
+pub struct Fasta;
+
+impl ChunkFormat for Fasta {
+    type Record = fasta::Record;
+    type Error = fasta::Error;
+
+    fn last_record_end(buf: &[u8]) -> Option<usize> {
+        // a record ends right before the next header
+        buf.windows(2).rposition(|w| w == b"\n>").map(|pos| pos + 1)
+    }
+
+    fn parse(chunk: &[u8], line: usize, offset: u64) -> fasta::Result<Vec<fasta::Record>> {
+        fasta::Reader::from_bufread(chunk)
+            .records()
+            .collect::<fasta::Result<_>>()
+            .map_err(|mut err| {
+                match &mut err {
+                    fasta::Error::MissingHeader {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fasta::Error::InvalidHeader {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fasta::Error::InvalidSequence {
+                        line: err_line,
+                        offset: err_offset,
+                    } => {
+                        *err_line += line;
+                        *err_offset += offset;
+                    }
+                    _ => (),
+                }
+                err
+            })
+    }
+}
+
+/// The FastQ format, yielding [`fastq::Record`](crate::io::fastq::Record)s.
+/// Record boundaries are determined like in [`fastq::Reader`](crate::io::fastq::Reader), hence
+/// wrapped records are supported.
+#[derive(Debug, Clone, Copy)]
+pub struct Fastq;
+
+impl ChunkFormat for Fastq {
+    type Record = fastq::Record;
+    type Error = fastq::Error;
+
+    fn last_record_end(buf: &[u8]) -> Option<usize> {
+        let mut end = 0;
+        let mut lines = Lines { buf, pos: 0 };
+        'records: loop {
+            // header
+            if lines.next().is_none() {
+                break;
+            }
+            let mut seq_len = 0;
+            let mut seq_lines = 0;
+            loop {
+                match lines.next() {
+                    Some(line) if line.starts_with(b"+") => break,
+                    Some(line) => {
+                        seq_len += trimmed_len(line);
+                        seq_lines += 1;
+                    }
+                    None => break 'records,
+                }
+            }
+            if seq_lines <= 1 {
+                if lines.next().is_none() {
+                    break;
+                }
+            } else {
+                let mut qual_len = 0;
+                while qual_len < seq_len {
+                    match lines.next() {
+                        Some(line) => qual_len += trimmed_len(line),
+                        None => break 'records,
+                    }
+                }
+            }
+            end = lines.pos;
+        }
+
+        if end > 0 {
+            Some(end)
+        } else {
+            None
+        }
+    }
+
+    fn parse(chunk: &[u8], line: usize, offset: u64) -> fastq::Result<Vec<fastq::Record>> {
+        fastq::Reader::from_bufread(chunk)
+            .records()
+            .collect::<fastq::Result<_>>()
+            .map_err(|mut err| {
+                match &mut err {
+                    fastq::Error::MissingAt {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fastq::Error::IncompleteRecord {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fastq::Error::LengthMismatch {
+                        line: err_line,
+                        offset: err_offset,
+                        ..
+                    }
+                    | fastq::Error::InvalidHeader {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fastq::Error::InvalidSequence {
+                        line: err_line,
+                        offset: err_offset,
+                    }
+                    | fastq::Error::InvalidQuality {
+                        line: err_line,
+                        offset: err_offset,
+                    } => {
+                        *err_line += line;
+                        *err_offset += offset;
+                    }
+                    _ => (),
+                }
+                err
+            })
+    }
+}
+
+/// Iterator over the complete (i.e. newline terminated) lines of a buffer.
+struct Lines<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let len = self.buf[self.pos..].iter().position(|&c| c == b'\n')? + 1;
+        let line = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Some(line)
+    }
+}
+
+fn trimmed_len(line: &[u8]) -> usize {
+    line.iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(0, |pos| pos + 1)
+}
+
+/// A chunk of whole records, along with its position in the input.
+struct Chunk {
+    index: usize,
+    line: usize,
+    offset: u64,
+    data: Vec<u8>,
+}
+
+/// A reader that parses FASTA or FastQ records on multiple threads.
+#[derive(Debug)]
+pub struct Reader<F, R> {
+    reader: R,
+    threads: usize,
+    chunk_size: usize,
+    order: Order,
+    format: PhantomData<F>,
+}
+
+impl<R: io::Read + Send + 'static> Reader<Fasta, R> {
+    /// Parse FASTA from the given reader with the given number of worker threads.
+    pub fn fasta(reader: R, threads: usize) -> Self {
+        Reader::new(reader, threads)
+    }
+}
+
+impl<R: io::Read + Send + 'static> Reader<Fastq, R> {
+    /// Parse FastQ from the given reader with the given number of worker threads.
+    pub fn fastq(reader: R, threads: usize) -> Self {
+        Reader::new(reader, threads)
+    }
+}
+
+impl<F: ChunkFormat + 'static, R: io::Read + Send + 'static> Reader<F, R> {
+    /// Parse records of format `F` from the given reader with the given number of worker
+    /// threads. Chunks have a size of 1 MiB and batches are returned in input order by default.
+    ///
+    /// # Panics
+    /// If `threads` is zero.
+    pub fn new(reader: R, threads: usize) -> Self {
+        assert!(threads > 0, "at least one worker thread is required");
+        Reader {
+            reader,
+            threads,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            order: Order::Preserved,
+            format: PhantomData,
+        }
+    }
+
+    /// Set the approximate size of the chunks handed to the workers in bytes.
+    /// Chunks are extended to the end of the last record they contain, and grow as needed to
+    /// hold at least one record.
+    ///
+    /// # Panics
+    /// If `chunk_size` is zero.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Set the order in which batches are returned.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Return an iterator over batches of parsed records, one batch per chunk.
+    pub fn batches(self) -> Batches<Vec<F::Record>, F::Error> {
+        self.process(|records| records)
+    }
+
+    /// Parse the input, and apply the given function to each batch of records on the worker
+    /// threads. Return an iterator over the results.
+    ///
+    /// At most four chunks per thread are in flight at a time, i.e. read but not yet returned
+    /// by the iterator, such that a slow chunk does not let the results of later chunks pile
+    /// up in memory. If parsing or processing a chunk panics, an error is returned in its
+    /// place.
+    pub fn process<T, P>(self, processor: P) -> Batches<T, F::Error>
+    where
+        T: Send + 'static,
+        P: Fn(Vec<F::Record>) -> T + Send + Sync + 'static,
+    {
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel::<Chunk>(self.threads);
+        let (result_tx, result_rx) = mpsc::sync_channel(self.threads * 2);
+        let in_flight = self.threads * 4;
+        let (token_tx, token_rx) = mpsc::sync_channel(in_flight);
+        for _ in 0..in_flight {
+            token_tx.send(()).unwrap();
+        }
+
+        let chunk_rx = Arc::new(Mutex::new(chunk_rx));
+        let processor = Arc::new(processor);
+        for _ in 0..self.threads {
+            let chunk_rx = Arc::clone(&chunk_rx);
+            let result_tx = result_tx.clone();
+            let processor = Arc::clone(&processor);
+            thread::spawn(move || loop {
+                let next = chunk_rx.lock().unwrap().recv();
+                let chunk = match next {
+                    Ok(chunk) => chunk,
+                    // all chunks have been processed
+                    Err(_) => break,
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    F::parse(&chunk.data, chunk.line, chunk.offset).map(&*processor)
+                }))
+                .unwrap_or_else(|_| Err(panicked("processing", chunk.index)));
+                if result_tx.send((chunk.index, result)).is_err() {
+                    // the consumer is gone
+                    break;
+                }
+            });
+        }
+
+        let (reader, chunk_size) = (self.reader, self.chunk_size);
+        thread::spawn(move || {
+            let mut index = 0;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                split_chunks::<F, R>(reader, chunk_size, &chunk_tx, &token_rx, &mut index)
+            }));
+            let err = match result {
+                Ok(Ok(())) => return,
+                Ok(Err(err)) => F::Error::from(err),
+                Err(_) => panicked("reading", index),
+            };
+            let _ = result_tx.send((index, Err(err)));
+        });
+
+        Batches {
+            receiver: result_rx,
+            tokens: token_tx,
+            order: self.order,
+            next_index: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+/// The error reported in place of a chunk whose reading or processing panicked.
+fn panicked<E: From<io::Error>>(stage: &str, index: usize) -> E {
+    chunk_error(format!("panicked while {} chunk {}", stage, index))
+}
+
+#[allow(clippy::io_other_error)]
+fn chunk_error<E: From<io::Error>>(message: String) -> E {
+    E::from(io::Error::new(io::ErrorKind::Other, message))
+}
+
+/// Read the input and send it to the workers in chunks of whole records, each of which
+/// requires a token. `index` is the index of the current chunk, i.e. of the failed chunk on
+/// failure.
+fn split_chunks<F: ChunkFormat, R: io::Read>(
+    mut reader: R,
+    chunk_size: usize,
+    chunk_tx: &mpsc::SyncSender<Chunk>,
+    tokens: &mpsc::Receiver<()>,
+    index: &mut usize,
+) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(chunk_size);
+    let mut target = chunk_size;
+    let (mut line, mut offset) = (0, 0);
+    let mut eof = false;
+
+    loop {
+        if !eof {
+            let wanted = target.saturating_sub(buf.len()) as u64;
+            let read = (&mut reader).take(wanted).read_to_end(&mut buf)?;
+            eof = (read as u64) < wanted;
+        }
+
+        let end = if eof {
+            buf.len()
+        } else {
+            match F::last_record_end(&buf) {
+                Some(end) => end,
+                None => {
+                    // the record does not fit into the chunk
+                    target = buf.len() * 2;
+                    continue;
+                }
+            }
+        };
+        if end == 0 {
+            return Ok(());
+        }
+
+        let rest = buf.split_off(end);
+        let data = mem::replace(&mut buf, rest);
+        let (lines, len) = (data.iter().filter(|&&c| c == b'\n').count(), data.len());
+        let chunk = Chunk {
+            index: *index,
+            line,
+            offset,
+            data,
+        };
+        if tokens.recv().is_err() || chunk_tx.send(chunk).is_err() {
+            // the consumer or the workers are gone
+            return Ok(());
+        }
+        *index += 1;
+        line += lines;
+        offset += len as u64;
+        target = chunk_size;
+    }
+}
+
+/// An iterator over the results of a parallel [`Reader`](Reader), one per chunk of records.
+#[derive(Debug)]
+pub struct Batches<T, E> {
+    receiver: mpsc::Receiver<(usize, Result<T, E>)>,
+    tokens: mpsc::SyncSender<()>,
+    order: Order,
+    next_index: usize,
+    pending: BTreeMap<usize, Result<T, E>>,
+}
+
+impl<T, E: From<io::Error>> Iterator for Batches<T, E> {
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Result<T, E>> {
+        let result = if self.order == Order::Unordered {
+            self.receiver.recv().ok().map(|(_, result)| result)
+        } else {
+            self.next_in_order()
+        };
+        // allow the next chunk to be read
+        let _ = self.tokens.try_send(());
+        result
+    }
+}
+
+impl<T, E: From<io::Error>> Batches<T, E> {
+    fn next_in_order(&mut self) -> Option<Result<T, E>> {
+        loop {
+            if let Some(result) = self.pending.remove(&self.next_index) {
+                self.next_index += 1;
+                return Some(result);
+            }
+            match self.receiver.recv() {
+                Ok((index, result)) => {
+                    self.pending.insert(index, result);
+                }
+                // all senders are gone, but later chunks are pending: report the missing one
+                Err(_) => {
+                    self.pending.keys().next()?;
+                    self.next_index += 1;
+                    return Some(Err(chunk_error(format!(
+                        "chunk {} was not processed",
+                        self.next_index - 1
+                    ))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FASTQ_FILE: &[u8] = b"@id1 desc\nACGT\n+\nIIII\n@id2\nACGTAC\nGTA\n+\n@IIIII\n@II\n\
+@id3\nGGGGGGGGGG\n+\nJJJJJJJJJJ\n@id4\nT\n+\n!\n";
+    const FASTA_FILE: &[u8] = b">id1 desc\nACGT\nAC\n>id2\nGGGGGGGGGGGG\n>id3\n\n>id4\nT\n";
+
+    fn sequential_fastq() -> Vec<fastq::Record> {
+        fastq::Reader::new(FASTQ_FILE)
+            .records()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_fastq_preserved_order() {
+        for chunk_size in &[1, 7, 30, 1000] {
+            for threads in &[1, 3] {
+                let records: Vec<fastq::Record> = Reader::fastq(FASTQ_FILE, *threads)
+                    .chunk_size(*chunk_size)
+                    .batches()
+                    .flat_map(|batch| batch.unwrap())
+                    .collect();
+                assert_eq!(records, sequential_fastq());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fastq_unordered() {
+        let mut records: Vec<fastq::Record> = Reader::fastq(FASTQ_FILE, 4)
+            .chunk_size(1)
+            .order(Order::Unordered)
+            .batches()
+            .flat_map(|batch| batch.unwrap())
+            .collect();
+        records.sort_by(|a, b| a.id().cmp(b.id()));
+        assert_eq!(records, sequential_fastq());
+    }
+
+    #[test]
+    fn test_fasta_preserved_order() {
+        let expected: Vec<String> = fasta::Reader::new(FASTA_FILE)
+            .records()
+            .map(|r| r.unwrap().to_string())
+            .collect();
+        for chunk_size in &[1, 10, 1000] {
+            let records: Vec<String> = Reader::fasta(FASTA_FILE, 2)
+                .chunk_size(*chunk_size)
+                .process(|records| records.iter().map(|r| r.to_string()).collect::<Vec<_>>())
+                .flat_map(|batch| batch.unwrap())
+                .collect();
+            assert_eq!(records, expected);
+        }
+    }
+
+    #[test]
+    fn test_fastq_error() {
+        let fq: &[u8] = b"@id1\nACGT\n+\nIIII\nid2\nACGT\n+\nIIII\n";
+        let batches: Vec<_> = Reader::fastq(fq, 2).chunk_size(1).batches().collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].as_ref().unwrap().len(), 1);
+        assert!(matches!(
+            batches[1],
+            Err(fastq::Error::MissingAt {
+                line: 5,
+                offset: 17
+            })
+        ));
+    }
+
+    #[test]
+    fn test_fasta_error_position() {
+        let fa: &[u8] = b">id1\nACGT\n>\xffid2\nACGT\n";
+        for chunk_size in &[1, 1000] {
+            let err = Reader::fasta(fa, 2)
+                .chunk_size(*chunk_size)
+                .batches()
+                .find_map(|batch| batch.err())
+                .unwrap();
+            assert!(matches!(
+                err,
+                fasta::Error::InvalidHeader {
+                    line: 3,
+                    offset: 10
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_processor_panic() {
+        for order in &[Order::Preserved, Order::Unordered] {
+            let results: Vec<_> = Reader::fastq(FASTQ_FILE, 2)
+                .chunk_size(1)
+                .order(*order)
+                .process(|records| {
+                    assert_ne!(records[0].id(), "id2");
+                    records
+                })
+                .collect();
+            let records: usize = results.iter().flatten().map(|batch| batch.len()).sum();
+            assert_eq!(records, 3);
+            assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+            if *order == Order::Preserved {
+                assert!(matches!(results[1], Err(fastq::Error::ReadError(_))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(Reader::fastq(&b""[..], 2).batches().count(), 0);
+        assert_eq!(Reader::fasta(&b""[..], 2).batches().count(), 0);
+    }
+
+    #[test]
+    fn test_fastq_last_record_end() {
+        assert_eq!(
+            Fastq::last_record_end(b"@id1\nACGT\n+\nIIII\n@id2\nAC"),
+            Some(17)
+        );
+        assert_eq!(Fastq::last_record_end(b"@id1\nACGT\n+\nIII"), None);
+        assert_eq!(
+            Fastq::last_record_end(b"@id\nAC\nGT\n+\n@I\nII\n"),
+            Some(18)
+        );
+        assert_eq!(
+            Fastq::last_record_end(b"@r1\nACGTACGTAC\n+\nIIII\n@r2\nGG\n+\nII\n@r3"),
+            Some(34)
+        );
+    }
+}
@@ -0,0 +1,1343 @@
+//! Reading and writing of alignments in the text based [SAM] format.
+//!
+//! The header is parsed into typed `@HD`, `@SQ`, `@RG`, `@PG` and `@CO` lines, records keep
+//! their optional fields as typed [`TagValue`](TagValue)s.
+//! Pairwise alignments computed by this crate can be converted into records with
+//! [`Record::from_alignment`](Record::from_alignment).
+//!
+//! [SAM]: https://samtools.github.io/hts-specs/SAMv1.pdf
+//!
+//! # Example
+//!
+//! ```
+//! use bio::alignment::pairwise::Aligner;
+//! use bio::io::{fastq, sam};
+//!
+//! let reference = b"ACCGTGGATGGGCGCCATAG";
+//! let read = fastq::Record::with_attrs("read1", None, b"GTGGATCGGC", b"IIIIIIIIII");
+//!
+//! let score = |a: u8, b: u8| if a == b { 1i32 } else { -1i32 };
+//! let mut aligner = Aligner::new(-5, -1, &score);
+//! let alignment = aligner.semiglobal(read.seq(), reference);
+//!
+//! let record = sam::Record::from_alignment(&alignment, &read, "ref", reference);
+//! assert_eq!(record.pos(), 4);
+//! assert_eq!(record.cigar().to_string(), "6=1X3=");
+//! assert_eq!(record.tag("MD"), Some(&sam::TagValue::String("6G3".to_owned())));
+//!
+//! let mut header = sam::Header::new();
+//! header.references.push(sam::ReferenceSequence::new("ref", reference.len() as u64));
+//! let mut writer = sam::Writer::new(Vec::new());
+//! writer.write_header(&header).unwrap();
+//! writer.write(&record).unwrap();
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::alignment::{Alignment, AlignmentOperation};
+use crate::io::compression::Decoder;
+use crate::io::fastq;
+use crate::utils::TextSlice;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("invalid CIGAR string: {0}")]
+    InvalidCigar(String),
+
+    #[error("invalid optional field: {0}")]
+    InvalidTag(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Bitwise flags of SAM records.
+pub mod flags {
+    /// The template has multiple segments.
+    pub const PAIRED: u16 = 0x1;
+    /// Each segment is properly aligned.
+    pub const PROPER_PAIR: u16 = 0x2;
+    /// The segment is unmapped.
+    pub const UNMAPPED: u16 = 0x4;
+    /// The next segment in the template is unmapped.
+    pub const MATE_UNMAPPED: u16 = 0x8;
+    /// The sequence is reverse complemented.
+    pub const REVERSE: u16 = 0x10;
+    /// The sequence of the next segment is reverse complemented.
+    pub const MATE_REVERSE: u16 = 0x20;
+    /// The first segment in the template.
+    pub const FIRST_IN_PAIR: u16 = 0x40;
+    /// The last segment in the template.
+    pub const SECOND_IN_PAIR: u16 = 0x80;
+    /// A secondary alignment.
+    pub const SECONDARY: u16 = 0x100;
+    /// The read fails platform or vendor quality checks.
+    pub const QC_FAIL: u16 = 0x200;
+    /// The read is a PCR or optical duplicate.
+    pub const DUPLICATE: u16 = 0x400;
+    /// A supplementary alignment.
+    pub const SUPPLEMENTARY: u16 = 0x800;
+}
+
+/// A reference sequence of the header (`@SQ` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceSequence {
+    /// The reference sequence name (`SN`).
+    pub name: String,
+    /// The reference sequence length (`LN`).
+    pub len: u64,
+    /// Further fields, e.g. `M5` or `UR`.
+    pub tags: Vec<(String, String)>,
+}
+
+impl ReferenceSequence {
+    /// Create a new reference sequence without further fields.
+    pub fn new(name: &str, len: u64) -> Self {
+        ReferenceSequence {
+            name: name.to_owned(),
+            len,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// A read group of the header (`@RG` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadGroup {
+    /// The read group identifier (`ID`).
+    pub id: String,
+    /// Further fields, e.g. `SM` or `PL`.
+    pub tags: Vec<(String, String)>,
+}
+
+impl ReadGroup {
+    /// Return the value of the field with the given tag, e.g. `SM`.
+    pub fn tag(&self, tag: &str) -> Option<&str> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+/// A program of the header (`@PG` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    /// The program record identifier (`ID`).
+    pub id: String,
+    /// Further fields, e.g. `PN`, `VN`, `CL` or `PP`.
+    pub tags: Vec<(String, String)>,
+}
+
+impl Program {
+    /// Return the value of the field with the given tag, e.g. `CL`.
+    pub fn tag(&self, tag: &str) -> Option<&str> {
+        find_tag(&self.tags, tag)
+    }
+}
+
+fn find_tag<'a>(tags: &'a [(String, String)], tag: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(t, _)| t == tag)
+        .map(|(_, value)| value.as_str())
+}
+
+/// A SAM header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    /// The format version of the `@HD` line (`VN`).
+    pub version: Option<String>,
+    /// The sorting order of the `@HD` line (`SO`).
+    pub sort_order: Option<String>,
+    /// Further fields of the `@HD` line, e.g. `GO`.
+    pub hd_tags: Vec<(String, String)>,
+    pub references: Vec<ReferenceSequence>,
+    pub read_groups: Vec<ReadGroup>,
+    pub programs: Vec<Program>,
+    /// The text of `@CO` lines.
+    pub comments: Vec<String>,
+}
+
+impl Header {
+    /// Create a new, empty header.
+    pub fn new() -> Self {
+        Header::default()
+    }
+
+    /// Return the reference sequence with the given name.
+    pub fn reference(&self, name: &str) -> Option<&ReferenceSequence> {
+        self.references.iter().find(|r| r.name == name)
+    }
+
+    /// Parse a header line (starting with `@`) and add it to this header.
+    fn push_line(&mut self, line: &str) -> std::result::Result<(), String> {
+        let mut fields = line.split('\t');
+        let kind = fields.next().unwrap_or("");
+        if kind == "@CO" {
+            self.comments.push(line.get(4..).unwrap_or("").to_owned());
+            return Ok(());
+        }
+
+        let mut tags = Vec::new();
+        for field in fields {
+            match (field.get(..2), field.get(2..3), field.get(3..)) {
+                (Some(tag), Some(":"), Some(value)) => {
+                    tags.push((tag.to_owned(), value.to_owned()))
+                }
+                _ => return Err(format!("invalid header field: {}", field)),
+            }
+        }
+        let mut take = |tag: &str| {
+            tags.iter()
+                .position(|(t, _)| t == tag)
+                .map(|i| tags.remove(i).1)
+        };
+        match kind {
+            "@HD" => {
+                self.version = take("VN");
+                self.sort_order = take("SO");
+                self.hd_tags = tags;
+            }
+            "@SQ" => {
+                let name = take("SN").ok_or("missing SN field in @SQ line")?;
+                let len = take("LN")
+                    .ok_or("missing LN field in @SQ line")?
+                    .parse()
+                    .map_err(|_| "invalid LN field in @SQ line")?;
+                self.references.push(ReferenceSequence { name, len, tags });
+            }
+            "@RG" => {
+                let id = take("ID").ok_or("missing ID field in @RG line")?;
+                self.read_groups.push(ReadGroup { id, tags });
+            }
+            "@PG" => {
+                let id = take("ID").ok_or("missing ID field in @PG line")?;
+                self.programs.push(Program { id, tags });
+            }
+            _ => return Err(format!("unknown header record type {}", kind)),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Header {
+    /// Format the header as SAM header lines, each terminated by a newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_tags(f: &mut fmt::Formatter<'_>, tags: &[(String, String)]) -> fmt::Result {
+            for (tag, value) in tags {
+                write!(f, "\t{}:{}", tag, value)?;
+            }
+            writeln!(f)
+        }
+
+        if self.version.is_some() || self.sort_order.is_some() || !self.hd_tags.is_empty() {
+            write!(f, "@HD")?;
+            if let Some(version) = &self.version {
+                write!(f, "\tVN:{}", version)?;
+            }
+            if let Some(sort_order) = &self.sort_order {
+                write!(f, "\tSO:{}", sort_order)?;
+            }
+            write_tags(f, &self.hd_tags)?;
+        }
+        for reference in &self.references {
+            write!(f, "@SQ\tSN:{}\tLN:{}", reference.name, reference.len)?;
+            write_tags(f, &reference.tags)?;
+        }
+        for read_group in &self.read_groups {
+            write!(f, "@RG\tID:{}", read_group.id)?;
+            write_tags(f, &read_group.tags)?;
+        }
+        for program in &self.programs {
+            write!(f, "@PG\tID:{}", program.id)?;
+            write_tags(f, &program.tags)?;
+        }
+        for comment in &self.comments {
+            writeln!(f, "@CO\t{}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+/// A CIGAR operation with its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cigar {
+    /// Alignment match, either sequence match or mismatch (`M`).
+    Match(u32),
+    /// Insertion to the reference (`I`).
+    Ins(u32),
+    /// Deletion from the reference (`D`).
+    Del(u32),
+    /// Skipped region of the reference (`N`).
+    RefSkip(u32),
+    /// Soft clipping, the clipped bases are present in the sequence (`S`).
+    SoftClip(u32),
+    /// Hard clipping, the clipped bases are not present in the sequence (`H`).
+    HardClip(u32),
+    /// Padding, silent deletion from the padded reference (`P`).
+    Pad(u32),
+    /// Sequence match (`=`).
+    Equal(u32),
+    /// Sequence mismatch (`X`).
+    Diff(u32),
+}
+
+impl Cigar {
+    /// The length of the operation.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(self) -> u32 {
+        match self {
+            Cigar::Match(len)
+            | Cigar::Ins(len)
+            | Cigar::Del(len)
+            | Cigar::RefSkip(len)
+            | Cigar::SoftClip(len)
+            | Cigar::HardClip(len)
+            | Cigar::Pad(len)
+            | Cigar::Equal(len)
+            | Cigar::Diff(len) => len,
+        }
+    }
+
+    /// The character representing the operation.
+    pub fn char(self) -> char {
+        match self {
+            Cigar::Match(_) => 'M',
+            Cigar::Ins(_) => 'I',
+            Cigar::Del(_) => 'D',
+            Cigar::RefSkip(_) => 'N',
+            Cigar::SoftClip(_) => 'S',
+            Cigar::HardClip(_) => 'H',
+            Cigar::Pad(_) => 'P',
+            Cigar::Equal(_) => '=',
+            Cigar::Diff(_) => 'X',
+        }
+    }
+
+    fn new(op: char, len: u32) -> Option<Self> {
+        Some(match op {
+            'M' => Cigar::Match(len),
+            'I' => Cigar::Ins(len),
+            'D' => Cigar::Del(len),
+            'N' => Cigar::RefSkip(len),
+            'S' => Cigar::SoftClip(len),
+            'H' => Cigar::HardClip(len),
+            'P' => Cigar::Pad(len),
+            '=' => Cigar::Equal(len),
+            'X' => Cigar::Diff(len),
+            _ => return None,
+        })
+    }
+
+    /// Return true if the operation consumes bases of the query sequence.
+    pub fn consumes_query(self) -> bool {
+        matches!(
+            self,
+            Cigar::Match(_) | Cigar::Ins(_) | Cigar::SoftClip(_) | Cigar::Equal(_) | Cigar::Diff(_)
+        )
+    }
+
+    /// Return true if the operation consumes bases of the reference sequence.
+    pub fn consumes_reference(self) -> bool {
+        matches!(
+            self,
+            Cigar::Match(_) | Cigar::Del(_) | Cigar::RefSkip(_) | Cigar::Equal(_) | Cigar::Diff(_)
+        )
+    }
+}
+
+/// A CIGAR string. An empty string represents an unavailable CIGAR (`*`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CigarString(pub Vec<Cigar>);
+
+impl CigarString {
+    /// The number of query bases covered by the CIGAR string, including soft clips.
+    pub fn query_len(&self) -> u64 {
+        self.0
+            .iter()
+            .filter(|op| op.consumes_query())
+            .map(|op| op.len() as u64)
+            .sum()
+    }
+
+    /// The number of reference bases covered by the CIGAR string.
+    pub fn reference_len(&self) -> u64 {
+        self.0
+            .iter()
+            .filter(|op| op.consumes_reference())
+            .map(|op| op.len() as u64)
+            .sum()
+    }
+
+    /// Append an operation, merging it with the last operation if that is of the same kind.
+    pub(crate) fn push(&mut self, op: Cigar) {
+        if let Some(last) = self.0.last_mut() {
+            if last.char() == op.char() {
+                *last = Cigar::new(op.char(), last.len() + op.len()).unwrap();
+                return;
+            }
+        }
+        self.0.push(op);
+    }
+}
+
+impl FromStr for CigarString {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ops = Vec::new();
+        if s == "*" {
+            return Ok(CigarString(ops));
+        }
+        let mut len_start = 0;
+        for (i, c) in s.char_indices() {
+            if c.is_ascii_digit() {
+                continue;
+            }
+            let op = s[len_start..i]
+                .parse()
+                .ok()
+                .and_then(|len| Cigar::new(c, len))
+                .ok_or_else(|| Error::InvalidCigar(s.to_owned()))?;
+            ops.push(op);
+            len_start = i + c.len_utf8();
+        }
+        if len_start != s.len() || ops.is_empty() {
+            return Err(Error::InvalidCigar(s.to_owned()));
+        }
+        Ok(CigarString(ops))
+    }
+}
+
+impl fmt::Display for CigarString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "*");
+        }
+        for op in &self.0 {
+            write!(f, "{}{}", op.len(), op.char())?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of an optional field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagValue {
+    /// A printable character (`A`).
+    Char(u8),
+    /// A signed integer (`i`).
+    Int(i64),
+    /// A single-precision float (`f`).
+    Float(f32),
+    /// A printable string (`Z`).
+    String(String),
+    /// A byte array in hex format (`H`).
+    Hex(String),
+    /// A numeric array (`B`).
+    Array(TagArray),
+}
+
+/// A numeric array of an optional field, typed by its element subtype.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagArray {
+    Int8(Vec<i8>),
+    UInt8(Vec<u8>),
+    Int16(Vec<i16>),
+    UInt16(Vec<u16>),
+    Int32(Vec<i32>),
+    UInt32(Vec<u32>),
+    Float(Vec<f32>),
+}
+
+/// Parse a comma separated list of array elements.
+fn parse_array<T: FromStr>(values: &[&str]) -> Option<Vec<T>> {
+    values.iter().map(|v| v.parse().ok()).collect()
+}
+
+fn write_array<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    subtype: char,
+    values: &[T],
+) -> fmt::Result {
+    write!(f, "B:{}", subtype)?;
+    for value in values {
+        write!(f, ",{}", value)?;
+    }
+    Ok(())
+}
+
+impl TagValue {
+    /// Parse a value of the given type.
+    fn parse(kind: &str, value: &str) -> Option<Self> {
+        Some(match kind {
+            "A" if value.len() == 1 => TagValue::Char(value.as_bytes()[0]),
+            "i" => TagValue::Int(value.parse().ok()?),
+            "f" => TagValue::Float(value.parse().ok()?),
+            "Z" => TagValue::String(value.to_owned()),
+            "H" => TagValue::Hex(value.to_owned()),
+            "B" => {
+                let mut elements = value.split(',');
+                let subtype = elements.next()?;
+                let values: Vec<&str> = elements.collect();
+                TagValue::Array(match subtype {
+                    "c" => TagArray::Int8(parse_array(&values)?),
+                    "C" => TagArray::UInt8(parse_array(&values)?),
+                    "s" => TagArray::Int16(parse_array(&values)?),
+                    "S" => TagArray::UInt16(parse_array(&values)?),
+                    "i" => TagArray::Int32(parse_array(&values)?),
+                    "I" => TagArray::UInt32(parse_array(&values)?),
+                    "f" => TagArray::Float(parse_array(&values)?),
+                    _ => return None,
+                })
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for TagValue {
+    /// Format the value with its type, e.g. `i:5`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagValue::Char(c) => write!(f, "A:{}", *c as char),
+            TagValue::Int(i) => write!(f, "i:{}", i),
+            TagValue::Float(v) => write!(f, "f:{}", v),
+            TagValue::String(s) => write!(f, "Z:{}", s),
+            TagValue::Hex(h) => write!(f, "H:{}", h),
+            TagValue::Array(TagArray::Int8(values)) => write_array(f, 'c', values),
+            TagValue::Array(TagArray::UInt8(values)) => write_array(f, 'C', values),
+            TagValue::Array(TagArray::Int16(values)) => write_array(f, 's', values),
+            TagValue::Array(TagArray::UInt16(values)) => write_array(f, 'S', values),
+            TagValue::Array(TagArray::Int32(values)) => write_array(f, 'i', values),
+            TagValue::Array(TagArray::UInt32(values)) => write_array(f, 'I', values),
+            TagValue::Array(TagArray::Float(values)) => write_array(f, 'f', values),
+        }
+    }
+}
+
+/// Check that a tag consists of a letter followed by a letter or digit.
+fn is_valid_tag(tag: &str) -> bool {
+    let tag = tag.as_bytes();
+    tag.len() == 2 && tag[0].is_ascii_alphabetic() && tag[1].is_ascii_alphanumeric()
+}
+
+/// Parse an optional field of the form `TAG:TYPE:VALUE`.
+pub(crate) fn parse_tag(field: &str) -> Option<(String, TagValue)> {
+    let mut parts = field.splitn(3, ':');
+    let tag = parts.next()?;
+    match (parts.next(), parts.next()) {
+        (Some(kind), Some(value)) if is_valid_tag(tag) => {
+            TagValue::parse(kind, value).map(|value| (tag.to_owned(), value))
+        }
+        _ => None,
+    }
+}
+
+/// A SAM record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    qname: String,
+    flags: u16,
+    rname: String,
+    pos: u64,
+    mapq: u8,
+    cigar: CigarString,
+    rnext: String,
+    pnext: u64,
+    tlen: i64,
+    seq: Vec<u8>,
+    qual: Vec<u8>,
+    tags: Vec<(String, TagValue)>,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Record {
+            qname: String::new(),
+            flags: 0,
+            rname: "*".to_owned(),
+            pos: 0,
+            mapq: 255,
+            cigar: CigarString::default(),
+            rnext: "*".to_owned(),
+            pnext: 0,
+            tlen: 0,
+            seq: Vec::new(),
+            qual: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl Record {
+    /// Create a new, empty record. Unavailable fields are set to `*` or 0, and the
+    /// mapping quality to 255 (unavailable).
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    /// Convert a pairwise alignment of the given query (x) against the given reference (y)
+    /// into a record. `reference` has to be the sequence the alignment was computed on.
+    ///
+    /// Clipped query bases are soft clipped, matches and mismatches are reported as `=`
+    /// and `X`. Deletions at the ends of the alignment are not reported but shift the
+    /// position. The edit distance (`NM`), mismatching positions (`MD`) and score (`AS`)
+    /// are added as optional fields. An alignment without any aligned base yields an
+    /// unmapped record. If the query has been reverse complemented before aligning, the
+    /// `REVERSE` flag has to be set by the caller.
+    pub fn from_alignment(
+        alignment: &Alignment,
+        query: &fastq::Record,
+        reference_name: &str,
+        reference: TextSlice<'_>,
+    ) -> Self {
+        let mut record = Record {
+            qname: query.id().to_owned(),
+            seq: query.seq().to_vec(),
+            qual: query.qual().to_vec(),
+            ..Record::new()
+        };
+
+        let ops: Vec<AlignmentOperation> = alignment
+            .operations
+            .iter()
+            .cloned()
+            .filter(|op| {
+                !matches!(
+                    op,
+                    AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_)
+                )
+            })
+            .collect();
+        let first = ops.iter().position(|op| *op != AlignmentOperation::Del);
+        let last = ops.iter().rposition(|op| *op != AlignmentOperation::Del);
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last))
+                if ops[first..=last]
+                    .iter()
+                    .any(|op| *op != AlignmentOperation::Ins) =>
+            {
+                (first, last)
+            }
+            _ => {
+                record.flags = flags::UNMAPPED;
+                return record;
+            }
+        };
+
+        record.rname = reference_name.to_owned();
+        record.pos = (alignment.ystart + first) as u64 + 1;
+
+        let mut cigar = CigarString::default();
+        if alignment.xstart > 0 {
+            cigar.push(Cigar::SoftClip(alignment.xstart as u32));
+        }
+        let mut md = String::new();
+        let mut md_matches = 0;
+        let mut edit_distance = 0;
+        let mut y = alignment.ystart + first;
+        let mut in_deletion = false;
+        for op in &ops[first..=last] {
+            match op {
+                AlignmentOperation::Match => {
+                    cigar.push(Cigar::Equal(1));
+                    md_matches += 1;
+                    y += 1;
+                }
+                AlignmentOperation::Subst => {
+                    cigar.push(Cigar::Diff(1));
+                    md.push_str(&md_matches.to_string());
+                    md.push(reference[y].to_ascii_uppercase() as char);
+                    md_matches = 0;
+                    edit_distance += 1;
+                    y += 1;
+                }
+                AlignmentOperation::Del => {
+                    cigar.push(Cigar::Del(1));
+                    if !in_deletion {
+                        md.push_str(&md_matches.to_string());
+                        md.push('^');
+                        md_matches = 0;
+                    }
+                    md.push(reference[y].to_ascii_uppercase() as char);
+                    edit_distance += 1;
+                    y += 1;
+                }
+                AlignmentOperation::Ins => {
+                    cigar.push(Cigar::Ins(1));
+                    edit_distance += 1;
+                }
+                _ => (),
+            }
+            in_deletion = *op == AlignmentOperation::Del;
+        }
+        md.push_str(&md_matches.to_string());
+        if alignment.xlen > alignment.xend {
+            cigar.push(Cigar::SoftClip((alignment.xlen - alignment.xend) as u32));
+        }
+
+        record.cigar = cigar;
+        record.push_tag("NM", TagValue::Int(edit_distance));
+        record.push_tag("MD", TagValue::String(md));
+        record.push_tag("AS", TagValue::Int(alignment.score as i64));
+        record
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.qname.is_empty()
+    }
+
+    /// The query template name.
+    pub fn qname(&self) -> &str {
+        &self.qname
+    }
+
+    /// The bitwise flags, see [`flags`](flags).
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// The reference sequence name, `*` if unavailable.
+    pub fn rname(&self) -> &str {
+        &self.rname
+    }
+
+    /// The 1-based leftmost mapping position, 0 if unavailable.
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// The mapping quality, 255 if unavailable.
+    pub fn mapq(&self) -> u8 {
+        self.mapq
+    }
+
+    pub fn cigar(&self) -> &CigarString {
+        &self.cigar
+    }
+
+    /// The reference sequence name of the next segment, `=` if identical to `rname`
+    /// and `*` if unavailable.
+    pub fn rnext(&self) -> &str {
+        &self.rnext
+    }
+
+    /// The 1-based position of the next segment, 0 if unavailable.
+    pub fn pnext(&self) -> u64 {
+        self.pnext
+    }
+
+    /// The observed template length.
+    pub fn tlen(&self) -> i64 {
+        self.tlen
+    }
+
+    /// The segment sequence, empty if unavailable.
+    pub fn seq(&self) -> TextSlice<'_> {
+        &self.seq
+    }
+
+    /// The base qualities (Phred+33), empty if unavailable.
+    pub fn qual(&self) -> &[u8] {
+        &self.qual
+    }
+
+    /// The optional fields in order of appearance.
+    pub fn tags(&self) -> &[(String, TagValue)] {
+        &self.tags
+    }
+
+    /// Return the value of the optional field with the given tag.
+    pub fn tag(&self, tag: &str) -> Option<&TagValue> {
+        self.tags
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, value)| value)
+    }
+
+    /// Set the optional field with the given tag, replacing an existing value.
+    pub fn push_tag(&mut self, tag: &str, value: TagValue) {
+        match self.tags.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, existing)) => *existing = value,
+            None => self.tags.push((tag.to_owned(), value)),
+        }
+    }
+
+    /// Remove the optional field with the given tag, returning its value.
+    pub fn remove_tag(&mut self, tag: &str) -> Option<TagValue> {
+        let i = self.tags.iter().position(|(t, _)| t == tag)?;
+        Some(self.tags.remove(i).1)
+    }
+
+    /// Return true if the segment is unmapped.
+    pub fn is_unmapped(&self) -> bool {
+        self.flags & flags::UNMAPPED != 0
+    }
+
+    /// Return true if the sequence is reverse complemented.
+    pub fn is_reverse(&self) -> bool {
+        self.flags & flags::REVERSE != 0
+    }
+
+    /// Return true if this is a secondary alignment.
+    pub fn is_secondary(&self) -> bool {
+        self.flags & flags::SECONDARY != 0
+    }
+
+    /// Return true if this is a supplementary alignment.
+    pub fn is_supplementary(&self) -> bool {
+        self.flags & flags::SUPPLEMENTARY != 0
+    }
+
+    pub fn qname_mut(&mut self) -> &mut String {
+        &mut self.qname
+    }
+
+    pub fn flags_mut(&mut self) -> &mut u16 {
+        &mut self.flags
+    }
+
+    pub fn rname_mut(&mut self) -> &mut String {
+        &mut self.rname
+    }
+
+    pub fn pos_mut(&mut self) -> &mut u64 {
+        &mut self.pos
+    }
+
+    pub fn mapq_mut(&mut self) -> &mut u8 {
+        &mut self.mapq
+    }
+
+    pub fn cigar_mut(&mut self) -> &mut CigarString {
+        &mut self.cigar
+    }
+
+    pub fn rnext_mut(&mut self) -> &mut String {
+        &mut self.rnext
+    }
+
+    pub fn pnext_mut(&mut self) -> &mut u64 {
+        &mut self.pnext
+    }
+
+    pub fn tlen_mut(&mut self) -> &mut i64 {
+        &mut self.tlen
+    }
+
+    pub fn seq_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.seq
+    }
+
+    pub fn qual_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.qual
+    }
+
+    /// Parse a record from a SAM line without line terminator.
+    fn parse(line: &str) -> std::result::Result<Self, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 11 {
+            return Err(format!(
+                "expected at least 11 tab separated fields, found {}",
+                fields.len()
+            ));
+        }
+        fn number<T: FromStr>(value: &str, name: &str) -> std::result::Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid {} field: {}", name, value))
+        }
+        fn bytes(value: &str) -> Vec<u8> {
+            if value == "*" {
+                Vec::new()
+            } else {
+                value.as_bytes().to_vec()
+            }
+        }
+
+        let mut record = Record {
+            qname: fields[0].to_owned(),
+            flags: number(fields[1], "FLAG")?,
+            rname: fields[2].to_owned(),
+            pos: number(fields[3], "POS")?,
+            mapq: number(fields[4], "MAPQ")?,
+            cigar: fields[5].parse().map_err(|e: Error| e.to_string())?,
+            rnext: fields[6].to_owned(),
+            pnext: number(fields[7], "PNEXT")?,
+            tlen: number(fields[8], "TLEN")?,
+            seq: bytes(fields[9]),
+            qual: bytes(fields[10]),
+            tags: Vec::new(),
+        };
+        if record.qname.is_empty() {
+            return Err("empty QNAME field".to_owned());
+        }
+        if !record.qual.is_empty() && record.qual.len() != record.seq.len() {
+            return Err(format!(
+                "QUAL has length {}, but SEQ has length {}",
+                record.qual.len(),
+                record.seq.len()
+            ));
+        }
+        for field in &fields[11..] {
+            match parse_tag(field) {
+                Some(tag) => record.tags.push(tag),
+                None => return Err(Error::InvalidTag((*field).to_owned()).to_string()),
+            }
+        }
+        Ok(record)
+    }
+}
+
+impl fmt::Display for Record {
+    /// Format the record as a SAM line without line terminator.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn bytes(value: &[u8]) -> std::borrow::Cow<'_, str> {
+            if value.is_empty() {
+                "*".into()
+            } else {
+                String::from_utf8_lossy(value)
+            }
+        }
+
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.qname,
+            self.flags,
+            self.rname,
+            self.pos,
+            self.mapq,
+            self.cigar,
+            self.rnext,
+            self.pnext,
+            self.tlen,
+            bytes(&self.seq),
+            bytes(&self.qual)
+        )?;
+        for (tag, value) in &self.tags {
+            write!(f, "\t{}:{}", tag, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A SAM reader. The header is read when the reader is created.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    header: Header,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .and_then(Reader::from_bufread)
+            .with_context(|| format!("Failed to read SAM from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Result<Self> {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`, reading the header.
+    pub fn from_bufread(bufreader: B) -> Result<Self> {
+        let mut reader = Reader {
+            reader: bufreader,
+            header: Header::new(),
+            line: String::new(),
+            line_no: 0,
+        };
+        while reader.reader.fill_buf()?.first() == Some(&b'@') {
+            reader.next_line()?;
+            let line_no = reader.line_no;
+            reader
+                .header
+                .push_line(&reader.line)
+                .map_err(|message| Error::Format {
+                    line: line_no,
+                    message,
+                })?;
+        }
+        Ok(reader)
+    }
+
+    /// The header of the SAM file.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+        let trimmed = self.line.trim_end_matches(&['\n', '\r'][..]).len();
+        self.line.truncate(trimmed);
+        Ok(true)
+    }
+
+    /// Read the next record into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line has too few fields, a field can't be
+    /// parsed or the qualities don't match the sequence length.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            if !self.next_line()? {
+                *record = Record::new();
+                return Ok(());
+            }
+            if !self.line.is_empty() {
+                break;
+            }
+        }
+        *record = Record::parse(&self.line).map_err(|message| Error::Format {
+            line: self.line_no,
+            message,
+        })?;
+        Ok(())
+    }
+
+    /// Return an iterator over the records of this SAM file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the records of a SAM file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::new();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A SAM writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write the header. This has to happen before writing any record.
+    pub fn write_header(&mut self, header: &Header) -> io::Result<()> {
+        write!(self.writer, "{}", header)
+    }
+
+    /// Write a record.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alignment::pairwise::Aligner;
+    use crate::alignment::AlignmentMode;
+    use crate::alignment::AlignmentOperation::*;
+
+    const SAM_FILE: &[u8] = b"@HD\tVN:1.6\tSO:coordinate
+@SQ\tSN:ref\tLN:45\tM5:a1b2
+@SQ\tSN:ref2\tLN:40
+@RG\tID:rg1\tSM:sample1\tPL:ILLUMINA
+@PG\tID:aligner\tPN:aligner\tCL:aligner -x ref.fa
+@CO\tsome comment: with colon
+r001\t99\tref\t7\t30\t8M2I4M1D3M\t=\t37\t39\tTTAGATAAAGGATACTG\t*\tNM:i:3\tRG:Z:rg1
+r002\t0\tref\t9\t30\t3S6M1P1I4M\t*\t0\t0\tAAAAGATAAGGATA\tIIIIIIIIIIIIII\tXA:A:x\tXF:f:-1.5\tXB:B:c,-1,2,3\tXH:H:1AE3
+r003\t4\t*\t0\t0\t*\t*\t0\t0\tGCCTAAGCTAA\t*
+";
+
+    #[test]
+    fn test_read_header() {
+        let reader = Reader::new(SAM_FILE).unwrap();
+        let header = reader.header();
+        assert_eq!(header.version.as_deref(), Some("1.6"));
+        assert_eq!(header.sort_order.as_deref(), Some("coordinate"));
+        assert_eq!(header.references.len(), 2);
+        assert_eq!(header.references[0].name, "ref");
+        assert_eq!(header.references[0].len, 45);
+        assert_eq!(
+            header.references[0].tags,
+            vec![("M5".to_owned(), "a1b2".to_owned())]
+        );
+        assert_eq!(header.reference("ref2").unwrap().len, 40);
+        assert_eq!(header.read_groups[0].id, "rg1");
+        assert_eq!(header.read_groups[0].tag("SM"), Some("sample1"));
+        assert_eq!(header.programs[0].tag("CL"), Some("aligner -x ref.fa"));
+        assert_eq!(header.comments, vec!["some comment: with colon"]);
+    }
+
+    #[test]
+    fn test_read_records() {
+        let records: Vec<Record> = Reader::new(SAM_FILE)
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+
+        let r001 = &records[0];
+        assert_eq!(r001.qname(), "r001");
+        assert_eq!(r001.flags(), 99);
+        assert_eq!(r001.rname(), "ref");
+        assert_eq!(r001.pos(), 7);
+        assert_eq!(r001.mapq(), 30);
+        assert_eq!(
+            r001.cigar().0,
+            vec![
+                Cigar::Match(8),
+                Cigar::Ins(2),
+                Cigar::Match(4),
+                Cigar::Del(1),
+                Cigar::Match(3)
+            ]
+        );
+        assert_eq!(r001.cigar().query_len(), 17);
+        assert_eq!(r001.cigar().reference_len(), 16);
+        assert_eq!(r001.rnext(), "=");
+        assert_eq!(r001.pnext(), 37);
+        assert_eq!(r001.tlen(), 39);
+        assert_eq!(r001.seq(), b"TTAGATAAAGGATACTG");
+        assert!(r001.qual().is_empty());
+        assert_eq!(r001.tag("NM"), Some(&TagValue::Int(3)));
+        assert_eq!(r001.tag("RG"), Some(&TagValue::String("rg1".to_owned())));
+
+        let r002 = &records[1];
+        assert_eq!(r002.tag("XA"), Some(&TagValue::Char(b'x')));
+        assert_eq!(r002.tag("XF"), Some(&TagValue::Float(-1.5)));
+        assert_eq!(
+            r002.tag("XB"),
+            Some(&TagValue::Array(TagArray::Int8(vec![-1, 2, 3])))
+        );
+        assert_eq!(r002.tag("XH"), Some(&TagValue::Hex("1AE3".to_owned())));
+
+        let r003 = &records[2];
+        assert!(r003.is_unmapped());
+        assert!(r003.cigar().0.is_empty());
+        assert_eq!(r003.rname(), "*");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let reader = Reader::new(SAM_FILE).unwrap();
+        let header = reader.header().clone();
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_header(&header).unwrap();
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(writer.writer.get_ref().as_slice(), SAM_FILE);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let too_few_fields = b"r1\t0\tref\t1\t30\t4M\n";
+        assert!(matches!(
+            Reader::new(&too_few_fields[..])
+                .unwrap()
+                .records()
+                .next()
+                .unwrap(),
+            Err(Error::Format { line: 1, .. })
+        ));
+
+        let bad_cigar = b"@SQ\tSN:ref\tLN:10\nr1\t0\tref\t1\t30\t4Q\t*\t0\t0\tACGT\t*\n";
+        assert!(matches!(
+            Reader::new(&bad_cigar[..])
+                .unwrap()
+                .records()
+                .next()
+                .unwrap(),
+            Err(Error::Format { line: 2, .. })
+        ));
+
+        let bad_qual = b"r1\t0\tref\t1\t30\t4M\t*\t0\t0\tACGT\tII\n";
+        assert!(Reader::new(&bad_qual[..])
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .is_err());
+
+        let bad_tag = b"r1\t0\tref\t1\t30\t4M\t*\t0\t0\tACGT\t*\tNM:x:1\n";
+        assert!(Reader::new(&bad_tag[..])
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .is_err());
+
+        assert!(matches!(
+            Reader::new(&b"@SQ\tSN:ref\n"[..]),
+            Err(Error::Format { line: 1, .. })
+        ));
+        assert!(matches!(
+            Reader::new(&b"@XX\tID:1\n"[..]),
+            Err(Error::Format { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_cigar_parse() {
+        assert_eq!(
+            "3S4=1X2I".parse::<CigarString>().unwrap().0,
+            vec![
+                Cigar::SoftClip(3),
+                Cigar::Equal(4),
+                Cigar::Diff(1),
+                Cigar::Ins(2)
+            ]
+        );
+        assert!("*".parse::<CigarString>().unwrap().0.is_empty());
+        for cigar in &["", "M", "4", "4M3", "4Z", "-1M"] {
+            assert!(
+                matches!(cigar.parse::<CigarString>(), Err(Error::InvalidCigar(_))),
+                "{}",
+                cigar
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_alignment() {
+        let alignment = Alignment {
+            score: 5,
+            xstart: 2,
+            ystart: 3,
+            xend: 10,
+            yend: 12,
+            ylen: 14,
+            xlen: 11,
+            operations: vec![
+                Match, Match, Subst, Ins, Match, Del, Del, Match, Subst, Match,
+            ],
+            mode: AlignmentMode::Semiglobal,
+        };
+        let reference = b"NNNACGTAGGCATNN";
+        let query = fastq::Record::with_attrs("q1", None, b"TTACTTTGAAT", b"IIIIIIIIIII");
+        let record = Record::from_alignment(&alignment, &query, "chr1", reference);
+
+        assert_eq!(record.qname(), "q1");
+        assert_eq!(record.flags(), 0);
+        assert_eq!(record.rname(), "chr1");
+        assert_eq!(record.pos(), 4);
+        assert_eq!(record.mapq(), 255);
+        assert_eq!(record.cigar().to_string(), "2S2=1X1I1=2D1=1X1=1S");
+        assert_eq!(record.cigar().query_len(), 11);
+        assert_eq!(record.cigar().reference_len(), 9);
+        assert_eq!(record.seq(), b"TTACTTTGAAT");
+        assert_eq!(record.qual(), b"IIIIIIIIIII");
+        assert_eq!(record.tag("NM"), Some(&TagValue::Int(5)));
+        assert_eq!(
+            record.tag("MD"),
+            Some(&TagValue::String("2G1^AG1C1".to_owned()))
+        );
+        assert_eq!(record.tag("AS"), Some(&TagValue::Int(5)));
+    }
+
+    #[test]
+    fn test_from_global_alignment() {
+        let score = |a: u8, b: u8| if a == b { 1i32 } else { -1i32 };
+        let mut aligner = Aligner::new(-5, -1, &score);
+        let reference = b"GGGACGTACGTCCC";
+        let query = fastq::Record::with_attrs("q2", None, b"ACGTACGT", b"");
+        let alignment = aligner.global(query.seq(), reference);
+        let record = Record::from_alignment(&alignment, &query, "chr1", reference);
+
+        // leading and trailing deletions shift the position instead of being reported
+        assert_eq!(record.pos(), 4);
+        assert_eq!(record.cigar().to_string(), "8=");
+        assert_eq!(record.tag("MD"), Some(&TagValue::String("8".to_owned())));
+        assert_eq!(record.tag("NM"), Some(&TagValue::Int(0)));
+        assert_eq!(
+            record.to_string(),
+            "q2\t0\tchr1\t4\t255\t8=\t*\t0\t0\tACGTACGT\t*\tNM:i:0\tMD:Z:8\tAS:i:-8"
+        );
+    }
+
+    #[test]
+    fn test_from_empty_alignment() {
+        let alignment = Alignment {
+            xlen: 4,
+            ylen: 10,
+            mode: AlignmentMode::Local,
+            ..Alignment::default()
+        };
+        let query = fastq::Record::with_attrs("q3", None, b"ACGT", b"IIII");
+        let record = Record::from_alignment(&alignment, &query, "chr1", b"TTTTTTTTTT");
+        assert!(record.is_unmapped());
+        assert_eq!(record.rname(), "*");
+        assert_eq!(record.pos(), 0);
+        assert_eq!(
+            record.to_string(),
+            "q3\t4\t*\t0\t255\t*\t*\t0\t0\tACGT\tIIII"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Extraction of spliced transcript, coding and protein sequences of gene models.
+//!
+//! Transcripts are taken from a [`gff::FeatureTree`](crate::io::gff::FeatureTree): every
+//! feature with `exon` or `CDS` children is considered a transcript. Their sequences are
+//! fetched from a genome given as [`fasta::IndexedReader`](crate::io::fasta::IndexedReader).
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::{fasta, gff, transcript};
+//! use std::io::Cursor;
+//!
+//! let genome: &[u8] = b">chr1\nCCATGGCTAAGTAAGTAAGTTCTAGCCCCC\n";
+//! let annotation: &[u8] = b"chr1\t.\tmRNA\t3\t26\t.\t+\t.\tID=t1
+//! chr1\t.\texon\t3\t10\t.\t+\t.\tParent=t1
+//! chr1\t.\texon\t19\t26\t.\t+\t.\tParent=t1
+//! chr1\t.\tCDS\t3\t10\t.\t+\t0\tID=cds1;Parent=t1
+//! chr1\t.\tCDS\t19\t25\t.\t+\t2\tID=cds1;Parent=t1
+//! ";
+//! let tree = gff::Reader::new(annotation, gff::GffType::GFF3)
+//!     .feature_tree()
+//!     .unwrap();
+//! let index = fasta::Index::build(genome).unwrap();
+//! let reader = fasta::IndexedReader::with_index(Cursor::new(genome), index);
+//! let mut extractor = transcript::Extractor::new(reader).translate(true);
+//! for sequences in extractor.sequences(&tree) {
+//!     let sequences = sequences.unwrap();
+//!     assert_eq!(sequences.id, "t1");
+//!     assert_eq!(sequences.transcript, b"ATGGCTAAGTTCTAGC");
+//!     assert_eq!(sequences.cds, b"ATGGCTAAGTTCTAG");
+//!     assert_eq!(sequences.protein.unwrap(), b"MAKF*");
+//! }
+//! ```
+
+use std::io;
+use std::ops::Range;
+
+use bio_types::strand::Strand;
+
+use crate::alphabets::dna;
+use crate::io::fasta;
+use crate::io::gff::{Feature, FeatureTree, Record};
+
+/// Feature types that make up the exons of a transcript without `exon` features.
+const EXONIC_TYPES: &[&str] = &[
+    "CDS",
+    "five_prime_UTR",
+    "three_prime_UTR",
+    "UTR",
+    "start_codon",
+    "stop_codon",
+];
+
+/// The exon and CDS structure of a transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    id: String,
+    seqname: String,
+    strand: Option<Strand>,
+    exons: Vec<Range<u64>>,
+    cds: Vec<Range<u64>>,
+    phase: u64,
+}
+
+impl Transcript {
+    /// Create the transcript model of the feature with the given index. Returns `None` if
+    /// the feature has no `exon` or `CDS` children.
+    ///
+    /// If there are no `exon` children, the exons are given by the CDS and UTR children.
+    pub fn from_feature(tree: &FeatureTree, index: usize) -> Option<Self> {
+        let feature = tree.feature(index);
+        let id = feature.id()?;
+        let records_of = |types: &[&str]| -> Vec<&Record> {
+            tree.children(feature)
+                .filter(|child| types.contains(&child.feature_type()))
+                .flat_map(Feature::records)
+                .collect()
+        };
+
+        let cds_records = records_of(&["CDS"]);
+        let mut exons = ranges(&records_of(&["exon"]));
+        if exons.is_empty() {
+            if cds_records.is_empty() {
+                return None;
+            }
+            exons = ranges(&records_of(EXONIC_TYPES));
+            merge(&mut exons);
+        }
+        let cds = ranges(&cds_records);
+
+        // only the phase of the first CDS segment in transcription order matters
+        let reverse = feature.strand() == Some(Strand::Reverse);
+        let first = if reverse {
+            cds_records.iter().max_by_key(|r| *r.end())
+        } else {
+            cds_records.iter().min_by_key(|r| *r.start())
+        };
+        let phase = first.and_then(|r| r.frame().parse().ok()).unwrap_or(0);
+
+        Some(Transcript {
+            id: id.to_owned(),
+            seqname: feature.seqname().to_owned(),
+            strand: feature.strand(),
+            exons,
+            cds,
+            phase,
+        })
+    }
+
+    /// Create the transcript models of all features with `exon` or `CDS` children.
+    pub fn all(tree: &FeatureTree) -> Vec<Self> {
+        (0..tree.len())
+            .filter_map(|i| Transcript::from_feature(tree, i))
+            .collect()
+    }
+
+    /// ID of the transcript feature.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Sequence name of the transcript.
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    /// Strand of the transcript. Transcripts without strand are treated as forward.
+    pub fn strand(&self) -> Option<Strand> {
+        self.strand
+    }
+
+    /// The exons as 0-based, half-open intervals in ascending order.
+    pub fn exons(&self) -> &[Range<u64>] {
+        &self.exons
+    }
+
+    /// The CDS segments as 0-based, half-open intervals in ascending order.
+    pub fn cds(&self) -> &[Range<u64>] {
+        &self.cds
+    }
+
+    /// The number of bases to skip at the start of the CDS to reach the first
+    /// complete codon, given by the phase of the first CDS segment.
+    pub fn phase(&self) -> u64 {
+        self.phase
+    }
+
+    fn is_reverse(&self) -> bool {
+        self.strand == Some(Strand::Reverse)
+    }
+}
+
+/// Convert the GFF coordinates of the given records into sorted 0-based, half-open intervals.
+fn ranges(records: &[&Record]) -> Vec<Range<u64>> {
+    let mut ranges: Vec<_> = records
+        .iter()
+        .map(|r| r.start().saturating_sub(1)..*r.end())
+        .collect();
+    ranges.sort_by_key(|r| (r.start, r.end));
+    ranges.dedup();
+    ranges
+}
+
+/// Merge overlapping and adjacent sorted intervals.
+fn merge(ranges: &mut Vec<Range<u64>>) {
+    let mut merged: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    *ranges = merged;
+}
+
+/// The sequences of a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequences {
+    /// ID of the transcript feature.
+    pub id: String,
+    /// The spliced transcript.
+    pub transcript: Vec<u8>,
+    /// The coding sequence, starting at the first complete codon. Empty for non-coding
+    /// transcripts.
+    pub cds: Vec<u8>,
+    /// The translated CDS, if translation is enabled. Stop codons are translated to `*`.
+    pub protein: Option<Vec<u8>>,
+}
+
+/// Extracts the sequences of transcripts from an indexed genome.
+#[derive(Debug)]
+pub struct Extractor<R: io::Read + io::Seek> {
+    reader: fasta::IndexedReader<R>,
+    translate: bool,
+    buffer: Vec<u8>,
+}
+
+impl<R: io::Read + io::Seek> Extractor<R> {
+    /// Create a new extractor reading from the given genome.
+    pub fn new(reader: fasta::IndexedReader<R>) -> Self {
+        Extractor {
+            reader,
+            translate: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Set whether the CDS shall be translated into a protein sequence (default: false).
+    pub fn translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// Fetch the given intervals, join them and reverse complement them if needed.
+    fn splice(&mut self, transcript: &Transcript, ranges: &[Range<u64>]) -> io::Result<Vec<u8>> {
+        let mut seq = Vec::new();
+        for range in ranges {
+            self.reader
+                .fetch(&transcript.seqname, range.start, range.end)?;
+            self.reader.read(&mut self.buffer)?;
+            seq.extend_from_slice(&self.buffer);
+        }
+        if transcript.is_reverse() {
+            seq = dna::revcomp(seq);
+        }
+        Ok(seq)
+    }
+
+    /// Extract the sequences of the given transcript.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sequence of the transcript is missing in the genome or an
+    /// interval exceeds its end.
+    pub fn extract(&mut self, transcript: &Transcript) -> io::Result<Sequences> {
+        let spliced = self.splice(transcript, &transcript.exons)?;
+        let mut cds = self.splice(transcript, &transcript.cds)?;
+        cds.drain(..(transcript.phase as usize).min(cds.len()));
+        let protein = if self.translate {
+            Some(dna::translate(&cds))
+        } else {
+            None
+        };
+        Ok(Sequences {
+            id: transcript.id.clone(),
+            transcript: spliced,
+            cds,
+            protein,
+        })
+    }
+
+    /// Iterate over the sequences of all transcripts of the given feature tree.
+    pub fn sequences<'a>(
+        &'a mut self,
+        tree: &FeatureTree,
+    ) -> impl Iterator<Item = io::Result<Sequences>> + 'a {
+        Transcript::all(tree)
+            .into_iter()
+            .map(move |transcript| self.extract(&transcript))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::gff;
+    use std::io::Cursor;
+
+    // chr2 is the reverse complement of chr1
+    const GENOME: &[u8] = b">chr1
+CCATGGCTAAGTAAGTAAGTTCTAGCCCCC
+>chr2
+GGGGGCTAGAACTTACTTACTTAGCCATGG
+";
+
+    const GFF3: &[u8] = b"chr1\t.\tgene\t3\t26\t.\t+\t.\tID=g1
+chr1\t.\tmRNA\t3\t26\t.\t+\t.\tID=t1;Parent=g1
+chr1\t.\texon\t3\t10\t.\t+\t.\tParent=t1
+chr1\t.\texon\t19\t26\t.\t+\t.\tParent=t1
+chr1\t.\tCDS\t3\t10\t.\t+\t0\tID=cds1;Parent=t1
+chr1\t.\tCDS\t19\t25\t.\t+\t2\tID=cds1;Parent=t1
+chr1\t.\tncRNA\t1\t30\t.\t+\t.\tID=nc1;Parent=g1
+chr1\t.\texon\t1\t4\t.\t+\t.\tParent=nc1
+chr1\t.\tgene\t3\t25\t.\t+\t.\tID=g2
+chr1\t.\tCDS\t3\t10\t.\t+\t0\tParent=g2
+chr1\t.\tCDS\t19\t25\t.\t+\t2\tParent=g2
+chr1\t.\tthree_prime_UTR\t26\t28\t.\t+\t.\tParent=g2
+";
+
+    const GTF: &[u8] = b"chr2\t.\texon\t21\t29\t.\t-\t.\tgene_id \"g3\"; transcript_id \"t3\";
+chr2\t.\texon\t5\t12\t.\t-\t.\tgene_id \"g3\"; transcript_id \"t3\";
+chr2\t.\tCDS\t21\t29\t.\t-\t1\tgene_id \"g3\"; transcript_id \"t3\";
+chr2\t.\tCDS\t6\t12\t.\t-\t0\tgene_id \"g3\"; transcript_id \"t3\";
+";
+
+    fn extractor() -> Extractor<Cursor<&'static [u8]>> {
+        let index = fasta::Index::build(GENOME).unwrap();
+        Extractor::new(fasta::IndexedReader::with_index(Cursor::new(GENOME), index))
+    }
+
+    #[test]
+    fn test_transcript_models() {
+        let tree = gff::Reader::new(GFF3, gff::GffType::GFF3)
+            .feature_tree()
+            .unwrap();
+        let transcripts = Transcript::all(&tree);
+        let ids: Vec<_> = transcripts.iter().map(Transcript::id).collect();
+        assert_eq!(ids, vec!["t1", "nc1", "g2"]);
+        assert_eq!(transcripts[0].exons(), &[2..10, 18..26]);
+        assert_eq!(transcripts[0].cds(), &[2..10, 18..25]);
+        assert_eq!(transcripts[0].phase(), 0);
+        assert!(transcripts[1].cds().is_empty());
+        // exons of transcripts without exon features are given by CDS and UTRs
+        assert_eq!(transcripts[2].exons(), &[2..10, 18..28]);
+    }
+
+    #[test]
+    fn test_extract_forward() {
+        let tree = gff::Reader::new(GFF3, gff::GffType::GFF3)
+            .feature_tree()
+            .unwrap();
+        let mut extractor = extractor().translate(true);
+        let sequences: Vec<_> = extractor.sequences(&tree).map(|s| s.unwrap()).collect();
+        assert_eq!(sequences[0].transcript, b"ATGGCTAAGTTCTAGC");
+        assert_eq!(sequences[0].cds, b"ATGGCTAAGTTCTAG");
+        assert_eq!(sequences[0].protein, Some(b"MAKF*".to_vec()));
+        assert_eq!(sequences[1].transcript, b"CCAT");
+        assert!(sequences[1].cds.is_empty());
+        assert_eq!(sequences[1].protein, Some(vec![]));
+        assert_eq!(sequences[2].transcript, b"ATGGCTAAGTTCTAGCCC");
+        assert_eq!(sequences[2].cds, b"ATGGCTAAGTTCTAG");
+    }
+
+    #[test]
+    fn test_extract_reverse_with_phase() {
+        let tree = gff::Reader::new(GTF, gff::GffType::GTF2)
+            .feature_tree()
+            .unwrap();
+        let transcripts = Transcript::all(&tree);
+        assert_eq!(transcripts.len(), 1);
+        assert_eq!(transcripts[0].phase(), 1);
+        let sequences = extractor().extract(&transcripts[0]).unwrap();
+        assert_eq!(sequences.id, "t3");
+        assert_eq!(sequences.transcript, b"CATGGCTAAGTTCTAGC");
+        assert_eq!(sequences.cds, b"ATGGCTAAGTTCTAG");
+        assert_eq!(sequences.protein, None);
+    }
+
+    #[test]
+    fn test_extract_missing_sequence() {
+        let gff = b"chr3\t.\tmRNA\t1\t10\t.\t+\t.\tID=t1
+chr3\t.\texon\t1\t10\t.\t+\t.\tParent=t1
+";
+        let tree = gff::Reader::new(&gff[..], gff::GffType::GFF3)
+            .feature_tree()
+            .unwrap();
+        let mut extractor = extractor();
+        assert!(extractor.sequences(&tree).next().unwrap().is_err());
+    }
+}
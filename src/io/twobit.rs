@@ -0,0 +1,670 @@
+//! Random access to sequences in the UCSC [2bit] format, and conversion of FASTA to 2bit.
+//!
+//! A 2bit file stores four bases per byte, with runs of `N` and of lowercase (soft-masked)
+//! bases kept as separate block lists. Other ambiguity codes can't be represented and are
+//! stored as `N`.
+//!
+//! [2bit]: https://genome.ucsc.edu/FAQ/FAQformat.html#format7
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::twobit;
+//! use std::io::Cursor;
+//!
+//! let mut writer = twobit::Writer::new(Vec::new());
+//! writer.write("chr1", b"ACGTNNNNacgtA").unwrap();
+//! writer.write("chr2", b"GGCC").unwrap();
+//! let data = writer.finish().unwrap();
+//!
+//! let mut reader = twobit::Reader::new(Cursor::new(data)).unwrap();
+//! assert_eq!(reader.sequences()[0].len, 13);
+//! let mut seq = Vec::new();
+//! reader.fetch("chr1", 2, 10).unwrap();
+//! reader.read(&mut seq).unwrap();
+//! assert_eq!(seq, b"GTNNNNAC");
+//!
+//! let mut reader = reader.soft_mask(true);
+//! reader.fetch_all("chr1").unwrap();
+//! reader.read(&mut seq).unwrap();
+//! assert_eq!(seq, b"ACGTNNNNacgtA");
+//! ```
+
+use anyhow::Context;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::{AsRef, TryFrom};
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use crate::io::fasta::{self, Sequence};
+use crate::utils::{Text, TextSlice};
+
+/// The signature at the start of every 2bit file.
+const SIGNATURE: u32 = 0x1A41_2743;
+
+/// The bases encoded by the 2-bit codes 0 to 3.
+const BASES: &[u8; 4] = b"TCAG";
+
+/// A sequence listed in the file index.
+#[derive(Debug, Clone)]
+struct IndexRecord {
+    name: String,
+    offset: u64,
+    len: u64,
+}
+
+/// The N and mask blocks of a sequence, as sorted, non-overlapping `[start, end)` intervals,
+/// and the file offset of its packed bases.
+#[derive(Debug, Clone)]
+struct Blocks {
+    rid: usize,
+    n_blocks: Vec<(u64, u64)>,
+    mask_blocks: Vec<(u64, u64)>,
+    dna_offset: u64,
+}
+
+fn invalid_data<S: Into<String>>(message: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// A reader of 2bit files, providing random access to their sequences.
+#[derive(Debug)]
+pub struct Reader<R: io::Read + io::Seek> {
+    reader: io::BufReader<R>,
+    big_endian: bool,
+    index: Vec<IndexRecord>,
+    name_to_rid: HashMap<String, usize>,
+    soft_mask: bool,
+    fetched: Option<(usize, u64, u64)>,
+    blocks: Option<Blocks>,
+}
+
+impl Reader<fs::File> {
+    /// Read from a given file path.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: &P) -> anyhow::Result<Self> {
+        fs::File::open(path)
+            .and_then(Self::new)
+            .with_context(|| format!("Failed to read 2bit from {:#?}", path))
+    }
+}
+
+impl<R: io::Read + io::Seek> Reader<R> {
+    /// Read from a given `io::Read + io::Seek`, parsing the header and the index. Both byte
+    /// orders and the 64-bit offsets of version 1 files are supported.
+    ///
+    /// # Errors
+    ///
+    /// If the signature or version is invalid, or the index is truncated.
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut reader = Reader {
+            reader: io::BufReader::new(reader),
+            big_endian: false,
+            index: Vec::new(),
+            name_to_rid: HashMap::new(),
+            soft_mask: false,
+            fetched: None,
+            blocks: None,
+        };
+        let mut signature = [0; 4];
+        reader.reader.read_exact(&mut signature)?;
+        if u32::from_be_bytes(signature) == SIGNATURE {
+            reader.big_endian = true;
+        } else if u32::from_le_bytes(signature) != SIGNATURE {
+            return Err(invalid_data("Invalid 2bit signature."));
+        }
+        let version = reader.read_u32()?;
+        if version > 1 {
+            return Err(invalid_data(format!(
+                "Unsupported 2bit version {}.",
+                version
+            )));
+        }
+        let count = reader.read_u32()?;
+        reader.read_u32()?;
+
+        for rid in 0..count as usize {
+            let mut name_len = [0; 1];
+            reader.reader.read_exact(&mut name_len)?;
+            let mut name = vec![0; name_len[0] as usize];
+            reader.reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| invalid_data("Invalid UTF-8 in 2bit sequence name."))?;
+            let offset = if version == 1 {
+                reader.read_u64()?
+            } else {
+                u64::from(reader.read_u32()?)
+            };
+            if reader.name_to_rid.insert(name.clone(), rid).is_some() {
+                return Err(invalid_data(format!(
+                    "Duplicate sequence name in 2bit: {}.",
+                    name
+                )));
+            }
+            reader.index.push(IndexRecord {
+                name,
+                offset,
+                len: 0,
+            });
+        }
+        for rid in 0..reader.index.len() {
+            reader
+                .reader
+                .seek(io::SeekFrom::Start(reader.index[rid].offset))?;
+            reader.index[rid].len = u64::from(reader.read_u32()?);
+        }
+        Ok(reader)
+    }
+
+    /// Return lowercase bases in soft-masked regions instead of uppercase ones only.
+    pub fn soft_mask(mut self, soft_mask: bool) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
+    /// Return a vector of the sequences in the file, in the order of the index.
+    pub fn sequences(&self) -> Vec<Sequence> {
+        self.index
+            .iter()
+            .map(|record| Sequence {
+                name: record.name.clone(),
+                len: record.len,
+            })
+            .collect()
+    }
+
+    /// Fetch an interval from the sequence with the given name for reading.
+    ///
+    /// `start` and `stop` are 0-based and `stop` is exclusive - i.e. `[start, stop)`
+    ///
+    /// # Errors
+    /// If the `seq_name` does not exist within the file.
+    pub fn fetch(&mut self, seq_name: &str, start: u64, stop: u64) -> io::Result<()> {
+        let rid = self.rid(seq_name)?;
+        self.fetch_by_rid(rid, start, stop)
+    }
+
+    /// Fetch an interval from the sequence with the given record index for reading.
+    ///
+    /// `start` and `stop` are 0-based and `stop` is exclusive - i.e. `[start, stop)`
+    ///
+    /// # Errors
+    /// If `rid` does not exist within the file.
+    pub fn fetch_by_rid(&mut self, rid: usize, start: u64, stop: u64) -> io::Result<()> {
+        if rid >= self.index.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid record index in 2bit file.",
+            ));
+        }
+        self.fetched = Some((rid, start, stop));
+        Ok(())
+    }
+
+    /// Fetch the whole sequence with the given name for reading.
+    pub fn fetch_all(&mut self, seq_name: &str) -> io::Result<()> {
+        let rid = self.rid(seq_name)?;
+        self.fetch_all_by_rid(rid)
+    }
+
+    /// Fetch the whole sequence with the given record index for reading.
+    pub fn fetch_all_by_rid(&mut self, rid: usize) -> io::Result<()> {
+        let len = self
+            .index
+            .get(rid)
+            .map(|record| record.len)
+            .unwrap_or_default();
+        self.fetch_by_rid(rid, 0, len)
+    }
+
+    /// Read the fetched sequence into the given vector, with runs of `N` expanded and, if
+    /// enabled, soft-masked bases in lowercase.
+    pub fn read(&mut self, seq: &mut Text) -> io::Result<()> {
+        let (rid, start, stop) = self.fetched.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No sequence fetched for reading.",
+            )
+        })?;
+        if stop > self.index[rid].len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "2bit read interval was out of bounds",
+            ));
+        } else if start > stop {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid query interval",
+            ));
+        }
+
+        match &self.blocks {
+            Some(blocks) if blocks.rid == rid => (),
+            _ => self.blocks = Some(self.read_blocks(rid)?),
+        }
+        let blocks = self.blocks.as_ref().unwrap();
+
+        let first_byte = start / 4;
+        #[allow(clippy::manual_div_ceil)]
+        let mut packed = vec![0; ((stop + 3) / 4 - first_byte) as usize];
+        self.reader
+            .seek(io::SeekFrom::Start(blocks.dna_offset + first_byte))?;
+        self.reader.read_exact(&mut packed)?;
+
+        seq.clear();
+        seq.extend((start..stop).map(|pos| {
+            let byte = packed[(pos / 4 - first_byte) as usize];
+            BASES[(byte >> (6 - 2 * (pos % 4)) & 3) as usize]
+        }));
+
+        let overlapping = |intervals: &[(u64, u64)]| {
+            // the intervals are sorted and disjoint, hence also sorted by their ends
+            let first = intervals
+                .binary_search_by(|&(_, end)| {
+                    if end <= start {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    }
+                })
+                .unwrap_err();
+            intervals[first..]
+                .iter()
+                .take_while(|&&(block_start, _)| block_start < stop)
+                .map(|&(block_start, end)| {
+                    (
+                        (block_start.max(start) - start) as usize,
+                        (end.min(stop) - start) as usize,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        for (from, to) in overlapping(&blocks.n_blocks) {
+            seq[from..to].iter_mut().for_each(|base| *base = b'N');
+        }
+        if self.soft_mask {
+            for (from, to) in overlapping(&blocks.mask_blocks) {
+                seq[from..to].make_ascii_lowercase();
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the record index of the given sequence name or io::Result::Err
+    fn rid(&self, seq_name: &str) -> io::Result<usize> {
+        self.name_to_rid.get(seq_name).copied().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown sequence name: {}.", seq_name),
+            )
+        })
+    }
+
+    /// Read the block lists preceding the packed bases of a sequence.
+    fn read_blocks(&mut self, rid: usize) -> io::Result<Blocks> {
+        // skip the sequence length
+        self.reader
+            .seek(io::SeekFrom::Start(self.index[rid].offset + 4))?;
+        let n_blocks = self.read_intervals()?;
+        let mask_blocks = self.read_intervals()?;
+        self.read_u32()?;
+        #[allow(clippy::seek_from_current)]
+        let dna_offset = self.reader.seek(io::SeekFrom::Current(0))?;
+        Ok(Blocks {
+            rid,
+            n_blocks,
+            mask_blocks,
+            dna_offset,
+        })
+    }
+
+    /// Read a block count followed by the block starts and sizes.
+    fn read_intervals(&mut self) -> io::Result<Vec<(u64, u64)>> {
+        let count = self.read_u32()? as usize;
+        let starts = (0..count)
+            .map(|_| self.read_u32().map(u64::from))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut intervals = Vec::with_capacity(count);
+        for start in starts {
+            intervals.push((start, start + u64::from(self.read_u32()?)));
+        }
+        intervals.sort_unstable();
+        Ok(intervals)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(buf)
+        } else {
+            u32::from_le_bytes(buf)
+        })
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(buf)
+        } else {
+            u64::from_le_bytes(buf)
+        })
+    }
+}
+
+/// A sequence encoded for a 2bit file.
+#[derive(Debug)]
+struct EncodedRecord {
+    name: String,
+    len: u32,
+    n_blocks: Vec<(u32, u32)>,
+    mask_blocks: Vec<(u32, u32)>,
+    packed: Vec<u8>,
+}
+
+impl EncodedRecord {
+    /// The size of the record in the file.
+    fn size(&self) -> u64 {
+        16 + 8 * (self.n_blocks.len() + self.mask_blocks.len()) as u64 + self.packed.len() as u64
+    }
+}
+
+/// Return the `(start, size)` runs of positions for which `pred` holds.
+fn blocks<F: Fn(u8) -> bool>(seq: TextSlice<'_>, pred: F) -> Vec<(u32, u32)> {
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    while pos < seq.len() {
+        if pred(seq[pos]) {
+            let start = pos;
+            while pos < seq.len() && pred(seq[pos]) {
+                pos += 1;
+            }
+            blocks.push((start as u32, (pos - start) as u32));
+        } else {
+            pos += 1;
+        }
+    }
+    blocks
+}
+
+/// A writer of 2bit files.
+///
+/// As the index precedes the sequences in the file, sequences are kept in memory, packed,
+/// until [`finish`](#method.finish) is called.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: W,
+    records: Vec<EncodedRecord>,
+}
+
+impl Writer<fs::File> {
+    /// Write to the given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to the given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            records: Vec::new(),
+        }
+    }
+
+    /// Add a sequence. Lowercase bases are recorded as soft-masked, and any base other than
+    /// `A`, `C`, `G` or `T` is stored as `N`.
+    ///
+    /// # Errors
+    /// If the name is already taken, longer than 255 bytes, or the sequence is longer than
+    /// 2^32 - 1 bases.
+    pub fn write(&mut self, name: &str, seq: TextSlice<'_>) -> io::Result<()> {
+        if name.len() > 255 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Sequence name longer than 255 bytes: {}.", name),
+            ));
+        }
+        if self.records.iter().any(|record| record.name == name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Duplicate sequence name in 2bit: {}.", name),
+            ));
+        }
+        let len = u32::try_from(seq.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Sequence too long for 2bit: {}.", name),
+            )
+        })?;
+
+        #[allow(clippy::manual_div_ceil)]
+        let mut packed = vec![0u8; (seq.len() + 3) / 4];
+        for (pos, base) in seq.iter().enumerate() {
+            let code = match base.to_ascii_uppercase() {
+                b'C' => 1,
+                b'A' => 2,
+                b'G' => 3,
+                _ => 0,
+            };
+            packed[pos / 4] |= code << (6 - 2 * (pos % 4));
+        }
+        self.records.push(EncodedRecord {
+            name: name.to_owned(),
+            len,
+            n_blocks: blocks(seq, |base| {
+                !matches!(base.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T')
+            }),
+            mask_blocks: blocks(seq, |base| base.is_ascii_lowercase()),
+            packed,
+        });
+        Ok(())
+    }
+
+    /// Add the sequence of a FASTA record, named by its id.
+    pub fn write_record(&mut self, record: &fasta::Record) -> io::Result<()> {
+        self.write(record.id(), record.seq())
+    }
+
+    /// Write the header, the index and all sequences, and return the underlying
+    /// `io::Write`. Files exceeding 4 GiB are written as version 1, with 64-bit offsets.
+    pub fn finish(mut self) -> io::Result<W> {
+        let index_size = |offset_size: u64| {
+            self.records
+                .iter()
+                .map(|record| 1 + record.name.len() as u64 + offset_size)
+                .sum::<u64>()
+        };
+        let data_size: u64 = self.records.iter().map(EncodedRecord::size).sum();
+        let version = if 16 + index_size(4) + data_size > u64::from(u32::MAX) {
+            1
+        } else {
+            0
+        };
+        let mut offset = 16 + index_size(if version == 1 { 8 } else { 4 });
+
+        let mut out = io::BufWriter::new(&mut self.writer);
+        for value in &[SIGNATURE, version, self.records.len() as u32, 0] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for record in &self.records {
+            out.write_all(&[record.name.len() as u8])?;
+            out.write_all(record.name.as_bytes())?;
+            if version == 1 {
+                out.write_all(&offset.to_le_bytes())?;
+            } else {
+                out.write_all(&(offset as u32).to_le_bytes())?;
+            }
+            offset += record.size();
+        }
+        for record in &self.records {
+            out.write_all(&record.len.to_le_bytes())?;
+            for blocks in &[&record.n_blocks, &record.mask_blocks] {
+                out.write_all(&(blocks.len() as u32).to_le_bytes())?;
+                for (start, _) in blocks.iter() {
+                    out.write_all(&start.to_le_bytes())?;
+                }
+                for (_, size) in blocks.iter() {
+                    out.write_all(&size.to_le_bytes())?;
+                }
+            }
+            out.write_all(&0u32.to_le_bytes())?;
+            out.write_all(&record.packed)?;
+        }
+        out.flush()?;
+        drop(out);
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A single sequence `ACGTNNacgN` in big-endian byte order.
+    const BIG_ENDIAN: &[u8] = &[
+        0x1A,
+        0x41,
+        0x27,
+        0x43,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        1,
+        0,
+        0,
+        0,
+        0, // header
+        3,
+        b's',
+        b'e',
+        b'q',
+        0,
+        0,
+        0,
+        24, // index
+        0,
+        0,
+        0,
+        10, // length
+        0,
+        0,
+        0,
+        2,
+        0,
+        0,
+        0,
+        4,
+        0,
+        0,
+        0,
+        9,
+        0,
+        0,
+        0,
+        2,
+        0,
+        0,
+        0,
+        1, // N blocks
+        0,
+        0,
+        0,
+        1,
+        0,
+        0,
+        0,
+        6,
+        0,
+        0,
+        0,
+        3, // mask blocks
+        0,
+        0,
+        0,
+        0, // reserved
+        0b1001_1100,
+        0b0000_1001,
+        0b1100_0000, // packed bases
+    ];
+
+    #[test]
+    fn test_big_endian() {
+        let mut reader = Reader::new(Cursor::new(BIG_ENDIAN)).unwrap();
+        assert_eq!(
+            reader.sequences(),
+            vec![Sequence {
+                name: "seq".to_owned(),
+                len: 10
+            }]
+        );
+        let mut seq = Vec::new();
+        reader.fetch_all("seq").unwrap();
+        reader.read(&mut seq).unwrap();
+        assert_eq!(seq, b"ACGTNNACGN");
+        let mut reader = reader.soft_mask(true);
+        reader.fetch("seq", 5, 9).unwrap();
+        reader.read(&mut seq).unwrap();
+        assert_eq!(seq, b"Nacg");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let sequences: Vec<(&str, &[u8])> = vec![
+            ("chr1", b"NNNNACGTACGTRYacgtnnACGTA"),
+            ("chr2", b""),
+            ("chrM", b"gattaca"),
+        ];
+        let mut writer = Writer::new(Vec::new());
+        for (name, seq) in &sequences {
+            writer.write(name, seq).unwrap();
+        }
+        assert!(writer.write("chr1", b"A").is_err());
+        let data = writer.finish().unwrap();
+
+        let mut reader = Reader::new(Cursor::new(data)).unwrap().soft_mask(true);
+        let mut seq = Vec::new();
+        for (rid, (name, expected)) in sequences.iter().enumerate() {
+            reader.fetch_all_by_rid(rid).unwrap();
+            reader.read(&mut seq).unwrap();
+            let expected: Vec<u8> = expected
+                .iter()
+                .map(|&b| match b {
+                    b'R' | b'Y' => b'N',
+                    b'n' => b'n',
+                    _ => b,
+                })
+                .collect();
+            assert_eq!(seq, expected, "{}", name);
+        }
+
+        let expected = b"NNNNACGTACGTNNacgtnnACGTA";
+        for start in 0..expected.len() {
+            for stop in start..=expected.len() {
+                reader.fetch("chr1", start as u64, stop as u64).unwrap();
+                reader.read(&mut seq).unwrap();
+                assert_eq!(seq, &expected[start..stop]);
+            }
+        }
+
+        assert!(reader.fetch("chrX", 0, 1).is_err());
+        reader.fetch("chrM", 0, 8).unwrap();
+        assert!(reader.read(&mut seq).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Reader::new(Cursor::new(b"\x43\x27\x41\x1B\0\0\0\0")).is_err());
+        assert!(Reader::new(Cursor::new(&BIG_ENDIAN[..20])).is_err());
+    }
+}
@@ -0,0 +1,1340 @@
+//! Reading and writing of variants in the [VCF] format, uncompressed or bgzipped.
+//!
+//! The meta-information lines are parsed into typed INFO, FORMAT, FILTER and contig
+//! definitions. Records keep their INFO column and per-sample columns as text, which is only
+//! decoded on access, e.g. via [`Record::info`](Record::info) or
+//! [`Sample::genotype`](Sample::genotype).
+//!
+//! [VCF]: https://samtools.github.io/hts-specs/VCFv4.3.pdf
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::vcf;
+//!
+//! let vcf = concat!(
+//!     "##fileformat=VCFv4.2\n",
+//!     "##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele Frequency\">\n",
+//!     "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n",
+//!     "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNA001\n",
+//!     "20\t14370\trs6054257\tG\tA,T\t29\tPASS\tAF=0.5,0.017\tGT\t1|2\n",
+//! );
+//! let reader = vcf::Reader::new(vcf.as_bytes()).unwrap();
+//! let af = reader.header().info("AF").unwrap().clone();
+//! for result in reader.records() {
+//!     let record = result.expect("Error reading record.");
+//!     assert_eq!(record.alt_alleles().len(), 2);
+//!     let values = record.info("AF").unwrap().decode(af.kind).unwrap();
+//!     assert_eq!(values, vcf::Value::Float(vec![Some(0.5), Some(0.017)]));
+//!     let genotype = record.sample(0).unwrap().genotype().unwrap().unwrap();
+//!     assert_eq!(genotype.alleles, vec![Some(1), Some(2)]);
+//!     assert!(genotype.phased);
+//! }
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::io::compression::{Decoder, Encoder, Format};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The number of values of an INFO or FORMAT field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Number {
+    /// A fixed number of values.
+    Count(usize),
+    /// One value per alternate allele (`A`).
+    Alternates,
+    /// One value per allele, including the reference (`R`).
+    Alleles,
+    /// One value per possible genotype (`G`).
+    Genotypes,
+    /// The number of values varies or is unknown (`.`).
+    Unknown,
+}
+
+impl Number {
+    /// The expected number of values for a record with the given number of alternate
+    /// alleles and the given ploidy, or `None` if it is unknown.
+    pub fn count(self, alt_alleles: usize, ploidy: usize) -> Option<usize> {
+        match self {
+            Number::Count(count) => Some(count),
+            Number::Alternates => Some(alt_alleles),
+            Number::Alleles => Some(alt_alleles + 1),
+            Number::Genotypes => {
+                // number of multisets of size ploidy over all alleles
+                let alleles = alt_alleles + 1;
+                Some((1..=ploidy).fold(1, |count, i| count * (alleles + i - 1) / i))
+            }
+            Number::Unknown => None,
+        }
+    }
+}
+
+impl FromStr for Number {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "A" => Number::Alternates,
+            "R" => Number::Alleles,
+            "G" => Number::Genotypes,
+            "." => Number::Unknown,
+            _ => Number::Count(
+                s.parse()
+                    .map_err(|_| Error::InvalidValue(format!("Number={}", s)))?,
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Count(count) => write!(f, "{}", count),
+            Number::Alternates => write!(f, "A"),
+            Number::Alleles => write!(f, "R"),
+            Number::Genotypes => write!(f, "G"),
+            Number::Unknown => write!(f, "."),
+        }
+    }
+}
+
+/// The type of the values of an INFO or FORMAT field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Integer,
+    Float,
+    Flag,
+    Character,
+    String,
+}
+
+impl FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "Integer" => ValueType::Integer,
+            "Float" => ValueType::Float,
+            "Flag" => ValueType::Flag,
+            "Character" => ValueType::Character,
+            "String" => ValueType::String,
+            _ => return Err(Error::InvalidValue(format!("Type={}", s))),
+        })
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueType::Integer => "Integer",
+            ValueType::Float => "Float",
+            ValueType::Flag => "Flag",
+            ValueType::Character => "Character",
+            ValueType::String => "String",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The definition of an INFO or FORMAT field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDefinition {
+    pub id: String,
+    pub number: Number,
+    pub kind: ValueType,
+    pub description: String,
+    /// Further attributes, e.g. `Source` or `Version`.
+    pub other: Vec<(String, String)>,
+}
+
+/// The definition of a filter (`##FILTER` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterDefinition {
+    pub id: String,
+    pub description: String,
+    pub other: Vec<(String, String)>,
+}
+
+/// The definition of a contig (`##contig` line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contig {
+    pub id: String,
+    pub length: Option<u64>,
+    /// Further attributes, e.g. `assembly` or `md5`.
+    pub other: Vec<(String, String)>,
+}
+
+/// A VCF header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    /// The format version, e.g. `VCFv4.2`.
+    pub file_format: String,
+    pub infos: Vec<FieldDefinition>,
+    pub formats: Vec<FieldDefinition>,
+    pub filters: Vec<FilterDefinition>,
+    pub contigs: Vec<Contig>,
+    /// All other meta-information lines as key and unparsed value, e.g. `##reference`
+    /// or `##ALT` lines.
+    pub other: Vec<(String, String)>,
+    pub samples: Vec<String>,
+}
+
+impl Header {
+    /// Create a new, empty header for VCF version 4.2.
+    pub fn new() -> Self {
+        Header {
+            file_format: "VCFv4.2".to_owned(),
+            ..Header::default()
+        }
+    }
+
+    /// Return the definition of the INFO field with the given id.
+    pub fn info(&self, id: &str) -> Option<&FieldDefinition> {
+        self.infos.iter().find(|d| d.id == id)
+    }
+
+    /// Return the definition of the FORMAT field with the given id.
+    pub fn format(&self, id: &str) -> Option<&FieldDefinition> {
+        self.formats.iter().find(|d| d.id == id)
+    }
+
+    /// Return the definition of the filter with the given id.
+    pub fn filter(&self, id: &str) -> Option<&FilterDefinition> {
+        self.filters.iter().find(|d| d.id == id)
+    }
+
+    /// Return the definition of the contig with the given id.
+    pub fn contig(&self, id: &str) -> Option<&Contig> {
+        self.contigs.iter().find(|c| c.id == id)
+    }
+
+    /// Return the index of the sample with the given name.
+    pub fn sample_index(&self, name: &str) -> Option<usize> {
+        self.samples.iter().position(|s| s == name)
+    }
+
+    /// Parse a meta-information line without the leading `##` and add it to this header.
+    fn push_meta_line(&mut self, line: &str) -> Result<()> {
+        let eq = line
+            .find('=')
+            .ok_or_else(|| Error::InvalidValue(format!("meta-information line ##{}", line)))?;
+        let (key, value) = (&line[..eq], &line[eq + 1..]);
+        match key {
+            "fileformat" => self.file_format = value.to_owned(),
+            "INFO" | "FORMAT" => {
+                let mut attrs = parse_structured(value)?;
+                let definition = FieldDefinition {
+                    id: take_attr(&mut attrs, "ID", value)?,
+                    number: take_attr(&mut attrs, "Number", value)?.parse()?,
+                    kind: take_attr(&mut attrs, "Type", value)?.parse()?,
+                    description: take_attr(&mut attrs, "Description", value)?,
+                    other: attrs,
+                };
+                if key == "INFO" {
+                    self.infos.push(definition);
+                } else {
+                    self.formats.push(definition);
+                }
+            }
+            "FILTER" => {
+                let mut attrs = parse_structured(value)?;
+                self.filters.push(FilterDefinition {
+                    id: take_attr(&mut attrs, "ID", value)?,
+                    description: take_attr(&mut attrs, "Description", value)?,
+                    other: attrs,
+                });
+            }
+            "contig" => {
+                let mut attrs = parse_structured(value)?;
+                let id = take_attr(&mut attrs, "ID", value)?;
+                let length = match take_attr(&mut attrs, "length", value) {
+                    Ok(length) => Some(
+                        length
+                            .parse()
+                            .map_err(|_| Error::InvalidValue(format!("length={}", length)))?,
+                    ),
+                    Err(_) => None,
+                };
+                self.contigs.push(Contig {
+                    id,
+                    length,
+                    other: attrs,
+                });
+            }
+            _ => self.other.push((key.to_owned(), value.to_owned())),
+        }
+        Ok(())
+    }
+}
+
+/// Parse the attributes of a structured meta-information value like
+/// `<ID=DP,Number=1,Description="Depth, total">`.
+fn parse_structured(value: &str) -> Result<Vec<(String, String)>> {
+    let invalid = || Error::InvalidValue(value.to_owned());
+    let inner = value
+        .strip_prefix('<')
+        .and_then(|v| v.strip_suffix('>'))
+        .ok_or_else(invalid)?;
+    let mut attrs = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while chars.peek().is_some() {
+        let key: String = chars.by_ref().take_while(|&c| c != '=').collect();
+        let mut attr_value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next().ok_or_else(invalid)? {
+                    '\\' => attr_value.push(chars.next().ok_or_else(invalid)?),
+                    '"' => break,
+                    c => attr_value.push(c),
+                }
+            }
+            match chars.next() {
+                None | Some(',') => (),
+                Some(_) => return Err(invalid()),
+            }
+        } else {
+            attr_value = chars.by_ref().take_while(|&c| c != ',').collect();
+        }
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        attrs.push((key, attr_value));
+    }
+    Ok(attrs)
+}
+
+fn take_attr(attrs: &mut Vec<(String, String)>, key: &str, value: &str) -> Result<String> {
+    let i = attrs
+        .iter()
+        .position(|(k, _)| k == key)
+        .ok_or_else(|| Error::InvalidValue(format!("missing {} in {}", key, value)))?;
+    Ok(attrs.remove(i).1)
+}
+
+/// Format an attribute of a structured meta-information value, quoting it if needed.
+fn write_attr(f: &mut fmt::Formatter<'_>, key: &str, value: &str, quote: bool) -> fmt::Result {
+    let quote = quote
+        || value
+            .chars()
+            .any(|c| matches!(c, ',' | '"' | '<' | '>' | '=' | '\\') || c.is_whitespace());
+    if quote {
+        write!(
+            f,
+            ",{}=\"{}\"",
+            key,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        write!(f, ",{}={}", key, value)
+    }
+}
+
+impl fmt::Display for Header {
+    /// Format the header as meta-information lines and the column header line,
+    /// each terminated by a newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_other(f: &mut fmt::Formatter<'_>, other: &[(String, String)]) -> fmt::Result {
+            for (key, value) in other {
+                write_attr(f, key, value, false)?;
+            }
+            writeln!(f, ">")
+        }
+
+        writeln!(f, "##fileformat={}", self.file_format)?;
+        for (key, value) in &self.other {
+            writeln!(f, "##{}={}", key, value)?;
+        }
+        for contig in &self.contigs {
+            write!(f, "##contig=<ID={}", contig.id)?;
+            if let Some(length) = contig.length {
+                write!(f, ",length={}", length)?;
+            }
+            write_other(f, &contig.other)?;
+        }
+        for (key, definitions) in &[("INFO", &self.infos), ("FORMAT", &self.formats)] {
+            for definition in definitions.iter() {
+                write!(
+                    f,
+                    "##{}=<ID={},Number={},Type={}",
+                    key, definition.id, definition.number, definition.kind
+                )?;
+                write_attr(f, "Description", &definition.description, true)?;
+                write_other(f, &definition.other)?;
+            }
+        }
+        for filter in &self.filters {
+            write!(f, "##FILTER=<ID={}", filter.id)?;
+            write_attr(f, "Description", &filter.description, true)?;
+            write_other(f, &filter.other)?;
+        }
+        write!(f, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+        if !self.samples.is_empty() {
+            write!(f, "\tFORMAT")?;
+            for sample in &self.samples {
+                write!(f, "\t{}", sample)?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// An alternate allele.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Allele {
+    /// A sequence of bases.
+    Bases(Vec<u8>),
+    /// A symbolic allele like `<DEL>` or `<*>`, given without the angle brackets.
+    Symbolic(String),
+    /// A breakend in its textual representation, e.g. `G]17:198982]`.
+    Breakend(String),
+    /// An allele missing due to an overlapping deletion (`*`).
+    Overlapped,
+}
+
+impl Allele {
+    /// Return true if this is a symbolic allele or breakend.
+    pub fn is_symbolic(&self) -> bool {
+        matches!(self, Allele::Symbolic(_) | Allele::Breakend(_))
+    }
+}
+
+impl FromStr for Allele {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "*" {
+            Ok(Allele::Overlapped)
+        } else if let Some(id) = s.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            Ok(Allele::Symbolic(id.to_owned()))
+        } else if s.contains('[')
+            || s.contains(']')
+            || (s.len() > 1 && (s.starts_with('.') || s.ends_with('.')))
+        {
+            Ok(Allele::Breakend(s.to_owned()))
+        } else if !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphabetic()) {
+            Ok(Allele::Bases(s.as_bytes().to_vec()))
+        } else {
+            Err(Error::InvalidValue(format!("allele {}", s)))
+        }
+    }
+}
+
+impl fmt::Display for Allele {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Allele::Bases(bases) => write!(f, "{}", String::from_utf8_lossy(bases)),
+            Allele::Symbolic(id) => write!(f, "<{}>", id),
+            Allele::Breakend(breakend) => write!(f, "{}", breakend),
+            Allele::Overlapped => write!(f, "*"),
+        }
+    }
+}
+
+/// Decoded values of an INFO or FORMAT field. Missing values (`.`) are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Integer(Vec<Option<i32>>),
+    Float(Vec<Option<f32>>),
+    Flag,
+    Character(Vec<Option<char>>),
+    String(Vec<Option<&'a str>>),
+}
+
+/// An undecoded INFO or FORMAT field of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    raw: Option<&'a str>,
+}
+
+impl<'a> Field<'a> {
+    /// The undecoded value, `None` for INFO flags.
+    pub fn raw(&self) -> Option<&'a str> {
+        self.raw
+    }
+
+    /// Return true if this is an INFO flag without value.
+    pub fn is_flag(&self) -> bool {
+        self.raw.is_none()
+    }
+
+    /// Iterate over the comma separated values. Missing values (`.`) are `None`.
+    pub fn values(&self) -> impl Iterator<Item = Option<&'a str>> {
+        self.raw
+            .into_iter()
+            .flat_map(|raw| raw.split(','))
+            .map(|value| if value == "." { None } else { Some(value) })
+    }
+
+    fn parse_values<T: FromStr>(&self) -> Result<Vec<Option<T>>> {
+        self.values()
+            .map(|value| match value {
+                Some(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| Error::InvalidValue(value.to_owned())),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Decode the values as integers.
+    pub fn integers(&self) -> Result<Vec<Option<i32>>> {
+        self.parse_values()
+    }
+
+    /// Decode the values as floats.
+    pub fn floats(&self) -> Result<Vec<Option<f32>>> {
+        self.parse_values()
+    }
+
+    /// Return the values as strings.
+    pub fn strings(&self) -> Vec<Option<&'a str>> {
+        self.values().collect()
+    }
+
+    /// Decode the values according to the given type, e.g. the one of the header definition.
+    pub fn decode(&self, kind: ValueType) -> Result<Value<'a>> {
+        Ok(match kind {
+            ValueType::Integer => Value::Integer(self.integers()?),
+            ValueType::Float => Value::Float(self.floats()?),
+            ValueType::Flag => Value::Flag,
+            ValueType::Character => Value::Character(self.parse_values()?),
+            ValueType::String => Value::String(self.strings()),
+        })
+    }
+}
+
+/// A genotype, given as allele indices where 0 is the reference allele.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Genotype {
+    /// The allele indices, `None` for missing alleles.
+    pub alleles: Vec<Option<usize>>,
+    /// Whether the alleles are phased (separated by `|`).
+    pub phased: bool,
+}
+
+impl FromStr for Genotype {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let alleles = s
+            .split(&['/', '|'][..])
+            .map(|allele| match allele {
+                "." => Ok(None),
+                _ => allele
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| Error::InvalidValue(format!("genotype {}", s))),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Genotype {
+            alleles,
+            phased: s.contains('|') && !s.contains('/'),
+        })
+    }
+}
+
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.phased { "|" } else { "/" };
+        for (i, allele) in self.alleles.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", separator)?;
+            }
+            match allele {
+                Some(allele) => write!(f, "{}", allele)?,
+                None => write!(f, ".")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The undecoded FORMAT fields of a sample of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample<'a> {
+    format: &'a str,
+    raw: &'a str,
+}
+
+impl<'a> Sample<'a> {
+    /// The undecoded sample column.
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+
+    /// Return the field with the given FORMAT key, or `None` if the key is not present
+    /// or the trailing field has been dropped.
+    pub fn get(&self, key: &str) -> Option<Field<'a>> {
+        let index = self.format.split(':').position(|k| k == key)?;
+        self.raw
+            .split(':')
+            .nth(index)
+            .map(|raw| Field { raw: Some(raw) })
+    }
+
+    /// Decode the genotype (`GT` field).
+    pub fn genotype(&self) -> Option<Result<Genotype>> {
+        self.get("GT")
+            .and_then(|field| field.raw)
+            .map(|raw| raw.parse())
+    }
+}
+
+/// A VCF record.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    chrom: String,
+    pos: u64,
+    ids: Vec<String>,
+    ref_allele: Vec<u8>,
+    alt_alleles: Vec<Allele>,
+    qual: Option<f32>,
+    filters: Vec<String>,
+    info: String,
+    format: String,
+    samples: Vec<String>,
+}
+
+impl Record {
+    /// Create a new, empty record.
+    pub fn new() -> Self {
+        Record::default()
+    }
+
+    /// Check if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chrom.is_empty()
+    }
+
+    pub fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    /// The 1-based position.
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// The identifiers, empty if missing.
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    pub fn ref_allele(&self) -> &[u8] {
+        &self.ref_allele
+    }
+
+    /// The alternate alleles, empty if there is none.
+    pub fn alt_alleles(&self) -> &[Allele] {
+        &self.alt_alleles
+    }
+
+    /// The phred-scaled quality, `None` if missing.
+    pub fn qual(&self) -> Option<f32> {
+        self.qual
+    }
+
+    /// The filters the record failed, `PASS` if it passed all filters, and empty if
+    /// filters have not been applied.
+    pub fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
+    /// Return true if the record passed all filters.
+    pub fn is_pass(&self) -> bool {
+        self.filters.len() == 1 && self.filters[0] == "PASS"
+    }
+
+    /// The undecoded INFO column, empty if missing.
+    pub fn info_raw(&self) -> &str {
+        &self.info
+    }
+
+    /// Iterate over the keys and undecoded fields of the INFO column.
+    pub fn info_fields(&self) -> impl Iterator<Item = (&str, Field<'_>)> {
+        self.info
+            .split(';')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.find('=') {
+                Some(eq) => (
+                    &entry[..eq],
+                    Field {
+                        raw: Some(&entry[eq + 1..]),
+                    },
+                ),
+                None => (entry, Field { raw: None }),
+            })
+    }
+
+    /// Return the INFO field with the given key.
+    pub fn info(&self, key: &str) -> Option<Field<'_>> {
+        self.info_fields()
+            .find(|(k, _)| *k == key)
+            .map(|(_, field)| field)
+    }
+
+    /// The keys of the FORMAT column.
+    pub fn format_keys(&self) -> impl Iterator<Item = &str> {
+        self.format.split(':').filter(|key| !key.is_empty())
+    }
+
+    /// The number of samples.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Return the sample with the given index.
+    pub fn sample(&self, index: usize) -> Option<Sample<'_>> {
+        self.samples.get(index).map(|raw| Sample {
+            format: &self.format,
+            raw,
+        })
+    }
+
+    /// Iterate over all samples.
+    pub fn samples(&self) -> impl Iterator<Item = Sample<'_>> {
+        let format = self.format.as_str();
+        self.samples.iter().map(move |raw| Sample { format, raw })
+    }
+
+    pub fn chrom_mut(&mut self) -> &mut String {
+        &mut self.chrom
+    }
+
+    pub fn pos_mut(&mut self) -> &mut u64 {
+        &mut self.pos
+    }
+
+    pub fn ids_mut(&mut self) -> &mut Vec<String> {
+        &mut self.ids
+    }
+
+    pub fn ref_allele_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.ref_allele
+    }
+
+    pub fn alt_alleles_mut(&mut self) -> &mut Vec<Allele> {
+        &mut self.alt_alleles
+    }
+
+    pub fn qual_mut(&mut self) -> &mut Option<f32> {
+        &mut self.qual
+    }
+
+    pub fn filters_mut(&mut self) -> &mut Vec<String> {
+        &mut self.filters
+    }
+
+    /// The undecoded INFO column, e.g. `DP=14;AF=0.5;DB`.
+    pub fn info_mut(&mut self) -> &mut String {
+        &mut self.info
+    }
+
+    /// The undecoded FORMAT column, e.g. `GT:GQ`.
+    pub fn format_mut(&mut self) -> &mut String {
+        &mut self.format
+    }
+
+    /// The undecoded sample columns, e.g. `0|1:48`.
+    pub fn samples_mut(&mut self) -> &mut Vec<String> {
+        &mut self.samples
+    }
+
+    /// Append a field to the INFO column. Flags have no value.
+    pub fn push_info(&mut self, key: &str, value: Option<&str>) {
+        if !self.info.is_empty() {
+            self.info.push(';');
+        }
+        self.info.push_str(key);
+        if let Some(value) = value {
+            self.info.push('=');
+            self.info.push_str(value);
+        }
+    }
+
+    /// Parse a record from a VCF line without line terminator.
+    fn parse(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 8 {
+            return Err(Error::InvalidValue(format!(
+                "expected at least 8 tab separated columns, found {}",
+                fields.len()
+            )));
+        }
+        fn list(value: &str, separator: char) -> Vec<String> {
+            if value == "." {
+                Vec::new()
+            } else {
+                value.split(separator).map(|v| v.to_owned()).collect()
+            }
+        }
+
+        Ok(Record {
+            chrom: fields[0].to_owned(),
+            pos: fields[1]
+                .parse()
+                .map_err(|_| Error::InvalidValue(format!("POS {}", fields[1])))?,
+            ids: list(fields[2], ';'),
+            ref_allele: fields[3].as_bytes().to_vec(),
+            alt_alleles: if fields[4] == "." {
+                Vec::new()
+            } else {
+                fields[4]
+                    .split(',')
+                    .map(|allele| allele.parse())
+                    .collect::<Result<_>>()?
+            },
+            qual: match fields[5] {
+                "." => None,
+                qual => Some(
+                    qual.parse()
+                        .map_err(|_| Error::InvalidValue(format!("QUAL {}", qual)))?,
+                ),
+            },
+            filters: list(fields[6], ';'),
+            info: if fields[7] == "." {
+                String::new()
+            } else {
+                fields[7].to_owned()
+            },
+            format: fields.get(8).map_or_else(String::new, |f| (*f).to_owned()),
+            samples: fields.iter().skip(9).map(|s| (*s).to_owned()).collect(),
+        })
+    }
+}
+
+impl fmt::Display for Record {
+    /// Format the record as a VCF line without line terminator.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_list<T: fmt::Display>(
+            f: &mut fmt::Formatter<'_>,
+            values: &[T],
+            separator: &str,
+        ) -> fmt::Result {
+            if values.is_empty() {
+                return write!(f, "\t.");
+            }
+            for (i, value) in values.iter().enumerate() {
+                write!(f, "{}{}", if i == 0 { "\t" } else { separator }, value)?;
+            }
+            Ok(())
+        }
+
+        write!(f, "{}\t{}", self.chrom, self.pos)?;
+        write_list(f, &self.ids, ";")?;
+        write!(f, "\t{}", String::from_utf8_lossy(&self.ref_allele))?;
+        write_list(f, &self.alt_alleles, ",")?;
+        match self.qual {
+            Some(qual) => write!(f, "\t{}", qual)?,
+            None => write!(f, "\t.")?,
+        }
+        write_list(f, &self.filters, ";")?;
+        write!(
+            f,
+            "\t{}",
+            if self.info.is_empty() {
+                "."
+            } else {
+                &self.info
+            }
+        )?;
+        if !self.format.is_empty() {
+            write!(f, "\t{}", self.format)?;
+            for sample in &self.samples {
+                write!(f, "\t{}", sample)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A VCF reader. The header is read when the reader is created.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    header: Header,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .and_then(Reader::from_bufread)
+            .with_context(|| format!("Failed to read VCF from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Result<Self> {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`, reading the header.
+    pub fn from_bufread(bufreader: B) -> Result<Self> {
+        let mut reader = Reader {
+            reader: bufreader,
+            header: Header::default(),
+            line: String::new(),
+            line_no: 0,
+        };
+        loop {
+            if !reader.next_line()? {
+                return Err(reader.format_error("missing #CHROM header line".to_owned()));
+            }
+            if let Some(meta) = reader.line.strip_prefix("##") {
+                let result = reader.header.push_meta_line(meta);
+                result.map_err(|e| reader.format_error(e.to_string()))?;
+            } else if let Some(columns) = reader.line.strip_prefix("#CHROM") {
+                let columns: Vec<&str> = columns.split('\t').skip(1).collect();
+                if columns.len() < 7 {
+                    return Err(reader.format_error("incomplete #CHROM header line".to_owned()));
+                }
+                reader.header.samples = columns
+                    .iter()
+                    .skip(8)
+                    .map(|sample| (*sample).to_owned())
+                    .collect();
+                return Ok(reader);
+            } else {
+                return Err(
+                    reader.format_error("expected meta-information or #CHROM line".to_owned())
+                );
+            }
+        }
+    }
+
+    /// The header of the VCF file.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn format_error(&self, message: String) -> Error {
+        Error::Format {
+            line: self.line_no,
+            message,
+        }
+    }
+
+    fn next_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+        let trimmed = self.line.trim_end_matches(&['\n', '\r'][..]).len();
+        self.line.truncate(trimmed);
+        Ok(true)
+    }
+
+    /// Read the next record into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a line has too few columns, a fixed column
+    /// can't be parsed or the number of sample columns differs from the header.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            if !self.next_line()? {
+                *record = Record::new();
+                return Ok(());
+            }
+            if !self.line.is_empty() {
+                break;
+            }
+        }
+        *record = Record::parse(&self.line).map_err(|e| self.format_error(e.to_string()))?;
+        if record.samples.len() != self.header.samples.len() {
+            return Err(self.format_error(format!(
+                "expected {} sample columns, found {}",
+                self.header.samples.len(),
+                record.samples.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Return an iterator over the records of this VCF file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the records of a VCF file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::new();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A VCF writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl Writer<Encoder<fs::File>> {
+    /// Write to the given file path, compressing the output in the given format.
+    /// Use `Format::Bgzf` for files that should be indexed with tabix.
+    pub fn to_file_with_compression<P: AsRef<Path>>(path: P, format: Format) -> io::Result<Self> {
+        fs::File::create(path).map(|file| Writer::new(Encoder::new(file, format)))
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write the header. This has to happen before writing any record.
+    pub fn write_header(&mut self, header: &Header) -> io::Result<()> {
+        write!(self.writer, "{}", header)
+    }
+
+    /// Write a record.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VCF_FILE: &[u8] = b"##fileformat=VCFv4.3
+##fileDate=20090805
+##reference=file:///seq/references/1000GenomesPilot-NCBI36.fasta
+##ALT=<ID=DEL,Description=\"Deletion\">
+##contig=<ID=20,length=62435964,assembly=B36>
+##contig=<ID=X>
+##INFO=<ID=NS,Number=1,Type=Integer,Description=\"Number of Samples With Data\">
+##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele Frequency\">
+##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP membership, build 129\">
+##INFO=<ID=SVTYPE,Number=1,Type=String,Description=\"Type of \\\"structural\\\" variant\",Source=dbsnp>
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Genotype Quality\">
+##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Phred-scaled genotype likelihoods\">
+##FILTER=<ID=q10,Description=\"Quality below 10\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNA00001\tNA00002
+20\t14370\trs6054257\tG\tA\t29\tPASS\tNS=3;AF=0.5;DB\tGT:GQ:PL\t0|0:48:0,10,100\t1|0:48
+20\t1110696\trs6040355;rs123\tA\tG,T\t67\tPASS\tNS=2;AF=0.333,0.667\tGT:GQ:PL\t1|2:21:10,20,30,40,50,60\t./.:.:.
+20\t1230237\t.\tT\t.\t.\t.\t.\tGT\t0/0\t0/0
+X\t2000\t.\tA\t<DEL>,*,G]17:198982]\t12.5\tq10\tSVTYPE=DEL\tGT\t0/1\t1
+";
+
+    #[test]
+    fn test_read_header() {
+        let reader = Reader::new(VCF_FILE).unwrap();
+        let header = reader.header();
+        assert_eq!(header.file_format, "VCFv4.3");
+        assert_eq!(header.samples, vec!["NA00001", "NA00002"]);
+        assert_eq!(header.sample_index("NA00002"), Some(1));
+
+        let af = header.info("AF").unwrap();
+        assert_eq!(af.number, Number::Alternates);
+        assert_eq!(af.kind, ValueType::Float);
+        assert_eq!(af.description, "Allele Frequency");
+        assert_eq!(
+            header.info("DB").unwrap().description,
+            "dbSNP membership, build 129"
+        );
+        let svtype = header.info("SVTYPE").unwrap();
+        assert_eq!(svtype.description, "Type of \"structural\" variant");
+        assert_eq!(
+            svtype.other,
+            vec![("Source".to_owned(), "dbsnp".to_owned())]
+        );
+        assert_eq!(header.format("PL").unwrap().number, Number::Genotypes);
+        assert_eq!(
+            header.filter("q10").unwrap().description,
+            "Quality below 10"
+        );
+
+        let contig = header.contig("20").unwrap();
+        assert_eq!(contig.length, Some(62435964));
+        assert_eq!(
+            contig.other,
+            vec![("assembly".to_owned(), "B36".to_owned())]
+        );
+        assert_eq!(header.contig("X").unwrap().length, None);
+        assert_eq!(header.other.len(), 3);
+        assert_eq!(header.other[2].0, "ALT");
+    }
+
+    #[test]
+    fn test_read_records() {
+        let reader = Reader::new(VCF_FILE).unwrap();
+        let header = reader.header().clone();
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 4);
+
+        let first = &records[0];
+        assert_eq!(first.chrom(), "20");
+        assert_eq!(first.pos(), 14370);
+        assert_eq!(first.ids(), &["rs6054257"]);
+        assert_eq!(first.ref_allele(), b"G");
+        assert_eq!(first.alt_alleles(), &[Allele::Bases(b"A".to_vec())]);
+        assert_eq!(first.qual(), Some(29.0));
+        assert!(first.is_pass());
+        assert_eq!(first.info("NS").unwrap().integers().unwrap(), vec![Some(3)]);
+        assert!(first.info("DB").unwrap().is_flag());
+        assert!(first.info("XX").is_none());
+        assert_eq!(
+            first.format_keys().collect::<Vec<_>>(),
+            vec!["GT", "GQ", "PL"]
+        );
+        let sample = first.sample(0).unwrap();
+        assert_eq!(
+            sample.genotype().unwrap().unwrap(),
+            Genotype {
+                alleles: vec![Some(0), Some(0)],
+                phased: true
+            }
+        );
+        assert_eq!(
+            sample.get("PL").unwrap().integers().unwrap(),
+            vec![Some(0), Some(10), Some(100)]
+        );
+        // trailing fields may be dropped
+        assert!(first.sample(1).unwrap().get("PL").is_none());
+
+        let multi = &records[1];
+        assert_eq!(multi.ids(), &["rs6040355", "rs123"]);
+        assert_eq!(multi.alt_alleles().len(), 2);
+        let af = multi.info("AF").unwrap();
+        assert_eq!(
+            af.decode(header.info("AF").unwrap().kind).unwrap(),
+            Value::Float(vec![Some(0.333), Some(0.667)])
+        );
+        assert_eq!(
+            header
+                .info("AF")
+                .unwrap()
+                .number
+                .count(multi.alt_alleles().len(), 2),
+            Some(af.values().count())
+        );
+        let pl = multi.sample(0).unwrap().get("PL").unwrap();
+        assert_eq!(
+            header
+                .format("PL")
+                .unwrap()
+                .number
+                .count(multi.alt_alleles().len(), 2),
+            Some(pl.values().count())
+        );
+        let missing = multi.sample(1).unwrap();
+        assert_eq!(
+            missing.genotype().unwrap().unwrap().alleles,
+            vec![None, None]
+        );
+        assert_eq!(missing.get("GQ").unwrap().integers().unwrap(), vec![None]);
+
+        let no_alt = &records[2];
+        assert!(no_alt.ids().is_empty());
+        assert!(no_alt.alt_alleles().is_empty());
+        assert_eq!(no_alt.qual(), None);
+        assert!(no_alt.filters().is_empty());
+        assert!(no_alt.info_fields().next().is_none());
+
+        let symbolic = &records[3];
+        assert_eq!(
+            symbolic.alt_alleles(),
+            &[
+                Allele::Symbolic("DEL".to_owned()),
+                Allele::Overlapped,
+                Allele::Breakend("G]17:198982]".to_owned())
+            ]
+        );
+        assert!(symbolic.alt_alleles()[0].is_symbolic());
+        assert_eq!(symbolic.filters(), &["q10"]);
+        assert_eq!(
+            symbolic.info("SVTYPE").unwrap().strings(),
+            vec![Some("DEL")]
+        );
+        let haploid = symbolic.sample(1).unwrap().genotype().unwrap().unwrap();
+        assert_eq!(haploid.alleles, vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_number_count() {
+        assert_eq!(Number::Count(2).count(3, 2), Some(2));
+        assert_eq!(Number::Alternates.count(3, 2), Some(3));
+        assert_eq!(Number::Alleles.count(3, 2), Some(4));
+        assert_eq!(Number::Genotypes.count(1, 2), Some(3));
+        assert_eq!(Number::Genotypes.count(2, 2), Some(6));
+        assert_eq!(Number::Genotypes.count(1, 1), Some(2));
+        assert_eq!(Number::Genotypes.count(1, 3), Some(4));
+        assert_eq!(Number::Unknown.count(1, 2), None);
+    }
+
+    #[test]
+    fn test_genotype() {
+        for gt in &["0/1", "1|2", "./.", "0", "0/1/2"] {
+            assert_eq!(gt.parse::<Genotype>().unwrap().to_string(), *gt);
+        }
+        assert!(!"0/1".parse::<Genotype>().unwrap().phased);
+        assert!("a/1".parse::<Genotype>().is_err());
+    }
+
+    fn write_vcf<W: io::Write>(writer: &mut Writer<W>) {
+        let reader = Reader::new(VCF_FILE).unwrap();
+        writer.write_header(&reader.header().clone()).unwrap();
+        for record in reader.records() {
+            writer.write(&record.unwrap()).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut writer = Writer::new(Vec::new());
+        write_vcf(&mut writer);
+        let output = writer.writer.into_inner().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            std::str::from_utf8(VCF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bgzip_roundtrip() {
+        let mut writer = Writer::new(Encoder::new(Vec::new(), Format::Bgzf));
+        write_vcf(&mut writer);
+        let compressed = writer.writer.into_inner().unwrap().finish().unwrap();
+
+        let decoder = Decoder::new(&compressed[..]).unwrap();
+        assert_eq!(decoder.format(), Format::Bgzf);
+        let reader = Reader::from_bufread(decoder).unwrap();
+        assert_eq!(reader.header().samples.len(), 2);
+        assert_eq!(reader.records().count(), 4);
+    }
+
+    #[test]
+    fn test_write_new_record() {
+        let mut header = Header::new();
+        header.samples.push("s1".to_owned());
+        let mut record = Record::new();
+        *record.chrom_mut() = "1".to_owned();
+        *record.pos_mut() = 100;
+        *record.ref_allele_mut() = b"AC".to_vec();
+        record.alt_alleles_mut().push(Allele::Bases(b"A".to_vec()));
+        record.push_info("DP", Some("10"));
+        record.push_info("SOMATIC", None);
+        *record.format_mut() = "GT".to_owned();
+        record.samples_mut().push("0/1".to_owned());
+
+        let mut writer = Writer::new(Vec::new());
+        writer.write_header(&header).unwrap();
+        writer.write(&record).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            writer.writer.get_ref().as_slice(),
+            &b"##fileformat=VCFv4.2
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1
+1\t100\t.\tAC\tA\t.\t.\tDP=10;SOMATIC\tGT\t0/1
+"[..]
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(matches!(
+            Reader::new(&b"##fileformat=VCFv4.2\n"[..]),
+            Err(Error::Format { line: 1, .. })
+        ));
+        assert!(matches!(
+            Reader::new(&b"##fileformat=VCFv4.2\n##INFO=<ID=X,Number=1>\n#CHROM\n"[..]),
+            Err(Error::Format { line: 2, .. })
+        ));
+        assert!(matches!(
+            Reader::new(&b"##INFO=<ID=X,Number=1,Type=Int,Description=\"x\">\n"[..]),
+            Err(Error::Format { line: 1, .. })
+        ));
+
+        let header =
+            b"##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1\n";
+        let mut bad_pos = header.to_vec();
+        bad_pos.extend_from_slice(b"1\tx\t.\tA\tC\t.\t.\t.\tGT\t0/1\n");
+        assert!(matches!(
+            Reader::new(&bad_pos[..]).unwrap().records().next().unwrap(),
+            Err(Error::Format { line: 3, .. })
+        ));
+
+        let mut missing_sample = header.to_vec();
+        missing_sample.extend_from_slice(b"1\t5\t.\tA\tC\t.\t.\t.\tGT\n");
+        assert!(matches!(
+            Reader::new(&missing_sample[..])
+                .unwrap()
+                .records()
+                .next()
+                .unwrap(),
+            Err(Error::Format { line: 3, .. })
+        ));
+    }
+}
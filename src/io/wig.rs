@@ -0,0 +1,493 @@
+//! Reading and writing of quantitative signal tracks in the [WIG] format.
+//!
+//! Both `fixedStep` and `variableStep` blocks are supported. Data lines are reported as
+//! [`bedgraph::Record`](crate::io::bedgraph::Record)s in 0-based, half-open coordinates,
+//! i.e. a value at the 1-based WIG position `p` with span `s` yields the interval
+//! `p - 1..p - 1 + s`. Track and browser lines are collected while reading.
+//!
+//! [WIG]: https://genome.ucsc.edu/goldenPath/help/wiggle.html
+//!
+//! # Example
+//!
+//! ```
+//! use bio::io::wig;
+//!
+//! let wig: &[u8] = b"track type=wiggle_0
+//! variableStep chrom=chr1 span=5
+//! 101\t2.5
+//! fixedStep chrom=chr2 start=11 step=10
+//! 1
+//! 2
+//! ";
+//! let reader = wig::Reader::new(wig);
+//! let records: Vec<_> = reader.records().map(|r| r.unwrap()).collect();
+//! assert_eq!((records[0].chrom(), records[0].start(), records[0].end()), ("chr1", 100, 105));
+//! assert_eq!((records[2].chrom(), records[2].start(), records[2].value()), ("chr2", 20, 2.0));
+//! ```
+
+use anyhow::Context;
+use std::convert::AsRef;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::io::bedgraph::{runs, Record, Track};
+use crate::io::compression::Decoder;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The declaration line of the current data block.
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Fixed {
+        chrom: String,
+        /// 0-based start of the next data line.
+        next: u64,
+        step: u64,
+        span: u64,
+    },
+    Variable {
+        chrom: String,
+        span: u64,
+    },
+}
+
+impl Block {
+    /// Parse a `fixedStep` or `variableStep` declaration line.
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next().unwrap_or_default();
+        let (mut chrom, mut start, mut step, mut span) = (None, None, None, 1);
+        for field in fields {
+            let sep = field
+                .find('=')
+                .ok_or_else(|| format!("invalid {} attribute '{}'", kind, field))?;
+            let (key, value) = (&field[..sep], &field[sep + 1..]);
+            if key == "chrom" {
+                chrom = Some(value.to_owned());
+                continue;
+            }
+            let value: u64 = value
+                .parse()
+                .map_err(|_| format!("invalid {} attribute '{}'", kind, field))?;
+            match key {
+                "start" if value > 0 => start = Some(value),
+                "step" => step = Some(value),
+                "span" if value > 0 => span = value,
+                _ => return Err(format!("invalid {} attribute '{}'", kind, field)),
+            }
+        }
+        let chrom = chrom.ok_or_else(|| format!("missing chrom in {} line", kind))?;
+        match kind {
+            "fixedStep" => Ok(Block::Fixed {
+                chrom,
+                next: start.ok_or("missing start in fixedStep line")? - 1,
+                step: step.ok_or("missing step in fixedStep line")?,
+                span,
+            }),
+            _ => Ok(Block::Variable { chrom, span }),
+        }
+    }
+}
+
+/// A WIG reader.
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    block: Option<Block>,
+    track: Option<Track>,
+    browser_lines: Vec<String>,
+    line: String,
+    line_no: usize,
+}
+
+impl Reader<Decoder<fs::File>> {
+    /// Read from a given file.
+    /// Gzip and BGZF compressed files are detected and decompressed transparently.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::open(path.as_ref())
+            .and_then(Decoder::new)
+            .map_err(|e| Error::FileOpen {
+                path: path.as_ref().to_owned(),
+                source: e,
+            })
+            .map(Reader::from_bufread)
+            .with_context(|| format!("Failed to read WIG from {:#?}", path))
+    }
+}
+
+impl<R: io::Read> Reader<io::BufReader<R>> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B: io::BufRead> Reader<B> {
+    /// Create a new reader with an object that implements `io::BufRead`.
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            block: None,
+            track: None,
+            browser_lines: Vec::new(),
+            line: String::new(),
+            line_no: 0,
+        }
+    }
+
+    /// The last track line read so far.
+    pub fn track(&self) -> Option<&Track> {
+        self.track.as_ref()
+    }
+
+    /// The browser lines read so far, without the leading `browser`.
+    pub fn browser_lines(&self) -> &[String] {
+        &self.browser_lines
+    }
+
+    /// Read the next data line into the given `Record`.
+    /// An empty record indicates that no more records can be read.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a declaration or data line is malformed, or
+    /// a data line occurs before the first declaration line.
+    pub fn read(&mut self, record: &mut Record) -> Result<()> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                *record = Record::default();
+                return Ok(());
+            }
+            self.line_no += 1;
+            let line = self.line.trim();
+            let line_no = self.line_no;
+            let format_error = |message: String| Error::Format {
+                line: line_no,
+                message,
+            };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(browser) = line.strip_prefix("browser") {
+                self.browser_lines.push(browser.trim().to_owned());
+                continue;
+            }
+            if line.starts_with("track") {
+                self.track = Some(line.parse().map_err(format_error)?);
+                continue;
+            }
+            if line.starts_with("fixedStep") || line.starts_with("variableStep") {
+                self.block = Some(Block::parse(line).map_err(format_error)?);
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let parse_value = |field: &str| {
+                field
+                    .parse::<f64>()
+                    .map_err(|_| format_error(format!("invalid value '{}'", field)))
+            };
+            *record = match (&mut self.block, fields.as_slice()) {
+                (
+                    Some(Block::Fixed {
+                        chrom,
+                        next,
+                        step,
+                        span,
+                    }),
+                    [value],
+                ) => {
+                    let start = *next;
+                    *next += *step;
+                    Record::new(chrom, start, start + *span, parse_value(value)?)
+                }
+                (Some(Block::Variable { chrom, span }), [pos, value]) => {
+                    let start = pos
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|&pos| pos > 0)
+                        .ok_or_else(|| format_error(format!("invalid position '{}'", pos)))?
+                        - 1;
+                    Record::new(chrom, start, start + *span, parse_value(value)?)
+                }
+                (None, _) => {
+                    return Err(format_error(
+                        "data line before fixedStep or variableStep line".to_owned(),
+                    ))
+                }
+                (Some(_), _) => {
+                    return Err(format_error(format!(
+                        "unexpected number of fields: {}",
+                        fields.len()
+                    )))
+                }
+            };
+            return Ok(());
+        }
+    }
+
+    /// Return an iterator over the records of this WIG file.
+    pub fn records(self) -> Records<B> {
+        Records { reader: self }
+    }
+}
+
+/// An iterator over the records of a WIG file.
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+}
+
+impl<B: io::BufRead> Iterator for Records<B> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        let mut record = Record::default();
+        match self.reader.read(&mut record) {
+            Ok(()) if record.is_empty() => None,
+            Ok(()) => Some(Ok(record)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A WIG writer.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    writer: io::BufWriter<W>,
+}
+
+impl Writer<fs::File> {
+    /// Write to a given file path.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::File::create(path).map(Writer::new)
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given `io::Write`.
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer: io::BufWriter::new(writer),
+        }
+    }
+
+    /// Write a track line.
+    pub fn write_track(&mut self, track: &Track) -> io::Result<()> {
+        writeln!(self.writer, "{}", track)
+    }
+
+    /// Write a browser line, given without the leading `browser`.
+    pub fn write_browser_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "browser {}", line)
+    }
+
+    /// Write a `fixedStep` block with the given values, the first one starting at the
+    /// given 0-based position.
+    pub fn write_fixed_step(
+        &mut self,
+        chrom: &str,
+        start: u64,
+        step: u64,
+        span: u64,
+        values: &[f64],
+    ) -> io::Result<()> {
+        write!(
+            self.writer,
+            "fixedStep chrom={} start={} step={}",
+            chrom,
+            start + 1,
+            step
+        )?;
+        if span != 1 {
+            write!(self.writer, " span={}", span)?;
+        }
+        writeln!(self.writer)?;
+        for value in values {
+            writeln!(self.writer, "{}", value)?;
+        }
+        Ok(())
+    }
+
+    /// Write a `variableStep` block with the given 0-based positions and values.
+    pub fn write_variable_step(
+        &mut self,
+        chrom: &str,
+        span: u64,
+        values: &[(u64, f64)],
+    ) -> io::Result<()> {
+        write!(self.writer, "variableStep chrom={}", chrom)?;
+        if span != 1 {
+            write!(self.writer, " span={}", span)?;
+        }
+        writeln!(self.writer)?;
+        for (pos, value) in values {
+            writeln!(self.writer, "{}\t{}", pos + 1, value)?;
+        }
+        Ok(())
+    }
+
+    /// Write the given per-base coverage of a chromosome, starting at position 0.
+    /// Every stretch of non-zero values is written as a `fixedStep` block with step and
+    /// span 1, stretches of zeros are omitted.
+    pub fn write_coverage<T>(&mut self, chrom: &str, coverage: &[T]) -> io::Result<()>
+    where
+        T: Copy + Into<f64>,
+    {
+        let mut stretch: Option<(u64, Vec<f64>)> = None;
+        for (start, end, value) in runs(coverage) {
+            match &mut stretch {
+                Some((stretch_start, values)) if *stretch_start + values.len() as u64 == start => {
+                    values.extend((start..end).map(|_| value))
+                }
+                _ => {
+                    if let Some((stretch_start, values)) = stretch.take() {
+                        self.write_fixed_step(chrom, stretch_start, 1, 1, &values)?;
+                    }
+                    stretch = Some((start, vec![value; (end - start) as usize]));
+                }
+            }
+        }
+        if let Some((stretch_start, values)) = stretch {
+            self.write_fixed_step(chrom, stretch_start, 1, 1, &values)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer, ensuring that everything is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush the writer and return the underlying `io::Write`.
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIG_FILE: &[u8] = b"browser position chr19:49304200-49310700
+track type=wiggle_0 name=\"variableStep\" visibility=full
+variableStep chrom=chr19 span=150
+49304701 10.0
+49304901 12.5
+# a comment
+fixedStep chrom=chr19 start=49307401 step=300 span=200
+1000
+900
+variableStep chrom=chr20
+5\t1
+";
+
+    #[test]
+    fn test_read() {
+        let mut reader = Reader::new(WIG_FILE);
+        let mut record = Record::default();
+        reader.read(&mut record).unwrap();
+        assert_eq!(record, Record::new("chr19", 49304700, 49304850, 10.0));
+        assert_eq!(
+            reader.browser_lines(),
+            &["position chr19:49304200-49310700"]
+        );
+        assert_eq!(reader.track().unwrap().get("name"), Some("variableStep"));
+
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            records,
+            vec![
+                Record::new("chr19", 49304900, 49305050, 12.5),
+                Record::new("chr19", 49307400, 49307600, 1000.0),
+                Record::new("chr19", 49307700, 49307900, 900.0),
+                Record::new("chr20", 4, 5, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let error_line = |wig: &'static [u8]| match Reader::new(wig).records().find(|r| r.is_err())
+        {
+            Some(Err(Error::Format { line, .. })) => line,
+            r => panic!("expected format error, got {:?}", r),
+        };
+        assert_eq!(error_line(b"1.0\n"), 1);
+        assert_eq!(error_line(b"fixedStep chrom=chr1 step=1\n1\n"), 1);
+        assert_eq!(error_line(b"fixedStep chrom=chr1 start=0 step=1\n"), 1);
+        assert_eq!(error_line(b"variableStep span=1\n"), 1);
+        assert_eq!(error_line(b"variableStep chrom=chr1\n1\t1\n0\t1\n"), 3);
+        assert_eq!(
+            error_line(b"fixedStep chrom=chr1 start=1 step=1\n1\t1\n"),
+            2
+        );
+        assert_eq!(error_line(b"fixedStep chrom=chr1 start=1 step=1\nx\n"), 2);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut writer = Writer::new(Vec::new());
+        writer.write_track(&Track::new("wiggle_0")).unwrap();
+        writer
+            .write_fixed_step("chr1", 10, 5, 5, &[1.0, 2.5])
+            .unwrap();
+        writer
+            .write_variable_step("chr2", 1, &[(0, 3.0), (99, 4.0)])
+            .unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "track type=wiggle_0
+fixedStep chrom=chr1 start=11 step=5 span=5
+1
+2.5
+variableStep chrom=chr2
+1\t3
+100\t4
+"
+        );
+
+        let records: Vec<Record> = Reader::new(&output[..])
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records[1], Record::new("chr1", 15, 20, 2.5));
+        assert_eq!(records[3], Record::new("chr2", 99, 100, 4.0));
+    }
+
+    #[test]
+    fn test_write_coverage() {
+        let mut writer = Writer::new(Vec::new());
+        writer
+            .write_coverage("chr1", &[0u32, 2, 2, 3, 0, 0, 1])
+            .unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "fixedStep chrom=chr1 start=2 step=1
+2
+2
+3
+fixedStep chrom=chr1 start=7 step=1
+1
+"
+        );
+    }
+}